@@ -0,0 +1,232 @@
+use crate::i18n::{Msg, error_lang};
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use yml_diff::{ConfigDiff, ConfigKey, DiffOptions};
+
+/// --emit-patch 输出的一个操作，与 JSON Patch（RFC 6902）相似，但 `path` 是扁平化后的键路径，
+/// 例如 `db.hosts[0]`，而不是 JSON Pointer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOp {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+}
+
+/// 把差异转换成操作列表，顺序与 [`apply_diff`] 相同：先倒序删除，再新增，最后修改
+///
+/// 命中 redact 规则的值写作 `***`，这样的操作列表不能直接用于重建新文件。
+pub fn diff_to_ops(diff: &ConfigDiff, opts: &DiffOptions) -> Vec<PatchOp> {
+    let value = |key: &ConfigKey, val: &Value| {
+        if opts.rules.is_redacted(key) {
+            Value::String("***".into())
+        } else {
+            val.clone()
+        }
+    };
+    let removes = diff.removed().keys().rev().map(|key| PatchOp::Remove {
+        path: key.to_string(),
+    });
+    let adds = diff.added().iter().map(|(key, &val)| PatchOp::Add {
+        path: key.to_string(),
+        value: value(key, val),
+    });
+    let replaces = diff
+        .modified()
+        .iter()
+        .map(|(key, &(_, new))| PatchOp::Replace {
+            path: key.to_string(),
+            value: value(key, new),
+        });
+    removes.chain(adds).chain(replaces).collect()
+}
+
+/// 依次把操作应用到文档上
+///
+/// 路径用 `separator` 拆分，数字段在序列中作为下标。`add` 会创建途中缺少的映射和序列；
+/// `remove` 和 `replace` 的路径必须存在，否则返回错误，说明文件与生成操作列表时的旧文件不一致。
+pub fn apply_ops(doc: &Value, ops: &[PatchOp], separator: &str) -> Result<Value> {
+    let mut doc = doc.clone();
+    for (i, op) in ops.iter().enumerate() {
+        let (PatchOp::Add { path, .. } | PatchOp::Remove { path } | PatchOp::Replace { path, .. }) =
+            op;
+        let key = ConfigKey::new(path.as_str(), separator);
+        let segments = segments(&key);
+        if !matches!(op, PatchOp::Add { .. }) && get_path(&doc, &segments).is_none() {
+            let n = i + 1;
+            return Err(anyhow!(
+                error_lang().format(Msg::PatchPathMissing, &[&n, path])
+            ));
+        }
+        match op {
+            PatchOp::Add { value, .. } | PatchOp::Replace { value, .. } => {
+                set_path(&mut doc, &segments, value.clone(), true)
+            }
+            PatchOp::Remove { .. } => {
+                remove_path(&mut doc, &segments);
+            }
+        }
+    }
+    Ok(doc)
+}
+
+/// 按键的各段取出文档中的值，序列中的段按下标解析
+pub fn get_path<'v>(doc: &'v Value, segments: &[&str]) -> Option<&'v Value> {
+    segments.iter().try_fold(doc, |cur, seg| match cur {
+        Value::Sequence(seq) => seg.parse::<usize>().ok().and_then(|i| seq.get(i)),
+        _ => cur.get(seg),
+    })
+}
+
+/// 把差异应用到旧文档上，重建出新文档
+///
+/// 先删除 `removed` 中的键（删除后变空的映射或序列一并移除），再写入
+/// `added` 和 `modified` 中的新值。`index_paths` 为真时数字段被当作序列下标，
+/// 写入不存在的下标会创建序列。
+pub fn apply_diff(old: &Value, diff: &ConfigDiff, index_paths: bool) -> Value {
+    let mut doc = old.clone();
+
+    // 倒序删除，保证同一序列中先删除较大的下标
+    for key in diff.removed().keys().rev() {
+        remove_path(&mut doc, &segments(key));
+    }
+    for (key, &val) in diff.added().iter() {
+        set_path(&mut doc, &segments(key), val.clone(), index_paths);
+    }
+    for (key, &(_, new)) in diff.modified().iter() {
+        set_path(&mut doc, &segments(key), new.clone(), index_paths);
+    }
+
+    doc
+}
+
+fn segments(key: &ConfigKey) -> Vec<&str> {
+    key.segments().collect()
+}
+
+fn set_path(doc: &mut Value, segments: &[&str], val: Value, index_paths: bool) {
+    let mut cur = doc;
+    for seg in segments {
+        cur = child_mut(cur, seg, index_paths);
+    }
+    *cur = val;
+}
+
+fn child_mut<'v>(cur: &'v mut Value, seg: &str, index_paths: bool) -> &'v mut Value {
+    let index = seg.parse::<usize>().ok().filter(|_| index_paths);
+    if let Some(i) = index {
+        if cur.is_null() {
+            *cur = Value::Sequence(Vec::new());
+        }
+        if let Value::Sequence(seq) = cur {
+            if seq.len() <= i {
+                seq.resize(i + 1, Value::Null);
+            }
+            return &mut seq[i];
+        }
+    }
+
+    if !cur.is_mapping() {
+        *cur = Value::Mapping(Mapping::new());
+    }
+    let Value::Mapping(map) = cur else {
+        unreachable!()
+    };
+    map.entry(Value::String(seg.to_string()))
+        .or_insert(Value::Null)
+}
+
+/// 删除路径上的值；删除后变空的父级容器也会被移除，返回当前节点是否已变空
+fn remove_path(cur: &mut Value, segments: &[&str]) -> bool {
+    let Some((seg, rest)) = segments.split_first() else {
+        return false;
+    };
+
+    match cur {
+        Value::Mapping(map) => {
+            let k = Value::String(seg.to_string());
+            let remove = match map.get_mut(&k) {
+                Some(child) if !rest.is_empty() => remove_path(child, rest),
+                Some(_) => true,
+                None => false,
+            };
+            if remove {
+                map.remove(&k);
+            }
+            map.is_empty()
+        }
+        Value::Sequence(seq) => {
+            let Some(i) = seg.parse::<usize>().ok().filter(|&i| i < seq.len()) else {
+                return false;
+            };
+            let remove = rest.is_empty() || remove_path(&mut seq[i], rest);
+            if remove {
+                seq.remove(i);
+            }
+            seq.is_empty()
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PatchOp, apply_diff, apply_ops, diff_to_ops};
+    use crate::{DiffOptions, IndexStyle, cmp_yml_vals};
+    use serde_yaml::Value;
+
+    fn round_trip(old: &str, new: &str, opts: &DiffOptions) {
+        let old: Value = serde_yaml::from_str(old).unwrap();
+        let new: Value = serde_yaml::from_str(new).unwrap();
+        let diff = cmp_yml_vals(&old, &new, opts);
+        let rebuilt = apply_diff(&old, &diff, opts.flatten.index_style.is_some());
+        assert_eq!(rebuilt, new);
+    }
+
+    #[test]
+    fn test_apply_reconstructs_new() {
+        let opts = DiffOptions::default();
+        round_trip("a: 1\nb:\n  c: 2\n", "a: 2\nd:\n  e: [1]\n", &opts);
+        round_trip("a:\n  b: 1\n", "a: 3\n", &opts);
+        round_trip("a: 3\n", "a:\n  b: {}\n", &opts);
+
+        let mut opts = DiffOptions::default();
+        opts.flatten.index_style = Some(IndexStyle::Bracket);
+        round_trip("s: [1, 2, 3]\n", "s: [1]\n", &opts);
+        round_trip("s: [1]\n", "s: [{a: 1}, 2]\nt: [x]\n", &opts);
+        round_trip("s: [1]\n", "s: []\n", &opts);
+    }
+
+    #[test]
+    fn test_ops_round_trip() {
+        let old: Value = serde_yaml::from_str("a: 1\nb:\n  c: 2\ns: [1, 2, 3]\n").unwrap();
+        let new: Value = serde_yaml::from_str("a: 2\nd: {e: x}\ns: [1]\n").unwrap();
+        let diff = cmp_yml_vals(&old, &new, &DiffOptions::default());
+        let ops = diff_to_ops(&diff, &DiffOptions::default());
+        assert_eq!(
+            ops[..2],
+            [
+                PatchOp::Remove {
+                    path: "s[2]".into()
+                },
+                PatchOp::Remove {
+                    path: "s[1]".into()
+                },
+            ]
+        );
+
+        // 经过 YAML 序列化后应用到旧文件上得到新文件
+        let text = serde_yaml::to_string(&ops).unwrap();
+        assert!(
+            text.contains("op: replace\n  path: a\n  value: 2"),
+            "{text}"
+        );
+        let ops: Vec<PatchOp> = serde_yaml::from_str(&text).unwrap();
+        assert_eq!(apply_ops(&old, &ops, ".").unwrap(), new);
+
+        // 删除不存在的路径说明文件与旧文件不一致
+        let err = apply_ops(&new, &ops, ".").unwrap_err().to_string();
+        assert!(err.contains("s[2]"), "{err}");
+    }
+}
@@ -0,0 +1,149 @@
+use serde_yaml::Value;
+use std::borrow::Cow;
+
+/// 决定同一个键的新旧值是否相等，供库的使用者插入领域相关的比较逻辑
+///
+/// 差异引擎对新旧文件中都存在的每个扁平键调用一次 [`ValueComparator::equal`]：
+///
+/// - `path` 是扁平化后的完整键路径，例如 `services.database.port`
+/// - `old` / `new` 是该键在两个文件中的叶子值；没有展开的序列或子树会整体传入
+///
+/// 返回 `true` 表示两者相等，该键不会出现在修改列表中。
+///
+/// ```
+/// use serde_yaml::Value;
+/// use yml_diff::compare::ValueComparator;
+///
+/// /// 忽略时间戳中 `T` 与空格分隔符的差别
+/// struct TimestampComparator;
+///
+/// impl ValueComparator for TimestampComparator {
+///     fn equal(&self, path: &str, old: &Value, new: &Value) -> bool {
+///         match (old.as_str(), new.as_str()) {
+///             (Some(a), Some(b)) if path.ends_with("time") => {
+///                 a.replace('T', " ") == b.replace('T', " ")
+///             }
+///             _ => old == new,
+///         }
+///     }
+/// }
+///
+/// let a = Value::String("2025-01-01T00:00:00".into());
+/// let b = Value::String("2025-01-01 00:00:00".into());
+/// assert!(TimestampComparator.equal("build.time", &a, &b));
+/// ```
+pub trait ValueComparator {
+    fn equal(&self, path: &str, old: &Value, new: &Value) -> bool;
+}
+
+/// 默认的比较方式：直接使用 `Value` 的 `PartialEq`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PartialEqComparator;
+
+impl ValueComparator for PartialEqComparator {
+    fn equal(&self, _path: &str, old: &Value, new: &Value) -> bool {
+        old == new
+    }
+}
+
+impl<F> ValueComparator for F
+where
+    F: Fn(&str, &Value, &Value) -> bool,
+{
+    fn equal(&self, path: &str, old: &Value, new: &Value) -> bool {
+        self(path, old, new)
+    }
+}
+
+/// 比较前对字符串值做的规范化，默认全部关闭
+///
+/// 规范化发生在 [`ValueComparator::equal`] 之前，自定义的比较器看到的也是规范化后的值；
+/// 报告中显示的仍然是原值。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StringNormalization {
+    /// 字符串不区分大小写
+    pub ignore_case: bool,
+    /// 去掉字符串首尾的空白
+    pub trim: bool,
+    /// `yes`/`no`、`on`/`off`、`true`/`false`（不区分大小写）形式的字符串视为布尔值
+    pub booleans: bool,
+}
+
+impl StringNormalization {
+    pub fn is_active(&self) -> bool {
+        self.ignore_case || self.trim || self.booleans
+    }
+
+    /// 规范化值中的每个字符串，序列、映射和带标签的值逐层处理；没有开启任何规范化时不复制
+    pub fn normalize<'a>(&self, val: &'a Value) -> Cow<'a, Value> {
+        if !self.is_active() {
+            return Cow::Borrowed(val);
+        }
+        Cow::Owned(self.normalize_owned(val))
+    }
+
+    fn normalize_owned(&self, val: &Value) -> Value {
+        match val {
+            Value::String(s) => self.normalize_str(s),
+            Value::Sequence(seq) => seq.iter().map(|v| self.normalize_owned(v)).collect(),
+            Value::Mapping(m) => Value::Mapping(
+                m.iter()
+                    .map(|(k, v)| (k.clone(), self.normalize_owned(v)))
+                    .collect(),
+            ),
+            Value::Tagged(t) => {
+                let mut t = t.clone();
+                t.value = self.normalize_owned(&t.value);
+                Value::Tagged(t)
+            }
+            _ => val.clone(),
+        }
+    }
+
+    fn normalize_str(&self, s: &str) -> Value {
+        let s = if self.trim { s.trim() } else { s };
+        if self.booleans {
+            match s.to_ascii_lowercase().as_str() {
+                "yes" | "on" | "true" => return Value::Bool(true),
+                "no" | "off" | "false" => return Value::Bool(false),
+                _ => {}
+            }
+        }
+        Value::String(if self.ignore_case {
+            s.to_lowercase()
+        } else {
+            s.to_string()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StringNormalization;
+    use serde_yaml::Value;
+
+    #[test]
+    fn test_string_normalization() {
+        let yaml = |s: &str| serde_yaml::from_str::<Value>(s).unwrap();
+        let all = StringNormalization {
+            ignore_case: true,
+            trim: true,
+            booleans: true,
+        };
+        assert_eq!(*all.normalize(&yaml("'  Prod '")), yaml("prod"));
+        assert_eq!(*all.normalize(&yaml("'On'")), yaml("true"));
+        assert_eq!(*all.normalize(&yaml("[no, ' x']")), yaml("[false, x]"));
+        // 只规范化值，不改动映射的键
+        assert_eq!(*all.normalize(&yaml("{A: ' B'}")), yaml("{A: b}"));
+
+        let trim = StringNormalization {
+            trim: true,
+            ..Default::default()
+        };
+        assert_eq!(*trim.normalize(&yaml("' Yes '")), yaml("'Yes'"));
+        assert_eq!(
+            *StringNormalization::default().normalize(&yaml("' a'")),
+            yaml("' a'")
+        );
+    }
+}
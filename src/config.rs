@@ -0,0 +1,221 @@
+use crate::i18n::{Msg, error_lang};
+use anyhow::{Result, anyhow};
+use serde_yaml::Value;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 项目级默认选项文件的文件名，从当前目录开始逐级向上查找
+pub const FILE_NAME: &str = ".yml-diff.yml";
+
+/// 值是路径的选项，相对路径按配置文件所在的目录解析
+const PATH_OPTIONS: &[&str] = &[
+    "rules",
+    "context-file",
+    "ignore-default-values",
+    "vars-file",
+    "validate-against",
+    "schema",
+];
+
+/// 指定要比较哪些文件的选项，每次运行都不同，不能写进配置文件
+const INPUT_OPTIONS: &[&str] = &[
+    "old",
+    "new",
+    "pair",
+    "pairs-file",
+    "stdin-pair",
+    "base",
+    "config",
+    "no-config",
+];
+
+/// 从配置文件读出的默认选项
+#[derive(Debug, Default)]
+pub struct Config {
+    /// 插在命令行参数之前的选项，命令行中再次给出的同名选项覆盖它们，可重复的选项则累加
+    pub args: Vec<OsString>,
+    /// 直接写在配置文件中的规则，写法同 --rules 的规则文件
+    pub rules: Option<Value>,
+}
+
+/// 找到本次运行使用的配置文件：`--config PATH` 优先；`--no-config` 时不使用；
+/// 否则从 `cwd` 开始逐级向上查找 [`FILE_NAME`]
+pub fn locate(args: &[OsString], cwd: &Path) -> Option<PathBuf> {
+    let mut iter = args.iter().skip(1);
+    let mut explicit = None;
+    while let Some(arg) = iter.next() {
+        let arg = arg.to_string_lossy();
+        if arg == "--no-config" {
+            return None;
+        } else if arg == "--config" {
+            explicit = iter.next().map(PathBuf::from);
+        } else if let Some(path) = arg.strip_prefix("--config=") {
+            explicit = Some(PathBuf::from(path));
+        } else if arg == "--" {
+            break;
+        }
+    }
+    explicit.or_else(|| {
+        cwd.ancestors()
+            .map(|dir| dir.join(FILE_NAME))
+            .find(|path| path.is_file())
+    })
+}
+
+/// 读取配置文件：顶层映射的每个键是一个长选项名（`-` 或 `_` 分隔均可），值的写法为
+///
+/// - `true` 写出选项本身，`false` 不写出；`null` 只写出选项，用于值可以省略的选项
+/// - 标量作为选项的值，序列中的每个元素各写一次选项
+/// - `rules` 可以是规则文件的路径，也可以直接是规则的映射
+///
+/// `is_option` 判断一个长选项名是否存在，未知的键作为错误报告，而不是留给命令行解析。
+pub fn load(path: &Path, is_option: impl Fn(&str) -> bool) -> Result<Config> {
+    let lang = error_lang();
+    let quoted = format!("{path:?}");
+    let text = fs::read_to_string(path)
+        .map_err(|e| anyhow!(lang.format(Msg::ReadConfigFileFailed, &[&e, &quoted])))?;
+    let value: Value = serde_yaml::from_str(&text)
+        .map_err(|e| anyhow!(lang.format(Msg::ParseConfigFileFailed, &[&e, &quoted])))?;
+    let map = match value {
+        Value::Mapping(map) => map,
+        Value::Null => return Ok(Config::default()),
+        _ => return Err(anyhow!(lang.format(Msg::ConfigNotMapping, &[&quoted]))),
+    };
+    let dir = path.parent().unwrap_or(Path::new(""));
+
+    let mut config = Config::default();
+    for (key, value) in map {
+        let Some(key) = key.as_str() else {
+            return Err(anyhow!(lang.format(Msg::ConfigKeyNotString, &[&quoted])));
+        };
+        let name = key.replace('_', "-");
+        if INPUT_OPTIONS.contains(&name.as_str()) {
+            return Err(anyhow!(
+                lang.format(Msg::ConfigInputOption, &[&name, &quoted])
+            ));
+        }
+        if !is_option(&name) {
+            return Err(anyhow!(
+                lang.format(Msg::ConfigUnknownOption, &[&key, &quoted])
+            ));
+        }
+        if name == "rules" && value.is_mapping() {
+            config.rules = Some(value);
+            continue;
+        }
+
+        let flag = OsString::from(format!("--{name}"));
+        let scalar = |value: &Value| -> Result<OsString> {
+            let text = match value {
+                Value::String(s) => s.clone(),
+                Value::Number(n) => n.to_string(),
+                Value::Bool(b) => b.to_string(),
+                _ => return Err(anyhow!(lang.format(Msg::ConfigNotScalar, &[&key, &quoted]))),
+            };
+            Ok(if PATH_OPTIONS.contains(&name.as_str()) {
+                dir.join(text).into_os_string()
+            } else {
+                text.into()
+            })
+        };
+        match &value {
+            Value::Bool(false) => {}
+            Value::Bool(true) | Value::Null => config.args.push(flag),
+            Value::Sequence(items) => {
+                for item in items {
+                    config.args.extend([flag.clone(), scalar(item)?]);
+                }
+            }
+            _ => config.args.extend([flag, scalar(&value)?]),
+        }
+    }
+    Ok(config)
+}
+
+/// 把配置文件中的选项插在程序名之后、命令行参数之前；第一个参数是子命令时不插入
+pub fn with_defaults(
+    mut args: Vec<OsString>,
+    config: &Config,
+    subcommands: &[&str],
+) -> Vec<OsString> {
+    if config.args.is_empty()
+        || args
+            .get(1)
+            .is_some_and(|arg| subcommands.iter().any(|s| arg == s))
+    {
+        return args;
+    }
+    let rest = args.split_off(1.min(args.len()));
+    args.extend(config.args.iter().cloned());
+    args.extend(rest);
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FILE_NAME, load, locate, with_defaults};
+    use std::ffi::OsString;
+    use std::fs;
+
+    #[test]
+    fn test_load_config() {
+        let root = std::env::temp_dir().join(format!("yml-diff-config-{}", std::process::id()));
+        let nested = root.join("a/b");
+        fs::create_dir_all(&nested).unwrap();
+        let path = root.join(FILE_NAME);
+        fs::write(
+            &path,
+            "ignore: ['*.timestamp', '**.build_id']\nformat: markdown\nquiet: true\n\
+             exit_code: false\nlist-key:\ncontext-file: docs/keys.yml\n\
+             rules: {\"**.password\": redact}\n",
+        )
+        .unwrap();
+        let args = |list: &[&str]| list.iter().map(OsString::from).collect::<Vec<_>>();
+
+        // 从子目录向上找到配置文件，--config 和 --no-config 优先
+        assert_eq!(locate(&args(&["yml-diff"]), &nested), Some(path.clone()));
+        assert_eq!(
+            locate(&args(&["yml-diff", "--config=x.yml"]), &nested),
+            Some("x.yml".into())
+        );
+        assert_eq!(locate(&args(&["yml-diff", "--no-config"]), &nested), None);
+
+        let config = load(&path, |name| name != "unknown").unwrap();
+        let context = root.join("docs/keys.yml");
+        let expected: Vec<OsString> = [
+            "--ignore",
+            "*.timestamp",
+            "--ignore",
+            "**.build_id",
+            "--format",
+            "markdown",
+            "--quiet",
+            "--list-key",
+            "--context-file",
+            context.to_str().unwrap(),
+        ]
+        .iter()
+        .map(OsString::from)
+        .collect();
+        assert_eq!(config.args, expected);
+        assert!(
+            config
+                .rules
+                .as_ref()
+                .is_some_and(|rules| rules.is_mapping())
+        );
+
+        let merged = with_defaults(args(&["yml-diff", "--format", "json"]), &config, &["apply"]);
+        assert_eq!(merged[1], "--ignore");
+        assert_eq!(merged[merged.len() - 2..], args(&["--format", "json"]));
+        let apply = args(&["yml-diff", "apply", "p.yml"]);
+        assert_eq!(with_defaults(apply.clone(), &config, &["apply"]), apply);
+
+        fs::write(&path, "unknown: 1\n").unwrap();
+        assert!(load(&path, |name| name != "unknown").is_err());
+        fs::write(&path, "old: a.yml\n").unwrap();
+        assert!(load(&path, |_| true).is_err());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
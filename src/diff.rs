@@ -0,0 +1,1848 @@
+use crate::compare::{PartialEqComparator, StringNormalization, ValueComparator};
+use crate::flatten::{FlattenOptions, flatten};
+use crate::migrate::{KeyRenames, Migration, pair_renamed_keys};
+use crate::pattern::KeyPattern;
+use crate::rename::{Rename, find_renames};
+use crate::reorder::{Reorder, align_sequences, is_under};
+use crate::rules::Rules;
+use crate::tags::TagOptions;
+use indexmap::IndexMap;
+use serde_yaml::Value;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::ops::{Deref, Range};
+
+/// 扁平键默认的段分隔符
+pub const DEFAULT_SEPARATOR: &str = ".";
+
+/// 扁平化后的键路径，按层级排序：有公共前缀时段数少的在前，下标段按数值排序
+///
+/// 键在构造时按分隔符拆成段，排序时不必反复拆分。通过 `From` 构造的键使用 [`DEFAULT_SEPARATOR`]，
+/// 其他分隔符需要用 [`ConfigKey::new`]；用不同分隔符构造的键之间的顺序没有意义。
+#[derive(Debug, Clone)]
+pub struct ConfigKey {
+    key: String,
+    /// 各段在 `key` 中的字节范围
+    segments: Vec<Range<usize>>,
+}
+
+impl Deref for ConfigKey {
+    type Target = String;
+    fn deref(&self) -> &Self::Target {
+        &self.key
+    }
+}
+
+impl From<String> for ConfigKey {
+    fn from(s: String) -> Self {
+        ConfigKey::new(s, DEFAULT_SEPARATOR)
+    }
+}
+
+impl From<&String> for ConfigKey {
+    fn from(s: &String) -> Self {
+        ConfigKey::new(s.clone(), DEFAULT_SEPARATOR)
+    }
+}
+
+impl From<&str> for ConfigKey {
+    fn from(s: &str) -> Self {
+        ConfigKey::new(s, DEFAULT_SEPARATOR)
+    }
+}
+
+impl From<ConfigKey> for String {
+    fn from(key: ConfigKey) -> Self {
+        key.key
+    }
+}
+
+impl AsRef<str> for ConfigKey {
+    fn as_ref(&self) -> &str {
+        &self.key
+    }
+}
+
+impl PartialEq for ConfigKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl Hash for ConfigKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key.hash(state);
+    }
+}
+
+impl Eq for ConfigKey {}
+
+// 实现自定义排序逻辑
+impl Ord for ConfigKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // 按照字典序比较，但考虑层级结构
+        // 使用自定义的比较逻辑来处理前缀关系
+        self.hierarchical_cmp(other)
+    }
+}
+
+impl ConfigKey {
+    /// 用 `separator` 拆分段的键，`separator` 可以是多个字符，但不能为空
+    pub fn new(key: impl Into<String>, separator: &str) -> Self {
+        let key = key.into();
+        let segments = segment_ranges(&key, separator);
+        ConfigKey { key, segments }
+    }
+
+    /// 键的各段，`a[0].b` 与 `a.0.b` 都是 `["a", "0", "b"]`；带引号的段不含两侧的引号，
+    /// `"a.b".c` 是 `["a.b", "c"]`，引号内的转义保持原样
+    pub fn segments(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.segments.iter().map(|r| &self.key[r.clone()])
+    }
+
+    /// 键中覆盖前 `depth` 段的前缀，例如 `a.b.c` 的前 2 段为 `a.b`；`depth` 超过段数时为整个键
+    pub fn section(&self, depth: usize) -> &str {
+        match depth.min(self.segments.len()) {
+            0 => "",
+            n => &self.key[..self.segments[n - 1].end],
+        }
+    }
+
+    /// 层级化比较：有公共前缀时，按段数排序（段数少的在前）
+    pub fn hierarchical_cmp(&self, other: &Self) -> Ordering {
+        let self_parts: Vec<&str> = self.segments().collect();
+        let other_parts: Vec<&str> = other.segments().collect();
+
+        // 找到公共前缀长度
+        let common_len = self_parts
+            .iter()
+            .zip(&other_parts)
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        if common_len == 0 {
+            // 无公共前缀，直接字典序比较
+            return self.key.cmp(&other.key);
+        }
+
+        // 有公共前缀时，优先按段数排序
+        match self_parts.len().cmp(&other_parts.len()) {
+            Ordering::Equal => {
+                // 段数相同，比较第一个不同的段
+                let a = self_parts.get(common_len);
+                let b = other_parts.get(common_len);
+                match (a, b) {
+                    (Some(a), Some(b)) => cmp_segment(a, b),
+                    _ => a.cmp(&b),
+                }
+                .then_with(|| self.key.cmp(&other.key)) // 兜底比较
+            }
+            order => order, // 段数不同，段数少的在前
+        }
+    }
+}
+
+/// 把以 `.` 分隔的扁平键拆成段，`a[0].b` 与 `a.0.b` 都拆成 `["a", "0", "b"]`
+pub fn key_segments(key: &str) -> Vec<&str> {
+    key_segments_with(key, DEFAULT_SEPARATOR)
+}
+
+/// 与 [`key_segments`] 相同，但使用指定的分隔符
+pub fn key_segments_with<'k>(key: &'k str, separator: &str) -> Vec<&'k str> {
+    segment_ranges(key, separator)
+        .into_iter()
+        .map(|r| &key[r])
+        .collect()
+}
+
+fn segment_ranges(key: &str, separator: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    loop {
+        let mut rest = start;
+        // 带引号的段（见 `flatten::quote_key`）：引号内的分隔符和方括号都属于键名
+        if let Some(close) = closing_quote(key, start) {
+            ranges.push(start + 1..close);
+            rest = close + 1;
+        }
+        let end = key[rest..].find(separator).map_or(key.len(), |i| rest + i);
+        // 拆出 `name[0][1]` 中的下标段
+        while let Some(open) = key[rest..end].find('[').map(|i| rest + i) {
+            let Some(close) = key[open..end].find(']').map(|i| open + i) else {
+                break;
+            };
+            if open > rest {
+                ranges.push(rest..open);
+            }
+            ranges.push(open + 1..close);
+            rest = close + 1;
+        }
+        if rest < end || start == end {
+            ranges.push(rest..end);
+        }
+        if end == key.len() {
+            return ranges;
+        }
+        start = end + separator.len();
+    }
+}
+
+/// 从 `start` 开始的段以 `"` 开头时，返回与之配对的结束引号的位置；`\` 转义下一个字符
+fn closing_quote(key: &str, start: usize) -> Option<usize> {
+    let mut chars = key[start..].char_indices();
+    if chars.next()?.1 != '"' {
+        return None;
+    }
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '"' => return Some(start + i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// 段比较：两个段都是下标时按数值比较（`2` 排在 `10` 前面），否则按字典序
+fn cmp_segment(a: &str, b: &str) -> Ordering {
+    match (a.parse::<u64>(), b.parse::<u64>()) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    }
+}
+
+impl PartialOrd for ConfigKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl DiffOptions {
+    /// 键是否在比较范围内：先按 `paths` 和 `only` 筛选，剩下的键再去掉被规则忽略的
+    pub fn includes(&self, key: &str) -> bool {
+        self.in_paths(key)
+            && (self.only.is_empty() || self.only.iter().any(|p| p.matches(key)))
+            && !self.rules.is_ignored(key)
+    }
+
+    /// 键对应的序列是否按多重集比较：命中 unordered 规则，或设置了 `ignore_array_order`
+    pub fn is_unordered(&self, key: &str) -> bool {
+        self.ignore_array_order || self.rules.is_unordered(key)
+    }
+
+    /// 键本身或它的某个上层前缀命中 `paths` 中的任一模式
+    fn in_paths(&self, key: &str) -> bool {
+        if self.paths.is_empty() {
+            return true;
+        }
+        let key = ConfigKey::new(key, &self.flatten.separator);
+        (1..=key.segments().count())
+            .any(|depth| self.paths.iter().any(|p| p.matches(key.section(depth))))
+    }
+}
+
+/// 两份配置的差异：按扁平键分成新增、删除和修改三类，每类都按 [`ConfigKey`] 的层级顺序排列，
+/// 设置了 [`DiffOptions::preserve_order`] 时按键在文档中的顺序排列
+///
+/// 值借用自参与比较的两个文档。
+///
+/// ```
+/// use serde_yaml::Value;
+/// use yml_diff::{ConfigDiff, ConfigKey, DiffOptions};
+///
+/// let old: Value = serde_yaml::from_str("db:\n  port: 80\n  user: a\n").unwrap();
+/// let new: Value = serde_yaml::from_str("db:\n  port: 81\n  host: b\n").unwrap();
+/// let diff = ConfigDiff::new(&old, &new, &DiffOptions::default());
+///
+/// assert_eq!(diff.added().keys().next().unwrap().as_str(), "db.host");
+/// assert_eq!(diff.removed().len(), 1);
+/// let (before, after) = diff.modified()[&ConfigKey::from("db.port")];
+/// assert_eq!((before, after), (&Value::from(80), &Value::from(81)));
+/// ```
+#[derive(Debug, Default)]
+pub struct ConfigDiff<'a> {
+    added: IndexMap<ConfigKey, &'a Value>,
+    removed: IndexMap<ConfigKey, &'a Value>,
+    modified: IndexMap<ConfigKey, (&'a Value, &'a Value)>,
+    /// 设置了 [`DiffOptions::preserve_order`] 时各键在文档中的位置，见 [`document_positions`]
+    positions: Option<HashMap<String, usize>>,
+    renamed: Vec<Rename>,
+    reordered: Vec<Reorder<'a>>,
+    migrated: Vec<Migration<'a>>,
+    /// 设置了 [`DiffOptions::track_unchanged`] 时两边值相等的键及其新值
+    unchanged: IndexMap<ConfigKey, &'a Value>,
+    unsorted_input: Option<UnsortedInput>,
+}
+
+/// 设置了 [`DiffOptions::assume_sorted`] 而输入的键实际没有排序时，第一处乱序的位置
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnsortedInput {
+    /// 乱序出现在旧文档中；否则在新文档中
+    pub old: bool,
+    /// 先出现的键
+    pub prev: ConfigKey,
+    /// 出现在 `prev` 之后、按顺序却不应排在它后面的键
+    pub key: ConfigKey,
+}
+
+/// 一处变更的内容
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChangeKind<'a> {
+    Added(&'a Value),
+    Removed(&'a Value),
+    Modified(&'a Value, &'a Value),
+}
+
+/// 差异中的一项变更：扁平键及其内容，供 [`ConfigDiff::retain`] 的过滤函数使用
+#[derive(Debug, Clone, Copy)]
+pub struct Change<'d, 'a> {
+    pub key: &'d str,
+    pub kind: ChangeKind<'a>,
+}
+
+impl<'a> ConfigDiff<'a> {
+    /// 比较两个已解析的文档，等同于 [`cmp_yml_vals`]
+    pub fn new(old: &'a Value, new: &'a Value, opts: &DiffOptions) -> Self {
+        cmp_yml_vals(old, new, opts)
+    }
+
+    /// 只存在于新文档中的键及其新值
+    pub fn added(&self) -> &IndexMap<ConfigKey, &'a Value> {
+        &self.added
+    }
+
+    /// 只存在于旧文档中的键及其旧值
+    pub fn removed(&self) -> &IndexMap<ConfigKey, &'a Value> {
+        &self.removed
+    }
+
+    /// 两边都存在但值不相等的键及其 (旧值, 新值)
+    pub fn modified(&self) -> &IndexMap<ConfigKey, (&'a Value, &'a Value)> {
+        &self.modified
+    }
+
+    /// 按键的顺序（设置了 [`DiffOptions::preserve_order`] 时按文档中的顺序）依次给出三类变更，
+    /// 同一个键只会出现在其中一类
+    pub fn changes(&self) -> impl Iterator<Item = Change<'_, 'a>> {
+        let added = self
+            .added
+            .iter()
+            .map(|(key, &val)| (key, ChangeKind::Added(val)));
+        let removed = (self.removed.iter()).map(|(key, &val)| (key, ChangeKind::Removed(val)));
+        let modified =
+            (self.modified.iter()).map(|(key, &(old, new))| (key, ChangeKind::Modified(old, new)));
+        let mut changes: Vec<_> = added.chain(removed).chain(modified).collect();
+        match &self.positions {
+            Some(positions) => changes.sort_by_key(|&(key, _)| positions.get(key.as_str())),
+            None => changes.sort_by_key(|&(key, _)| key),
+        }
+        changes.into_iter().map(|(key, kind)| Change { key, kind })
+    }
+
+    /// 设置了 [`DiffOptions::detect_renames`] 时找到的被重命名的子树
+    ///
+    /// 这是对删除和新增的另一种解读，其中的键仍然留在 [`removed`](Self::removed) 和
+    /// [`added`](Self::added) 中，报告时由调用方决定是否合并显示。
+    pub fn renamed(&self) -> &[Rename] {
+        &self.renamed
+    }
+
+    /// 设置了 [`DiffOptions::detect_reorders`] 时内容不变、只是在序列中换了位置的元素
+    ///
+    /// 与重命名不同，这些元素不会同时出现在 [`removed`](Self::removed) 和 [`added`](Self::added) 中。
+    pub fn reordered(&self) -> &[Reorder<'a>] {
+        &self.reordered
+    }
+
+    /// 按 [`DiffOptions::key_renames`] 配对的旧键和新键，按旧键的顺序排列
+    ///
+    /// 配对的键不再出现在 [`removed`](Self::removed) 和 [`added`](Self::added) 中；值相同的迁移也算作差异。
+    pub fn migrated(&self) -> &[Migration<'a>] {
+        &self.migrated
+    }
+
+    /// 设置了 [`DiffOptions::track_unchanged`] 时两边都存在且值相等的键及其（新文档中的）值，按键排序；
+    /// 不属于差异，[`is_empty`](Self::is_empty) 和 [`changes`](Self::changes) 都不考虑它们
+    pub fn unchanged(&self) -> &IndexMap<ConfigKey, &'a Value> {
+        &self.unchanged
+    }
+
+    /// 设置了 [`DiffOptions::assume_sorted`] 而输入的键没有排序时的第一处乱序，此时差异可能不正确
+    ///
+    /// 只有调试构建会检查，发布构建中总是 `None`。
+    pub fn unsorted_input(&self) -> Option<&UnsortedInput> {
+        self.unsorted_input.as_ref()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.modified.is_empty()
+            && self.reordered.is_empty()
+            && self.migrated.is_empty()
+    }
+
+    /// 只保留过滤函数返回 `true` 的变更
+    ///
+    /// 忽略默认值、数值变化阈值等比较后的过滤都通过它实现，自定义过滤也可以用同样的方式
+    /// 在差异返回之后追加。例如忽略 `metadata` 下的所有键：
+    ///
+    /// ```
+    /// # use yml_diff::{DiffOptions, cmp_yml_vals};
+    /// # let old: serde_yaml::Value = serde_yaml::from_str("metadata: {rev: 1}").unwrap();
+    /// # let new: serde_yaml::Value = serde_yaml::from_str("metadata: {rev: 2}").unwrap();
+    /// # let opts = DiffOptions::default();
+    /// let mut diff = cmp_yml_vals(&old, &new, &opts);
+    /// diff.retain(|change| !change.key.starts_with("metadata."));
+    /// # assert!(diff.is_empty());
+    /// ```
+    pub fn retain(&mut self, mut pred: impl FnMut(&Change) -> bool) {
+        self.added.retain(|key, &mut val| {
+            pred(&Change {
+                key,
+                kind: ChangeKind::Added(val),
+            })
+        });
+        self.removed.retain(|key, &mut val| {
+            pred(&Change {
+                key,
+                kind: ChangeKind::Removed(val),
+            })
+        });
+        self.modified.retain(|key, &mut (old, new)| {
+            pred(&Change {
+                key,
+                kind: ChangeKind::Modified(old, new),
+            })
+        });
+        // 重命名中只保留两端都还在的叶子
+        let (added, removed) = (&self.added, &self.removed);
+        for rename in &mut self.renamed {
+            rename
+                .moved
+                .retain(|(from, to)| removed.contains_key(from) && added.contains_key(to));
+        }
+        self.renamed.retain(|rename| !rename.moved.is_empty());
+    }
+}
+
+/// 控制比较过程的选项
+#[derive(Debug, Default)]
+pub struct DiffOptions {
+    pub rules: Rules,
+    pub flatten: FlattenOptions,
+    /// 非空时只比较命中其中任一模式的键
+    pub only: Vec<KeyPattern>,
+    /// 非空时只比较位于命中其中任一模式的子树下的键，如 `db.replication` 或 `services.*.image`
+    pub paths: Vec<KeyPattern>,
+    /// 扁平键 → 文档中的默认值
+    pub defaults: HashMap<String, Value>,
+    /// 数值修改的最小相对变化（百分比）
+    pub min_percent_change: Option<f64>,
+    /// 整体比较的值中要忽略的嵌套字段名
+    pub ignore_keys_in_values: Vec<String>,
+    /// 两个文件的键已排序，可以归并比较
+    pub assume_sorted: bool,
+    /// 叶子值按字符串形式比较
+    pub compare_as_strings: bool,
+    /// 整数与浮点数严格区分
+    pub strict_number_types: bool,
+    /// 值为 null 的键视为不存在
+    pub null_is_absent: bool,
+    /// 序列不按下标展开，整体按多重集比较，嵌套的序列同样忽略顺序
+    pub ignore_array_order: bool,
+    /// 至少一边是浮点数时，两个数值之差不超过该绝对误差即视为相等
+    pub epsilon: Option<f64>,
+    /// 数值按规范化后的值比较：整数与数值相同的浮点数相等，浮点数忽略 15 位有效数字之后的舍入误差
+    pub normalize_numbers: bool,
+    /// 比较前对字符串值做的规范化（大小写、首尾空白、布尔值写法）
+    pub strings: StringNormalization,
+    /// 比较前按标签的含义规范化带标签的值和时间戳字符串
+    pub tags: TagOptions,
+    /// 差异按键在文档中出现的顺序排列，而不是按键排序；`assume_sorted` 时两者相同
+    pub preserve_order: bool,
+    /// 在删除和新增的键之间找出被重命名的子树，见 [`ConfigDiff::renamed`]
+    pub detect_renames: bool,
+    /// 按元素内容对齐序列：插入或删除元素不再让后面的元素都显示为修改，
+    /// 换了位置的元素见 [`ConfigDiff::reordered`]
+    pub detect_reorders: bool,
+    /// 与 `detect_reorders` 一样对齐序列，但不报告只是换了位置的元素
+    pub ignore_reorders: bool,
+    /// 已知的键改名表：旧键只在旧文档、新键只在新文档中时配对，见 [`ConfigDiff::migrated`]
+    pub key_renames: KeyRenames,
+    /// 同时记录值没有变化的键，见 [`ConfigDiff::unchanged`]
+    pub track_unchanged: bool,
+    /// 超过 [`PARALLEL_SORT_THRESHOLD`] 条的差异使用多线程排序
+    #[cfg(feature = "parallel")]
+    pub parallel_sort: bool,
+}
+
+/// 比较两个文档，按 `opts` 扁平化后逐键比较
+pub fn cmp_yml_vals<'a>(old: &'a Value, new: &'a Value, opts: &DiffOptions) -> ConfigDiff<'a> {
+    cmp_yml_vals_with(old, new, opts, &PartialEqComparator)
+}
+
+/// 与 [`cmp_yml_vals`] 相同，但用 `cmp` 判断值是否相等
+///
+/// 规则文件中的 float-tolerance / unordered 优先生效，其余键交给 `cmp`。
+pub fn cmp_yml_vals_with<'a>(
+    old: &'a Value,
+    new: &'a Value,
+    opts: &DiffOptions,
+    cmp: &dyn ValueComparator,
+) -> ConfigDiff<'a> {
+    if opts.assume_sorted {
+        return finish_diff(cmp_sorted(old, new, opts, cmp), old, new, opts, cmp);
+    }
+
+    let mut old_key_vals = extract_key_vals(old, opts);
+    let mut new_key_vals = extract_key_vals(new, opts);
+
+    // 被规则忽略的键（以及按设置视为不存在的 null）不参与任何比较
+    old_key_vals.retain(|k, v| is_present(k, v, opts));
+    new_key_vals.retain(|k, v| is_present(k, v, opts));
+
+    let positions = (opts.preserve_order).then(|| document_positions(&old_key_vals, &new_key_vals));
+    let key = |k: &str| ConfigKey::new(k, &opts.flatten.separator);
+    let old_keys: HashSet<_> = old_key_vals.keys().collect();
+    let new_keys: HashSet<_> = new_key_vals.keys().collect();
+
+    let added_keys: Vec<&str> = new_keys
+        .difference(&old_keys)
+        .map(|&k| k.as_str())
+        .collect();
+
+    let added = ordered_map(
+        added_keys
+            .into_iter()
+            .filter_map(|k| new_key_vals.get(k).map(|&v| (key(k), v)))
+            .collect(),
+        positions.as_ref(),
+        opts,
+    );
+
+    let removed_keys: Vec<&str> = old_keys
+        .difference(&new_keys)
+        .map(|&k| k.as_str())
+        .collect();
+
+    let removed = ordered_map(
+        removed_keys
+            .into_iter()
+            .filter_map(|k| old_key_vals.get(k).map(|&v| (key(k), v)))
+            .collect(),
+        positions.as_ref(),
+        opts,
+    );
+
+    let mut unchanged = Vec::new();
+    let modified = ordered_map(
+        old_keys
+            .intersection(&new_keys)
+            .filter_map(|&k| match (old_key_vals.get(k), new_key_vals.get(k)) {
+                (Some(&old), Some(&new)) if !values_equal(k, old, new, opts, cmp) => {
+                    Some((key(k), (old, new)))
+                }
+                (Some(_), Some(&new)) if opts.track_unchanged => {
+                    unchanged.push((key(k), new));
+                    None
+                }
+                _ => None,
+            })
+            .collect(),
+        positions.as_ref(),
+        opts,
+    );
+
+    let diff = ConfigDiff {
+        added,
+        removed,
+        modified,
+        positions,
+        renamed: Vec::new(),
+        reordered: Vec::new(),
+        migrated: Vec::new(),
+        unchanged: sorted_map(unchanged, opts),
+        unsorted_input: None,
+    };
+    finish_diff(diff, old, new, opts, cmp)
+}
+
+/// 扁平键是否参与比较：在比较范围内，且设置了 `null_is_absent` 时值不为 null
+fn is_present(key: &str, val: &Value, opts: &DiffOptions) -> bool {
+    if opts.null_is_absent && val.is_null() {
+        return false;
+    }
+    opts.includes(key)
+}
+
+/// 对比较结果做的后处理：对齐序列、去掉默认值、过滤变化过小的数值修改、找出重命名
+fn finish_diff<'a>(
+    mut diff: ConfigDiff<'a>,
+    old: &'a Value,
+    new: &'a Value,
+    opts: &DiffOptions,
+    cmp: &dyn ValueComparator,
+) -> ConfigDiff<'a> {
+    if opts.detect_reorders || opts.ignore_reorders {
+        let reordered = realign_sequences(&mut diff, old, new, opts, cmp);
+        if !opts.ignore_reorders {
+            diff.reordered = reordered;
+        }
+    }
+    if !opts.key_renames.is_empty() {
+        diff.migrated = migrate_keys(&mut diff, opts, cmp);
+    }
+    filter_diff(&mut diff, opts, cmp);
+    if opts.detect_renames {
+        diff.renamed = find_renames(&diff, old, new, opts);
+    }
+    diff
+}
+
+/// 按元素内容对齐两边的序列，重新计算下标错开的元素的变更，返回换了位置的元素
+///
+/// 按下标比较时，在序列开头插入一个元素会让后面的每个元素都显示为修改。对齐之后，
+/// 下标错开的元素中配对成功的不再有变更，没有配对的按整个元素删除或新增。
+fn realign_sequences<'a>(
+    diff: &mut ConfigDiff<'a>,
+    old: &'a Value,
+    new: &'a Value,
+    opts: &DiffOptions,
+    cmp: &dyn ValueComparator,
+) -> Vec<Reorder<'a>> {
+    let alignments = align_sequences(old, new, opts, cmp);
+    if alignments.is_empty() {
+        return Vec::new();
+    }
+    let flatten_opts = compare_flatten_options(opts);
+    let separator = &opts.flatten.separator;
+    let key = |k: &str| ConfigKey::new(k, separator);
+
+    let (mut shifted, mut old_prefixes, mut new_prefixes) = (Vec::new(), Vec::new(), Vec::new());
+    let mut reordered = Vec::new();
+    for alignment in &alignments {
+        let path = &alignment.sequence;
+        for i in alignment.shifted() {
+            shifted.push(flatten_opts.index_key(path, i));
+            if alignment.old_to_new.get(i) == Some(&None) {
+                old_prefixes.push(flatten_opts.index_key(path, i));
+            }
+            if alignment.new_to_old.get(i) == Some(&None) {
+                new_prefixes.push(flatten_opts.index_key(path, i));
+            }
+        }
+        reordered.extend(alignment.moves.iter().map(|&(from, to)| Reorder {
+            sequence: key(path),
+            from,
+            to,
+            value: &alignment.new[to],
+        }));
+    }
+
+    let under = |k: &str, prefixes: &[String]| prefixes.iter().any(|p| is_under(k, p, separator));
+    diff.retain(|change| !under(change.key, &shifted));
+    diff.unchanged.retain(|k, _| !under(k.as_str(), &shifted));
+    let entries = |doc: &'a Value, prefixes: &[String]| {
+        flatten(doc, &flatten_opts)
+            .filter(|(k, v)| is_present(k, v, opts) && under(k, prefixes))
+            .map(|(k, v)| (key(&k), v))
+            .collect::<Vec<_>>()
+    };
+    let positions = diff.positions.as_ref();
+    let merge = |map: &mut IndexMap<ConfigKey, &'a Value>, entries: Vec<(ConfigKey, &'a Value)>| {
+        let merged = std::mem::take(map).into_iter().chain(entries).collect();
+        *map = ordered_map(merged, positions, opts);
+    };
+    merge(&mut diff.removed, entries(old, &old_prefixes));
+    merge(&mut diff.added, entries(new, &new_prefixes));
+    reordered
+}
+
+/// 把改名表中配对的删除和新增从差异中取出，按新键的比较规则比较它们的值
+fn migrate_keys<'a>(
+    diff: &mut ConfigDiff<'a>,
+    opts: &DiffOptions,
+    cmp: &dyn ValueComparator,
+) -> Vec<Migration<'a>> {
+    let pairs = pair_renamed_keys(
+        &diff.removed,
+        &diff.added,
+        &opts.key_renames,
+        &opts.flatten.separator,
+    );
+    pairs
+        .into_iter()
+        .filter_map(|(from, to)| {
+            let old = diff.removed.shift_remove(&from)?;
+            let new = diff.added.shift_remove(&to)?;
+            let changed = !values_equal(&to, old, new, opts, cmp);
+            Some(Migration {
+                from,
+                to,
+                old,
+                new,
+                changed,
+            })
+        })
+        .collect()
+}
+
+/// 去掉默认值，过滤变化过小的数值修改
+fn filter_diff(diff: &mut ConfigDiff, opts: &DiffOptions, cmp: &dyn ValueComparator) {
+    strip_defaults(diff, opts, cmp);
+    if let Some(min_percent) = opts.min_percent_change {
+        diff.retain(|change| match change.kind {
+            ChangeKind::Modified(old, new) => !is_insignificant_change(old, new, min_percent),
+            _ => true,
+        });
+    }
+}
+
+/// 比较两份已经扁平化的条目，例如 [`flatten_reader`](crate::stream::flatten_reader) 的结果
+///
+/// 条目先按键排序再归并，不建立哈希表。扁平化方式由调用方决定，`opts.flatten` 只用于拆分键路径；
+/// 没有完整的文档，因此不支持 [`DiffOptions::detect_renames`] 和 [`DiffOptions::preserve_order`]。
+pub fn cmp_flat_entries<'a>(
+    old: &'a [(String, Value)],
+    new: &'a [(String, Value)],
+    opts: &DiffOptions,
+) -> ConfigDiff<'a> {
+    // 归并需要全序，按键的字节序排序；报告中的顺序最后再按 ConfigKey 排
+    let sorted = |entries: &'a [(String, Value)]| {
+        let mut entries: Vec<(&'a str, &'a Value)> = entries
+            .iter()
+            .filter(|(k, v)| is_present(k, v, opts))
+            .map(|(k, v)| (k.as_str(), v))
+            .collect();
+        entries.sort_unstable_by_key(|&(k, _)| k);
+        entries.into_iter().peekable()
+    };
+    let key = |k: &str| ConfigKey::new(k, &opts.flatten.separator);
+    let mut old_iter = sorted(old);
+    let mut new_iter = sorted(new);
+
+    let (mut added, mut removed, mut modified) = (Vec::new(), Vec::new(), Vec::new());
+    let mut unchanged = Vec::new();
+    loop {
+        let order = match (old_iter.peek(), new_iter.peek()) {
+            (None, None) => break,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some((a, _)), Some((b, _))) => a.cmp(b),
+        };
+        match order {
+            Ordering::Less => {
+                let (k, v) = old_iter.next().expect("已经 peek 过");
+                removed.push((key(k), v));
+            }
+            Ordering::Greater => {
+                let (k, v) = new_iter.next().expect("已经 peek 过");
+                added.push((key(k), v));
+            }
+            Ordering::Equal => {
+                let (k, old) = old_iter.next().expect("已经 peek 过");
+                let (_, new) = new_iter.next().expect("已经 peek 过");
+                if !values_equal(k, old, new, opts, &PartialEqComparator) {
+                    modified.push((key(k), (old, new)));
+                } else if opts.track_unchanged {
+                    unchanged.push((key(k), new));
+                }
+            }
+        }
+    }
+
+    let mut diff = ConfigDiff {
+        added: sorted_map(added, opts),
+        removed: sorted_map(removed, opts),
+        modified: sorted_map(modified, opts),
+        positions: None,
+        renamed: Vec::new(),
+        reordered: Vec::new(),
+        migrated: Vec::new(),
+        unchanged: sorted_map(unchanged, opts),
+        unsorted_input: None,
+    };
+    filter_diff(&mut diff, opts, &PartialEqComparator);
+    diff
+}
+
+/// 超过这个条数的差异才值得多线程排序，更少时线程调度的开销比排序本身还大
+#[cfg(feature = "parallel")]
+const PARALLEL_SORT_THRESHOLD: usize = 10_000;
+
+/// 把差异条目按键排序后放入 IndexMap
+///
+/// 几十万条差异时排序是主要开销，数量足够多时用 rayon 并行排序。
+#[cfg_attr(not(feature = "parallel"), allow(unused_variables))]
+fn sorted_map<V: Send>(
+    mut entries: Vec<(ConfigKey, V)>,
+    opts: &DiffOptions,
+) -> IndexMap<ConfigKey, V> {
+    #[cfg(feature = "parallel")]
+    if opts.parallel_sort && entries.len() > PARALLEL_SORT_THRESHOLD {
+        use rayon::slice::ParallelSliceMut;
+        entries.par_sort_by(|(a, _), (b, _)| a.cmp(b));
+        return entries.into_iter().collect();
+    }
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+    entries.into_iter().collect()
+}
+
+/// 按 `positions` 给出的文档位置排列差异条目，没有位置信息时按键排序
+fn ordered_map<V: Send>(
+    mut entries: Vec<(ConfigKey, V)>,
+    positions: Option<&HashMap<String, usize>>,
+    opts: &DiffOptions,
+) -> IndexMap<ConfigKey, V> {
+    let Some(positions) = positions else {
+        return sorted_map(entries, opts);
+    };
+    entries.sort_by_key(|(key, _)| positions.get(key.as_str()));
+    entries.into_iter().collect()
+}
+
+/// 两份文档的键合并后各自的位置
+///
+/// 新文档的键保持在新文档中的顺序；只在旧文档中出现的键排在它之前最近一个两边共有的键后面，
+/// 这样删除的键会出现在它原来所在的位置附近。
+fn document_positions(
+    old: &IndexMap<String, &Value>,
+    new: &IndexMap<String, &Value>,
+) -> HashMap<String, usize> {
+    // (锚点, 旧文档中的下标, 键)：新文档的键以自身为锚点，下标记为 0，排在跟随它的删除键之前
+    let mut order: Vec<(usize, usize, &str)> = (new.keys().enumerate())
+        .map(|(i, k)| (i + 1, 0, k.as_str()))
+        .collect();
+    let mut anchor = 0;
+    for (j, k) in old.keys().enumerate() {
+        match new.get_index_of(k) {
+            Some(i) => anchor = i + 1,
+            None => order.push((anchor, j + 1, k)),
+        }
+    }
+    order.sort_unstable();
+    (order.into_iter().enumerate())
+        .map(|(pos, (_, _, k))| (k.to_string(), pos))
+        .collect()
+}
+
+/// 两个数值的相对变化是否小于 `min_percent`；非数值的修改总是保留
+///
+/// 旧值为 0 时无法计算百分比，只要新值不为 0 就视为显著变化。
+fn is_insignificant_change(old: &Value, new: &Value, min_percent: f64) -> bool {
+    let (Some(a), Some(b)) = (old.as_f64(), new.as_f64()) else {
+        return false;
+    };
+    if a == 0.0 {
+        return b == 0.0;
+    }
+    (b - a).abs() / a.abs() * 100.0 < min_percent
+}
+
+/// 过滤掉只是显式写出默认值、或者改回默认值的差异
+fn strip_defaults(diff: &mut ConfigDiff, opts: &DiffOptions, cmp: &dyn ValueComparator) {
+    if opts.defaults.is_empty() {
+        return;
+    }
+    let is_default = |key: &str, val: &Value| {
+        opts.defaults
+            .get(key)
+            .is_some_and(|default| values_equal(key, val, default, opts, cmp))
+    };
+    diff.retain(|change| match change.kind {
+        ChangeKind::Added(new) | ChangeKind::Modified(_, new) => !is_default(change.key, new),
+        ChangeKind::Removed(_) => true,
+    });
+}
+
+/// 按照规则判断同一个键的新旧值是否相等
+pub fn values_equal(
+    key: &str,
+    old: &Value,
+    new: &Value,
+    opts: &DiffOptions,
+    cmp: &dyn ValueComparator,
+) -> bool {
+    let old = &*strip_nested_keys(old, &opts.ignore_keys_in_values);
+    let new = &*strip_nested_keys(new, &opts.ignore_keys_in_values);
+    let old = &*opts.tags.normalize(old);
+    let new = &*opts.tags.normalize(new);
+    let old = &*opts.strings.normalize(old);
+    let new = &*opts.strings.normalize(new);
+    if opts.strict_number_types
+        && number_kind(old)
+            .zip(number_kind(new))
+            .is_some_and(|(a, b)| a != b)
+    {
+        return false;
+    }
+    if let Some(tolerance) = opts.rules.tolerance(key)
+        && let (Some(a), Some(b)) = (old.as_f64(), new.as_f64())
+    {
+        return (a - b).abs() <= tolerance;
+    }
+    if let Some(epsilon) = opts.epsilon
+        && let Some((a, b)) = float_pair(old, new)
+    {
+        return (a - b).abs() <= epsilon;
+    }
+    if opts.normalize_numbers
+        && let (Value::Number(a), Value::Number(b)) = (old, new)
+    {
+        return normalized_number(a) == normalized_number(b);
+    }
+    if opts.rules.is_unordered(key)
+        && let (Value::Sequence(a), Value::Sequence(b)) = (old, new)
+    {
+        return unordered_eq(a, b, |x, y| x == y);
+    }
+    if opts.ignore_array_order
+        && [old, new]
+            .iter()
+            .any(|v| v.is_sequence() || v.is_mapping() || matches!(v, Value::Tagged(_)))
+    {
+        return ignore_order_eq(old, new);
+    }
+    if opts.compare_as_strings {
+        return string_eq(old, new);
+    }
+    cmp.equal(key, old, new)
+}
+
+/// 两个数值中至少有一个是浮点数时，返回二者的浮点值；两个整数之间总是精确比较
+pub fn float_pair(a: &Value, b: &Value) -> Option<(f64, f64)> {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) if x.is_f64() || y.is_f64() => {
+            Some((x.as_f64()?, y.as_f64()?))
+        }
+        _ => None,
+    }
+}
+
+/// 数值的规范形式：整数保持原样，浮点数保留 15 位有效数字，整数值的浮点数转成整数
+///
+/// 15 位是 f64 能够精确往返的十进制位数，`0.1 + 0.2` 的结果 `0.30000000000000004` 规范化后就是 `0.3`。
+fn normalized_number(n: &serde_yaml::Number) -> String {
+    match n.as_f64() {
+        Some(f) if n.is_f64() && f.is_finite() => {
+            let rounded: f64 = format!("{f:.14e}").parse().unwrap_or(f);
+            if rounded.fract() == 0.0 && rounded.abs() < 2f64.powi(63) {
+                (rounded as i64).to_string()
+            } else {
+                rounded.to_string()
+            }
+        }
+        _ => n.to_string(),
+    }
+}
+
+/// 把叶子值转成 [`get_val_string`] 的形式后比较；映射按键的字符串形式配对
+fn string_eq(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Sequence(a), Value::Sequence(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(x, y)| string_eq(x, y))
+        }
+        (Value::Mapping(a), Value::Mapping(b)) => {
+            let b: HashMap<Cow<str>, &Value> =
+                b.iter().map(|(k, v)| (get_val_string(k), v)).collect();
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(k, x)| b.get(&get_val_string(k)).is_some_and(|y| string_eq(x, y)))
+        }
+        (Value::Sequence(_) | Value::Mapping(_), _)
+        | (_, Value::Sequence(_) | Value::Mapping(_)) => false,
+        _ => get_val_string(a) == get_val_string(b),
+    }
+}
+
+/// 去掉值内部任意层级的映射中名为 `keys` 的字段；没有需要去掉的字段时不复制
+fn strip_nested_keys<'a>(val: &'a Value, keys: &[String]) -> Cow<'a, Value> {
+    fn contains(val: &Value, keys: &[String]) -> bool {
+        match val {
+            Value::Mapping(m) => m.iter().any(|(k, v)| {
+                k.as_str().is_some_and(|k| keys.iter().any(|i| i == k)) || contains(v, keys)
+            }),
+            Value::Sequence(seq) => seq.iter().any(|v| contains(v, keys)),
+            Value::Tagged(t) => contains(&t.value, keys),
+            _ => false,
+        }
+    }
+    fn strip(val: &mut Value, keys: &[String]) {
+        match val {
+            Value::Mapping(m) => {
+                m.retain(|k, _| !k.as_str().is_some_and(|k| keys.iter().any(|i| i == k)));
+                m.values_mut().for_each(|v| strip(v, keys));
+            }
+            Value::Sequence(seq) => seq.iter_mut().for_each(|v| strip(v, keys)),
+            Value::Tagged(t) => strip(&mut t.value, keys),
+            _ => {}
+        }
+    }
+
+    if keys.is_empty() || !contains(val, keys) {
+        return Cow::Borrowed(val);
+    }
+    let mut val = val.clone();
+    strip(&mut val, keys);
+    Cow::Owned(val)
+}
+
+/// 把两个序列当作多重集比较：元素相同且每个元素出现次数相同，元素之间用 `eq` 比较
+fn unordered_eq(a: &[Value], b: &[Value], eq: impl Fn(&Value, &Value) -> bool) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut used = vec![false; b.len()];
+    a.iter().all(|x| {
+        let found = b.iter().enumerate().find(|&(i, y)| !used[i] && eq(x, y));
+        match found {
+            Some((i, _)) => {
+                used[i] = true;
+                true
+            }
+            None => false,
+        }
+    })
+}
+
+/// 按多重集比较的两个序列之间真正的变化：只在旧序列中的元素和只在新序列中的元素，
+/// 各自保持原来的顺序；只是顺序不同的元素不在其中
+///
+/// 重复的元素按个数计算，`[a, a, b]` → `[a, b]` 删除了一个 `a`。嵌套的序列同样忽略顺序。
+pub fn multiset_changes<'a>(
+    old: &'a [Value],
+    new: &'a [Value],
+) -> (Vec<&'a Value>, Vec<&'a Value>) {
+    let mut used = vec![false; new.len()];
+    let mut removed = Vec::new();
+    for x in old {
+        match (new.iter().enumerate()).find(|&(i, y)| !used[i] && ignore_order_eq(x, y)) {
+            Some((i, _)) => used[i] = true,
+            None => removed.push(x),
+        }
+    }
+    let added = (new.iter().zip(used))
+        .filter(|&(_, used)| !used)
+        .map(|(y, _)| y)
+        .collect();
+    (removed, added)
+}
+
+/// 忽略所有层级上序列的元素顺序比较两个值；重复元素的个数仍然要相同
+fn ignore_order_eq(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Sequence(a), Value::Sequence(b)) => unordered_eq(a, b, ignore_order_eq),
+        (Value::Mapping(a), Value::Mapping(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(k, x)| b.get(k).is_some_and(|y| ignore_order_eq(x, y)))
+        }
+        (Value::Tagged(a), Value::Tagged(b)) => {
+            a.tag == b.tag && ignore_order_eq(&a.value, &b.value)
+        }
+        _ => a == b,
+    }
+}
+
+/// --assume-sorted 的比较方式：同时遍历两个扁平键流，像归并排序一样逐个比较
+///
+/// 映射的键都已排序时，扁平化按文档顺序输出的键按段比较也是有序的，
+/// 因此只需一次线性扫描，不必为两个文件各建一张哈希表。
+fn cmp_sorted<'a>(
+    old: &'a Value,
+    new: &'a Value,
+    opts: &DiffOptions,
+    cmp: &dyn ValueComparator,
+) -> ConfigDiff<'a> {
+    let flatten_opts = compare_flatten_options(opts);
+    let stream = |value| {
+        flatten(value, &flatten_opts)
+            .filter(|(k, v)| is_present(k, v, opts))
+            .map(|(k, v)| (ConfigKey::new(k, &opts.flatten.separator), v))
+            .peekable()
+    };
+    let mut old_iter = stream(old);
+    let mut new_iter = stream(new);
+
+    let (mut added, mut removed, mut modified) = (Vec::new(), Vec::new(), Vec::new());
+    let mut unchanged = Vec::new();
+    #[cfg(debug_assertions)]
+    let (mut old_check, mut new_check) = (SortCheck::new(true), SortCheck::new(false));
+    loop {
+        let order = match (old_iter.peek(), new_iter.peek()) {
+            (None, None) => break,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some((a, _)), Some((b, _))) => cmp_key_stream(a, b),
+        };
+        match order {
+            Ordering::Less => {
+                let (k, v) = old_iter.next().expect("已经 peek 过");
+                #[cfg(debug_assertions)]
+                old_check.check(&k);
+                removed.push((k, v));
+            }
+            Ordering::Greater => {
+                let (k, v) = new_iter.next().expect("已经 peek 过");
+                #[cfg(debug_assertions)]
+                new_check.check(&k);
+                added.push((k, v));
+            }
+            Ordering::Equal => {
+                let (k, old) = old_iter.next().expect("已经 peek 过");
+                let (_, new) = new_iter.next().expect("已经 peek 过");
+                #[cfg(debug_assertions)]
+                {
+                    old_check.check(&k);
+                    new_check.check(&k);
+                }
+                if !values_equal(&k, old, new, opts, cmp) {
+                    modified.push((k, (old, new)));
+                } else if opts.track_unchanged {
+                    unchanged.push((k, new));
+                }
+            }
+        }
+    }
+
+    ConfigDiff {
+        added: sorted_map(added, opts),
+        removed: sorted_map(removed, opts),
+        modified: sorted_map(modified, opts),
+        positions: None,
+        renamed: Vec::new(),
+        reordered: Vec::new(),
+        migrated: Vec::new(),
+        unchanged: sorted_map(unchanged, opts),
+        #[cfg(debug_assertions)]
+        unsorted_input: old_check.found.or(new_check.found),
+        #[cfg(not(debug_assertions))]
+        unsorted_input: None,
+    }
+}
+
+/// 扁平键流的顺序：逐段比较，数字段按数值比较
+fn cmp_key_stream(a: &ConfigKey, b: &ConfigKey) -> Ordering {
+    a.segments()
+        .zip(b.segments())
+        .map(|(x, y)| cmp_segment(x, y))
+        .find(|o| o.is_ne())
+        .unwrap_or_else(|| a.segments.len().cmp(&b.segments.len()))
+}
+
+/// 调试构建中检查键流确实有序，记下不满足 [`DiffOptions::assume_sorted`] 假定的第一处
+#[cfg(debug_assertions)]
+struct SortCheck {
+    old: bool,
+    last: Option<ConfigKey>,
+    found: Option<UnsortedInput>,
+}
+
+#[cfg(debug_assertions)]
+impl SortCheck {
+    fn new(old: bool) -> Self {
+        SortCheck {
+            old,
+            last: None,
+            found: None,
+        }
+    }
+
+    fn check(&mut self, key: &ConfigKey) {
+        if let Some(prev) = self.last.take()
+            && self.found.is_none()
+            && cmp_key_stream(&prev, key).is_ge()
+        {
+            self.found = Some(UnsortedInput {
+                old: self.old,
+                prev,
+                key: key.clone(),
+            });
+        }
+        self.last = Some(key.clone());
+    }
+}
+
+/// 按比较时的方式扁平化文档：在 [`DiffOptions::flatten`] 的基础上，需要忽略顺序比较的序列整体保留
+pub fn extract_key_vals<'a>(value: &'a Value, opts: &DiffOptions) -> IndexMap<String, &'a Value> {
+    flatten(value, &compare_flatten_options(opts)).collect()
+}
+
+/// 比较时实际使用的扁平化选项
+pub fn compare_flatten_options(opts: &DiffOptions) -> FlattenOptions {
+    // 命中 unordered 规则的序列需要整体比较，不能按下标展开
+    let mut flatten_opts = opts.flatten.clone();
+    flatten_opts.opaque.extend(opts.rules.unordered_patterns());
+    if opts.ignore_array_order {
+        flatten_opts.index_style = None;
+    }
+    flatten_opts
+}
+
+/// 值在报告中的显示形式
+///
+/// 标量直接借用或按规范写法输出；序列写作 `[a, b]`，映射按键排序后写作 `{a: 1, b: 2}`，
+/// 带标签的值与 YAML 中的写法相同，写作 `!secret value`。
+pub fn get_val_string(val: &Value) -> Cow<'_, str> {
+    match val {
+        Value::Null => Cow::Borrowed("null"),
+        Value::Bool(b) => {
+            if *b {
+                Cow::Borrowed("true")
+            } else {
+                Cow::Borrowed("false")
+            }
+        }
+        Value::Number(n) => Cow::Owned(n.to_string()),
+        Value::String(s) => Cow::Borrowed(s),
+        Value::Sequence(seq) => {
+            let mut prefix = String::from("[");
+            let arr: Vec<_> = seq.iter().map(|v| get_val_string(v)).collect();
+            let arr_str = arr.join(", ");
+            prefix.push_str(&arr_str);
+            prefix.push(']');
+            Cow::Owned(prefix)
+        }
+        Value::Mapping(m) => {
+            // 按键排序，保证同一个映射每次都渲染成相同的字符串
+            let mut entries: Vec<(Cow<str>, Cow<str>)> = m
+                .iter()
+                .map(|(k, v)| (get_val_string(k), get_val_string(v)))
+                .collect();
+            entries.sort();
+            let entries: Vec<String> = entries.iter().map(|(k, v)| format!("{k}: {v}")).collect();
+            Cow::Owned(format!("{{{}}}", entries.join(", ")))
+        }
+        Value::Tagged(t) => Cow::Owned(format!("{} {}", t.tag, get_val_string(&t.value))),
+    }
+}
+
+/// 数值是整数还是浮点数；非数值返回 `None`
+pub fn number_kind(val: &Value) -> Option<&'static str> {
+    match val {
+        Value::Number(n) if n.is_f64() => Some("浮点数"),
+        Value::Number(_) => Some("整数"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flatten::IndexStyle;
+    use crate::rules::RuleAction;
+
+    #[test]
+    fn test_rules_control_comparison() {
+        let old: Value = serde_yaml::from_str(
+            "build:\n  time: 1\ntimeouts:\n  read: 1.0\nhosts: [a, b]\ndb:\n  password: x\n",
+        )
+        .unwrap();
+        let new: Value = serde_yaml::from_str(
+            "build:\n  time: 2\ntimeouts:\n  read: 1.005\nhosts: [b, a]\ndb:\n  password: y\n",
+        )
+        .unwrap();
+        let rules: Value = serde_yaml::from_str(
+            "\"*.password\": redact\n\"timeouts.*\": float-tolerance=0.01\nhosts: unordered\nbuild.time: ignore\n",
+        )
+        .unwrap();
+        let opts = DiffOptions {
+            rules: Rules::from_value(&rules).unwrap(),
+            ..Default::default()
+        };
+
+        let diff = cmp_yml_vals(&old, &new, &opts);
+
+        assert!(diff.added().is_empty());
+        assert!(diff.removed().is_empty());
+        let modified: Vec<&str> = diff.modified().keys().map(|k| k.as_str()).collect();
+        assert_eq!(modified, vec!["db.password"]);
+        assert!(opts.rules.is_redacted("db.password"));
+    }
+
+    #[test]
+    fn test_index_style_dot_and_bracket() {
+        let value: Value = serde_yaml::from_str("servers:\n  - host: a\n  - host: b\n").unwrap();
+
+        let dot = DiffOptions {
+            flatten: FlattenOptions {
+                index_style: Some(IndexStyle::Dot),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut keys: Vec<_> = extract_key_vals(&value, &dot).into_keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["servers.0.host", "servers.1.host"]);
+
+        let bracket = DiffOptions {
+            flatten: FlattenOptions {
+                index_style: Some(IndexStyle::Bracket),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut keys: Vec<_> = extract_key_vals(&value, &bracket).into_keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["servers[0].host", "servers[1].host"]);
+
+        // 默认按 bracket 写法展开；不展开序列时，序列作为整体
+        let mut keys: Vec<_> = extract_key_vals(&value, &DiffOptions::default())
+            .into_keys()
+            .collect();
+        keys.sort();
+        assert_eq!(keys, vec!["servers[0].host", "servers[1].host"]);
+
+        let whole = DiffOptions {
+            flatten: FlattenOptions {
+                index_style: None,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let keys: Vec<_> = extract_key_vals(&value, &whole).into_keys().collect();
+        assert_eq!(keys, vec!["servers"]);
+    }
+
+    #[test]
+    fn test_index_keys_sort_numerically() {
+        for (a, b) in [("a[2]", "a[10]"), ("a.2", "a.10"), ("a[2].x", "a[10].x")] {
+            assert!(ConfigKey::from(a) < ConfigKey::from(b), "{a} < {b}");
+        }
+        // 两种写法拆出相同的段
+        assert_eq!(key_segments("a[0].b[1]"), key_segments("a.0.b.1"));
+    }
+
+    #[test]
+    fn test_null_is_absent() {
+        let missing: Value = serde_yaml::from_str("host: a\n").unwrap();
+        let null: Value = serde_yaml::from_str("host: a\nretries: null\n").unwrap();
+        let set: Value = serde_yaml::from_str("host: a\nretries: 3\n").unwrap();
+
+        let mut opts = DiffOptions::default();
+        assert_eq!(cmp_yml_vals(&null, &missing, &opts).removed().len(), 1);
+        assert_eq!(cmp_yml_vals(&missing, &null, &opts).added().len(), 1);
+
+        opts.null_is_absent = true;
+        for assume_sorted in [false, true] {
+            opts.assume_sorted = assume_sorted;
+            // 旧文件为 null、新文件为 null、两边都为 null
+            assert!(cmp_yml_vals(&null, &missing, &opts).is_empty());
+            assert!(cmp_yml_vals(&missing, &null, &opts).is_empty());
+            assert!(cmp_yml_vals(&null, &null, &opts).is_empty());
+            // null 换成具体的值仍然是新增
+            let diff = cmp_yml_vals(&null, &set, &opts);
+            assert_eq!(diff.added().keys().next().unwrap().as_str(), "retries");
+            assert!(diff.modified().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_ignore_array_order() {
+        let old: Value = serde_yaml::from_str(
+            "hosts: [a, b, c]\nrules:\n  - {name: x, ports: [80, 443]}\n  - {name: y, ports: [1]}\ndup: [a, a, b]\n",
+        )
+        .unwrap();
+        let new: Value = serde_yaml::from_str(
+            "hosts: [c, a, b]\nrules:\n  - {name: y, ports: [1]}\n  - {ports: [443, 80], name: x}\ndup: [a, b, b]\n",
+        )
+        .unwrap();
+
+        let mut opts = DiffOptions::default();
+        assert!(cmp_yml_vals(&old, &new, &opts).modified().len() > 3);
+
+        opts.ignore_array_order = true;
+        let diff = cmp_yml_vals(&old, &new, &opts);
+        // 重复元素的个数不同仍然算修改
+        let modified: Vec<&str> = diff.modified().keys().map(|k| k.as_str()).collect();
+        assert_eq!(modified, vec!["dup"]);
+        assert!(diff.added().is_empty() && diff.removed().is_empty());
+    }
+
+    #[test]
+    fn test_sequence_elements_are_compared_individually() {
+        let servers = |changed: usize, count: usize| -> Value {
+            let list: Vec<String> = (0..count)
+                .map(|i| {
+                    let host = if i == changed { "new" } else { "old" };
+                    format!("{{host: {host}-{i}, port: 80}}")
+                })
+                .collect();
+            serde_yaml::from_str(&format!("servers: [{}]", list.join(", "))).unwrap()
+        };
+        let old = servers(usize::MAX, 50);
+        let new = servers(7, 51);
+        let diff = cmp_yml_vals(&old, &new, &DiffOptions::default());
+
+        let modified: Vec<&str> = diff.modified().keys().map(|k| k.as_str()).collect();
+        assert_eq!(modified, vec!["servers[7].host"]);
+        let added: Vec<&str> = diff.added().keys().map(|k| k.as_str()).collect();
+        assert_eq!(added, vec!["servers[50].host", "servers[50].port"]);
+        assert!(diff.removed().is_empty());
+    }
+
+    #[test]
+    fn test_epsilon() {
+        let num = |s: &str| serde_yaml::from_str::<Value>(s).unwrap();
+        let equal = |a: &str, b: &str, epsilon: f64| {
+            let opts = DiffOptions {
+                epsilon: Some(epsilon),
+                ..Default::default()
+            };
+            values_equal("k", &num(a), &num(b), &opts, &PartialEqComparator)
+        };
+
+        assert!(equal("0.1", "0.10000000001", 1e-9));
+        assert!(!equal("0.1", "0.1001", 1e-9));
+        // 误差边界本身算相等
+        assert!(equal("1.0", "1.5", 0.5));
+        assert!(!equal("1.0", "1.5000001", 0.5));
+        // 整数与浮点数按数值比较，两个整数总是精确比较
+        assert!(equal("1", "1.0", 0.0));
+        assert!(equal("1", "1.25", 0.5));
+        assert!(!equal("1", "2", 5.0));
+        // 非数值不受影响
+        assert!(!equal("\"0.1\"", "0.1", 1.0));
+    }
+
+    #[test]
+    fn test_normalize_numbers() {
+        let num = |s: &str| serde_yaml::from_str::<Value>(s).unwrap();
+        let equal = |a: &str, b: &str, normalize_numbers: bool| {
+            let opts = DiffOptions {
+                normalize_numbers,
+                ..Default::default()
+            };
+            values_equal("k", &num(a), &num(b), &opts, &PartialEqComparator)
+        };
+
+        assert!(!equal("1", "1.0", false));
+        assert!(equal("1", "1.0", true));
+        assert!(!equal("0.30000000000000004", "0.3", false));
+        assert!(equal("0.30000000000000004", "0.3", true));
+        assert!(equal("1.5e3", "1500", true));
+        // 真正的数值变化和非数值不受影响
+        assert!(!equal("0.3", "0.31", true));
+        assert!(!equal("1", "2", true));
+        assert!(!equal("\"1\"", "1.0", true));
+    }
+
+    #[test]
+    fn test_get_val_string_mapping_is_sorted() {
+        let a: Value = serde_yaml::from_str("{z: 1, a: {y: [1, 2], b: true}, m: null}").unwrap();
+        let b: Value = serde_yaml::from_str("{m: null, a: {b: true, y: [1, 2]}, z: 1}").unwrap();
+        assert_eq!(
+            get_val_string(&a),
+            "{a: {b: true, y: [1, 2]}, m: null, z: 1}"
+        );
+        assert_eq!(get_val_string(&a), get_val_string(&b));
+        assert!(matches!(
+            get_val_string(&a["a"]["b"]),
+            Cow::Borrowed("true")
+        ));
+    }
+
+    #[test]
+    fn test_only_patterns() {
+        let old: Value =
+            serde_yaml::from_str("database:\n  host: a\n  password: x\napp:\n  port: 1\n").unwrap();
+        let new: Value = serde_yaml::from_str(
+            "database:\n  host: b\n  password: y\n  pool: 4\napp:\n  port: 2\n",
+        )
+        .unwrap();
+        let mut opts = DiffOptions {
+            only: vec!["database.*".parse().unwrap()],
+            ..Default::default()
+        };
+        let diff = cmp_yml_vals(&old, &new, &opts);
+        let modified: Vec<&str> = diff.modified().keys().map(|k| k.as_str()).collect();
+        assert_eq!(modified, vec!["database.host", "database.password"]);
+        assert_eq!(diff.added().len(), 1);
+
+        // --ignore 从 --only 留下的键中再去掉一部分
+        opts.rules
+            .push("*.password".parse().unwrap(), RuleAction::Ignore);
+        let diff = cmp_yml_vals(&old, &new, &opts);
+        let modified: Vec<&str> = diff.modified().keys().map(|k| k.as_str()).collect();
+        assert_eq!(modified, vec!["database.host"]);
+    }
+
+    #[test]
+    fn test_paths() {
+        let old: Value = serde_yaml::from_str(
+            "db:\n  replication: {mode: async, lag: 1}\n  host: a\nservices:\n  web: {image: w:1, port: 1}\n  api: {image: a:1}\n",
+        )
+        .unwrap();
+        let new: Value = serde_yaml::from_str(
+            "db:\n  replication: {mode: sync, lag: 2}\n  host: b\nservices:\n  web: {image: w:2, port: 2}\n  api: {image: a:2}\n",
+        )
+        .unwrap();
+        let modified = |paths: &[&str]| -> Vec<String> {
+            let opts = DiffOptions {
+                paths: paths.iter().map(|p| p.parse().unwrap()).collect(),
+                ..Default::default()
+            };
+            let diff = cmp_yml_vals(&old, &new, &opts);
+            diff.modified().keys().map(|k| k.to_string()).collect()
+        };
+
+        assert_eq!(
+            modified(&["db.replication"]),
+            ["db.replication.lag", "db.replication.mode"]
+        );
+        assert_eq!(
+            modified(&["services.*.image"]),
+            ["services.api.image", "services.web.image"]
+        );
+        // 前缀按段匹配，`db.rep` 不是 `db.replication` 的上层
+        assert!(modified(&["db.rep"]).is_empty());
+    }
+
+    #[test]
+    fn test_preserve_order() {
+        let old: Value = serde_yaml::from_str("zeta: 1\nbeta: 1\nmid: 1\nalpha: 1\n").unwrap();
+        let new: Value = serde_yaml::from_str("zeta: 2\nalpha: 2\nnew: 1\n").unwrap();
+        let keys = |diff: &ConfigDiff| -> Vec<String> {
+            diff.changes()
+                .map(|change| change.key.to_string())
+                .collect()
+        };
+
+        let diff = cmp_yml_vals(&old, &new, &DiffOptions::default());
+        assert_eq!(keys(&diff), vec!["alpha", "beta", "mid", "new", "zeta"]);
+
+        let opts = DiffOptions {
+            preserve_order: true,
+            ..Default::default()
+        };
+        let diff = cmp_yml_vals(&old, &new, &opts);
+        // 删除的 beta、mid 跟在旧文件中它们之前的共有键 zeta 后面
+        assert_eq!(keys(&diff), vec!["zeta", "beta", "mid", "alpha", "new"]);
+        let modified: Vec<&str> = diff.modified().keys().map(|k| k.as_str()).collect();
+        assert_eq!(modified, vec!["zeta", "alpha"]);
+    }
+
+    #[test]
+    fn test_custom_separator() {
+        let old: Value =
+            serde_yaml::from_str("logging:\n  level.com.example: info\n  x: [1]\n").unwrap();
+        let new: Value =
+            serde_yaml::from_str("logging:\n  level.com.example: debug\n  x: [1, 2]\n").unwrap();
+        let mut opts = DiffOptions::default();
+        opts.flatten.separator = "::".to_string();
+        let diff = cmp_yml_vals(&old, &new, &opts);
+
+        let (key, _) = diff.modified().iter().next().unwrap();
+        assert_eq!(key.as_str(), "logging::level.com.example");
+        assert_eq!(
+            key.segments().collect::<Vec<_>>(),
+            ["logging", "level.com.example"]
+        );
+        assert_eq!(key_segments_with("a::b[2]::c", "::"), ["a", "b", "2", "c"]);
+
+        // 多字符分隔符下层级排序仍然成立：段数少的在前，下标按数值排序
+        let key = |k: &str| ConfigKey::new(k, "::");
+        assert!(key("a::z") < key("a::b::c"));
+        assert!(key("a::2") < key("a::10"));
+        assert!(key("a.b::c") > key("a.b"));
+        assert_eq!(key("a::b::c").section(2), "a::b");
+    }
+
+    #[test]
+    fn test_keys_containing_separator() {
+        let old: Value = serde_yaml::from_str(
+            "\"a.b\": {c: 1}\na: {b: {c: 1}}\nx:\n  \"y.z\": {\"w[0]\": 1}\n  y: {z: [1]}\n",
+        )
+        .unwrap();
+        let new: Value = serde_yaml::from_str(
+            "\"a.b\": {c: 2}\na: {b: {c: 1}}\nx:\n  \"y.z\": {\"w[0]\": 1}\n  y: {z: [2]}\n",
+        )
+        .unwrap();
+        let diff = cmp_yml_vals(&old, &new, &DiffOptions::default());
+        let modified: Vec<&str> = diff.modified().keys().map(|k| k.as_str()).collect();
+        assert_eq!(modified, vec!["\"a.b\".c", "x.y.z[0]"]);
+        assert!(diff.added().is_empty() && diff.removed().is_empty());
+
+        let keys: HashSet<String> = extract_key_vals(&old, &DiffOptions::default())
+            .into_keys()
+            .collect();
+        assert!(keys.contains("x.\"y.z\".\"w[0]\""));
+
+        let segments = |k: &str| {
+            ConfigKey::from(k)
+                .segments()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(segments("\"a.b\".c"), ["a.b", "c"]);
+        assert_eq!(segments("x.\"y.z\".\"w[0]\""), ["x", "y.z", "w[0]"]);
+        assert_eq!(segments("\"a.b\"[1].c"), ["a.b", "1", "c"]);
+        assert_eq!(segments(r#""q\".x""#), [r#"q\".x"#]);
+        // 带引号的段与同名的嵌套路径段数不同，按层级排序
+        assert!(ConfigKey::from("\"a.b\".c") < ConfigKey::from("a.b.c"));
+    }
+
+    #[test]
+    fn test_ignore_default_values() {
+        let old: Value = serde_yaml::from_str("pool: 20\nretries: 5\n").unwrap();
+        let new: Value =
+            serde_yaml::from_str("pool: 10\nretries: 3\ntimeout: 30\nport: 80\n").unwrap();
+        let defaults: Value = serde_yaml::from_str("pool: 10\ntimeout: 30\n").unwrap();
+
+        let mut opts = DiffOptions::default();
+        opts.defaults = extract_key_vals(&defaults, &opts)
+            .into_iter()
+            .map(|(k, v)| (k, v.clone()))
+            .collect();
+        let diff = cmp_yml_vals(&old, &new, &opts);
+
+        let added: Vec<&str> = diff.added().keys().map(|k| k.as_str()).collect();
+        let modified: Vec<&str> = diff.modified().keys().map(|k| k.as_str()).collect();
+        assert_eq!(added, vec!["port"]);
+        assert_eq!(modified, vec!["retries"]);
+    }
+
+    #[test]
+    fn test_min_percent_change() {
+        let old: Value = serde_yaml::from_str("a: 100\nb: 100\nc: 0\nd: x\n").unwrap();
+        let new: Value = serde_yaml::from_str("a: 100.4\nb: 105\nc: 1\nd: y\n").unwrap();
+        let opts = DiffOptions {
+            min_percent_change: Some(1.0),
+            ..Default::default()
+        };
+
+        let diff = cmp_yml_vals(&old, &new, &opts);
+        let modified: Vec<&str> = diff.modified().keys().map(|k| k.as_str()).collect();
+        assert_eq!(modified, vec!["b", "c", "d"]);
+    }
+
+    #[test]
+    fn test_custom_value_comparator() {
+        let old: Value = serde_yaml::from_str("env: PROD\nport: 80\n").unwrap();
+        let new: Value = serde_yaml::from_str("env: prod\nport: 81\n").unwrap();
+        let ignore_case = |_: &str, a: &Value, b: &Value| match (a.as_str(), b.as_str()) {
+            (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+            _ => a == b,
+        };
+
+        let diff = cmp_yml_vals_with(&old, &new, &DiffOptions::default(), &ignore_case);
+        let modified: Vec<&str> = diff.modified().keys().map(|k| k.as_str()).collect();
+        assert_eq!(modified, vec!["port"]);
+    }
+
+    #[test]
+    fn test_flow_and_block_style_are_equal() {
+        let flow: Value =
+            serde_yaml::from_str("db: {host: a, ports: [80, 443], opts: {tls: true}}\n").unwrap();
+        let block: Value = serde_yaml::from_str(
+            "db:\n  host: a\n  ports:\n    - 80\n    - 443\n  opts:\n    tls: true\n",
+        )
+        .unwrap();
+
+        let mut opts = DiffOptions::default();
+        assert!(cmp_yml_vals(&flow, &block, &opts).is_empty());
+        opts.flatten.index_style = Some(IndexStyle::Bracket);
+        assert!(cmp_yml_vals(&flow, &block, &opts).is_empty());
+        // 整棵子树作为一个值比较时同样与书写风格无关
+        opts.flatten.max_depth = Some(0);
+        assert!(cmp_yml_vals(&flow, &block, &opts).is_empty());
+
+        assert_eq!(
+            get_val_string(&flow["db"]["ports"]),
+            get_val_string(&block["db"]["ports"])
+        );
+    }
+
+    #[test]
+    fn test_ignore_keys_in_values() {
+        let old: Value = serde_yaml::from_str(
+            "jobs:\n  - {name: a, lastUpdated: 1}\n  - {name: b, lastUpdated: 1}\n",
+        )
+        .unwrap();
+        let new: Value = serde_yaml::from_str(
+            "jobs:\n  - {name: a, lastUpdated: 2}\n  - {name: c, lastUpdated: 2}\n",
+        )
+        .unwrap();
+
+        let mut opts = DiffOptions::default();
+        opts.flatten.max_depth = Some(1);
+        assert_eq!(cmp_yml_vals(&old, &new, &opts).modified().len(), 2);
+
+        opts.ignore_keys_in_values = vec!["lastUpdated".into()];
+        let diff = cmp_yml_vals(&old, &new, &opts);
+        let modified: Vec<&str> = diff.modified().keys().map(|k| k.as_str()).collect();
+        assert_eq!(modified, vec!["jobs[1]"]);
+    }
+
+    #[test]
+    fn test_multiset_changes() {
+        let seq = |text: &str| serde_yaml::from_str::<Vec<Value>>(text).unwrap();
+        let old = seq("[a, a, b, [1, 2]]");
+        let new = seq("[[2, 1], c, b, a]");
+        let (removed, added) = multiset_changes(&old, &new);
+        assert_eq!(removed, [&Value::from("a")]);
+        assert_eq!(added, [&Value::from("c")]);
+    }
+
+    #[test]
+    fn test_assume_sorted_matches_default() {
+        let old: Value =
+            serde_yaml::from_str("a: 1\nb:\n  c: 1\n  d: [1, 2]\nb-x: 1\nz: {k: 1}\n").unwrap();
+        let new: Value =
+            serde_yaml::from_str("a: 2\nb:\n  d: [1, 3]\n  e: 1\nb-x: 1\ny: 1\n").unwrap();
+
+        let mut opts = DiffOptions::default();
+        opts.flatten.index_style = Some(IndexStyle::Bracket);
+        let keys = |diff: &ConfigDiff| {
+            let list =
+                |m: Vec<&ConfigKey>| m.into_iter().map(|k| k.to_string()).collect::<Vec<_>>();
+            (
+                list(diff.added().keys().collect()),
+                list(diff.removed().keys().collect()),
+                list(diff.modified().keys().collect()),
+            )
+        };
+        let expected = keys(&cmp_yml_vals(&old, &new, &opts));
+        opts.assume_sorted = true;
+        assert_eq!(keys(&cmp_yml_vals(&old, &new, &opts)), expected);
+
+        let entries = |value: &Value| -> Vec<(String, Value)> {
+            let mut entries: Vec<_> = flatten(value, &opts.flatten)
+                .map(|(k, v)| (k, v.clone()))
+                .collect();
+            entries.reverse();
+            entries
+        };
+        let (old, new) = (entries(&old), entries(&new));
+        assert_eq!(keys(&cmp_flat_entries(&old, &new, &opts)), expected);
+    }
+
+    #[test]
+    fn test_assume_sorted_records_unsorted_input() {
+        let yaml = |s: &str| serde_yaml::from_str::<Value>(s).unwrap();
+        let opts = DiffOptions {
+            assume_sorted: true,
+            ..Default::default()
+        };
+        let (sorted, unsorted) = (yaml("a: 1\nb: 2\n"), yaml("a: 1\nc: 3\nb: 2\n"));
+        assert!(
+            cmp_yml_vals(&sorted, &sorted, &opts)
+                .unsorted_input()
+                .is_none()
+        );
+        // 只有调试构建会检查
+        if cfg!(debug_assertions) {
+            let diff = cmp_yml_vals(&sorted, &unsorted, &opts);
+            let found = diff.unsorted_input().unwrap();
+            assert!(!found.old);
+            assert_eq!((found.prev.as_str(), found.key.as_str()), ("c", "b"));
+        }
+    }
+
+    #[test]
+    fn test_compare_as_strings() {
+        let old: Value =
+            serde_yaml::from_str("a: true\nb: 80\nc: 1\nd: {x: 1}\ne: [1, \"2\"]\n").unwrap();
+        let new: Value =
+            serde_yaml::from_str("a: \"true\"\nb: \"80\"\nc: 1.0\nd: {x: \"1\"}\ne: [\"1\", 2]\n")
+                .unwrap();
+
+        let mut opts = DiffOptions::default();
+        assert_eq!(cmp_yml_vals(&old, &new, &opts).modified().len(), 6);
+
+        opts.compare_as_strings = true;
+        let diff = cmp_yml_vals(&old, &new, &opts);
+        let modified: Vec<&str> = diff.modified().keys().map(|k| k.as_str()).collect();
+        assert_eq!(modified, vec!["c"]);
+    }
+
+    #[test]
+    fn test_retain_filters_changes() {
+        let old: Value = serde_yaml::from_str("metadata:\n  rev: 1\n  by: a\nport: 80\n").unwrap();
+        let new: Value = serde_yaml::from_str("metadata:\n  rev: 2\n  at: b\nport: 81\n").unwrap();
+        let mut diff = cmp_yml_vals(&old, &new, &DiffOptions::default());
+        diff.retain(|change| !change.key.starts_with("metadata."));
+
+        assert!(diff.added().is_empty() && diff.removed().is_empty());
+        let modified: Vec<&str> = diff.modified().keys().map(|k| k.as_str()).collect();
+        assert_eq!(modified, vec!["port"]);
+    }
+
+    #[test]
+    fn test_sequences_of_mappings_diff_by_element() {
+        let old: Value = serde_yaml::from_str(
+            "servers:\n  - host: a\n    ports: [80, 443]\n  - host: b\n    ports: [80]\n",
+        )
+        .unwrap();
+        let new: Value = serde_yaml::from_str(
+            "servers:\n  - host: a\n    ports: [80, 8443]\n  - host: c\n    ports: [80, 81]\n",
+        )
+        .unwrap();
+        let diff = cmp_yml_vals(&old, &new, &DiffOptions::default());
+
+        let added: Vec<&str> = diff.added().keys().map(|k| k.as_str()).collect();
+        let modified: Vec<&str> = diff.modified().keys().map(|k| k.as_str()).collect();
+        assert_eq!(added, vec!["servers[1].ports[1]"]);
+        // 层级排序：段数少的键在前
+        assert_eq!(modified, vec!["servers[1].host", "servers[0].ports[1]"]);
+        assert!(diff.removed().is_empty());
+
+        // 下标按数值排序
+        let seq: Value =
+            serde_yaml::from_str(&format!("s: {:?}\n", (0..12).collect::<Vec<_>>())).unwrap();
+        let diff = cmp_yml_vals(&Value::Null, &seq, &DiffOptions::default());
+        let added: Vec<&str> = diff.added().keys().map(|k| k.as_str()).collect();
+        assert_eq!(added[2..4], ["s[2]", "s[3]"]);
+        assert_eq!(added.last(), Some(&"s[11]"));
+    }
+
+    #[test]
+    fn test_ignore_patterns() {
+        let old: Value = serde_yaml::from_str(
+            "metadata:\n  timestamp: 1\n  name: a\nsecrets:\n  db:\n    password: x\nbuild:\n  hash: a\n",
+        )
+        .unwrap();
+        let new: Value = serde_yaml::from_str(
+            "metadata:\n  timestamp: 2\n  name: b\nsecrets:\n  db:\n    password: y\nbuild:\n  hash: b\n",
+        )
+        .unwrap();
+        let mut opts = DiffOptions::default();
+        for pattern in ["*.timestamp", "secrets.**", "build.hash"] {
+            opts.rules
+                .push(pattern.parse().unwrap(), RuleAction::Ignore);
+        }
+        let diff = cmp_yml_vals(&old, &new, &opts);
+        let modified: Vec<&str> = diff.modified().keys().map(|k| k.as_str()).collect();
+        assert_eq!(modified, vec!["metadata.name"]);
+        assert!(diff.added().is_empty() && diff.removed().is_empty());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_sort_matches_serial() {
+        // 乱序且超过并行阈值的键，数字段按数值排序
+        let entries: Vec<(ConfigKey, usize)> = (0..PARALLEL_SORT_THRESHOLD * 2)
+            .map(|i| {
+                let n = i.wrapping_mul(2_654_435_761) % (PARALLEL_SORT_THRESHOLD * 2);
+                let key = format!("section{}.items[{}].key{}", n % 50, n / 50 % 120, n / 6_000);
+                (key.into(), n)
+            })
+            .collect();
+        let sort = |parallel_sort| {
+            let opts = DiffOptions {
+                parallel_sort,
+                ..Default::default()
+            };
+            let sorted = sorted_map(entries.clone(), &opts);
+            sorted.into_iter().collect::<Vec<_>>()
+        };
+        let serial = sort(false);
+        assert_eq!(serial.len(), entries.len());
+        assert_eq!(sort(true), serial);
+    }
+}
@@ -0,0 +1,116 @@
+use crate::i18n::{Msg, error_lang};
+use anyhow::{Result, anyhow};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// 目录比较中的一对文件，按相对路径配对；只存在于一侧目录的文件另一侧为 `None`
+#[derive(Debug, PartialEq)]
+pub struct DirEntryPair {
+    pub relative: PathBuf,
+    pub old: Option<PathBuf>,
+    pub new: Option<PathBuf>,
+}
+
+/// 目录比较时收集的配置文件扩展名（不区分大小写），各自按 [`InputFormat::detect`] 解析
+///
+/// [`InputFormat::detect`]: crate::format::InputFormat::detect
+const CONFIG_EXTENSIONS: [&str; 4] = ["yml", "yaml", "json", "toml"];
+
+/// 递归遍历两个目录中的配置文件，按相对路径配对，结果按路径排序
+///
+/// 只按完整的相对路径配对：`app.yml` 与 `app.json` 是两个不同的文件。
+pub fn pair_dirs(old: &Path, new: &Path) -> Result<Vec<DirEntryPair>> {
+    let old_files = config_files(old)?;
+    let new_files = config_files(new)?;
+    Ok(old_files
+        .union(&new_files)
+        .map(|relative| DirEntryPair {
+            relative: relative.clone(),
+            old: old_files.contains(relative).then(|| old.join(relative)),
+            new: new_files.contains(relative).then(|| new.join(relative)),
+        })
+        .collect())
+}
+
+/// 目录下所有配置文件相对于 `root` 的路径
+fn config_files(root: &Path) -> Result<BTreeSet<PathBuf>> {
+    let mut files = BTreeSet::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let read_error = |e: io::Error| {
+            let dir = format!("{dir:?}");
+            anyhow!(error_lang().format(Msg::ReadDirFailed, &[&e, &dir]))
+        };
+        let entries = fs::read_dir(&dir).map_err(read_error)?;
+        for entry in entries {
+            let path = entry.map_err(read_error)?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if is_config(&path) {
+                // 路径由 root 下的 read_dir 得到，一定以 root 开头
+                files.insert(path.strip_prefix(root).unwrap().to_path_buf());
+            }
+        }
+    }
+    Ok(files)
+}
+
+fn is_config(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            CONFIG_EXTENSIONS
+                .iter()
+                .any(|c| ext.eq_ignore_ascii_case(c))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DirEntryPair, pair_dirs};
+    use std::fs;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_pair_dirs_by_relative_path() {
+        let dir = std::env::temp_dir().join(format!("yml-diff-dirs-{}", std::process::id()));
+        let files = [
+            "old/app.yml",
+            "old/conf/db.yaml",
+            "old/readme.md",
+            "old/gone.yml",
+            "old/Cargo.toml",
+            "new/app.yml",
+            "new/Cargo.toml",
+            "new/conf/app.json",
+            "new/conf/db.yaml",
+            "new/conf/cache.YML",
+        ];
+        for name in files {
+            let path = dir.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "a: 1\n").unwrap();
+        }
+
+        let (old, new) = (dir.join("old"), dir.join("new"));
+        let pair = |relative: &str, in_old: bool, in_new: bool| DirEntryPair {
+            relative: PathBuf::from(relative),
+            old: in_old.then(|| old.join(relative)),
+            new: in_new.then(|| new.join(relative)),
+        };
+        assert_eq!(
+            pair_dirs(&old, &new).unwrap(),
+            vec![
+                pair("Cargo.toml", true, true),
+                pair("app.yml", true, true),
+                pair("conf/app.json", false, true),
+                pair("conf/cache.YML", false, true),
+                pair("conf/db.yaml", true, true),
+                pair("gone.yml", true, false),
+            ]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
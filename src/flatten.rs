@@ -0,0 +1,422 @@
+use crate::pattern::KeyPattern;
+use serde_yaml::Value;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::str::FromStr;
+
+/// 扁平化序列下标的写法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexStyle {
+    /// `servers.0.host`
+    Dot,
+    /// `servers[0].host`
+    Bracket,
+}
+
+/// 控制 [`flatten`] 如何把嵌套结构展开成扁平键
+#[derive(Debug, Clone)]
+pub struct FlattenOptions {
+    /// 段之间的分隔符，默认 `.`
+    pub separator: String,
+    /// 最大展开深度，顶层键的深度为 0；`Some(0)` 时只展开到顶层键
+    pub max_depth: Option<usize>,
+    /// 序列元素的下标写法，默认 [`IndexStyle::Bracket`]；为 `None` 时序列作为整体
+    pub index_style: Option<IndexStyle>,
+    /// 命中这些模式的键不再展开，整体作为一个值
+    pub opaque: Vec<KeyPattern>,
+    /// 把非字符串的标量键转换成字符串参与扁平化，见 [`coerce_key`]
+    pub coerce_key_types: bool,
+    /// 命中这些模式的序列按元素的某个字段配对，而不是按下标，见 [`ListKey`]
+    pub list_keys: Vec<ListKey>,
+}
+
+/// 按字段配对序列元素：`containers: [{name: api, ...}, {name: sidecar, ...}]` 展开为
+/// `containers[name=api].image`、`containers[name=sidecar].image`，元素顺序的变化不再产生差异
+///
+/// 写作 `路径=字段`，路径的写法同 [`KeyPattern`]，例如 `**.containers=name`；省略路径时对所有序列生效。
+/// 可以用逗号给出多个候选字段，如 `name,id`，使用第一个能区分所有元素的字段：每个元素都是映射，
+/// 该字段都是标量且各不相同。没有这样的字段时序列仍按下标展开。
+#[derive(Debug, Clone)]
+pub struct ListKey {
+    pub pattern: KeyPattern,
+    pub fields: Vec<String>,
+}
+
+impl ListKey {
+    /// 只写 `--list-key` 时使用的候选字段
+    pub const DEFAULT_FIELDS: &str = "name,id";
+}
+
+impl FromStr for ListKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (pattern, fields) = s.rsplit_once('=').unwrap_or(("**", s));
+        let fields: Vec<String> = fields
+            .split(',')
+            .map(str::trim)
+            .filter(|f| !f.is_empty())
+            .map(String::from)
+            .collect();
+        if fields.is_empty() {
+            return Err(format!("--list-key 缺少字段名: {s:?}"));
+        }
+        Ok(ListKey {
+            pattern: pattern.parse()?,
+            fields,
+        })
+    }
+}
+
+impl Default for FlattenOptions {
+    fn default() -> Self {
+        FlattenOptions {
+            separator: ".".to_string(),
+            max_depth: None,
+            index_style: Some(IndexStyle::Bracket),
+            opaque: Vec::new(),
+            coerce_key_types: false,
+            list_keys: Vec::new(),
+        }
+    }
+}
+
+/// 把 YAML 值展开成 `(扁平键, 叶子值)` 序列
+///
+/// 映射总是展开，非字符串的键默认会被跳过；序列按 `index_style` 的写法逐个元素展开，
+/// `index_style` 为 `None` 时序列作为整体。
+/// 空映射、空序列、超过 `max_depth` 的子树以及命中 `opaque` 的键都作为整体返回。
+/// 根节点本身不是映射或序列时不产生任何键。
+///
+/// 返回的顺序未作保证，需要稳定顺序时请由调用方自行排序。
+pub fn flatten<'a>(
+    value: &'a Value,
+    opts: &FlattenOptions,
+) -> impl Iterator<Item = (String, &'a Value)> {
+    let mut iter = Flatten {
+        stack: Vec::new(),
+        opts,
+    };
+    // 根节点总是展开，其子节点的深度为 0
+    iter.push_children("", value, 0);
+    iter
+}
+
+struct Flatten<'a, 'o> {
+    /// 待处理的 (键, 值, 深度)，按倒序压栈以便大致按文档顺序输出
+    stack: Vec<(String, &'a Value, usize)>,
+    opts: &'o FlattenOptions,
+}
+
+impl<'a> Iterator for Flatten<'a, '_> {
+    type Item = (String, &'a Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((key, value, depth)) = self.stack.pop() {
+            if !self.is_nested(&key, value, depth) {
+                return Some((key, value));
+            }
+            self.push_children(&key, value, depth + 1);
+        }
+        None
+    }
+}
+
+impl<'a> Flatten<'a, '_> {
+    fn push_children(&mut self, key: &str, value: &'a Value, depth: usize) {
+        match value {
+            Value::Mapping(map) => {
+                let children: Vec<_> = map
+                    .iter()
+                    .filter_map(|(k, v)| {
+                        let k = if self.opts.coerce_key_types {
+                            coerce_key(k)?
+                        } else {
+                            Cow::Borrowed(k.as_str()?)
+                        };
+                        Some((self.join(key, &k), v, depth))
+                    })
+                    .collect();
+                self.stack.extend(children.into_iter().rev());
+            }
+            Value::Sequence(seq) if self.opts.index_style.is_some() => {
+                let children: Vec<_> = match self.list_key(key, seq) {
+                    Some((field, ids)) => (ids.into_iter().zip(seq))
+                        .map(|(id, v)| (format!("{key}[{field}={id}]"), v, depth))
+                        .collect(),
+                    None => (seq.iter().enumerate())
+                        .map(|(i, v)| (self.index(key, i), v, depth))
+                        .collect(),
+                };
+                self.stack.extend(children.into_iter().rev());
+            }
+            _ => {}
+        }
+    }
+
+    /// 序列命中 [`ListKey`] 时，返回用来配对的字段和每个元素在该字段上的值
+    fn list_key<'s>(&self, key: &str, seq: &'s [Value]) -> Option<(&str, Vec<Cow<'s, str>>)> {
+        let list_key = self
+            .opts
+            .list_keys
+            .iter()
+            .find(|l| l.pattern.matches(key))?;
+        list_key.fields.iter().find_map(|field| {
+            let ids: Vec<Cow<str>> = seq
+                .iter()
+                .map(|item| coerce_key(item.as_mapping()?.get(field.as_str())?))
+                .collect::<Option<_>>()?;
+            // 值中含有分隔符或方括号时无法写进键路径
+            let usable = ids.iter().all(|id| {
+                !(id.contains(&self.opts.separator) || id.contains('[') || id.contains(']'))
+            });
+            let unique = ids.iter().collect::<HashSet<_>>().len() == ids.len();
+            (usable && unique).then_some((field.as_str(), ids))
+        })
+    }
+
+    fn is_nested(&self, key: &str, value: &Value, depth: usize) -> bool {
+        let expandable = match value {
+            Value::Mapping(map) => !map.is_empty(),
+            Value::Sequence(seq) => self.opts.index_style.is_some() && !seq.is_empty(),
+            _ => false,
+        };
+        expandable
+            && self.opts.max_depth.is_none_or(|max| depth < max)
+            && !self.opts.opaque.iter().any(|p| p.matches(key))
+    }
+
+    fn join(&self, prefix: &str, key: &str) -> String {
+        let key = quote_key(key, &self.opts.separator);
+        if prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{prefix}{}{key}", self.opts.separator)
+        }
+    }
+
+    fn index(&self, prefix: &str, i: usize) -> String {
+        self.opts.index_key(prefix, i)
+    }
+}
+
+impl FlattenOptions {
+    /// 序列 `prefix` 中第 `i` 个元素的扁平键，写法与 [`flatten`] 相同
+    pub fn index_key(&self, prefix: &str, i: usize) -> String {
+        match self.index_style {
+            Some(IndexStyle::Bracket) => format!("{prefix}[{i}]"),
+            _ => {
+                let i = i.to_string();
+                if prefix.is_empty() {
+                    i
+                } else {
+                    format!("{prefix}{}{i}", self.separator)
+                }
+            }
+        }
+    }
+}
+
+/// [`flatten`] 中按下标展开的每个非空序列：`(序列的扁平键, 元素)`，根节点本身是序列时键为空字符串
+///
+/// 外层的序列排在它内部的序列之前；按 [`ListKey`] 配对的序列不在其中。
+pub fn indexed_sequences<'a>(
+    value: &'a Value,
+    opts: &FlattenOptions,
+) -> Vec<(String, &'a [Value])> {
+    let mut iter = Flatten {
+        stack: vec![(String::new(), value, 0)],
+        opts,
+    };
+    let mut sequences = Vec::new();
+    while let Some((key, value, depth)) = iter.stack.pop() {
+        // 根节点总是展开，其子节点的深度为 0
+        let is_root = key.is_empty();
+        if !is_root && !iter.is_nested(&key, value, depth) {
+            continue;
+        }
+        if let Value::Sequence(seq) = value
+            && opts.index_style.is_some()
+            && !seq.is_empty()
+            && iter.list_key(&key, seq).is_none()
+        {
+            sequences.push((key.clone(), seq.as_slice()));
+        }
+        iter.push_children(&key, value, if is_root { 0 } else { depth + 1 });
+    }
+    sequences
+}
+
+/// 开启 [`FlattenOptions::coerce_key_types`] 时，找出同一映射中规范化后同名的键（如 `8080` 与 `"8080"`），
+/// 返回第一个这样的扁平键；[`flatten`] 会为两者产生同一个键，调用方应当在扁平化前报错
+pub fn coerced_key_conflict(value: &Value, opts: &FlattenOptions) -> Option<String> {
+    if !opts.coerce_key_types {
+        return None;
+    }
+    let mut iter = Flatten {
+        stack: vec![(String::new(), value, 0)],
+        opts,
+    };
+    while let Some((key, value, depth)) = iter.stack.pop() {
+        let is_root = key.is_empty();
+        if !is_root && !iter.is_nested(&key, value, depth) {
+            continue;
+        }
+        if let Value::Mapping(map) = value {
+            let mut seen = HashSet::new();
+            for k in map.keys().filter_map(coerce_key) {
+                if !seen.insert(k.clone()) {
+                    return Some(iter.join(&key, &k));
+                }
+            }
+        }
+        iter.push_children(&key, value, if is_root { 0 } else { depth + 1 });
+    }
+    None
+}
+
+/// 键名本身含有分隔符或 `[` 时加上双引号，避免与嵌套的键或下标混淆，例如 `{"a.b": {c: 1}}`
+/// 扁平化为 `"a.b".c`；引号内的 `"` 和 `\` 用反斜杠转义。其余键名原样返回
+pub fn quote_key<'k>(key: &'k str, separator: &str) -> Cow<'k, str> {
+    if !(key.contains(separator) || key.contains('[') || key.starts_with('"')) {
+        return Cow::Borrowed(key);
+    }
+    let escaped = key.replace('\\', "\\\\").replace('"', "\\\"");
+    Cow::Owned(format!("\"{escaped}\""))
+}
+
+/// 把标量键规范化成字符串，使 `8080:` 与 `"8080":` 被视为同一个键
+///
+/// 同一映射中两者同时出现时规范化后会重名，见 [`coerced_key_conflict`]。
+///
+/// - 字符串保持原样
+/// - 整数按十进制书写，浮点数使用 serde_yaml 的输出形式（如 `1.5`、`.inf`）
+/// - 布尔值为 `true` / `false`，空值为 `null`
+/// - 序列、映射和带标签的键无法规范化，返回 `None`
+pub fn coerce_key(key: &Value) -> Option<Cow<'_, str>> {
+    match key {
+        Value::String(s) => Some(Cow::Borrowed(s)),
+        Value::Number(n) => Some(Cow::Owned(n.to_string())),
+        Value::Bool(b) => Some(Cow::Borrowed(if *b { "true" } else { "false" })),
+        Value::Null => Some(Cow::Borrowed("null")),
+        Value::Sequence(_) | Value::Mapping(_) | Value::Tagged(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FlattenOptions, IndexStyle, ListKey, coerced_key_conflict, flatten, quote_key};
+    use serde_yaml::Value;
+
+    fn keys(value: &Value, opts: &FlattenOptions) -> Vec<String> {
+        let mut keys: Vec<_> = flatten(value, opts).map(|(k, _)| k).collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn test_flatten_options() {
+        let value: Value = serde_yaml::from_str("a:\n  b:\n    c: 1\n  d: [x, y]\n").unwrap();
+
+        assert_eq!(
+            keys(&value, &FlattenOptions::default()),
+            vec!["a.b.c", "a.d[0]", "a.d[1]"]
+        );
+
+        let opts = FlattenOptions {
+            index_style: None,
+            ..Default::default()
+        };
+        assert_eq!(keys(&value, &opts), vec!["a.b.c", "a.d"]);
+
+        let opts = FlattenOptions {
+            separator: "/".to_string(),
+            index_style: Some(IndexStyle::Dot),
+            ..Default::default()
+        };
+        assert_eq!(keys(&value, &opts), vec!["a/b/c", "a/d/0", "a/d/1"]);
+
+        let opts = FlattenOptions {
+            max_depth: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(keys(&value, &opts), vec!["a.b", "a.d"]);
+
+        let opts = FlattenOptions {
+            max_depth: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(keys(&value, &opts), vec!["a"]);
+    }
+
+    #[test]
+    fn test_coerce_key_types() {
+        let typed: Value = serde_yaml::from_str("8080: a\ntrue: b\n1.5: c\n").unwrap();
+        let quoted: Value = serde_yaml::from_str("\"8080\": a\n\"true\": b\n\"1.5\": c\n").unwrap();
+
+        // 默认跳过非字符串键
+        assert!(keys(&typed, &FlattenOptions::default()).is_empty());
+
+        let opts = FlattenOptions {
+            coerce_key_types: true,
+            ..Default::default()
+        };
+        // 键名 `1.5` 含有分隔符，加引号与嵌套的 `1` → `5` 区分
+        assert_eq!(keys(&typed, &opts), vec!["\"1.5\"", "8080", "true"]);
+        assert_eq!(keys(&typed, &opts), keys(&quoted, &opts));
+
+        // 同一映射中的 `8080` 与 `"8080"` 规范化后重名
+        assert_eq!(coerced_key_conflict(&typed, &opts), None);
+        let both: Value = serde_yaml::from_str("db:\n  8080: a\n  \"8080\": b\n").unwrap();
+        assert_eq!(
+            coerced_key_conflict(&both, &opts).as_deref(),
+            Some("db.8080")
+        );
+        assert_eq!(
+            coerced_key_conflict(&both, &FlattenOptions::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_list_keys() {
+        let value: Value = serde_yaml::from_str(
+            "containers:\n- {name: api, image: a}\n- {name: sidecar, image: b}\n\
+             ports: [{port: 80}, {port: 80}]\n",
+        )
+        .unwrap();
+        let opts = FlattenOptions {
+            list_keys: vec![ListKey::DEFAULT_FIELDS.parse().unwrap()],
+            ..Default::default()
+        };
+        assert_eq!(
+            keys(&value, &opts),
+            vec![
+                "containers[name=api].image",
+                "containers[name=api].name",
+                "containers[name=sidecar].image",
+                "containers[name=sidecar].name",
+                "ports[0].port",
+                "ports[1].port",
+            ]
+        );
+
+        // 只对命中路径的序列生效；字段值重复时退回按下标展开
+        let opts = FlattenOptions {
+            list_keys: vec!["ports=port".parse().unwrap()],
+            ..Default::default()
+        };
+        assert!(keys(&value, &opts).contains(&"containers[0].image".to_string()));
+        assert!(keys(&value, &opts).contains(&"ports[1].port".to_string()));
+        assert!("x=".parse::<ListKey>().is_err());
+    }
+
+    #[test]
+    fn test_quote_key() {
+        assert_eq!(quote_key("plain", "."), "plain");
+        assert_eq!(quote_key("a.b", "."), "\"a.b\"");
+        assert_eq!(quote_key("a.b", "/"), "a.b");
+        assert_eq!(quote_key("w[0]", "/"), "\"w[0]\"");
+        assert_eq!(quote_key(r#""q"\x"#, "."), r#""\"q\"\\x""#);
+    }
+}
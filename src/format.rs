@@ -0,0 +1,582 @@
+use crate::i18n::{Msg, error_lang};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// 输入文件的格式，每个文件单独判断
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum InputFormat {
+    Yaml,
+    Json,
+    Toml,
+}
+
+impl InputFormat {
+    /// 按扩展名判断格式；没有可识别的扩展名时根据内容猜测
+    ///
+    /// 内容以 `{` 或 `[` 开头时按 JSON 解析，其余按 YAML 解析。TOML 文件必须
+    /// 使用 `.toml` 扩展名，因为它的内容与 YAML 无法可靠区分。
+    pub fn detect(path: &Path, bytes: &[u8]) -> Self {
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+        match ext.as_deref() {
+            Some("json") => InputFormat::Json,
+            Some("toml") => InputFormat::Toml,
+            Some("yml" | "yaml") => InputFormat::Yaml,
+            _ => match bytes.iter().find(|b| !b.is_ascii_whitespace()) {
+                Some(b'{' | b'[') => InputFormat::Json,
+                _ => InputFormat::Yaml,
+            },
+        }
+    }
+
+    /// 把文件内容解析成统一的 YAML 值，之后的比较与格式无关
+    ///
+    /// YAML 文件可以包含多个以 `---` 分隔的文档，每个文档一个值；JSON 和 TOML 总是一个值。
+    /// YAML 中的别名在解析时就替换成锚点处的值；`resolve_merge_keys` 为 `true` 时合并键 `<<` 也会展开，
+    /// 因此比较的是配置实际生效的内容，与锚点的写法无关，为 `false` 时 `<<` 保留为普通的键。
+    /// 同一映射中重复的键不会被后一个值悄悄覆盖，而是作为解析错误报告；YAML 文件的错误中列出
+    /// 文件里所有重复的键及其每次出现的行号，见 [`duplicate_keys`]。
+    ///
+    /// 返回的错误中 `path` 为空，由调用方填写。
+    pub fn parse(self, bytes: &[u8], resolve_merge_keys: bool) -> Result<Vec<Value>, ParseError> {
+        match self {
+            InputFormat::Yaml => serde_yaml::Deserializer::from_slice(bytes)
+                .map(|doc| {
+                    let mut value = Value::deserialize(doc).map_err(|e| {
+                        if e.to_string().contains("duplicate entry")
+                            && let Some(err) = duplicate_keys_error(bytes)
+                        {
+                            return err;
+                        }
+                        let location = e.location().map(|l| Location {
+                            line: l.line(),
+                            column: l.column(),
+                        });
+                        ParseError::new(error_lang().format(Msg::ParseYamlFailed, &[&e]), location)
+                    })?;
+                    if resolve_merge_keys {
+                        value.apply_merge().map_err(|e| {
+                            ParseError::new(error_lang().format(Msg::MergeKeysFailed, &[&e]), None)
+                        })?;
+                    }
+                    Ok(value)
+                })
+                .collect(),
+            InputFormat::Json => serde_json::from_slice(bytes).map(|v| vec![v]).map_err(|e| {
+                // 非语法错误（如 I/O）没有位置，行号为 0
+                let location = (e.line() > 0).then(|| Location {
+                    line: e.line(),
+                    column: e.column(),
+                });
+                ParseError::new(error_lang().format(Msg::ParseJsonFailed, &[&e]), location)
+            }),
+            InputFormat::Toml => {
+                let text = std::str::from_utf8(bytes).map_err(|e| {
+                    ParseError::new(error_lang().format(Msg::TomlNotUtf8, &[&e]), None)
+                })?;
+                toml::from_str(text).map(|v| vec![v]).map_err(|e| {
+                    let location = e.span().map(|span| Location::of_offset(text, span.start));
+                    ParseError::new(error_lang().format(Msg::ParseTomlFailed, &[&e]), location)
+                })
+            }
+        }
+    }
+}
+
+/// 输入文件解析失败：带有文件路径，解析器能给出位置时还带有出错的行号和列号
+#[derive(Debug)]
+pub struct ParseError {
+    pub path: PathBuf,
+    pub message: String,
+    pub location: Option<Location>,
+}
+
+/// 文件中的位置，行号和列号都从 1 开始
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Location {
+    /// 文本中字节偏移 `offset` 所在的位置，列号按字符计
+    fn of_offset(text: &str, offset: usize) -> Self {
+        let before = &text[..offset.min(text.len())];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        Location {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+impl ParseError {
+    fn new(message: String, location: Option<Location>) -> Self {
+        ParseError {
+            path: PathBuf::new(),
+            message,
+            location,
+        }
+    }
+}
+
+impl ParseError {
+    /// --strict 的诊断信息：哪一侧的文件、出错的行列号，以及出错的行和上一行，下方用 `^` 指出出错的列
+    ///
+    /// `source` 是交给解析器的内容；没有位置信息时只有第一行。
+    pub fn diagnostic(&self, side: &str, source: &[u8]) -> String {
+        let lang = error_lang();
+        let Some(location) = self.location else {
+            return format!("{side} {:?}: {}", self.path, self.message);
+        };
+        let at = lang.format(Msg::AtLineColumn, &[&location.line, &location.column]);
+        let mut text = format!("{side} {:?} {at}: {}", self.path, self.message);
+        let source = String::from_utf8_lossy(source);
+        let lines: Vec<&str> = source.lines().collect();
+        // 文件意外结束时位置在最后一行之后，改为指向最后一行的末尾
+        let (line, column) = match lines.last() {
+            Some(last) if location.line > lines.len() => (lines.len(), last.chars().count() + 1),
+            _ => (location.line, location.column),
+        };
+        let width = line.to_string().len();
+        for no in line.saturating_sub(1).max(1)..=line {
+            if let Some(text_line) = lines.get(no - 1) {
+                text.push_str(&format!("\n  {no:>width$} | {text_line}"));
+            }
+        }
+        let pad = " ".repeat(column.saturating_sub(1));
+        text.push_str(&format!("\n  {:>width$} | {pad}^", ""));
+        text
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {:?}", self.message, self.path)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// 解析文件内容，返回其中的所有文档；`format` 为 `None` 时按文件自身的格式解析，
+/// 否则按指定的格式解析，不再根据扩展名和内容判断。`resolve_merge_keys` 见 [`InputFormat::parse`]
+pub fn parse_file(
+    path: &Path,
+    bytes: &[u8],
+    format: Option<InputFormat>,
+    resolve_merge_keys: bool,
+) -> Result<Vec<Value>> {
+    format
+        .unwrap_or_else(|| InputFormat::detect(path, bytes))
+        .parse(bytes, resolve_merge_keys)
+        .map_err(|e| {
+            ParseError {
+                path: path.to_path_buf(),
+                ..e
+            }
+            .into()
+        })
+}
+
+/// YAML 源文本中重复出现的一个键
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateKey {
+    /// 扁平化后的键路径，序列下标为数字段，如 `servers.0.port`
+    pub key: String,
+    /// 第一次出现的位置
+    pub first: Location,
+    /// 重复出现的位置
+    pub duplicate: Location,
+}
+
+/// 块风格映射中的一层：键所在的列、该层的键路径前缀和已经出现过的键
+struct Block {
+    column: usize,
+    path: String,
+    seen: HashMap<String, Location>,
+    last_key: Option<String>,
+    items: usize,
+}
+
+impl Block {
+    fn child_path(&self, segment: &str) -> String {
+        Self::join(&self.path, segment)
+    }
+
+    fn join(path: &str, segment: &str) -> String {
+        if path.is_empty() {
+            segment.to_string()
+        } else {
+            format!("{path}.{segment}")
+        }
+    }
+}
+
+/// 逐行扫描 YAML 源文本，找出同一个块风格映射中重复出现的键
+///
+/// serde_yaml 遇到重复的键时只报告第一处，且位置指向所在的映射而不是重复的键；这里按缩进
+/// 还原映射的层级，给出所有重复的键及其两次出现的行号。只能识别块风格的写法：
+/// flow 风格（`{a: 1}`）中的键和跨行的引号字符串都不会检查。
+pub fn duplicate_keys(text: &str) -> Vec<DuplicateKey> {
+    let mut duplicates = Vec::new();
+    let mut blocks: Vec<Block> = Vec::new();
+    // 块标量（`|`、`>`）所属键的列，之后缩进更深的行都是标量的内容
+    let mut scalar_column: Option<usize> = None;
+
+    for (i, line) in text.lines().enumerate() {
+        let content = line.trim_start_matches(' ');
+        let mut column = line.len() - content.len();
+        if let Some(c) = scalar_column {
+            if content.trim().is_empty() || column > c {
+                continue;
+            }
+            scalar_column = None;
+        }
+        let mut content = content.trim_end();
+        if content.is_empty() || content.starts_with('#') {
+            continue;
+        }
+        if content == "---" || content.starts_with("--- ") || content == "..." {
+            blocks.clear();
+            continue;
+        }
+
+        // 序列的元素：`- ` 之后的内容从新的一列开始，是一个新的映射
+        while content == "-" || content.starts_with("- ") {
+            blocks.retain(|b| b.column <= column);
+            let path = match blocks.last_mut() {
+                Some(parent) => {
+                    let base = match &parent.last_key {
+                        Some(key) => parent.child_path(key),
+                        None => parent.path.clone(),
+                    };
+                    parent.items += 1;
+                    Block::join(&base, &(parent.items - 1).to_string())
+                }
+                None => String::new(),
+            };
+            let rest = content[1..].trim_start_matches(' ');
+            column += content.len() - rest.len();
+            content = rest;
+            blocks.push(Block {
+                column,
+                path,
+                seen: HashMap::new(),
+                last_key: None,
+                items: 0,
+            });
+        }
+
+        let Some((key, value)) = split_key(content) else {
+            continue;
+        };
+        blocks.retain(|b| b.column <= column);
+        if blocks.last().is_none_or(|b| b.column < column) {
+            let path = match blocks.last() {
+                Some(parent) => parent.child_path(parent.last_key.as_deref().unwrap_or_default()),
+                None => String::new(),
+            };
+            blocks.push(Block {
+                column,
+                path,
+                seen: HashMap::new(),
+                last_key: None,
+                items: 0,
+            });
+        }
+        let block = blocks.last_mut().expect("刚刚确保了当前层存在");
+        let location = Location {
+            line: i + 1,
+            column: column + 1,
+        };
+        match block.seen.get(key) {
+            Some(&first) => duplicates.push(DuplicateKey {
+                key: block.child_path(key),
+                first,
+                duplicate: location,
+            }),
+            None => {
+                block.seen.insert(key.to_string(), location);
+            }
+        }
+        block.last_key = Some(key.to_string());
+        block.items = 0;
+        if value.starts_with('|') || value.starts_with('>') {
+            scalar_column = Some(column);
+        }
+    }
+    duplicates
+}
+
+/// 把 `key: value` 拆成键和值，键可以带引号；不是键值行时返回 `None`
+fn split_key(content: &str) -> Option<(&str, &str)> {
+    let (key, rest) = match content.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let end = content[1..].find(quote)? + 1;
+            (&content[1..end], content[end + 1..].trim_start())
+        }
+        '{' | '[' | '?' | '&' | '*' | '!' | '|' | '>' | '#' => return None,
+        _ => {
+            let colon = content
+                .match_indices(':')
+                .map(|(i, _)| i)
+                .find(|&i| content[i + 1..].is_empty() || content[i + 1..].starts_with(' '))?;
+            (content[..colon].trim_end(), &content[colon..])
+        }
+    };
+    let value = rest.strip_prefix(':')?;
+    if !(value.is_empty() || value.starts_with(' ')) {
+        return None;
+    }
+    Some((key, value.trim_start()))
+}
+
+/// serde_yaml 报告了重复的键时，换成列出所有重复键的错误；扫描不出重复的键时返回 `None`
+fn duplicate_keys_error(bytes: &[u8]) -> Option<ParseError> {
+    let text = std::str::from_utf8(bytes).ok()?;
+    let duplicates = duplicate_keys(text);
+    let first = duplicates.first()?.duplicate;
+    let lang = error_lang();
+    let list: Vec<String> = duplicates
+        .iter()
+        .map(|d| {
+            let (first, duplicate) = (d.first.line, d.duplicate.line);
+            lang.format(Msg::DuplicateKey, &[&d.key, &first, &duplicate])
+        })
+        .collect();
+    let (count, list) = (duplicates.len(), list.join(", "));
+    let message = lang.format(Msg::DuplicateKeys, &[&count, &list]);
+    Some(ParseError::new(message, Some(first)))
+}
+
+/// 多文档文件中各文档所在的键，扁平化后为 `doc[0].a`、`doc[1].b` ……
+pub const DOCUMENTS_KEY: &str = "doc";
+
+/// 把一对文件的文档合成两个可比较的值
+///
+/// 两边都只有一个文档时直接比较文档本身；任一边有多个文档时，两边的文档都放进 `doc` 键下比较。
+/// 所有文档都带有 `kind` 和 `metadata.name`（如 Kubernetes 清单）且在各自文件中不重复时，
+/// 按 `kind/name` 配对，扁平化后为 `doc.Service/web.spec.port`，文档的顺序变化不影响结果；
+/// 否则放进 `{doc: [文档0, 文档1, ...]}` 中逐个按下标比较。
+/// 两种方式下只存在于一边的文档都整体作为新增或删除。
+pub fn combine_documents(old: Vec<Value>, new: Vec<Value>) -> (Value, Value) {
+    combine_documents_by(old, new, document_identity)
+}
+
+/// 与 [`combine_documents`] 相同，但用 `identity` 给出的标识配对文档
+pub fn combine_documents_by(
+    old: Vec<Value>,
+    new: Vec<Value>,
+    identity: fn(&Value) -> Option<String>,
+) -> (Value, Value) {
+    if old.len() <= 1 && new.len() <= 1 {
+        let single = |docs: Vec<Value>| docs.into_iter().next().unwrap_or(Value::Null);
+        return (single(old), single(new));
+    }
+    let wrap = |docs: Value| {
+        let mut map = Mapping::new();
+        map.insert(DOCUMENTS_KEY.into(), docs);
+        Value::Mapping(map)
+    };
+    match (
+        keyed_documents(&old, identity),
+        keyed_documents(&new, identity),
+    ) {
+        (Some(old), Some(new)) => (wrap(Value::Mapping(old)), wrap(Value::Mapping(new))),
+        _ => (wrap(Value::Sequence(old)), wrap(Value::Sequence(new))),
+    }
+}
+
+/// 文档的 `kind/metadata.name`，两者都是字符串时才有
+fn document_identity(doc: &Value) -> Option<String> {
+    let kind = doc.get("kind")?.as_str()?;
+    let name = doc.get("metadata")?.get("name")?.as_str()?;
+    Some(format!("{kind}/{name}"))
+}
+
+/// 按 `identity` 给出的标识索引文档；有文档缺少标识或标识重复时返回 `None`
+fn keyed_documents(docs: &[Value], identity: fn(&Value) -> Option<String>) -> Option<Mapping> {
+    let mut map = Mapping::new();
+    for doc in docs {
+        let identity = identity(doc)?;
+        if map.insert(identity.into(), doc.clone()).is_some() {
+            return None;
+        }
+    }
+    Some(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InputFormat, combine_documents, duplicate_keys, parse_file};
+    use std::path::Path;
+
+    #[test]
+    fn test_detect_format() {
+        let detect = |name: &str, text: &str| InputFormat::detect(Path::new(name), text.as_bytes());
+        assert_eq!(detect("a.json", "a: 1"), InputFormat::Json);
+        assert_eq!(detect("a.TOML", ""), InputFormat::Toml);
+        assert_eq!(detect("a.yml", "{}"), InputFormat::Yaml);
+        assert_eq!(detect("a.conf", "  {\"a\": 1}"), InputFormat::Json);
+        assert_eq!(detect("a", "a: 1"), InputFormat::Yaml);
+    }
+
+    #[test]
+    fn test_duplicate_keys_are_rejected() {
+        let err = parse_file(
+            Path::new("a.yml"),
+            b"db:\n  port: 1\n  port: 2\n",
+            None,
+            true,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(
+            err.contains("1 处重复的键: db.port（第 2 行与第 3 行）"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        let yaml = "\
+a: 1
+db:
+  port: 1
+  script: |
+    port: 9
+    port: 9
+  'port': 2
+servers:
+  - name: a
+    name: b
+  - name: c
+a: 2
+";
+        let found: Vec<_> = duplicate_keys(yaml)
+            .into_iter()
+            .map(|d| (d.key, d.first.line, d.duplicate.line, d.duplicate.column))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("db.port".to_string(), 3, 7, 3),
+                ("servers.0.name".to_string(), 9, 10, 5),
+                ("a".to_string(), 1, 12, 1),
+            ]
+        );
+        // 不同文档、不同序列元素中的同名键不算重复
+        assert!(duplicate_keys("a: 1\n---\na: 2\nl:\n- x: 1\n- x: 2\n").is_empty());
+    }
+
+    #[test]
+    fn test_merge_keys_can_be_kept() {
+        let path = Path::new("app.yml");
+        let yaml = b"base: &base {port: 80}\napp:\n  <<: *base\n  host: a\n";
+        let resolved = parse_file(path, yaml, None, true).unwrap();
+        assert_eq!(resolved[0]["app"]["port"], 80);
+        assert!(resolved[0]["app"].get("<<").is_none());
+
+        // 合并键保留为普通的键，别名仍然展开
+        let kept = parse_file(path, yaml, None, false).unwrap();
+        assert!(kept[0]["app"].get("port").is_none());
+        assert_eq!(kept[0]["app"]["<<"]["port"], 80);
+    }
+
+    #[test]
+    fn test_format_override() {
+        // 扩展名与内容不符时按指定的格式解析
+        let path = Path::new("config.yml");
+        let toml = b"[db]\nport = 80\n";
+        assert!(parse_file(path, toml, None, true).is_err());
+        let docs = parse_file(path, toml, Some(InputFormat::Toml), true).unwrap();
+        assert_eq!(docs[0]["db"]["port"], 80);
+    }
+
+    #[test]
+    fn test_mixed_formats_parse_to_same_value() {
+        let dir = std::env::temp_dir().join(format!("yml-diff-formats-{}", std::process::id()));
+        let files = [
+            ("old/app.yml", "app:\n  port: 80\n  tags: [a, b]\n"),
+            (
+                "new/app.json",
+                r#"{"app": {"port": 80, "tags": ["a", "b"]}}"#,
+            ),
+            ("old/db.toml", "[db]\nhost = \"x\"\npool = 4\n"),
+            ("new/db.yaml", "db:\n  host: x\n  pool: 4\n"),
+        ];
+        for (name, text) in files {
+            let path = dir.join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, text).unwrap();
+        }
+        let load = |name: &str| {
+            let path = dir.join(name);
+            parse_file(&path, &std::fs::read(&path).unwrap(), None, true).unwrap()
+        };
+
+        assert_eq!(load("old/app.yml"), load("new/app.json"));
+        assert_eq!(load("old/db.toml"), load("new/db.yaml"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_multi_document_files() {
+        let path = std::path::Path::new("manifest.yml");
+        let old = parse_file(
+            path,
+            b"kind: Service\nport: 80\n---\nkind: Deployment\nreplicas: 1\n",
+            None,
+            true,
+        )
+        .unwrap();
+        let new = parse_file(
+            path,
+            b"kind: Service\nport: 81\n---\nkind: Deployment\nreplicas: 1\n---\nkind: ConfigMap\n",
+            None,
+            true,
+        )
+        .unwrap();
+        assert_eq!((old.len(), new.len()), (2, 3));
+
+        let (old, new) = combine_documents(old, new);
+        assert_eq!(old["doc"][0]["port"], 80);
+        assert_eq!(new["doc"][2]["kind"], "ConfigMap");
+
+        let single = parse_file(path, b"a: 1\n", None, true).unwrap();
+        let (old, _) = combine_documents(single, Vec::new());
+        assert_eq!(old["a"], 1);
+    }
+
+    #[test]
+    fn test_documents_matched_by_kind_and_name() {
+        let path = Path::new("manifest.yml");
+        let doc = |kind: &str, name: &str, port: u16| {
+            format!("kind: {kind}\nmetadata:\n  name: {name}\nport: {port}\n")
+        };
+        let old = [doc("Service", "web", 80), doc("Service", "db", 5432)].join("---\n");
+        let new = [doc("Service", "db", 5432), doc("Service", "web", 81)].join("---\n");
+        let (old, new) = combine_documents(
+            parse_file(path, old.as_bytes(), None, true).unwrap(),
+            parse_file(path, new.as_bytes(), None, true).unwrap(),
+        );
+        // 文档顺序不同，仍按 kind/name 配对
+        assert_eq!(old["doc"]["Service/web"]["port"], 80);
+        assert_eq!(new["doc"]["Service/web"]["port"], 81);
+        assert_eq!(old["doc"]["Service/db"], new["doc"]["Service/db"]);
+
+        // 有文档缺少 metadata.name 时退回按下标配对
+        let old = [doc("Service", "web", 80), "kind: ConfigMap\n".into()].join("---\n");
+        let (old, _) = combine_documents(
+            parse_file(path, old.as_bytes(), None, true).unwrap(),
+            Vec::new(),
+        );
+        assert_eq!(old["doc"][1]["kind"], "ConfigMap");
+    }
+}
@@ -0,0 +1,108 @@
+use crate::i18n::{Msg, error_lang};
+use anyhow::{Result, anyhow};
+use std::ffi::OsString;
+use std::path::Path;
+use std::process::Command;
+
+/// git 调用外部 diff 程序时在环境中设置的变量，用来识别 GIT_EXTERNAL_DIFF 的调用方式
+const EXTERNAL_DIFF_ENV: &str = "GIT_DIFF_PATH_TOTAL";
+
+/// 读取文件在某个提交中的内容，相当于 `git show <rev>:<path>`
+///
+/// 相对路径按当前目录解析（`<rev>:./<path>`），绝对路径先换算成相对于仓库根目录的路径。
+pub fn show(rev: &str, path: &Path) -> Result<Vec<u8>> {
+    let object = if path.is_absolute() {
+        let root = git(&["rev-parse", "--show-toplevel"])?;
+        let root = String::from_utf8_lossy(&root);
+        let relative = path.strip_prefix(root.trim_end()).map_err(|_| {
+            let path = format!("{path:?}");
+            anyhow!(error_lang().format(Msg::NotInGitRepo, &[&path]))
+        })?;
+        format!("{rev}:{}", relative.display())
+    } else {
+        format!("{rev}:./{}", path.display())
+    };
+    git(&["show", &object])
+        .map_err(|e| anyhow!(error_lang().format(Msg::GitShowFailed, &[&object, &e])))
+}
+
+fn git(args: &[&str]) -> Result<Vec<u8>> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .map_err(|e| anyhow!(error_lang().format(Msg::RunGitFailed, &[&e])))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{}",
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// 作为 GIT_EXTERNAL_DIFF 或 `diff.<driver>.command` 被 git 调用时，把命令行末尾的 7 个参数
+/// `path old-file old-hex old-mode new-file new-hex new-mode` 换成 `--old`/`--new`
+///
+/// 写在这 7 个参数之前的选项原样保留，例如 `GIT_EXTERNAL_DIFF="yml-diff --k8s"`。
+/// 不是被 git 调用时原样返回。
+pub fn external_diff_args(args: Vec<OsString>, env: Option<OsString>) -> Vec<OsString> {
+    if env.is_none() || args.len() < 8 {
+        return args;
+    }
+    let mut args = args;
+    let tail = args.split_off(args.len() - 7);
+    let [path, old, _, _, new, _, _] = <[OsString; 7]>::try_from(tail).expect("正好 7 个参数");
+    args.extend([
+        "--old".into(),
+        old,
+        "--new".into(),
+        new,
+        "--git-path".into(),
+        path,
+    ]);
+    args
+}
+
+/// 当前进程的环境中与 [`external_diff_args`] 有关的变量
+pub fn external_diff_env() -> Option<OsString> {
+    std::env::var_os(EXTERNAL_DIFF_ENV)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::external_diff_args;
+    use std::ffi::OsString;
+
+    #[test]
+    fn test_external_diff_args() {
+        let args = |list: &[&str]| list.iter().map(OsString::from).collect::<Vec<_>>();
+        let git_args = args(&[
+            "yml-diff",
+            "--k8s",
+            "app.yml",
+            "/tmp/old_app.yml",
+            "abc123",
+            "100644",
+            "app.yml",
+            "def456",
+            "100644",
+        ]);
+        assert_eq!(
+            external_diff_args(git_args.clone(), Some("1".into())),
+            args(&[
+                "yml-diff",
+                "--k8s",
+                "--old",
+                "/tmp/old_app.yml",
+                "--new",
+                "app.yml",
+                "--git-path",
+                "app.yml",
+            ])
+        );
+        // 不是被 git 调用时不做任何改动
+        assert_eq!(external_diff_args(git_args.clone(), None), git_args);
+        let short = args(&["yml-diff", "-o", "a.yml", "-n", "b.yml"]);
+        assert_eq!(external_diff_args(short.clone(), Some("1".into())), short);
+    }
+}
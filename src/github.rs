@@ -0,0 +1,117 @@
+use crate::i18n::Msg;
+use crate::{ReportOptions, change_lines, display_val};
+use std::io::{self, Write};
+use yml_diff::rules::Severity;
+use yml_diff::{ChangeKind, ConfigDiff, DiffOptions};
+
+/// --format github：每处差异输出一条 GitHub Actions 的 workflow command，在 pull request 中
+/// 显示为新文件上的行内注释
+///
+/// 规则文件没有指定严重程度时每处差异都是 `::error`；指定了严重程度时按级别输出
+/// `::error` / `::warning` / `::notice`，没有命中任何级别的键为 `::notice`。
+/// 能在新文件中定位到的键带有 `line`，删除的键只标注在文件上。
+pub fn print_github(
+    out: &mut dyn Write,
+    diff: &ConfigDiff,
+    opts: &DiffOptions,
+    report: &ReportOptions,
+) -> io::Result<()> {
+    let file = report
+        .labels
+        .as_ref()
+        .map(|(_, new)| new.trim_start_matches("./"));
+    let rated = opts.rules.has_severities();
+    for change in diff.changes() {
+        let command = match opts.rules.severity(change.key) {
+            Some(Severity::Error) => "error",
+            Some(Severity::Warn) => "warning",
+            Some(Severity::Info) => "notice",
+            None if rated => "notice",
+            None => "error",
+        };
+        let value = |val| display_val(change.key, val, opts, report);
+        let key = change.key;
+        let message = match change.kind {
+            ChangeKind::Added(new) => report.lang.format(Msg::GithubAdded, &[&key, &value(new)]),
+            ChangeKind::Removed(old) => {
+                report.lang.format(Msg::GithubRemoved, &[&key, &value(old)])
+            }
+            ChangeKind::Modified(old, new) => {
+                (report.lang).format(Msg::GithubModified, &[&key, &value(old), &value(new)])
+            }
+        };
+
+        let mut properties = Vec::new();
+        if let Some(file) = file {
+            properties.push(format!("file={}", escape_property(file)));
+            let (_, line) = change_lines(change.key, &change.kind, opts, report);
+            if let Some(line) = line {
+                properties.push(format!("line={line}"));
+            }
+        }
+        properties.push(format!("title={}", escape_property(change.key)));
+        writeln!(
+            out,
+            "::{command} {}::{}",
+            properties.join(","),
+            escape_data(&message)
+        )?;
+    }
+    Ok(())
+}
+
+/// workflow command 消息中的 `%` 和换行需要转义
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// 属性值中还要转义作为分隔符的 `:` 和 `,`
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::print_github;
+    use crate::ReportOptions;
+    use serde_yaml::Value;
+    use yml_diff::rules::Rules;
+    use yml_diff::{DiffOptions, cmp_yml_vals};
+
+    #[test]
+    fn test_print_github() {
+        let old_text = "db:\n  port: 80\nname: a\n";
+        let new_text = "db:\n  port: 81\ntags: 'x,y'\n";
+        let old: Value = serde_yaml::from_str(old_text).unwrap();
+        let new: Value = serde_yaml::from_str(new_text).unwrap();
+        let report = ReportOptions {
+            labels: Some(("old/app.yml".into(), "./config/app.yml".into())),
+            old_source: Some(old_text.into()),
+            new_source: Some(new_text.into()),
+            ..Default::default()
+        };
+
+        let opts = DiffOptions::default();
+        let mut out = Vec::new();
+        print_github(&mut out, &cmp_yml_vals(&old, &new, &opts), &opts, &report).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "::error file=config/app.yml,line=2,title=db.port::db.port 从 80 改为 81\n\
+             ::error file=config/app.yml,title=name::删除 name: a\n\
+             ::error file=config/app.yml,line=3,title=tags::新增 tags: x,y\n"
+        );
+
+        let rules: Value = serde_yaml::from_str("\"db.*\": warn\n").unwrap();
+        let opts = DiffOptions {
+            rules: Rules::from_value(&rules).unwrap(),
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        print_github(&mut out, &cmp_yml_vals(&old, &new, &opts), &opts, &report).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("::warning file=config/app.yml,line=2,title=db.port::"));
+        assert!(out.contains("::notice file=config/app.yml,title=name::"));
+    }
+}
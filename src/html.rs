@@ -0,0 +1,143 @@
+use crate::i18n::Msg;
+use crate::tree::{Node, segment_label};
+use crate::{ReportOptions, change_location, display_val};
+use std::io::{self, Write};
+use yml_diff::report::HTML_STYLE;
+pub use yml_diff::report::escape_html as escape;
+use yml_diff::{ChangeKind, ConfigDiff, DiffOptions};
+
+/// --format html：独立的 HTML 报告，变更按键路径组成可以折叠的树，新增、删除、修改分别着色
+pub fn print_html(
+    out: &mut dyn Write,
+    diff: &ConfigDiff,
+    opts: &DiffOptions,
+    report: &ReportOptions,
+) -> io::Result<()> {
+    let root = Node::build(diff, opts);
+    let lang = report.lang;
+    let title = lang.get(Msg::ReportName);
+
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html lang=\"{}\">", lang.get(Msg::HtmlLang))?;
+    writeln!(out, "<head>")?;
+    writeln!(out, "<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>{title}</title>")?;
+    writeln!(out, "<style>\n{HTML_STYLE}</style>")?;
+    writeln!(out, "</head>")?;
+    writeln!(out, "<body>")?;
+    writeln!(out, "<h1>{title}</h1>")?;
+    if let Some((old, new)) = &report.labels {
+        writeln!(
+            out,
+            "<p><code>{}</code> → <code>{}</code></p>",
+            escape(old),
+            escape(new)
+        )?;
+    }
+    writeln!(
+        out,
+        "<p class=\"stats\"><span class=\"added\">{} {}</span><span class=\"removed\">{} {}</span>\
+         <span class=\"modified\">{} {}</span></p>",
+        lang.get(Msg::HtmlAdded),
+        diff.added().len(),
+        lang.get(Msg::HtmlRemoved),
+        diff.removed().len(),
+        lang.get(Msg::HtmlModified),
+        diff.modified().len()
+    )?;
+    if diff.is_empty() {
+        writeln!(
+            out,
+            "<p class=\"added\">{}</p>",
+            lang.get(Msg::NoDifferences)
+        )?;
+    } else {
+        writeln!(out, "<ul class=\"tree\">")?;
+        for (seg, node) in &root.children {
+            print_node(out, seg, node, opts, report)?;
+        }
+        writeln!(out, "</ul>")?;
+    }
+    writeln!(out, "</body>")?;
+    writeln!(out, "</html>")
+}
+
+fn print_node(
+    out: &mut dyn Write,
+    seg: &str,
+    node: &Node,
+    opts: &DiffOptions,
+    report: &ReportOptions,
+) -> io::Result<()> {
+    if let Some((key, kind)) = &node.change {
+        let value = |val| escape(&display_val(key, val, opts, report));
+        let (class, mark, detail) = match *kind {
+            ChangeKind::Added(val) => ("added", '+', format!("<code>{}</code>", value(val))),
+            ChangeKind::Removed(val) => ("removed", '-', format!("<code>{}</code>", value(val))),
+            ChangeKind::Modified(old, new) => (
+                "modified",
+                '~',
+                format!(
+                    "<code class=\"old\">{}</code> → <code class=\"new\">{}</code>",
+                    value(old),
+                    value(new)
+                ),
+            ),
+        };
+        let location = match change_location(key, kind, opts, report) {
+            Some(location) => format!(" <span class=\"location\">{}</span>", escape(&location)),
+            None => String::new(),
+        };
+        writeln!(
+            out,
+            "<li class=\"{class}\" title=\"{}\">{mark} <span class=\"key\">{}</span>{location}: {detail}</li>",
+            escape(key),
+            escape(&segment_label(seg, &opts.flatten.separator))
+        )?;
+    }
+    if node.children.is_empty() {
+        return Ok(());
+    }
+    writeln!(
+        out,
+        "<li><details open><summary>{} <span class=\"count\">({})</span></summary><ul>",
+        escape(&segment_label(seg, &opts.flatten.separator)),
+        node.count() - usize::from(node.change.is_some())
+    )?;
+    for (child_seg, child) in &node.children {
+        print_node(out, child_seg, child, opts, report)?;
+    }
+    writeln!(out, "</ul></details></li>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape, print_html};
+    use crate::ReportOptions;
+    use serde_yaml::Value;
+    use yml_diff::{DiffOptions, cmp_yml_vals};
+
+    #[test]
+    fn test_print_html() {
+        assert_eq!(
+            escape("<a href='x'>&\"</a>"),
+            "&lt;a href=&#39;x&#39;&gt;&amp;&quot;&lt;/a&gt;"
+        );
+
+        let old: Value = serde_yaml::from_str("db:\n  host: a\n  port: 1\nx: '<b>'\n").unwrap();
+        let new: Value = serde_yaml::from_str("db:\n  host: b\n  user: u\n").unwrap();
+        let opts = DiffOptions::default();
+        let diff = cmp_yml_vals(&old, &new, &opts);
+        let mut out = Vec::new();
+        print_html(&mut out, &diff, &opts, &ReportOptions::default()).unwrap();
+        let html = String::from_utf8(out).unwrap();
+
+        assert!(html.starts_with("<!DOCTYPE html>") && html.trim_end().ends_with("</html>"));
+        assert!(html.contains("<summary>db <span class=\"count\">(3)</span></summary>"));
+        assert!(html.contains(
+            "<li class=\"modified\" title=\"db.host\">~ <span class=\"key\">host</span>: \
+             <code class=\"old\">a</code> → <code class=\"new\">b</code></li>"
+        ));
+        assert!(html.contains("<code>&lt;b&gt;</code>") && !html.contains("<b>"));
+    }
+}
@@ -0,0 +1,904 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// 报告和错误信息使用的语言
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Lang {
+    #[default]
+    Zh,
+    En,
+}
+
+/// 错误信息的语言；错误在远离报告选项的地方生成，启动时设置一次
+static ERROR_LANG: AtomicU8 = AtomicU8::new(0);
+
+impl Lang {
+    /// 没有指定 --lang 时的语言：按 LC_ALL、LC_MESSAGES、LANG 的顺序取第一个非空的值
+    pub fn from_env() -> Lang {
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty());
+        Lang::from_locale(locale.as_deref())
+    }
+
+    /// `zh_CN.UTF-8` 等中文区域为中文；未设置或为 `C` / `POSIX` 时保持原来的中文输出，其余区域为英文
+    fn from_locale(locale: Option<&str>) -> Lang {
+        match locale {
+            None | Some("C" | "POSIX") => Lang::Zh,
+            Some(locale) if locale.starts_with("C.") || locale.starts_with("zh") => Lang::Zh,
+            Some(_) => Lang::En,
+        }
+    }
+
+    pub fn get(self, msg: Msg) -> &'static str {
+        match self {
+            Lang::Zh => zh(msg),
+            Lang::En => en(msg),
+        }
+    }
+
+    /// 按顺序替换消息中的 `{}`
+    pub fn format(self, msg: Msg, args: &[&dyn fmt::Display]) -> String {
+        let mut args = args.iter();
+        let mut parts = self.get(msg).split("{}");
+        let mut text = parts.next().unwrap_or_default().to_string();
+        for part in parts {
+            if let Some(arg) = args.next() {
+                text.push_str(&arg.to_string());
+            }
+            text.push_str(part);
+        }
+        text
+    }
+}
+
+pub fn set_error_lang(lang: Lang) {
+    ERROR_LANG.store(lang as u8, Ordering::Relaxed);
+}
+
+/// 错误信息使用的语言
+pub fn error_lang() -> Lang {
+    match ERROR_LANG.load(Ordering::Relaxed) {
+        0 => Lang::Zh,
+        _ => Lang::En,
+    }
+}
+
+/// 需要翻译的文字，`{}` 是按顺序填入的参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Msg {
+    ReportTitle,
+    Stats,
+    StatAdded,
+    StatRemoved,
+    StatMoved,
+    StatRenamed,
+    StatReordered,
+    StatMigrated,
+    StatModified,
+    TypeChanges,
+    WhitespaceOnly,
+    DetailsOpen,
+    DetailsSeparator,
+    DetailsClose,
+    AddedSection,
+    RemovedSection,
+    RenamedSection,
+    MovedAlong,
+    ReorderedSection,
+    MigratedSection,
+    MigrateReason,
+    MovedSection,
+    ModifiedSection,
+    TypeChange,
+    InlineDiff,
+    Before,
+    After,
+    OldSide,
+    NewSide,
+    Reason,
+    ReasonRedacted,
+    OnlyInNew,
+    OnlyInOld,
+    RenameReason,
+    MoveReason,
+    DifferentKinds,
+    DifferentNumberKinds,
+    BeyondTolerance,
+    BeyondEpsilon,
+    UnorderedDiffers,
+    SameKindDiffers,
+    BySeverity,
+    NoDifferences,
+    KindBool,
+    KindNumber,
+    KindString,
+    KindSequence,
+    KindMapping,
+    KindTagged,
+    ReadFileFailed,
+    ReadStdinFailed,
+    CreateOutputFailed,
+    WriteOutputFailed,
+    ParseYamlFailed,
+    MergeKeysFailed,
+    ParseJsonFailed,
+    TomlNotUtf8,
+    ParseTomlFailed,
+    OldFile,
+    NewFile,
+    AtLineColumn,
+    FilesFailedToParse,
+    CoercedKeyConflict,
+    UnsortedInput,
+    LowMemoryMultipleDocuments,
+    ReportName,
+    HtmlLang,
+    HtmlAdded,
+    HtmlRemoved,
+    HtmlModified,
+    Added,
+    Removed,
+    Modified,
+    Changes,
+    CountsHeader,
+    AddedHeading,
+    RemovedHeading,
+    ModifiedHeading,
+    AddedTableHeader,
+    RemovedTableHeader,
+    ModifiedTableHeader,
+    GithubAdded,
+    GithubRemoved,
+    GithubModified,
+    JunitAdded,
+    JunitRemoved,
+    BySection,
+    DeepestChange,
+    DiffstatSummary,
+    UnchangedSections,
+    UnchangedSection,
+    KeyCaseSection,
+    NoKeyCaseMismatch,
+    SummaryTitle,
+    PairFailed,
+    PairUnchanged,
+    PairFailedSeeErrors,
+    SummaryTotals,
+    SummaryOnlyInDirs,
+    SummaryFailures,
+    SummaryChanges,
+    OnlyInOldDir,
+    OnlyInNewDir,
+    OldStdinDocument,
+    NewStdinDocument,
+    StandardInput,
+    AcceptedWritten,
+    RoundTripPassed,
+    MissingOutputPath,
+    CurrentDirFailed,
+    WatchStdin,
+    MultiplePlainOutputs,
+    UnsupportedEncoding,
+    InvalidTagHandler,
+    InvalidConfigRules,
+    SopsDecryptStdinPair,
+    BothStdin,
+    SerializePatchFailed,
+    SerializeOpsFailed,
+    SerializeMergeFailed,
+    SerializeResultFailed,
+    #[cfg(feature = "schema")]
+    SchemaViolations,
+    #[cfg(feature = "schema")]
+    SchemaBreaking,
+    InteractiveNeedsTerminal,
+    LowMemoryOnePair,
+    LowMemoryNoDir,
+    LowMemoryNoToml,
+    EmptyInput,
+    MergeConflicts,
+    ParseOpsFailed,
+    KeyNotFound,
+    NotNormalized,
+    RoundTripUnflattenable,
+    RoundTripFailed,
+    FanOutOptions,
+    FanOutFilesOnly,
+    DirMismatch,
+    DirUnsupportedOptions,
+    HtmlOnePair,
+    OutputTargetOnePair,
+    PairsFailed,
+    TypeChangeEntry,
+    TypeChangesFound,
+    LimitExceeded,
+    LimitSeparator,
+    ChangeLimitsExceeded,
+    SeverityChanged,
+    PairsFileNotUtf8,
+    PairsFileLine,
+    ParseConfigFailed,
+    StdinPairCount,
+    ThreeWayTitle,
+    Missing,
+    OursOnlySection,
+    TheirsOnlySection,
+    BothSameSection,
+    ConflictSection,
+    ConflictBase,
+    ConflictOurs,
+    ConflictTheirs,
+    MergedTitle,
+    FilePairs,
+    SeenInPairs,
+    NothingToMerge,
+    WillMerge,
+    MergeSkipped,
+    MatrixTitle,
+    MatrixAllSame,
+    MatrixKey,
+    MatrixBaseline,
+    MatrixTargetCount,
+    ReviewSection,
+    ReviewAccepted,
+    ReviewRejected,
+    ReviewPrompt,
+    ReviewFirst,
+    ReviewUnknown,
+    ReviewDone,
+    ReviewHelp,
+    PhaseParseOld,
+    PhaseParseNew,
+    PhaseCompare,
+    PhaseRender,
+    Timings,
+    TimingsTotal,
+    ProgressBar,
+    ProgressBytes,
+    ProgressPairs,
+    ReadConfigFileFailed,
+    ParseConfigFileFailed,
+    ConfigNotMapping,
+    ConfigKeyNotString,
+    ConfigInputOption,
+    ConfigUnknownOption,
+    ConfigNotScalar,
+    ReadDirFailed,
+    DuplicateKey,
+    DuplicateKeys,
+    NotInGitRepo,
+    GitShowFailed,
+    RunGitFailed,
+    InvalidVariable,
+    PatchPathMissing,
+    InvalidConfigMapUrl,
+    RunCurlFailed,
+    DownloadFailed,
+    RunKubectlFailed,
+    ReadConfigMapFailed,
+    ParseKubectlFailed,
+    InvalidBase64,
+    ConfigMapKeyMissing,
+    SopsToml,
+    WriteTempFailed,
+    RunSopsFailed,
+    SopsDecryptFailed,
+    CreateDirFailed,
+    SerializeDiffFailed,
+    WriteDiffFileFailed,
+    TransformNeedsExec,
+    TransformParseFailed,
+    TransformSerializeFailed,
+    StartCommandFailed,
+    WaitCommandFailed,
+    CommandFailed,
+    #[cfg(feature = "schema")]
+    SchemaRequired,
+    #[cfg(feature = "schema")]
+    SchemaType,
+    #[cfg(feature = "schema")]
+    SchemaNotAllowed,
+    #[cfg(feature = "schema")]
+    SchemaOther,
+    #[cfg(feature = "schema")]
+    InvalidSchema,
+    #[cfg(feature = "schema")]
+    YamlToJsonFailed,
+    #[cfg(feature = "schema")]
+    BreakingSection,
+    #[cfg(feature = "schema")]
+    NoBreaking,
+    #[cfg(feature = "schema")]
+    SchemaSection,
+    #[cfg(feature = "schema")]
+    SchemaValid,
+}
+
+fn zh(msg: Msg) -> &'static str {
+    match msg {
+        Msg::ReportTitle => "=== YAML 配置文件差异报告 ===",
+        Msg::Stats => "统计信息:",
+        Msg::StatAdded => "  新增: {}",
+        Msg::StatRemoved => "  删除: {}",
+        Msg::StatMoved => "  移动: {}",
+        Msg::StatRenamed => "  重命名: {}",
+        Msg::StatReordered => "  重排: {}",
+        Msg::StatMigrated => "  迁移: {}",
+        Msg::StatModified => "  修改: {}{}",
+        Msg::TypeChanges => "类型变化 {}",
+        Msg::WhitespaceOnly => "仅空白 {}",
+        Msg::DetailsOpen => "（",
+        Msg::DetailsSeparator => "，",
+        Msg::DetailsClose => "）",
+        Msg::AddedSection => "新增的配置项:",
+        Msg::RemovedSection => "删除的配置项:",
+        Msg::RenamedSection => "重命名的配置段:",
+        Msg::MovedAlong => "（{} 项随之移动）",
+        Msg::ReorderedSection => "位置变化的元素:",
+        Msg::MigratedSection => "按改名表迁移的配置项:",
+        Msg::MigrateReason => "旧键只在旧文件、新键只在新文件中，按 --renames 配对",
+        Msg::MovedSection => "移动的配置项:",
+        Msg::ModifiedSection => "修改的配置项:",
+        Msg::TypeChange => " [类型变更: {} → {}]",
+        Msg::InlineDiff => "  差异",
+        Msg::Before => "  修改前 {}",
+        Msg::After => "  修改后 {}",
+        Msg::OldSide => "旧",
+        Msg::NewSide => "新",
+        Msg::Reason => "    原因: {}",
+        Msg::ReasonRedacted => "    原因: {}，值已按规则隐藏",
+        Msg::OnlyInNew => "仅存在于新文件",
+        Msg::OnlyInOld => "仅存在于旧文件",
+        Msg::RenameReason => "子树中的大部分键和值相同，路径不同",
+        Msg::MoveReason => "值和键名相同，父路径不同",
+        Msg::DifferentKinds => "值不同（{} → {}）",
+        Msg::DifferentNumberKinds => "数值类型不同（{} → {}）",
+        Msg::BeyondTolerance => "数值相差 {} 超出误差 {}",
+        Msg::BeyondEpsilon => "数值相差 {} 超出 --epsilon {}",
+        Msg::UnorderedDiffers => "按无序序列比较，元素不同",
+        Msg::SameKindDiffers => "值不同（同为{}）",
+        Msg::BySeverity => "按严重程度:",
+        Msg::NoDifferences => "没有发现配置差异",
+        Msg::KindBool => "布尔",
+        Msg::KindNumber => "数值",
+        Msg::KindString => "字符串",
+        Msg::KindSequence => "序列",
+        Msg::KindMapping => "映射",
+        Msg::KindTagged => "标签 {}",
+        Msg::ReadFileFailed => "读取配置文件失败！{}: {}",
+        Msg::ReadStdinFailed => "读取标准输入失败！{}",
+        Msg::CreateOutputFailed => "创建输出文件失败！{}: {}",
+        Msg::WriteOutputFailed => "写入输出文件失败！{}: {}",
+        Msg::ParseYamlFailed => "解析 YAML 失败！{}",
+        Msg::MergeKeysFailed => "展开 YAML 合并键失败！{}",
+        Msg::ParseJsonFailed => "解析 JSON 失败！{}",
+        Msg::TomlNotUtf8 => "TOML 文件不是有效的 UTF-8！{}",
+        Msg::ParseTomlFailed => "解析 TOML 失败！{}",
+        Msg::OldFile => "旧文件",
+        Msg::NewFile => "新文件",
+        Msg::AtLineColumn => "第 {} 行第 {} 列",
+        Msg::FilesFailedToParse => "{} 个文件解析失败:",
+        Msg::CoercedKeyConflict => {
+            "--coerce-key-types 后键 {} 重名：同一映射中同时有字符串键和同名的非字符串键（如 \"8080\" 与 8080）: {}"
+        }
+        Msg::UnsortedInput => {
+            "警告: {}的键没有排序（{} 出现在 {} 之前），--assume-sorted 的结果可能不正确"
+        }
+        Msg::LowMemoryMultipleDocuments => "--low-memory 只支持单个 YAML 文档: {}",
+        Msg::ReportName => "YAML 配置文件差异报告",
+        Msg::HtmlLang => "zh-CN",
+        Msg::HtmlAdded => "新增",
+        Msg::HtmlRemoved => "删除",
+        Msg::HtmlModified => "修改",
+        Msg::Added => "新增",
+        Msg::Removed => "删除",
+        Msg::Modified => "修改",
+        Msg::Changes => "变更",
+        Msg::CountsHeader => "| 新增 | 删除 | 修改 |",
+        Msg::AddedHeading => "新增的配置项",
+        Msg::RemovedHeading => "删除的配置项",
+        Msg::ModifiedHeading => "修改的配置项",
+        Msg::AddedTableHeader => "| 键 | 新值 |",
+        Msg::RemovedTableHeader => "| 键 | 旧值 |",
+        Msg::ModifiedTableHeader => "| 键 | 旧值 → 新值 |",
+        Msg::GithubAdded => "新增 {}: {}",
+        Msg::GithubRemoved => "删除 {}: {}",
+        Msg::GithubModified => "{} 从 {} 改为 {}",
+        Msg::JunitAdded => "新增: {}",
+        Msg::JunitRemoved => "删除: {}",
+        Msg::BySection => "按配置段:",
+        Msg::DeepestChange => "最深的变更: {} ({} 层)",
+        Msg::DiffstatSummary => " {} 个配置段变更，新增 {}，删除 {}，修改 {}",
+        Msg::UnchangedSections => "未变化的配置段:",
+        Msg::UnchangedSection => "{} (未变化，{} 个键)",
+        Msg::KeyCaseSection => "大小写不一致的键:",
+        Msg::NoKeyCaseMismatch => "没有只有大小写不同的键",
+        Msg::SummaryTitle => "=== 汇总 ===",
+        Msg::PairFailed => "失败",
+        Msg::PairUnchanged => "无差异",
+        Msg::PairFailedSeeErrors => "比较失败，详见错误输出",
+        Msg::SummaryTotals => "共 {} 组文件，{} 组有差异，{} 组相同",
+        Msg::SummaryOnlyInDirs => "，仅存在于旧目录 {} 个，仅存在于新目录 {} 个",
+        Msg::SummaryFailures => "，{} 组失败",
+        Msg::SummaryChanges => "；合计 {} {} {}",
+        Msg::OnlyInOldDir => "{}（仅存在于旧目录）",
+        Msg::OnlyInNewDir => "{}（仅存在于新目录）",
+        Msg::OldStdinDocument => "标准输入中的旧文档",
+        Msg::NewStdinDocument => "标准输入中的新文档",
+        Msg::StandardInput => "标准输入",
+        Msg::AcceptedWritten => "已把接受的变更写入 {}",
+        Msg::RoundTripPassed => "往返校验通过",
+        Msg::MissingOutputPath => "缺少输出文件的路径: {}",
+        Msg::CurrentDirFailed => "无法获取当前目录！{}",
+        Msg::WatchStdin => "--watch 不能与标准输入一起使用",
+        Msg::MultiplePlainOutputs => "只能有一个不带格式的 --output，其余的请写成 FORMAT:PATH",
+        Msg::UnsupportedEncoding => "不支持的字符编码: {}",
+        Msg::InvalidTagHandler => "应为 TAG=KIND 的形式，如 !date=timestamp: {}",
+        Msg::InvalidConfigRules => "配置文件中的规则无效！{}",
+        Msg::SopsDecryptStdinPair => "--sops decrypt 不能与 --stdin-pair 一起使用",
+        Msg::BothStdin => {
+            "--old 和 --new 不能同时为 -，标准输入只能读取一次；需要都从标准输入读取时请使用 --stdin-pair"
+        }
+        Msg::SerializePatchFailed => "序列化 patch 失败！{}",
+        Msg::SerializeOpsFailed => "序列化操作列表失败！{}",
+        Msg::SerializeMergeFailed => "序列化合并结果失败！{}",
+        Msg::SerializeResultFailed => "序列化结果失败！{}",
+        #[cfg(feature = "schema")]
+        Msg::SchemaViolations => "新配置文件有 {} 处不符合 schema",
+        #[cfg(feature = "schema")]
+        Msg::SchemaBreaking => "有 {} 处变更破坏了 schema",
+        Msg::InteractiveNeedsTerminal => "--interactive 需要在终端中运行",
+        Msg::LowMemoryOnePair => "--low-memory 只能比较一对文件",
+        Msg::LowMemoryNoDir => "--low-memory 不支持目录比较: {}",
+        Msg::LowMemoryNoToml => "--low-memory 不支持 TOML 文件: {}",
+        Msg::EmptyInput => "{} 没有任何配置项（空文件、只有注释或只有 null），可能是被截断的文件",
+        Msg::MergeConflicts => "旧版本与新版本有 {} 处冲突",
+        Msg::ParseOpsFailed => "解析操作列表失败！{}: {}",
+        Msg::KeyNotFound => "键不存在: {}: {}",
+        Msg::NotNormalized => "文件未规范化: {}",
+        Msg::RoundTripUnflattenable => {
+            "往返校验失败！由差异重建的文件与新文件不一致，差异位于无法扁平化的部分"
+        }
+        Msg::RoundTripFailed => "往返校验失败！由差异重建的文件与新文件不一致: {}",
+        Msg::FanOutOptions => {
+            "指定多个 --new 时不能使用 --old-rev、--new-rev、--base 或 --interactive"
+        }
+        Msg::FanOutFilesOnly => "指定多个 --new 时每个输入都必须是文件: {}",
+        Msg::DirMismatch => "--old 和 --new 必须同为文件或同为目录: {}, {}",
+        Msg::DirUnsupportedOptions => {
+            "--patch、--emit-patch、--split-output、--key-case-report、--base、--old-rev/--new-rev 和 --interactive 不支持目录比较"
+        }
+        Msg::HtmlOnePair => "--format html 每次只能比较一对文件",
+        Msg::OutputTargetOnePair => "--output FORMAT:PATH 每次只能比较一对文件",
+        Msg::PairsFailed => "{}/{} 组文件比较失败: {}",
+        Msg::TypeChangeEntry => "{}（{} → {}）",
+        Msg::TypeChangesFound => "{} 处配置项的类型发生了变化: {}",
+        Msg::LimitExceeded => "{} {} 处，超过 {} {}",
+        Msg::LimitSeparator => "；",
+        Msg::ChangeLimitsExceeded => "变更数量超出上限: {}",
+        Msg::SeverityChanged => "{} 处 {} 级别的配置项发生了变化: {}",
+        Msg::PairsFileNotUtf8 => "文件对清单不是有效的 UTF-8！{}: {}",
+        Msg::PairsFileLine => "文件对清单第 {} 行格式错误，应为以制表符或逗号分隔的两个路径",
+        Msg::ParseConfigFailed => "解析配置文件失败！{}",
+        Msg::StdinPairCount => "--stdin-pair 需要两个以 --- 分隔的文档，实际只读到 {} 个",
+        Msg::ThreeWayTitle => "=== YAML 配置文件三方比较报告 ===",
+        Msg::Missing => "<不存在>",
+        Msg::OursOnlySection => "只在旧版本中修改的配置项:",
+        Msg::TheirsOnlySection => "只在新版本中修改的配置项:",
+        Msg::BothSameSection => "两边修改相同的配置项:",
+        Msg::ConflictSection => "冲突的配置项:",
+        Msg::ConflictBase => "    基准   {}",
+        Msg::ConflictOurs => "    旧版本 {}",
+        Msg::ConflictTheirs => "    新版本 {}",
+        Msg::MergedTitle => "=== YAML 配置文件合并差异报告 ===",
+        Msg::FilePairs => "文件对:",
+        Msg::SeenInPairs => "(出现于 {}/{} 组: {})",
+        Msg::NothingToMerge => "没有需要合并的变更",
+        Msg::WillMerge => "将从新文件合并 {} 处变更:",
+        Msg::MergeSkipped => "另有 {} 处变更不在 --only 的范围内，保留旧文件中的内容",
+        Msg::MatrixTitle => "=== 基线 {} 与 {} 个目标的差异矩阵 ===",
+        Msg::MatrixAllSame => "所有目标都与基线相同",
+        Msg::MatrixKey => "键",
+        Msg::MatrixBaseline => "基线",
+        Msg::MatrixTargetCount => "  {}: {} 处差异",
+        Msg::ReviewSection => "── {} ({} 处) ──",
+        Msg::ReviewAccepted => " [已接受]",
+        Msg::ReviewRejected => " [已拒绝]",
+        Msg::ReviewPrompt => "接受这处变更？[y,n,s,a,d,p,q,?] ",
+        Msg::ReviewFirst => "已经是第一处变更",
+        Msg::ReviewUnknown => "未知的命令: {}，输入 ? 查看帮助",
+        Msg::ReviewDone => "已接受 {} 处，已拒绝 {} 处，未审阅 {} 处",
+        Msg::ReviewHelp => {
+            "\
+y - 接受这处变更
+n - 拒绝这处变更
+s - 暂不决定，看下一处（直接回车相同）
+a - 接受这处及同一段中剩下的变更
+d - 拒绝这处及同一段中剩下的变更
+p - 回到上一处
+q - 结束审阅，剩下的变更不做决定
+? - 显示帮助"
+        }
+        Msg::PhaseParseOld => "解析旧文件",
+        Msg::PhaseParseNew => "解析新文件",
+        Msg::PhaseCompare => "比较",
+        Msg::PhaseRender => "输出报告",
+        Msg::Timings => "耗时:",
+        Msg::TimingsTotal => "  合计: {}",
+        Msg::ProgressBar => "读取 {} [{}{}] {}% {}/{} MB",
+        Msg::ProgressBytes => "读取 {} {} MB",
+        Msg::ProgressPairs => "已读取 {}/{} 组文件",
+        Msg::ReadConfigFileFailed => "读取配置文件失败！{}: {}",
+        Msg::ParseConfigFileFailed => "解析配置文件失败！{}: {}",
+        Msg::ConfigNotMapping => "配置文件必须是一个映射: {}",
+        Msg::ConfigKeyNotString => "配置文件中的键必须是字符串: {}",
+        Msg::ConfigInputOption => "配置文件中不能设置 {}，要比较的文件请在命令行中指定: {}",
+        Msg::ConfigUnknownOption => "配置文件中有未知的选项 {}: {}",
+        Msg::ConfigNotScalar => "配置文件中 {} 的值必须是标量: {}",
+        Msg::ReadDirFailed => "读取目录失败！{}: {}",
+        Msg::DuplicateKey => "{}（第 {} 行与第 {} 行）",
+        Msg::DuplicateKeys => "解析 YAML 失败！{} 处重复的键: {}",
+        Msg::NotInGitRepo => "文件不在当前 git 仓库中: {}",
+        Msg::GitShowFailed => "读取 {} 失败！{}",
+        Msg::RunGitFailed => "无法执行 git！{}",
+        Msg::InvalidVariable => "变量必须写成 key=value 的形式: {}",
+        Msg::PatchPathMissing => "第 {} 个操作的路径不存在: {}",
+        Msg::InvalidConfigMapUrl => "ConfigMap 地址应为 k8s://命名空间/ConfigMap 名/键: {}",
+        Msg::RunCurlFailed => "无法执行 curl！{}",
+        Msg::DownloadFailed => "下载失败！{}: {}",
+        Msg::RunKubectlFailed => "无法执行 kubectl！{}",
+        Msg::ReadConfigMapFailed => "读取 ConfigMap 失败！{}: {}",
+        Msg::ParseKubectlFailed => "解析 kubectl 的输出失败！{}: {}",
+        Msg::InvalidBase64 => "binaryData 不是合法的 base64: {}",
+        Msg::ConfigMapKeyMissing => "ConfigMap 中没有这个键: {}",
+        Msg::SopsToml => "sops 不支持 TOML 文件: {}",
+        Msg::WriteTempFailed => "写入临时文件失败！{}: {}",
+        Msg::RunSopsFailed => "无法执行 sops！{}",
+        Msg::SopsDecryptFailed => "sops 解密 {} 失败！{}",
+        Msg::CreateDirFailed => "创建输出目录失败！{}: {}",
+        Msg::SerializeDiffFailed => "序列化差异失败！{}",
+        Msg::WriteDiffFileFailed => "写入差异文件失败！{}: {}",
+        Msg::TransformNeedsExec => {
+            "转换 {} 需要执行外部命令，请确认命令可信后同时指定 --allow-exec"
+        }
+        Msg::TransformParseFailed => "{} 转换解析失败！{}",
+        Msg::TransformSerializeFailed => "{} 转换序列化失败！{}",
+        Msg::StartCommandFailed => "启动转换命令失败！{}: {}",
+        Msg::WaitCommandFailed => "等待转换命令失败！{}: {}",
+        Msg::CommandFailed => "转换命令执行失败（{}）: {}",
+        #[cfg(feature = "schema")]
+        Msg::SchemaRequired => "删除了必需的键",
+        #[cfg(feature = "schema")]
+        Msg::SchemaType => "类型不符合 schema",
+        #[cfg(feature = "schema")]
+        Msg::SchemaNotAllowed => "schema 不允许的键",
+        #[cfg(feature = "schema")]
+        Msg::SchemaOther => "不符合 schema",
+        #[cfg(feature = "schema")]
+        Msg::InvalidSchema => "无效的 JSON Schema！{}",
+        #[cfg(feature = "schema")]
+        Msg::YamlToJsonFailed => "无法把 YAML 转换为 JSON！{}",
+        #[cfg(feature = "schema")]
+        Msg::BreakingSection => "破坏 schema 的变更:",
+        #[cfg(feature = "schema")]
+        Msg::NoBreaking => "没有引入新的 schema 违规",
+        #[cfg(feature = "schema")]
+        Msg::SchemaSection => "Schema 校验:",
+        #[cfg(feature = "schema")]
+        Msg::SchemaValid => "新配置文件符合 schema",
+    }
+}
+
+fn en(msg: Msg) -> &'static str {
+    match msg {
+        Msg::ReportTitle => "=== YAML configuration diff report ===",
+        Msg::Stats => "Statistics:",
+        Msg::StatAdded => "  Added: {}",
+        Msg::StatRemoved => "  Removed: {}",
+        Msg::StatMoved => "  Moved: {}",
+        Msg::StatRenamed => "  Renamed: {}",
+        Msg::StatReordered => "  Reordered: {}",
+        Msg::StatMigrated => "  Migrated: {}",
+        Msg::StatModified => "  Modified: {}{}",
+        Msg::TypeChanges => "type changes {}",
+        Msg::WhitespaceOnly => "whitespace only {}",
+        Msg::DetailsOpen => " (",
+        Msg::DetailsSeparator => ", ",
+        Msg::DetailsClose => ")",
+        Msg::AddedSection => "Added keys:",
+        Msg::RemovedSection => "Removed keys:",
+        Msg::RenamedSection => "Renamed sections:",
+        Msg::MovedAlong => " ({} keys moved along)",
+        Msg::ReorderedSection => "Reordered elements:",
+        Msg::MigratedSection => "Keys migrated by the rename table:",
+        Msg::MigrateReason => {
+            "old key only in the old file, new key only in the new file, paired by --renames"
+        }
+        Msg::MovedSection => "Moved keys:",
+        Msg::ModifiedSection => "Modified keys:",
+        Msg::TypeChange => " [type change: {} → {}]",
+        Msg::InlineDiff => "  diff",
+        Msg::Before => "  before {}",
+        Msg::After => "  after  {}",
+        Msg::OldSide => "old",
+        Msg::NewSide => "new",
+        Msg::Reason => "    reason: {}",
+        Msg::ReasonRedacted => "    reason: {}, value hidden by rule",
+        Msg::OnlyInNew => "only in the new file",
+        Msg::OnlyInOld => "only in the old file",
+        Msg::RenameReason => "most keys and values in the subtree match under a different path",
+        Msg::MoveReason => "same value and key name under a different parent",
+        Msg::DifferentKinds => "values differ ({} → {})",
+        Msg::DifferentNumberKinds => "number types differ ({} → {})",
+        Msg::BeyondTolerance => "numbers differ by {}, beyond tolerance {}",
+        Msg::BeyondEpsilon => "numbers differ by {}, beyond --epsilon {}",
+        Msg::UnorderedDiffers => "elements differ when compared as an unordered sequence",
+        Msg::SameKindDiffers => "values differ (both {})",
+        Msg::BySeverity => "By severity:",
+        Msg::NoDifferences => "No configuration differences found",
+        Msg::KindBool => "bool",
+        Msg::KindNumber => "number",
+        Msg::KindString => "string",
+        Msg::KindSequence => "sequence",
+        Msg::KindMapping => "mapping",
+        Msg::KindTagged => "tag {}",
+        Msg::ReadFileFailed => "Failed to read config file! {}: {}",
+        Msg::ReadStdinFailed => "Failed to read standard input! {}",
+        Msg::CreateOutputFailed => "Failed to create output file! {}: {}",
+        Msg::WriteOutputFailed => "Failed to write output file! {}: {}",
+        Msg::ParseYamlFailed => "Failed to parse YAML! {}",
+        Msg::MergeKeysFailed => "Failed to expand YAML merge keys! {}",
+        Msg::ParseJsonFailed => "Failed to parse JSON! {}",
+        Msg::TomlNotUtf8 => "TOML file is not valid UTF-8! {}",
+        Msg::ParseTomlFailed => "Failed to parse TOML! {}",
+        Msg::OldFile => "old file",
+        Msg::NewFile => "new file",
+        Msg::AtLineColumn => "line {}, column {}",
+        Msg::FilesFailedToParse => "{} file(s) failed to parse:",
+        Msg::CoercedKeyConflict => {
+            "key {} is ambiguous with --coerce-key-types: the same mapping has a string key and a non-string key with the same text (e.g. \"8080\" and 8080): {}"
+        }
+        Msg::UnsortedInput => {
+            "warning: keys in the {} are not sorted ({} appears before {}), --assume-sorted results may be wrong"
+        }
+        Msg::LowMemoryMultipleDocuments => {
+            "--low-memory only supports files with a single YAML document: {}"
+        }
+        Msg::ReportName => "YAML configuration diff report",
+        Msg::HtmlLang => "en",
+        Msg::HtmlAdded => "Added",
+        Msg::HtmlRemoved => "Removed",
+        Msg::HtmlModified => "Modified",
+        Msg::Added => "added",
+        Msg::Removed => "removed",
+        Msg::Modified => "modified",
+        Msg::Changes => "changes",
+        Msg::CountsHeader => "| Added | Removed | Modified |",
+        Msg::AddedHeading => "Added keys",
+        Msg::RemovedHeading => "Removed keys",
+        Msg::ModifiedHeading => "Modified keys",
+        Msg::AddedTableHeader => "| Key | New value |",
+        Msg::RemovedTableHeader => "| Key | Old value |",
+        Msg::ModifiedTableHeader => "| Key | Old value → new value |",
+        Msg::GithubAdded => "added {}: {}",
+        Msg::GithubRemoved => "removed {}: {}",
+        Msg::GithubModified => "{} changed from {} to {}",
+        Msg::JunitAdded => "added: {}",
+        Msg::JunitRemoved => "removed: {}",
+        Msg::BySection => "By section:",
+        Msg::DeepestChange => "Deepest change: {} ({} levels)",
+        Msg::DiffstatSummary => " {} section(s) changed, {} added, {} removed, {} modified",
+        Msg::UnchangedSections => "Unchanged sections:",
+        Msg::UnchangedSection => "{} (unchanged, {} keys)",
+        Msg::KeyCaseSection => "Keys that differ only in case:",
+        Msg::NoKeyCaseMismatch => "No keys differ only in case",
+        Msg::SummaryTitle => "=== Summary ===",
+        Msg::PairFailed => "failed",
+        Msg::PairUnchanged => "no differences",
+        Msg::PairFailedSeeErrors => "Comparison failed, see the error output",
+        Msg::SummaryTotals => "{} file pair(s), {} with differences, {} identical",
+        Msg::SummaryOnlyInDirs => ", {} only in the old directory, {} only in the new directory",
+        Msg::SummaryFailures => ", {} failed",
+        Msg::SummaryChanges => "; total {} {} {}",
+        Msg::OnlyInOldDir => "{} (only in the old directory)",
+        Msg::OnlyInNewDir => "{} (only in the new directory)",
+        Msg::OldStdinDocument => "old document from standard input",
+        Msg::NewStdinDocument => "new document from standard input",
+        Msg::StandardInput => "standard input",
+        Msg::AcceptedWritten => "Accepted changes written to {}",
+        Msg::RoundTripPassed => "Round-trip check passed",
+        Msg::MissingOutputPath => "Missing the path of the output file: {}",
+        Msg::CurrentDirFailed => "Failed to get the current directory! {}",
+        Msg::WatchStdin => "--watch cannot be used with standard input",
+        Msg::MultiplePlainOutputs => {
+            "Only one --output may omit the format; write the others as FORMAT:PATH"
+        }
+        Msg::UnsupportedEncoding => "Unsupported character encoding: {}",
+        Msg::InvalidTagHandler => "Expected TAG=KIND, e.g. !date=timestamp: {}",
+        Msg::InvalidConfigRules => "Invalid rules in the config file! {}",
+        Msg::SopsDecryptStdinPair => "--sops decrypt cannot be used with --stdin-pair",
+        Msg::BothStdin => {
+            "--old and --new cannot both be -, standard input can only be read once; use --stdin-pair to read both from standard input"
+        }
+        Msg::SerializePatchFailed => "Failed to serialize the patch! {}",
+        Msg::SerializeOpsFailed => "Failed to serialize the operation list! {}",
+        Msg::SerializeMergeFailed => "Failed to serialize the merge result! {}",
+        Msg::SerializeResultFailed => "Failed to serialize the result! {}",
+        #[cfg(feature = "schema")]
+        Msg::SchemaViolations => "The new config file has {} schema violation(s)",
+        #[cfg(feature = "schema")]
+        Msg::SchemaBreaking => "{} change(s) break the schema",
+        Msg::InteractiveNeedsTerminal => "--interactive must run in a terminal",
+        Msg::LowMemoryOnePair => "--low-memory can only compare a single pair of files",
+        Msg::LowMemoryNoDir => "--low-memory does not support comparing directories: {}",
+        Msg::LowMemoryNoToml => "--low-memory does not support TOML files: {}",
+        Msg::EmptyInput => {
+            "{} has no config keys (empty, only comments or only null); it may be a truncated file"
+        }
+        Msg::MergeConflicts => "The old and new versions conflict in {} place(s)",
+        Msg::ParseOpsFailed => "Failed to parse the operation list! {}: {}",
+        Msg::KeyNotFound => "Key not found: {}: {}",
+        Msg::NotNormalized => "File is not normalized: {}",
+        Msg::RoundTripUnflattenable => {
+            "Round-trip check failed! The file rebuilt from the diff differs from the new file in parts that cannot be flattened"
+        }
+        Msg::RoundTripFailed => {
+            "Round-trip check failed! The file rebuilt from the diff differs from the new file: {}"
+        }
+        Msg::FanOutOptions => {
+            "--old-rev, --new-rev, --base and --interactive cannot be used with multiple --new"
+        }
+        Msg::FanOutFilesOnly => "Every input must be a file when multiple --new are given: {}",
+        Msg::DirMismatch => "--old and --new must both be files or both be directories: {}, {}",
+        Msg::DirUnsupportedOptions => {
+            "--patch, --emit-patch, --split-output, --key-case-report, --base, --old-rev/--new-rev and --interactive do not support comparing directories"
+        }
+        Msg::HtmlOnePair => "--format html can only compare a single pair of files",
+        Msg::OutputTargetOnePair => "--output FORMAT:PATH can only compare a single pair of files",
+        Msg::PairsFailed => "{}/{} file pair(s) failed to compare: {}",
+        Msg::TypeChangeEntry => "{} ({} → {})",
+        Msg::TypeChangesFound => "{} key(s) changed type: {}",
+        Msg::LimitExceeded => "{} {}, over {} {}",
+        Msg::LimitSeparator => "; ",
+        Msg::ChangeLimitsExceeded => "Change count over the limit: {}",
+        Msg::SeverityChanged => "{} key(s) at {} level changed: {}",
+        Msg::PairsFileNotUtf8 => "The file pair list is not valid UTF-8! {}: {}",
+        Msg::PairsFileLine => {
+            "Line {} of the file pair list is malformed, expected two paths separated by a tab or comma"
+        }
+        Msg::ParseConfigFailed => "Failed to parse the config file! {}",
+        Msg::StdinPairCount => "--stdin-pair needs two documents separated by ---, only {} found",
+        Msg::ThreeWayTitle => "=== YAML configuration three-way diff report ===",
+        Msg::Missing => "<missing>",
+        Msg::OursOnlySection => "Keys modified only in the old version:",
+        Msg::TheirsOnlySection => "Keys modified only in the new version:",
+        Msg::BothSameSection => "Keys modified the same way on both sides:",
+        Msg::ConflictSection => "Conflicting keys:",
+        Msg::ConflictBase => "    base   {}",
+        Msg::ConflictOurs => "    old    {}",
+        Msg::ConflictTheirs => "    new    {}",
+        Msg::MergedTitle => "=== YAML configuration merged diff report ===",
+        Msg::FilePairs => "File pairs:",
+        Msg::SeenInPairs => "(in {}/{} pairs: {})",
+        Msg::NothingToMerge => "No changes to merge",
+        Msg::WillMerge => "Will merge {} change(s) from the new file:",
+        Msg::MergeSkipped => {
+            "{} more change(s) are outside --only and keep the content of the old file"
+        }
+        Msg::MatrixTitle => "=== Diff matrix of baseline {} against {} target(s) ===",
+        Msg::MatrixAllSame => "All targets match the baseline",
+        Msg::MatrixKey => "Key",
+        Msg::MatrixBaseline => "Baseline",
+        Msg::MatrixTargetCount => "  {}: {} difference(s)",
+        Msg::ReviewSection => "── {} ({}) ──",
+        Msg::ReviewAccepted => " [accepted]",
+        Msg::ReviewRejected => " [rejected]",
+        Msg::ReviewPrompt => "Accept this change? [y,n,s,a,d,p,q,?] ",
+        Msg::ReviewFirst => "Already at the first change",
+        Msg::ReviewUnknown => "Unknown command: {}, enter ? for help",
+        Msg::ReviewDone => "{} accepted, {} rejected, {} not reviewed",
+        Msg::ReviewHelp => {
+            "\
+y - accept this change
+n - reject this change
+s - skip for now and show the next change (same as Enter)
+a - accept this and the remaining changes in the section
+d - reject this and the remaining changes in the section
+p - go back to the previous change
+q - stop reviewing and leave the remaining changes undecided
+? - show help"
+        }
+        Msg::PhaseParseOld => "parse old file",
+        Msg::PhaseParseNew => "parse new file",
+        Msg::PhaseCompare => "compare",
+        Msg::PhaseRender => "render report",
+        Msg::Timings => "Timings:",
+        Msg::TimingsTotal => "  total: {}",
+        Msg::ProgressBar => "reading {} [{}{}] {}% {}/{} MB",
+        Msg::ProgressBytes => "reading {} {} MB",
+        Msg::ProgressPairs => "{}/{} file pairs read",
+        Msg::ReadConfigFileFailed => "Failed to read the config file! {}: {}",
+        Msg::ParseConfigFileFailed => "Failed to parse the config file! {}: {}",
+        Msg::ConfigNotMapping => "The config file must be a mapping: {}",
+        Msg::ConfigKeyNotString => "Keys in the config file must be strings: {}",
+        Msg::ConfigInputOption => {
+            "{} cannot be set in the config file, give the files to compare on the command line: {}"
+        }
+        Msg::ConfigUnknownOption => "Unknown option {} in the config file: {}",
+        Msg::ConfigNotScalar => "The value of {} in the config file must be a scalar: {}",
+        Msg::ReadDirFailed => "Failed to read directory! {}: {}",
+        Msg::DuplicateKey => "{} (lines {} and {})",
+        Msg::DuplicateKeys => "Failed to parse YAML! {} duplicate key(s): {}",
+        Msg::NotInGitRepo => "File is not in the current git repository: {}",
+        Msg::GitShowFailed => "Failed to read {}! {}",
+        Msg::RunGitFailed => "Failed to run git! {}",
+        Msg::InvalidVariable => "Variables must be written as key=value: {}",
+        Msg::PatchPathMissing => "The path of operation {} does not exist: {}",
+        Msg::InvalidConfigMapUrl => {
+            "ConfigMap address should be k8s://namespace/configmap-name/key: {}"
+        }
+        Msg::RunCurlFailed => "Failed to run curl! {}",
+        Msg::DownloadFailed => "Download failed! {}: {}",
+        Msg::RunKubectlFailed => "Failed to run kubectl! {}",
+        Msg::ReadConfigMapFailed => "Failed to read the ConfigMap! {}: {}",
+        Msg::ParseKubectlFailed => "Failed to parse the output of kubectl! {}: {}",
+        Msg::InvalidBase64 => "binaryData is not valid base64: {}",
+        Msg::ConfigMapKeyMissing => "The ConfigMap has no such key: {}",
+        Msg::SopsToml => "sops does not support TOML files: {}",
+        Msg::WriteTempFailed => "Failed to write a temporary file! {}: {}",
+        Msg::RunSopsFailed => "Failed to run sops! {}",
+        Msg::SopsDecryptFailed => "sops failed to decrypt {}! {}",
+        Msg::CreateDirFailed => "Failed to create the output directory! {}: {}",
+        Msg::SerializeDiffFailed => "Failed to serialize the diff! {}",
+        Msg::WriteDiffFileFailed => "Failed to write the diff file! {}: {}",
+        Msg::TransformNeedsExec => {
+            "Transform {} runs an external command; pass --allow-exec once you trust the command"
+        }
+        Msg::TransformParseFailed => "The {} transform failed to parse! {}",
+        Msg::TransformSerializeFailed => "The {} transform failed to serialize! {}",
+        Msg::StartCommandFailed => "Failed to start the transform command! {}: {}",
+        Msg::WaitCommandFailed => "Failed to wait for the transform command! {}: {}",
+        Msg::CommandFailed => "The transform command failed ({}): {}",
+        #[cfg(feature = "schema")]
+        Msg::SchemaRequired => "removed a required key",
+        #[cfg(feature = "schema")]
+        Msg::SchemaType => "type does not match the schema",
+        #[cfg(feature = "schema")]
+        Msg::SchemaNotAllowed => "key not allowed by the schema",
+        #[cfg(feature = "schema")]
+        Msg::SchemaOther => "does not match the schema",
+        #[cfg(feature = "schema")]
+        Msg::InvalidSchema => "Invalid JSON Schema! {}",
+        #[cfg(feature = "schema")]
+        Msg::YamlToJsonFailed => "Failed to convert YAML to JSON! {}",
+        #[cfg(feature = "schema")]
+        Msg::BreakingSection => "Changes that break the schema:",
+        #[cfg(feature = "schema")]
+        Msg::NoBreaking => "No new schema violations",
+        #[cfg(feature = "schema")]
+        Msg::SchemaSection => "Schema validation:",
+        #[cfg(feature = "schema")]
+        Msg::SchemaValid => "The new config file matches the schema",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Lang, Msg};
+
+    #[test]
+    fn test_lang() {
+        assert_eq!(Lang::from_locale(Some("zh_CN.UTF-8")), Lang::Zh);
+        assert_eq!(Lang::from_locale(Some("en_US.UTF-8")), Lang::En);
+        assert_eq!(Lang::from_locale(Some("de_DE")), Lang::En);
+        assert_eq!(Lang::from_locale(Some("C.UTF-8")), Lang::Zh);
+        assert_eq!(Lang::from_locale(None), Lang::Zh);
+        assert_eq!(
+            Lang::En.format(Msg::TypeChange, &[&"string", &"number"]),
+            " [type change: string → number]"
+        );
+        assert_eq!(Lang::Zh.format(Msg::StatAdded, &[&3]), "  新增: 3");
+    }
+}
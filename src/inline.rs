@@ -0,0 +1,221 @@
+use colored::*;
+
+/// 字符串至少有这么多个字符时，修改才按字符级差异显示
+pub const INLINE_DIFF_MIN_LEN: usize = 40;
+
+/// 去掉公共前后缀后，中间部分的 LCS 表超过这么多格时不再逐字符（逐行）比较，整段作为删除 + 新增
+const MAX_LCS_CELLS: usize = 4_000_000;
+
+/// 省略的未变化行数量提示
+const SKIPPED: &str = "…";
+
+/// 字符级差异中的一段
+#[derive(Debug, PartialEq)]
+pub enum Segment {
+    Same(String),
+    Removed(String),
+    Added(String),
+}
+
+/// 按最长公共子序列逐字符比较两个字符串，相邻的同类字符合并成一段
+pub fn char_diff(old: &str, new: &str) -> Vec<Segment> {
+    let old: Vec<char> = old.chars().collect();
+    let new: Vec<char> = new.chars().collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old_mid, new_mid) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    let mut segments = Vec::new();
+    push(&mut segments, Segment::Same, &old[..prefix]);
+    if old_mid.len() * new_mid.len() > MAX_LCS_CELLS {
+        push(&mut segments, Segment::Removed, old_mid);
+        push(&mut segments, Segment::Added, new_mid);
+    } else {
+        lcs_diff(&mut segments, old_mid, new_mid);
+    }
+    push(&mut segments, Segment::Same, &old[old.len() - suffix..]);
+    segments
+}
+
+fn lcs_diff(segments: &mut Vec<Segment>, old: &[char], new: &[char]) {
+    for edit in lcs_edits(old, new) {
+        match edit {
+            Edit::Same(i) => push(segments, Segment::Same, &old[i..=i]),
+            Edit::Removed(i) => push(segments, Segment::Removed, &old[i..=i]),
+            Edit::Added(j) => push(segments, Segment::Added, &new[j..=j]),
+        }
+    }
+}
+
+/// 最长公共子序列给出的一步编辑，带有元素在旧序列或新序列中的下标
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Edit {
+    Same(usize),
+    Removed(usize),
+    Added(usize),
+}
+
+fn lcs_edits<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Edit> {
+    // lcs[i][j]：old[i..] 与 new[j..] 的最长公共子序列长度
+    let width = new.len() + 1;
+    let mut lcs = vec![0usize; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i * width + j] = if old[i] == new[j] {
+                lcs[(i + 1) * width + j + 1] + 1
+            } else {
+                lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut edits = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            edits.push(Edit::Same(i));
+            (i, j) = (i + 1, j + 1);
+        } else if i < old.len()
+            && (j == new.len() || lcs[(i + 1) * width + j] >= lcs[i * width + j + 1])
+        {
+            // 同样长时先删后增，输出为 `[-旧-]{+新+}`
+            edits.push(Edit::Removed(i));
+            i += 1;
+        } else {
+            edits.push(Edit::Added(j));
+            j += 1;
+        }
+    }
+    edits
+}
+
+/// 逐行比较多行字符串（内嵌的脚本、证书、nginx 配置等），只保留变化的行和前后各 `context` 行
+///
+/// 未变化的行以两个空格开头，删除的行以红色的 `-` 开头，新增的行以绿色的 `+` 开头；
+/// 省略的未变化行写作一行 `…`。每行都加上 `indent` 缩进。
+pub fn render_lines(old: &str, new: &str, context: usize, indent: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let edits = if old.len() * new.len() > MAX_LCS_CELLS {
+        (0..old.len())
+            .map(Edit::Removed)
+            .chain((0..new.len()).map(Edit::Added))
+            .collect()
+    } else {
+        lcs_edits(&old, &new)
+    };
+
+    // 与最近的变化行相距不超过 context 的未变化行需要显示
+    let changed: Vec<usize> = (edits.iter().enumerate())
+        .filter(|(_, edit)| !matches!(edit, Edit::Same(_)))
+        .map(|(i, _)| i)
+        .collect();
+    let near = |i: usize| {
+        let next = changed.partition_point(|&c| c < i);
+        changed.get(next).is_some_and(|&c| c - i <= context)
+            || next > 0 && i - changed[next - 1] <= context
+    };
+
+    let mut lines = Vec::new();
+    let mut skipping = false;
+    for (i, edit) in edits.iter().enumerate() {
+        let line = match *edit {
+            Edit::Same(k) if near(i) => format!("  {}", old[k]),
+            Edit::Same(_) => {
+                if !skipping {
+                    lines.push(format!("{indent}{}", SKIPPED.dimmed()));
+                }
+                skipping = true;
+                continue;
+            }
+            Edit::Removed(k) => format!("- {}", old[k]).red().to_string(),
+            Edit::Added(k) => format!("+ {}", new[k]).green().to_string(),
+        };
+        skipping = false;
+        lines.push(format!("{indent}{line}"));
+    }
+    lines.join("\n")
+}
+
+/// 追加一段字符，与前一段同类时直接合并
+fn push(segments: &mut Vec<Segment>, kind: fn(String) -> Segment, chars: &[char]) {
+    if chars.is_empty() {
+        return;
+    }
+    let text: String = chars.iter().collect();
+    match (segments.last_mut(), kind(text)) {
+        (Some(Segment::Same(last)), Segment::Same(text))
+        | (Some(Segment::Removed(last)), Segment::Removed(text))
+        | (Some(Segment::Added(last)), Segment::Added(text)) => last.push_str(&text),
+        (_, segment) => segments.push(segment),
+    }
+}
+
+/// 把字符级差异渲染成一行：删除的部分写作红色的 `[-…-]`，新增的部分写作绿色的 `{+…+}`，
+/// 与 `git diff --word-diff=plain` 的写法相同，不带颜色时也能分辨
+pub fn render(segments: &[Segment]) -> String {
+    segments
+        .iter()
+        .map(|segment| match segment {
+            Segment::Same(text) => text.clone(),
+            Segment::Removed(text) => format!("[-{text}-]").red().to_string(),
+            Segment::Added(text) => format!("{{+{text}+}}").green().to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Segment, char_diff, render, render_lines};
+
+    #[test]
+    fn test_char_diff() {
+        let segments = char_diff(
+            "postgres://app@db-1.internal:5432/orders",
+            "postgres://app@db-2.internal:6432/orders",
+        );
+        assert_eq!(
+            segments,
+            vec![
+                Segment::Same("postgres://app@db-".into()),
+                Segment::Removed("1".into()),
+                Segment::Added("2".into()),
+                Segment::Same(".internal:".into()),
+                Segment::Removed("5".into()),
+                Segment::Added("6".into()),
+                Segment::Same("432/orders".into()),
+            ]
+        );
+
+        colored::control::set_override(false);
+        assert_eq!(render(&char_diff("abc", "aXc")), "a[-b-]{+X+}c");
+        assert_eq!(render(&char_diff("", "新值")), "{+新值+}");
+        assert_eq!(render(&char_diff("same", "same")), "same");
+    }
+
+    #[test]
+    fn test_render_lines() {
+        colored::control::set_override(false);
+        let old = "server {\n  listen 80;\n  a;\n  b;\n  c;\n  d;\n  root /srv;\n}\n";
+        let new = "server {\n  listen 8080;\n  a;\n  b;\n  c;\n  d;\n  root /srv;\n}\n";
+        assert_eq!(
+            render_lines(old, new, 1, "    "),
+            "      server {\n    -   listen 80;\n    +   listen 8080;\n        a;\n    …"
+        );
+        // 相距不远的两处变化之间不省略
+        let new = new.replace("root /srv", "root /data");
+        assert_eq!(
+            render_lines(old, &new, 3, ""),
+            "  server {\n-   listen 80;\n+   listen 8080;\n    a;\n    b;\n    c;\n    d;\n\
+             -   root /srv;\n+   root /data;\n  }"
+        );
+    }
+}
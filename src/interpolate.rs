@@ -0,0 +1,106 @@
+use crate::i18n::{Msg, error_lang};
+use anyhow::{Result, anyhow};
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+use std::env;
+use std::sync::LazyLock;
+use yml_diff::flatten::{FlattenOptions, flatten};
+use yml_diff::get_val_string;
+
+/// `${NAME}`、`${NAME:默认值}` 与 `${NAME:-默认值}`
+static ENV_PLACEHOLDER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_.\-]*)(?::-?([^}]*))?\}").expect("合法的正则表达式")
+});
+
+/// Go 模板中只取值的写法 `{{ .Values.a.b }}`，其他模板语法原样保留
+static TEMPLATE_PLACEHOLDER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\{\{-?\s*\.([A-Za-z_][A-Za-z0-9_.]*)\s*-?\}\}").expect("合法的正则表达式")
+});
+
+/// 替换占位符时使用的变量：--vars / --vars-file 给出的值优先，--resolve-env 时再查找环境变量
+#[derive(Debug, Default)]
+pub struct Vars {
+    values: HashMap<String, String>,
+    env: bool,
+}
+
+impl Vars {
+    pub fn new(env: bool) -> Self {
+        Vars {
+            values: HashMap::new(),
+            env,
+        }
+    }
+
+    /// 加入一个 `key=value` 形式的变量
+    pub fn push(&mut self, pair: &str) -> Result<()> {
+        let (key, value) = pair
+            .split_once('=')
+            .ok_or_else(|| anyhow!(error_lang().format(Msg::InvalidVariable, &[&pair])))?;
+        self.values.insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    /// 加入 YAML/JSON 文件中的变量，嵌套的键以 `.` 连接，如 Helm 的 values 文件
+    pub fn extend_from_file(&mut self, doc: &serde_yaml::Value) {
+        let opts = FlattenOptions::default();
+        self.values
+            .extend(flatten(doc, &opts).map(|(key, val)| (key, get_val_string(val).into_owned())));
+    }
+
+    fn lookup(&self, name: &str) -> Option<String> {
+        self.values
+            .get(name)
+            .cloned()
+            .or_else(|| self.env.then(|| env::var(name).ok()).flatten())
+    }
+
+    /// 替换文本中能够解析的占位符；找不到变量且没有默认值的占位符原样保留
+    ///
+    /// `{{ .Values.a }}` 依次查找 `Values.a` 和去掉 `Values.` 之后的 `a`，
+    /// 这样可以直接用 Helm 的 values 文件作为 --vars-file。
+    pub fn interpolate(&self, text: &str) -> String {
+        let text = ENV_PLACEHOLDER.replace_all(text, |caps: &Captures| {
+            self.lookup(&caps[1])
+                .or_else(|| caps.get(2).map(|default| default.as_str().to_string()))
+                .unwrap_or_else(|| caps[0].to_string())
+        });
+        TEMPLATE_PLACEHOLDER
+            .replace_all(&text, |caps: &Captures| {
+                let path = &caps[1];
+                self.lookup(path)
+                    .or_else(|| path.strip_prefix("Values.").and_then(|p| self.lookup(p)))
+                    .unwrap_or_else(|| caps[0].to_string())
+            })
+            .into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Vars;
+
+    #[test]
+    fn test_interpolate() {
+        let mut vars = Vars::new(false);
+        vars.push("DB_HOST=db.internal").unwrap();
+        vars.extend_from_file(&serde_yaml::from_str("replicas: 3\nimage: {tag: v2}\n").unwrap());
+        assert!(vars.push("missing-equals").is_err());
+
+        assert_eq!(
+            vars.interpolate(
+                "host: ${DB_HOST:localhost}\nport: ${DB_PORT:5432}\nuser: ${DB_USER:-app}\n"
+            ),
+            "host: db.internal\nport: 5432\nuser: app\n"
+        );
+        assert_eq!(
+            vars.interpolate("replicas: {{ .Values.replicas }}\ntag: {{.Values.image.tag}}\n"),
+            "replicas: 3\ntag: v2\n"
+        );
+        // 无法解析的占位符和其他模板语法原样保留
+        assert_eq!(
+            vars.interpolate("a: ${UNKNOWN}\nb: {{ .Values.nope }}\nc: {{ include \"x\" . }}\n"),
+            "a: ${UNKNOWN}\nb: {{ .Values.nope }}\nc: {{ include \"x\" . }}\n"
+        );
+    }
+}
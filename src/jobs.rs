@@ -0,0 +1,67 @@
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// 没有指定 --jobs 时的并发数：可用的 CPU 核数
+pub fn default_jobs() -> usize {
+    thread::available_parallelism().map_or(1, NonZeroUsize::get)
+}
+
+/// 用最多 `jobs` 个线程对每一项执行 `f`，结果的顺序与 `items` 相同，与各项完成的先后无关
+///
+/// 各线程从同一个计数器领取下一项，耗时差别很大的文件也能均匀分配。`jobs` 为 1 或只有一项时
+/// 直接在当前线程中依次执行。
+pub fn map<'a, T: Sync, R: Send>(
+    items: &'a [T],
+    jobs: usize,
+    f: impl Fn(&'a T) -> R + Sync,
+) -> Vec<R> {
+    let jobs = jobs.clamp(1, items.len().max(1));
+    if jobs == 1 {
+        return items.iter().map(f).collect();
+    }
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<(usize, R)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(i) else {
+                            return done;
+                        };
+                        done.push((i, f(item)));
+                    }
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("比较线程不应 panic"))
+            .collect()
+    });
+    results.sort_unstable_by_key(|&(i, _)| i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::map;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_map_keeps_order() {
+        let items: Vec<u64> = (0..20).collect();
+        // 前面的项更慢，完成顺序与输入顺序相反
+        let slow = |&i: &u64| {
+            thread::sleep(Duration::from_millis(20 - i));
+            i * 2
+        };
+        let expected: Vec<u64> = items.iter().map(|i| i * 2).collect();
+        assert_eq!(map(&items, 4, slow), expected);
+        assert_eq!(map(&items, 1, slow), expected);
+        assert!(map(&[] as &[u64], 8, slow).is_empty());
+    }
+}
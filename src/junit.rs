@@ -0,0 +1,127 @@
+use crate::html::escape;
+use crate::i18n::Msg;
+use crate::{ReportOptions, display_val};
+use std::io::{self, Write};
+use yml_diff::{ChangeKind, ConfigDiff, DiffOptions};
+
+/// 测试用例的 classname，CI 中按它归类
+const CLASS_NAME: &str = "yml-diff";
+
+/// --format junit：JUnit XML 报告，每处差异是一个失败的测试用例，可以直接交给 Jenkins、GitLab 等
+/// 展示测试结果的组件
+///
+/// 每组比较的文件是一个 `<testsuite>`；键路径是用例名，`old → new` 是失败信息。
+/// 没有差异的一组输出一个通过的用例，否则部分 CI 会把空的测试套件当作错误。
+pub fn print_junit(
+    out: &mut dyn Write,
+    suites: &[(String, &ConfigDiff)],
+    opts: &DiffOptions,
+    report: &ReportOptions,
+) -> io::Result<()> {
+    let tests = |diff: &ConfigDiff| diff.changes().count().max(1);
+    let failures = |diff: &ConfigDiff| diff.changes().count();
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<testsuites name="{CLASS_NAME}" tests="{}" failures="{}">"#,
+        suites.iter().map(|(_, diff)| tests(diff)).sum::<usize>(),
+        suites.iter().map(|(_, diff)| failures(diff)).sum::<usize>()
+    )?;
+    for (name, diff) in suites {
+        writeln!(
+            out,
+            r#"  <testsuite name="{}" tests="{}" failures="{}">"#,
+            escape(name),
+            tests(diff),
+            failures(diff)
+        )?;
+        if diff.is_empty() {
+            writeln!(
+                out,
+                r#"    <testcase classname="{CLASS_NAME}" name="{}"/>"#,
+                escape(report.lang.get(Msg::NoDifferences))
+            )?;
+        }
+        for change in diff.changes() {
+            let value = |val| display_val(change.key, val, opts, report);
+            let (kind, message) = match change.kind {
+                ChangeKind::Added(new) => {
+                    ("added", report.lang.format(Msg::JunitAdded, &[&value(new)]))
+                }
+                ChangeKind::Removed(old) => (
+                    "removed",
+                    report.lang.format(Msg::JunitRemoved, &[&value(old)]),
+                ),
+                ChangeKind::Modified(old, new) => {
+                    ("modified", format!("{} → {}", value(old), value(new)))
+                }
+            };
+            writeln!(
+                out,
+                r#"    <testcase classname="{CLASS_NAME}" name="{}">"#,
+                escape(change.key)
+            )?;
+            writeln!(
+                out,
+                r#"      <failure type="{kind}" message="{}"/>"#,
+                escape(&message)
+            )?;
+            writeln!(out, "    </testcase>")?;
+        }
+        writeln!(out, "  </testsuite>")?;
+    }
+    writeln!(out, "</testsuites>")
+}
+
+/// 单组比较的测试套件名：`旧文件 → 新文件`
+pub fn suite_name(report: &ReportOptions) -> String {
+    match &report.labels {
+        Some((old, new)) => format!("{old} → {new}"),
+        None => CLASS_NAME.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::print_junit;
+    use crate::ReportOptions;
+    use serde_yaml::Value;
+    use yml_diff::{ConfigDiff, DiffOptions, cmp_yml_vals};
+
+    #[test]
+    fn test_print_junit() {
+        let old: Value = serde_yaml::from_str("port: 80\nhost: '<a>'\n").unwrap();
+        let new: Value = serde_yaml::from_str("port: 81\nuser: u\n").unwrap();
+        let opts = DiffOptions::default();
+        let diff = cmp_yml_vals(&old, &new, &opts);
+        let empty = ConfigDiff::default();
+
+        let mut out = Vec::new();
+        let suites = [
+            ("a.yml → b.yml".to_string(), &diff),
+            ("c.yml → d.yml".to_string(), &empty),
+        ];
+        print_junit(&mut out, &suites, &opts, &ReportOptions::default()).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="yml-diff" tests="4" failures="3">
+  <testsuite name="a.yml → b.yml" tests="3" failures="3">
+    <testcase classname="yml-diff" name="host">
+      <failure type="removed" message="删除: &lt;a&gt;"/>
+    </testcase>
+    <testcase classname="yml-diff" name="port">
+      <failure type="modified" message="80 → 81"/>
+    </testcase>
+    <testcase classname="yml-diff" name="user">
+      <failure type="added" message="新增: u"/>
+    </testcase>
+  </testsuite>
+  <testsuite name="c.yml → d.yml" tests="1" failures="0">
+    <testcase classname="yml-diff" name="没有发现配置差异"/>
+  </testsuite>
+</testsuites>
+"#
+        );
+    }
+}
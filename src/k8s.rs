@@ -0,0 +1,81 @@
+use serde_yaml::Value;
+
+/// 由 API server 写入、与清单内容无关的 `metadata` 字段，--k8s 时比较前去掉
+pub const SERVER_METADATA_FIELDS: [&str; 6] = [
+    "resourceVersion",
+    "managedFields",
+    "creationTimestamp",
+    "uid",
+    "generation",
+    "selfLink",
+];
+
+/// --k8s 时的文档标识：`apiVersion/kind/namespace/name`，集群级资源没有 namespace 一段
+pub fn resource_identity(doc: &Value) -> Option<String> {
+    let api_version = doc.get("apiVersion")?.as_str()?;
+    let kind = doc.get("kind")?.as_str()?;
+    let metadata = doc.get("metadata")?;
+    let name = metadata.get("name")?.as_str()?;
+    Some(match metadata.get("namespace").and_then(Value::as_str) {
+        Some(namespace) => format!("{api_version}/{kind}/{namespace}/{name}"),
+        None => format!("{api_version}/{kind}/{name}"),
+    })
+}
+
+/// 去掉资源中由集群填充的部分：顶层的 `status` 和 [`SERVER_METADATA_FIELDS`]
+///
+/// `kind: List` 的文档对其中的每个资源同样处理。
+pub fn strip_server_fields(doc: &mut Value) {
+    let Value::Mapping(map) = doc else {
+        return;
+    };
+    map.remove("status");
+    if let Some(Value::Mapping(metadata)) = map.get_mut("metadata") {
+        for field in SERVER_METADATA_FIELDS {
+            metadata.remove(field);
+        }
+    }
+    if let Some(Value::Sequence(items)) = map.get_mut("items") {
+        items.iter_mut().for_each(strip_server_fields);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{resource_identity, strip_server_fields};
+    use serde_yaml::Value;
+
+    #[test]
+    fn test_k8s_documents() {
+        let yaml = |s: &str| serde_yaml::from_str::<Value>(s).unwrap();
+        let mut doc = yaml(
+            "apiVersion: apps/v1\nkind: Deployment\n\
+             metadata: {name: web, namespace: prod, resourceVersion: '42', uid: x, labels: {app: web}}\n\
+             spec: {replicas: 2}\nstatus: {readyReplicas: 2}\n",
+        );
+        assert_eq!(
+            resource_identity(&doc).as_deref(),
+            Some("apps/v1/Deployment/prod/web")
+        );
+        strip_server_fields(&mut doc);
+        assert_eq!(
+            doc,
+            yaml(
+                "apiVersion: apps/v1\nkind: Deployment\n\
+                 metadata: {name: web, namespace: prod, labels: {app: web}}\nspec: {replicas: 2}\n"
+            )
+        );
+
+        let cluster_role = yaml(
+            "apiVersion: rbac.authorization.k8s.io/v1\nkind: ClusterRole\nmetadata: {name: admin}\n",
+        );
+        assert_eq!(
+            resource_identity(&cluster_role).as_deref(),
+            Some("rbac.authorization.k8s.io/v1/ClusterRole/admin")
+        );
+        assert_eq!(
+            resource_identity(&yaml("kind: Service\nmetadata: {name: a}\n")),
+            None
+        );
+    }
+}
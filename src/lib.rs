@@ -0,0 +1,46 @@
+//! yml-diff 中可以被其他工具复用的部分
+//!
+//! 命令行工具只负责读取文件和输出报告，比较本身都在这里。[`DiffOptions`] 的字段都是公开的，
+//! 用结构体更新语法只写需要修改的选项即可：
+//!
+//! ```
+//! use serde_yaml::Value;
+//! use yml_diff::rules::RuleAction;
+//! use yml_diff::{DiffOptions, cmp_yml_vals};
+//!
+//! let old: Value = serde_yaml::from_str("db: {host: a, password: x}\nreplicas: 2\n").unwrap();
+//! let new: Value = serde_yaml::from_str("db: {host: b, password: y}\nreplicas: 2.0\n").unwrap();
+//!
+//! let mut opts = DiffOptions {
+//!     strict_number_types: true,
+//!     ..Default::default()
+//! };
+//! opts.rules.push("*.password".parse().unwrap(), RuleAction::Ignore);
+//!
+//! let diff = cmp_yml_vals(&old, &new, &opts);
+//! let modified: Vec<&str> = diff.modified().keys().map(|k| k.as_str()).collect();
+//! assert_eq!(modified, ["db.host", "replicas"]);
+//! ```
+//!
+//! 比较和 [`report`] 中的渲染都不读写文件、不输出颜色，可以编译到 wasm32；只有 `Rules::load` 等
+//! 从路径读取的便捷函数需要文件系统。启用 `wasm` feature 后可以用 `diff_str` 直接比较两段文本。
+
+pub mod compare;
+pub mod diff;
+pub mod flatten;
+pub mod migrate;
+pub mod pattern;
+pub mod rename;
+pub mod reorder;
+pub mod report;
+pub mod rules;
+pub mod stream;
+pub mod tags;
+
+pub use diff::{
+    Change, ChangeKind, ConfigDiff, ConfigKey, DiffOptions, UnsortedInput, cmp_yml_vals,
+    cmp_yml_vals_with, extract_key_vals, get_val_string,
+};
+pub use report::DiffReport;
+#[cfg(feature = "wasm")]
+pub use report::diff_str;
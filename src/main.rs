@@ -1,14 +1,72 @@
 use anyhow::{Result, anyhow};
-use clap::Parser;
+use clap::{CommandFactory, Parser};
 use colored::*;
+use encoding_rs::{Encoding, UTF_8};
+use serde::Serialize;
 use serde_yaml::Value;
+use sha2::{Digest, Sha256};
 use std::borrow::Cow;
-use std::cmp::Ordering;
 use std::collections::{BTreeMap, HashMap, HashSet};
-use std::fs::File;
-use std::io::BufReader;
-use std::ops::Deref;
-use std::path::PathBuf;
+use std::env;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use xxhash_rust::xxh3::xxh3_64;
+use yml_diff::compare::StringNormalization;
+use yml_diff::diff::{
+    cmp_flat_entries, compare_flatten_options, float_pair, multiset_changes, number_kind,
+};
+use yml_diff::flatten::{FlattenOptions, IndexStyle, ListKey, coerced_key_conflict, flatten};
+use yml_diff::migrate::KeyRenames;
+use yml_diff::pattern::KeyPattern;
+use yml_diff::rules::{RuleAction, Rules, Severity};
+use yml_diff::stream::{self, StreamError};
+use yml_diff::tags::{TagHandler, TagOptions};
+use yml_diff::{
+    ChangeKind, ConfigDiff, ConfigKey, DiffOptions, cmp_yml_vals, extract_key_vals, get_val_string,
+};
+
+mod apply;
+mod config;
+mod dir;
+mod format;
+mod git;
+mod github;
+mod html;
+mod i18n;
+mod inline;
+mod interpolate;
+mod jobs;
+mod junit;
+mod k8s;
+mod markdown;
+mod matrix;
+mod merge;
+mod normalize;
+mod patch;
+mod progress;
+mod remote;
+mod review;
+#[cfg(feature = "schema")]
+mod schema;
+mod side_by_side;
+mod sops;
+mod source;
+mod split;
+mod three_way;
+mod transform;
+mod tree;
+mod unified;
+mod watch;
+
+use format::{InputFormat, ParseError, combine_documents, combine_documents_by, parse_file};
+use i18n::{Lang, Msg};
+use interpolate::Vars;
+use progress::{Phase, ProgressReader};
+use sops::SopsMode;
+use transform::Transform;
 
 #[derive(Parser)]
 #[command(
@@ -18,307 +76,3804 @@ use std::path::PathBuf;
     long_about = "Compare two YAML config files and display differences in a clear, colored format. Perfect for tracking config changes across versions.",
     after_help = "Author: nobody <1085529137@qq.com>"
 )]
+#[command(group(clap::ArgGroup::new("pairs").multiple(true)))]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+// 配置文件中的选项插在命令行参数之前，命令行再次给出时覆盖
+#[command(args_override_self = true)]
 struct Args {
-    /// 旧版本的 YAML 配置文件路径
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// 旧版本的 YAML 配置文件路径，`-` 表示从标准输入读取；为目录时与 --new 目录中
+    /// 相对路径相同的 `.yml`/`.yaml`/`.json`/`.toml` 文件逐个比较，只存在于一侧的文件整体记为新增或删除。
+    /// 也可以是 `http(s)://` 地址（用 curl 下载，请求头取自环境变量 YML_DIFF_HTTP_HEADERS，每行一个）
+    /// 或 `k8s://命名空间/ConfigMap 名/键`（用 kubectl 从当前集群读取）
+    #[arg(short, long, required_unless_present_any = ["stdin_pair", "pairs"])]
+    old: Option<PathBuf>,
+
+    /// 新版本的 YAML 配置文件路径，`-` 表示从标准输入读取；为目录时见 --old。
+    /// 重复指定时把 --old 作为基线与每个文件比较，输出哪些键在哪些文件中与基线不同的矩阵
+    #[arg(short, long, required_unless_present_any = ["stdin_pair", "pairs"])]
+    new: Vec<PathBuf>,
+
+    /// 一组要比较的旧/新文件，可重复指定以比较多组文件
+    #[arg(
+        long,
+        num_args = 2,
+        value_names = ["OLD", "NEW"],
+        group = "pairs",
+        conflicts_with_all = ["old", "new", "stdin_pair"]
+    )]
+    pair: Vec<PathBuf>,
+
+    /// 从清单文件读取要比较的文件对：每行一对旧/新路径，以制表符或逗号分隔，
+    /// 空行和以 `#` 开头的行会被跳过；相对路径相对于清单文件所在的目录
+    #[arg(
+        long,
+        value_name = "PATH",
+        group = "pairs",
+        conflicts_with_all = ["old", "new", "stdin_pair"]
+    )]
+    pairs_file: Option<PathBuf>,
+
+    /// 把多组文件对的差异合并成一个去重的报告，并标注每处变更出现在哪些文件对中
+    #[arg(long, requires = "pairs")]
+    merge_report: bool,
+
+    /// 从标准输入读取以 `---` 分隔的两个文档，第一个作为旧版本，第二个作为新版本
+    #[arg(long, conflicts_with_all = ["old", "new"])]
+    stdin_pair: bool,
+
+    /// 从 git 的这个提交（或分支、标签）中读取 --old 文件，而不是读取工作区中的文件，
+    /// 例如 `-o app.yml -n app.yml --old-rev HEAD~1`
+    #[arg(long, value_name = "REV", conflicts_with_all = ["stdin_pair", "pairs"])]
+    old_rev: Option<String>,
+
+    /// 从 git 的这个提交中读取 --new 文件，不指定时读取工作区中的文件
+    #[arg(long, value_name = "REV", conflicts_with_all = ["stdin_pair", "pairs"])]
+    new_rev: Option<String>,
+
+    /// 作为 GIT_EXTERNAL_DIFF 被调用时 git 传入的文件路径，用于报告的标题
+    #[arg(long, hide = true)]
+    git_path: Option<PathBuf>,
+
+    /// 输入文件的格式，默认按扩展名判断（.json / .toml / .yml / .yaml），没有可识别的扩展名时
+    /// 根据内容猜测；扩展名与内容不符或从标准输入读取时可以用它指定，对两个文件同时生效
+    #[arg(long, value_enum, value_name = "FORMAT", conflicts_with = "stdin_pair")]
+    input_format: Option<InputFormat>,
+
+    /// 不展开 YAML 合并键 `<<`，把它当作普通的键比较，用于检查合并键本身的写法是否变化。
+    /// 别名 `*name` 在解析时总是替换成锚点处的值，不受影响
+    #[arg(long)]
+    no_resolve_aliases: bool,
+
+    /// 按 Kubernetes 清单比较：多文档文件中的资源按 `apiVersion/kind/namespace/name` 配对，
+    /// 并去掉集群填充的 `status` 与 `metadata` 中的 resourceVersion、managedFields、
+    /// creationTimestamp、uid、generation、selfLink，适合比较两次 Helm/kustomize 渲染的结果
+    #[arg(long)]
+    k8s: bool,
+
+    /// 比较 SOPS 加密的文件：mask（只写 --sops 时）去掉 `sops` 元数据块，每个 `ENC[...]` 密文都显示为
+    /// `ENC[类型]`，重新加密不再产生差异，但加密的值本身被修改也无法发现；
+    /// decrypt 先用 sops 命令解密两个文件再比较，密钥由 sops 自行查找
+    #[arg(
+        long,
+        value_enum,
+        value_name = "MODE",
+        num_args = 0..=1,
+        default_missing_value = "mask"
+    )]
+    sops: Option<SopsMode>,
+
+    /// 三方比较的共同祖先：分别比较它与 --old、--new，把每处变更归为只在旧版本中修改、
+    /// 只在新版本中修改、两边相同或冲突；有冲突时以错误退出
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["pairs", "stdin_pair", "swap", "patch", "emit_patch", "split_output"]
+    )]
+    base: Option<PathBuf>,
+
+    /// 把三方合并的结果写入该文件：以旧版本为底，加上只有新版本做出的变更，冲突的键保留旧版本的值。
+    /// 可以与 --old 相同，配合 `--base %O --old %A --new %B --merge-output %A` 作为 git 的合并驱动
+    #[arg(long, value_name = "PATH", requires = "base")]
+    merge_output: Option<PathBuf>,
+
+    /// 比较多组文件（目录、--pair、--pairs-file、多个 --new）时最多同时处理 N 组，默认为 CPU 核数；
+    /// 报告的顺序与并发数无关
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    jobs: Option<usize>,
+
+    /// 把新文件当作旧文件、旧文件当作新文件比较，查看反向的差异：新增与删除互换，
+    /// 修改前后的值也随之互换
+    #[arg(long)]
+    swap: bool,
+
+    /// 解析前对两个文件的内容都做的预处理，可以重复指定，按顺序执行。内置的转换有
+    /// trim、sort-keys、lowercase-keys、strip-comment-keys（去掉 `#`、`//` 开头的键和 `_comment`）、
+    /// base64-decode:模式（解码扁平键命中模式的值）；其余写法作为一条 shell 命令执行，
+    /// 文件内容从标准输入传入，标准输出作为解析的内容
+    #[arg(long, value_name = "TRANSFORM", conflicts_with = "stdin_pair")]
+    transform: Vec<Transform>,
+
+    /// 只对旧文件做的预处理，在 --transform 之后执行，写法同 --transform
+    #[arg(long, value_name = "TRANSFORM", conflicts_with = "stdin_pair")]
+    transform_old: Option<Transform>,
+
+    /// 只对新文件做的预处理，在 --transform 之后执行，写法同 --transform
+    #[arg(long, value_name = "TRANSFORM", conflicts_with = "stdin_pair")]
+    transform_new: Option<Transform>,
+
+    /// 允许 --transform / --transform-old / --transform-new 执行 shell 命令。命令以当前用户的权限运行，
+    /// 只应对可信的命令开启
+    #[arg(long)]
+    allow_exec: bool,
+
+    /// 比较前替换两个文件中的占位符：`${NAME}`、`${NAME:默认值}` 取环境变量，
+    /// 找不到时使用默认值；`{{ .Values.a.b }}` 取 --vars / --vars-file 中的值。
+    /// 无法解析的占位符原样保留
+    #[arg(long)]
+    resolve_env: bool,
+
+    /// 替换占位符使用的变量，写成 `key=value`，可重复指定，优先于环境变量和 --vars-file
+    #[arg(long, value_name = "KEY=VALUE")]
+    vars: Vec<String>,
+
+    /// 替换占位符使用的变量文件（YAML/JSON），嵌套的键以 `.` 连接，如 Helm 的 values.yaml
+    #[arg(long, value_name = "PATH")]
+    vars_file: Option<PathBuf>,
+
+    /// 项目级默认选项文件，默认从当前目录逐级向上查找 `.yml-diff.yml`。文件中每个键是一个长选项名，
+    /// 如 `ignore: ['*.timestamp']`、`format: markdown`、`quiet: true`，`rules` 可以直接写规则映射；
+    /// 命令行中的选项覆盖文件中的同名选项，可重复的选项（如 --ignore）则累加
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// 不读取任何配置文件
+    #[arg(long, conflicts_with = "config")]
+    no_config: bool,
+
+    /// 配置文件中直接写出的规则，见 --config
+    #[arg(skip)]
+    config_rules: Option<Value>,
+
+    /// 按键模式定制比较行为的规则文件（ignore / redact / float-tolerance=N / unordered /
+    /// severity=info|warn|error）；指定了严重程度时报告按级别分组，
+    /// 出现 warn 级别的变更时退出码为 3，出现 error 级别的变更时为 4
     #[arg(short, long)]
-    old: PathBuf,
+    rules: Option<PathBuf>,
+
+    /// 忽略命中该模式的扁平键，它们不会出现在任何差异中；可重复指定。
+    /// `*` 只匹配一段内的字符，`**` 可以跨越 `.`，例如 `*.timestamp`、`secrets.**`；
+    /// 以 `re:` 开头时按正则表达式在键路径中搜索，例如 `re:^image\.(tag|digest)$`
+    #[arg(long, value_name = "PATTERN")]
+    ignore: Vec<KeyPattern>,
+
+    /// 命中该模式的键照常比较和报告，但所有输出格式中的值都显示为 `***`，可重复指定；
+    /// 写法同 --ignore，例如 `**.password`、`**token*`；相当于规则文件中的 redact
+    #[arg(long, value_name = "PATTERN")]
+    mask: Vec<KeyPattern>,
+
+    /// 只比较命中该模式的扁平键，可重复指定，命中任一模式即可；写法同 --ignore。
+    /// 与 --ignore 同时使用时先按 --only 筛选，再去掉 --ignore 命中的键
+    #[arg(long, value_name = "PATTERN")]
+    only: Vec<KeyPattern>,
+
+    /// 只比较该路径下的子树，可重复指定；路径中可以使用 --ignore 的通配写法，
+    /// 例如 `db.replication`、`services.*.image`。与 --only 不同，命中的是键本身或它的任一上层
+    #[arg(long, value_name = "PATH")]
+    path: Vec<KeyPattern>,
+
+    /// 为每一项差异附上分类原因，便于排查意外的差异
+    #[arg(long)]
+    explain: bool,
+
+    /// 不展开嵌套的映射，每个顶层键的值（包括整棵子树）作为一个整体比较，
+    /// 用于快速查看哪些顶层配置发生了变化
+    #[arg(long)]
+    no_recurse: bool,
+
+    /// 最多展开到第 N 层，更深的子树整体作为一个值比较；顶层键的深度为 0，
+    /// `--max-depth 0` 等同于 --no-recurse。两个文件按同样的深度展开
+    #[arg(long, value_name = "N", conflicts_with = "no_recurse")]
+    max_depth: Option<usize>,
+
+    /// 扁平键各段之间的分隔符，可以是多个字符；键名本身含有 `.` 时可改用 `/` 或 `::`
+    #[arg(
+        long,
+        value_name = "STR",
+        default_value = ".",
+        value_parser = clap::builder::NonEmptyStringValueParser::new()
+    )]
+    separator: String,
+
+    /// 序列按下标逐个元素比较时下标的写法：dot 为 `a.0`，bracket 为 `a[0]`
+    #[arg(long, value_enum, default_value_t = IndexStyleArg::Bracket)]
+    index_style: IndexStyleArg,
+
+    /// 不展开序列，把整个序列作为一个值比较
+    #[arg(long, conflicts_with = "index_style")]
+    sequences_as_values: bool,
+
+    /// 命中路径的序列按元素的某个字段配对，而不是按下标，写作 `路径=字段`，如 `**.containers=name`，
+    /// 键路径写作 `containers[name=api].image`；可以用逗号给出多个候选字段，省略路径时对所有序列生效，
+    /// 只写 --list-key 时为 `name,id`。可重复指定。生成补丁和合并结果时仍按下标，因此不能同时使用
+    #[arg(
+        long,
+        value_name = "PATH=FIELD",
+        num_args = 0..=1,
+        default_missing_value = ListKey::DEFAULT_FIELDS,
+        conflicts_with_all = [
+            "sequences_as_values",
+            "patch",
+            "emit_patch",
+            "base",
+            "round_trip_check"
+        ]
+    )]
+    list_key: Vec<ListKey>,
+
+    /// 先比较文件原始字节的哈希，完全相同时跳过解析直接报告无差异
+    #[arg(long)]
+    baseline_hash: bool,
+
+    /// 记录各键默认值的 YAML 文件；新增的值等于默认值、或修改后回到默认值时不报告
+    #[arg(long, value_name = "FILE")]
+    ignore_default_values: Option<PathBuf>,
+
+    /// 把数值、布尔等非字符串键转换成字符串，使 `8080:` 与 `"8080":` 匹配为同一个键
+    #[arg(long)]
+    coerce_key_types: bool,
+
+    /// 数值的相对变化小于该百分比时不报告为修改
+    #[arg(long, value_name = "P")]
+    min_percent_change: Option<f64>,
+
+    /// 键路径 → 说明文字的 YAML/JSON 文件，用于在报告中注释每个变更的键
+    #[arg(long, value_name = "FILE")]
+    context_file: Option<PathBuf>,
+
+    /// 文本报告中在每个变更的键前后各显示最多 N 个值没有变化的同级键（暗色），便于看清变更所在的位置
+    #[arg(long, value_name = "N")]
+    context: Option<usize>,
+
+    /// 多行字符串（内嵌脚本、证书等）被修改时逐行比较，只显示变化的行及其前后各 N 行
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    value_context: usize,
+
+    /// 用 JSON Schema（JSON 或 YAML 书写）校验新版本配置，违规项附在差异报告之后
+    #[cfg(feature = "schema")]
+    #[arg(long, value_name = "SCHEMA")]
+    validate_against: Option<PathBuf>,
+
+    /// 用 JSON Schema 分别校验新旧两个文件，列出这次修改引入的违规（删除了必需的键、
+    /// 类型不符、additionalProperties 不允许的新键等），有这样的变更时以错误退出
+    #[cfg(feature = "schema")]
+    #[arg(long, value_name = "SCHEMA")]
+    schema: Option<PathBuf>,
+
+    /// 在每个变更下方附上源文件中定义该键的原始行（启发式定位，见 source 模块的说明）
+    #[arg(long)]
+    annotate_source: bool,
+
+    /// 在每个变更旁标出它在旧、新文件中的行号，如 `old.yml:42 → new.yml:45`，所有输出格式都有；
+    /// 定位方式与 --annotate-source 相同，找不到的一侧不标出
+    #[arg(long)]
+    line_numbers: bool,
+
+    /// 超过 N 个字符（默认 256）的字符串只显示长度，如 `<string, 2048 chars>`；比较仍使用完整的值
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "256")]
+    show_value_lengths: Option<usize>,
+
+    /// 命中该键模式的值只显示内容的短哈希（SHA-256 前 8 位），内容相同则哈希相同；可重复指定
+    #[arg(long, value_name = "KEY_PATTERN")]
+    value_hash: Vec<KeyPattern>,
+
+    /// 把值相同、键名相同但父路径不同的「删除 + 新增」合并报告为移动，
+    /// 例如 `logging.level` 移到 `app.logging.level`；只影响文本报告
+    #[arg(long)]
+    report_moved: bool,
+
+    /// 识别整个子树的改名或移动（例如 `db` → `database`），在报告中单独列出，随之移动且值不变的键
+    /// 不再逐个显示为删除和新增；JSON 输出中另有 `renamed` 字段
+    #[arg(long)]
+    detect_renames: bool,
+
+    /// 按元素内容对齐序列：在序列中插入或删除元素时，后面的元素不再逐个显示为修改；
+    /// 内容不变、只是换了位置的元素在报告中单独列出，JSON 输出中另有 `reordered` 字段
+    #[arg(long)]
+    detect_reorders: bool,
+
+    /// 与 --detect-reorders 一样对齐序列，但只是换了位置的元素不算差异
+    #[arg(long, conflicts_with = "detect_reorders")]
+    ignore_reorders: bool,
+
+    /// 已知的键改名表，`旧键: 新键` 的 YAML 映射，例如框架升级时的配置项迁移
+    /// `server.max_threads: server.thread_pool.max`；旧键只在旧文件、新键只在新文件中时配对为迁移并比较值，
+    /// 不再显示为删除和新增；JSON 输出中另有 `migrated` 字段
+    #[arg(long, value_name = "PATH")]
+    renames: Option<PathBuf>,
+
+    /// 在报告中突出显示命中该键模式的键（加粗、下划线、反色），不论变更类型；可重复指定
+    #[arg(long, value_name = "KEY_PATTERN")]
+    highlight_key: Vec<KeyPattern>,
+
+    /// 自检：用旧文件和差异重建新文件，与实际的新文件不一致时报错
+    #[arg(long)]
+    round_trip_check: bool,
+
+    /// 整体比较映射类型的值（如未展开的序列元素、--max-depth 以下的子树）时，
+    /// 先去掉其中任意层级上名为 KEY 的字段；可重复指定
+    #[arg(long, value_name = "KEY")]
+    ignore_keys_in_values: Vec<String>,
+
+    /// 假定两个文件中所有映射的键都已排序，按归并方式线性比较扁平键，省去构建哈希表的内存。
+    /// 假定不成立时结果不正确；调试构建会检查并给出警告
+    #[arg(long)]
+    assume_sorted: bool,
 
-    /// 新版本的 YAML 配置文件路径
+    /// 用于几百 MB 的生成文件：解析时直接展开成扁平键，不构建完整的文档树，
+    /// 两边按键排序后归并比较。只省去文档树和哈希表的开销，是常数倍的节省：
+    /// 内存仍与文件大小成正比，而不是与差异大小成正比。
+    /// 只支持单个文档的 YAML / JSON 文件，不支持预处理、多文档和重命名检测
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "stdin_pair",
+            "pair",
+            "pairs_file",
+            "old_rev",
+            "new_rev",
+            "base",
+            "transform",
+            "transform_old",
+            "transform_new",
+            "vars",
+            "vars_file",
+            "k8s",
+            "sops",
+            "list_key",
+            "no_sort",
+            "detect_renames",
+            "detect_reorders",
+            "ignore_reorders",
+            "renames",
+            "patch",
+            "emit_patch",
+            "interactive",
+            "annotate_source",
+            "line_numbers",
+            "round_trip_check",
+            "key_case_report",
+            "summarize_unchanged_subtrees",
+            "strict"
+        ]
+    )]
+    low_memory: bool,
+
+    /// 读取大文件时在标准错误中显示进度条，多组文件时显示已读取的组数；
+    /// 结束后在标准错误中输出各阶段（解析旧文件、解析新文件、比较、输出报告）的耗时
+    #[arg(long)]
+    progress: bool,
+
+    /// 解析失败时给出出错的行列号和指向出错位置的源文本，并且继续解析另一个文件，
+    /// 一次报告两边所有的解析错误，每个错误都标明是旧文件还是新文件
+    #[arg(long)]
+    strict: bool,
+
+    /// 文本报告和错误信息的语言；默认按 LC_ALL、LC_MESSAGES、LANG 选择，中文区域或未设置时为中文，
+    /// 其余为英文
+    #[arg(long, value_enum)]
+    lang: Option<Lang>,
+
+    /// 按键在文件中出现的顺序输出差异，而不是按键排序；只在旧文件中的键排在它原来的位置附近
+    #[arg(long)]
+    no_sort: bool,
+
+    /// 比较前把每个叶子值都转成显示用的字符串，只比较文本：`true` 与 `"true"`、`80` 与 `"80"`
+    /// 视为相等，`1` 与 `1.0` 的文本不同，仍视为不相等。
+    /// 字符串原样使用，数字使用其规范写法，布尔为 true/false，空值为 null，
+    /// 带标签的值为 `!tag:值`；序列和映射逐个元素比较
+    #[arg(long)]
+    compare_as_strings: bool,
+
+    /// 整数与浮点数永远不相等，即使数值相同（如 `1` 与 `1.0`），误差规则也不会让二者相等；
+    /// 解释差异时会指出整数与浮点数的区别
+    #[arg(long)]
+    strict_number_types: bool,
+
+    /// 至少一边是浮点数时，两个数值之差的绝对值不超过 EPSILON 即视为相等（如 `0.1` 与 `0.10000000001`）；
+    /// 整数与浮点数按数值比较，两个整数之间总是精确比较
+    #[arg(long, visible_alias = "numeric-tolerance", value_name = "EPSILON")]
+    epsilon: Option<f64>,
+
+    /// 数值按规范化后的值比较：`1` 与 `1.0` 相等，`0.30000000000000004` 与 `0.3` 相等
+    /// （浮点数只比较前 15 位有效数字）；与 --strict-number-types 同时使用时整数与浮点数仍然不相等
+    #[arg(long)]
+    normalize_numbers: bool,
+
+    /// 按标签的含义比较：能解析为 YAML 时间戳的字符串换算成 UTC 后比较（`2024-01-01` 与
+    /// `2024-01-01T08:00:00+08:00` 不同，与 `2024-01-01T00:00:00Z` 相同），`!timestamp` 的值同样处理；
+    /// `!binary` 的值解码后比较字节内容，报告中只显示长度和哈希。
+    /// 注意 `!!timestamp`、`!!binary` 等核心标签在解析时已被去掉，其值按普通字符串处理
+    #[arg(long)]
+    compare_tags: bool,
+
+    /// 为本地标签指定比较方式，形如 `!date=timestamp`，可以重复；方式为 timestamp（按时刻）、
+    /// binary（按解码后的字节）或 untagged（忽略标签只比较其中的值，如 `!secret a` 与 `a` 相等）
+    #[arg(long, value_name = "TAG=KIND", value_parser = parse_tag_handler)]
+    tag_handler: Vec<(String, TagHandler)>,
+
+    /// 字符串比较时不区分大小写（如 `PROD` 与 `prod`）；报告中仍显示原值
+    #[arg(long)]
+    ignore_case: bool,
+
+    /// 字符串比较时忽略首尾空白
+    #[arg(long)]
+    trim_strings: bool,
+
+    /// `yes`/`no`、`on`/`off`、`true`/`false`（不区分大小写）视为同一个布尔值，
+    /// 如 `enabled: on` 与 `enabled: true` 相等
+    #[arg(long)]
+    normalize_booleans: bool,
+
+    /// 命中该模式的序列按多重集比较（如 `**.allowed_origins`、`tags`），只是顺序不同不算差异，
+    /// 报告中只列出真正新增和删除的元素；可重复指定，写法同 --ignore，相当于规则文件中的 unordered
+    #[arg(long, value_name = "PATTERN")]
+    unordered: Vec<KeyPattern>,
+
+    /// 序列不按下标逐个比较，而是把元素相同的序列视为相等，不论顺序；嵌套在序列中的序列同样忽略顺序。
+    /// 重复元素的个数仍然要相同：`[a, a, b]` 与 `[a, b, b]` 不相等
+    #[arg(long)]
+    ignore_array_order: bool,
+
+    /// 把值为 null 的键视为不存在：`retries: null` 与缺少 `retries` 不算差异
+    #[arg(long)]
+    null_is_absent: bool,
+
+    /// 在报告末尾为没有任何变更的顶层配置段各输出一行确认，例如 `✓ database (未变化，40 个键)`
+    #[arg(long)]
+    summarize_unchanged_subtrees: bool,
+
+    /// 任一输入扁平化后没有任何键（空文件、只有注释或只有 null）时报错，
+    /// 防止被截断的文件悄悄得出「没有差异」
+    #[arg(long)]
+    fail_on_empty_input: bool,
+
+    /// 另外列出两个文件中只有大小写不同的键（如 `maxConns` 与 `MaxConns`），与主报告互不影响
+    #[arg(long, conflicts_with = "pairs")]
+    key_case_report: bool,
+
+    /// 差异条目很多时使用多线程排序
+    #[cfg(feature = "parallel")]
+    #[arg(long)]
+    parallel_sort: bool,
+
+    /// 输出报告使用的字符编码，例如旧版 Windows 控制台可用 gbk；无法表示的字符会被替换
+    #[arg(long, value_name = "LABEL", default_value = "utf-8", value_parser = parse_encoding)]
+    output_encoding: &'static Encoding,
+
+    /// 只输出变更的扁平键路径，每行一个，不带值和格式；marked 会在行首加上 `+`/`-`/`~`
+    #[arg(
+        long,
+        value_enum,
+        value_name = "STYLE",
+        num_args = 0..=1,
+        default_missing_value = "plain",
+        conflicts_with = "format"
+    )]
+    changed_keys_only: Option<KeyListStyle>,
+
+    /// 以 JSON 输出各类变更的数量，修改再细分为值变化、类型变化和仅空白变化
+    #[arg(long, conflicts_with_all = ["format", "changed_keys_only"])]
+    count_by_type: bool,
+
+    /// 只输出新增、删除、修改的数量，不列出具体的键；与 `--format json` 一起使用时输出
+    /// `{"added": N, "removed": N, "modified": N}`
+    #[arg(long, conflicts_with_all = ["changed_keys_only", "count_by_type"])]
+    summary: bool,
+
+    /// 只输出按键的前 N 段（默认 1）分组的变更数量，每组一行，形如 `database: +3 -1 ~5`，
+    /// 变更总数多的组排在前面；与 `--format json` 一起使用时输出 JSON 数组
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        default_missing_value = "1",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..),
+        conflicts_with_all = ["changed_keys_only", "count_by_type", "summary"]
+    )]
+    stats_by_prefix: Option<usize>,
+
+    /// 只输出统计：各类变更的总数、每个顶层配置段的变更数量和层级最深的变更键；
+    /// 与 `--format json` 一起使用时输出 JSON 对象
+    #[arg(
+        long,
+        conflicts_with_all = ["changed_keys_only", "count_by_type", "summary", "stats_by_prefix"]
+    )]
+    stats: bool,
+
+    /// 不输出报告，改为输出 RFC 7386 merge patch 形式的 YAML：新增和修改的键写出新值，
+    /// 删除的键写作 null，有变化的序列整体写出新值；合并到旧文件上即可得到新文件
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "pairs",
+            "format",
+            "changed_keys_only",
+            "count_by_type",
+            "summary",
+            "stats_by_prefix",
+            "stats"
+        ]
+    )]
+    patch: bool,
+
+    /// 不输出报告，改为输出操作列表：每项为 `{op: add|remove|replace, path, value}`，
+    /// `path` 是扁平化后的键路径；默认为 YAML，`--format json` 时为 JSON。
+    /// 用 `yml-diff apply` 把它应用到旧文件上即可得到新文件
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "pairs",
+            "patch",
+            "changed_keys_only",
+            "count_by_type",
+            "summary",
+            "stats_by_prefix",
+            "stats"
+        ]
+    )]
+    emit_patch: bool,
+
+    /// 不输出报告，改为在终端中逐处审阅变更：按顶层段分组显示旧值和新值，
+    /// 输入 y/n 接受或拒绝，a/d 处理整段，p 回到上一处，q 结束
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "pairs",
+            "stdin_pair",
+            "base",
+            "format",
+            "patch",
+            "emit_patch",
+            "output",
+            "silent",
+            "watch"
+        ]
+    )]
+    interactive: bool,
+
+    /// 审阅结束后把接受的变更写成操作列表（格式同 --emit-patch），
+    /// 用 `yml-diff apply` 把它应用到旧文件上即可只引入这些变更
+    #[arg(long, value_name = "FILE", requires = "interactive")]
+    accepted_patch: Option<PathBuf>,
+
+    /// 另外把新增、删除、修改的配置项分别写入 DIR 下的 added.yml / removed.yml / modified.yml，
+    /// 目录不存在时自动创建
+    #[arg(long, value_name = "DIR", conflicts_with = "pairs")]
+    split_output: Option<PathBuf>,
+
+    /// 把报告写入该文件而不是标准输出；写入文件时默认不带颜色，除非指定 `--color always`。
+    /// 写成 `FORMAT:PATH`（如 `json:diff.json`）时另外按该格式把报告写入文件，标准输出照常输出报告；
+    /// 可重复指定，一次运行写出多种格式
+    #[arg(long, value_name = "[FORMAT:]PATH", value_parser = parse_output_target)]
+    output: Vec<OutputTarget>,
+
+    /// 像 diff(1) 一样用退出码表示结果：没有差异时为 0，有差异时为 1，出错时为 2
+    #[arg(long)]
+    exit_code: bool,
+
+    /// 不输出任何报告，只用退出码表示结果（同 --exit-code）；错误信息仍然输出到标准错误，
+    /// 适合脚本和 pre-commit 钩子
+    #[arg(long, conflicts_with_all = ["output", "quiet"])]
+    silent: bool,
+
+    /// 比较一次之后继续监视 --old、--new（可以是目录）和 --base，任一文件变化时清屏并重新比较，
+    /// 直到按 Ctrl-C 退出；编辑配置文件时可以随时看到与基准的差异
+    #[arg(long, conflicts_with_all = ["stdin_pair", "pairs", "merge_output", "exit_code", "silent"])]
+    watch: bool,
+
+    /// 有任何键的值改变了类型（如 `8080` 变成 `"8080"`）时报错退出，报告照常输出；
+    /// 用于在 CI 中拦截不小心加上或去掉的引号
+    #[arg(long)]
+    fail_on_type_change: bool,
+
+    /// 变更总数超过 N 时报错退出，报告照常输出；用于在 CI 中容忍少量常规变更，只拦截大范围的改动
+    #[arg(long, value_name = "N")]
+    max_changes: Option<usize>,
+
+    /// 新增的配置项超过 N 处时报错退出
+    #[arg(long, value_name = "N")]
+    max_added: Option<usize>,
+
+    /// 删除的配置项超过 N 处时报错退出；`--max-removed 0` 拦截任何删除
+    #[arg(long, value_name = "N")]
+    max_removed: Option<usize>,
+
+    /// 修改的配置项超过 N 处时报错退出
+    #[arg(long, value_name = "N")]
+    max_modified: Option<usize>,
+
+    /// 不输出报告的标题和统计信息，只输出各项差异；没有差异时不输出任何内容
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// 输出格式
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+
+    /// `--format tree` 的简写
+    #[arg(long, conflicts_with = "format")]
+    tree: bool,
+
+    /// `--format side-by-side` 的简写
+    #[arg(long, conflicts_with_all = ["format", "tree"])]
+    side_by_side: bool,
+
+    /// 何时使用颜色：auto 在标准输出是终端且没有设置 NO_COLOR 环境变量时使用；
+    /// JSON 输出总是不带颜色
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+}
+
+/// --color 的取值
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    /// 把 --emit-patch 生成的操作列表应用到文件上，结果以 YAML 写到标准输出
+    Apply(ApplyArgs),
+    /// 按键路径读取文件中的一个值或一棵子树，结果写到标准输出，便于在脚本中使用
+    Get(GetArgs),
+    /// 按差异报告中键的顺序重排文件中所有映射的键并统一格式，使文本 diff 只包含真正的改动
+    Normalize(NormalizeArgs),
+    /// 以旧文件为基础，只把选定类型的变更从新文件合并进来，例如把默认配置模板中新增的键补进现有配置；
+    /// 将要合并的变更列在标准错误中，合并结果以 YAML 写到标准输出
+    Merge(MergeArgs),
+    /// 列出文件中的扁平键路径，每行一个，便于在脚本中 grep、排序或比较两个文件的键清单
+    Keys(KeysArgs),
+}
+
+#[derive(clap::Args)]
+struct ApplyArgs {
+    /// --emit-patch 生成的操作列表，YAML 或 JSON
+    patch: PathBuf,
+
+    /// 要应用操作的配置文件，`-` 表示从标准输入读取
+    file: PathBuf,
+
+    /// 操作路径中各段之间的分隔符，应与生成操作列表时的 --separator 相同
+    #[arg(long, default_value = ".")]
+    separator: String,
+
+    /// 把结果写入该文件而不是标准输出，可以与 FILE 相同以原地修改
+    #[arg(long, value_name = "PATH")]
+    output: Option<PathBuf>,
+}
+
+#[derive(clap::Args)]
+struct GetArgs {
+    /// 要读取的配置文件，`-` 表示从标准输入读取
+    #[arg(short, long)]
+    file: PathBuf,
+
+    /// 键路径，写法与差异报告中的键相同，如 `spring.datasource.url`、`servers[0].host`
     #[arg(short, long)]
+    key: String,
+
+    /// 键路径中各段之间的分隔符
+    #[arg(long, default_value = ".")]
+    separator: String,
+
+    /// 以 JSON 输出；默认标量直接输出原文，映射和序列以 YAML 输出
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(clap::Args)]
+struct KeysArgs {
+    /// 要列出键的配置文件，`-` 表示从标准输入读取
+    file: PathBuf,
+
+    /// 只列出位于该路径下的键，写法同主命令的 --path，如 `spring.datasource` 或 `services.*.env`；
+    /// 可重复指定
+    #[arg(long, value_name = "KEY_PATTERN")]
+    path: Vec<KeyPattern>,
+
+    /// 在键后面附上值，写作 `键: 值`
+    #[arg(long)]
+    values: bool,
+
+    /// 按差异报告中键的顺序输出；默认按键在文件中出现的顺序
+    #[arg(long)]
+    sort: bool,
+
+    /// 键路径中各段之间的分隔符
+    #[arg(long, default_value = ".")]
+    separator: String,
+}
+
+#[derive(clap::Args)]
+struct NormalizeArgs {
+    /// 要规范化的文件，`-` 表示从标准输入读取；注释不会保留
+    file: PathBuf,
+
+    /// 把结果写入该文件而不是标准输出，可以与 FILE 相同以原地修改
+    #[arg(long, value_name = "PATH", conflicts_with = "check")]
+    output: Option<PathBuf>,
+
+    /// 只检查文件是否已经规范化，不输出结果；未规范化时报错
+    #[arg(long)]
+    check: bool,
+}
+
+#[derive(clap::Args)]
+struct MergeArgs {
+    /// 作为基础的文件，如现有的配置；`-` 表示从标准输入读取
+    #[arg(long)]
+    old: PathBuf,
+
+    /// 从中取用变更的文件，如新版本的默认配置模板
+    #[arg(long)]
     new: PathBuf,
+
+    /// 要合并的变更类型，可以用逗号分隔或重复指定
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "added")]
+    only: Vec<merge::MergeKind>,
+
+    /// 把结果写入该文件而不是标准输出，可以与 --old 相同以原地修改
+    #[arg(long, value_name = "PATH")]
+    output: Option<PathBuf>,
+
+    /// 只列出将要合并的变更，不输出合并结果
+    #[arg(long, conflicts_with = "output")]
+    dry_run: bool,
 }
-#[derive(Debug, Clone, PartialEq, Eq)]
-struct ConfigKey(String);
 
-impl Deref for ConfigKey {
-    type Target = String;
-    fn deref(&self) -> &Self::Target {
-        &self.0
-    }
+/// 报告的输出格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+enum OutputFormat {
+    /// 完整的分组报告
+    #[default]
+    Text,
+    /// 类似 `git diff --stat`，每个顶层配置段一行
+    Diffstat,
+    /// 机器可读的 JSON，值保留原始类型
+    Json,
+    /// 类似 `diff -u`，`---`/`+++` 文件头之后按键的顺序逐个输出 `@@ 键路径 @@` 与 `-` 旧值 / `+` 新值
+    Unified,
+    /// 独立的 HTML 报告，变更按键路径组成可以折叠的树
+    Html,
+    /// GitHub 风格的 Markdown，适合作为 pull request 的评论
+    Markdown,
+    /// 按公共前缀缩进分组，每组附带变更数，长前缀不再逐行重复
+    Tree,
+    /// 类似 `diff -y`，旧值和新值左右两栏对齐，栏宽按终端宽度（或 COLUMNS 环境变量）计算
+    SideBySide,
+    /// JUnit XML，每处差异是一个失败的测试用例，可以交给 CI 的测试结果组件展示
+    Junit,
+    /// GitHub Actions 的 `::error file=...,line=...::` 命令，差异显示为 pull request 中的行内注释
+    Github,
 }
 
-impl From<String> for ConfigKey {
-    fn from(s: String) -> Self {
-        ConfigKey(s)
-    }
+/// --output 的一个目标：不带格式时代替标准输出，带格式时另外写入
+#[derive(Debug, Clone, PartialEq)]
+struct OutputTarget {
+    format: Option<OutputFormat>,
+    path: PathBuf,
 }
 
-impl From<&String> for ConfigKey {
-    fn from(s: &String) -> Self {
-        ConfigKey(s.clone())
+/// `json:diff.json` 中冒号前是已知的格式名时按 `FORMAT:PATH` 解析，否则整个值都是路径
+fn parse_output_target(s: &str) -> Result<OutputTarget> {
+    let format = s.split_once(':').and_then(|(format, path)| {
+        let format = <OutputFormat as clap::ValueEnum>::from_str(format, true).ok()?;
+        Some((format, path))
+    });
+    match format {
+        Some((_, "")) => Err(anyhow!(
+            i18n::error_lang().format(Msg::MissingOutputPath, &[&s])
+        )),
+        Some((format, path)) => Ok(OutputTarget {
+            format: Some(format),
+            path: path.into(),
+        }),
+        None => Ok(OutputTarget {
+            format: None,
+            path: s.into(),
+        }),
     }
 }
 
-impl From<&str> for ConfigKey {
-    fn from(s: &str) -> Self {
-        ConfigKey(s.into())
-    }
+/// --changed-keys-only 的输出写法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum KeyListStyle {
+    /// 只有键路径
+    Plain,
+    /// 键路径前加上变更标记
+    Marked,
 }
 
-impl AsRef<str> for ConfigKey {
-    fn as_ref(&self) -> &str {
-        &self.0
-    }
+/// --index-style 的取值；库中的 [`IndexStyle`] 不依赖 clap，在这里转换
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum IndexStyleArg {
+    /// `servers.0.host`
+    Dot,
+    /// `servers[0].host`
+    Bracket,
 }
 
-// 实现自定义排序逻辑
-impl Ord for ConfigKey {
-    fn cmp(&self, other: &Self) -> Ordering {
-        // 按照字典序比较，但考虑层级结构
-        // 使用自定义的比较逻辑来处理前缀关系
-        self.hierarchical_cmp(other)
+impl From<IndexStyleArg> for IndexStyle {
+    fn from(style: IndexStyleArg) -> Self {
+        match style {
+            IndexStyleArg::Dot => IndexStyle::Dot,
+            IndexStyleArg::Bracket => IndexStyle::Bracket,
+        }
     }
 }
 
-impl ConfigKey {
-    /// 层级化比较：有公共前缀时，按段数排序（段数少的在前）
-    fn hierarchical_cmp(&self, other: &Self) -> Ordering {
-        let self_parts: Vec<&str> = self.0.split('.').collect();
-        let other_parts: Vec<&str> = other.0.split('.').collect();
+/// 控制报告输出的选项
+#[derive(Debug, Default, Clone)]
+struct ReportOptions {
+    /// 文本报告中标题、分节和说明文字的语言
+    lang: Lang,
+    explain: bool,
+    /// 文本报告不输出标题和统计信息
+    quiet: bool,
+    format: OutputFormat,
+    /// 设置后只输出变更的键路径，优先于 `format`
+    changed_keys_only: Option<KeyListStyle>,
+    /// 只输出 JSON 形式的分类计数
+    count_by_type: bool,
+    /// 只输出统计信息，优先于 `format` 中的逐键内容
+    summary: bool,
+    /// 只输出按键的前若干段分组的变更数量
+    stats_by_prefix: Option<usize>,
+    /// 只输出总数、各顶层配置段的数量和最深的变更键
+    stats: bool,
+    /// 扁平键 → 人类可读的说明，只用于文本报告
+    descriptions: HashMap<String, String>,
+    /// 字符串超过该长度时只显示长度
+    value_length_threshold: Option<usize>,
+    /// 只显示内容哈希的键
+    hashed: Vec<KeyPattern>,
+    /// 需要突出显示的键
+    highlighted: Vec<KeyPattern>,
+    /// 把删除 + 新增合并为移动
+    report_moved: bool,
+    /// 每个变更前后显示的未变化同级键个数
+    context: Option<usize>,
+    /// 逐行比较多行字符串时，变化的行前后显示的未变化行数
+    value_context: usize,
+    /// side-by-side 输出的总宽度，没有设置时使用默认宽度
+    width: Option<usize>,
+    /// 在变更下方附上源文件中的原始行
+    annotate_source: bool,
+    /// 在变更旁标出源文件中的行号
+    line_numbers: bool,
+    /// 用于 --annotate-source 和 --line-numbers 的源文件文本
+    old_source: Option<String>,
+    new_source: Option<String>,
+    /// unified 输出头部 `---` / `+++` 行中的旧、新文件名
+    labels: Option<(String, String)>,
+}
 
-        // 找到公共前缀长度
-        let common_len = self_parts
-            .iter()
-            .zip(&other_parts)
-            .take_while(|(a, b)| a == b)
-            .count();
-
-        if common_len == 0 {
-            // 无公共前缀，直接字典序比较
-            return self.0.cmp(&other.0);
-        }
-
-        // 有公共前缀时，优先按段数排序
-        match self_parts.len().cmp(&other_parts.len()) {
-            Ordering::Equal => {
-                // 段数相同，比较第一个不同的段
-                self_parts
-                    .get(common_len)
-                    .cmp(&other_parts.get(common_len))
-                    .then_with(|| self.0.cmp(&other.0)) // 兜底比较
+fn main() -> ExitCode {
+    // 解析命令行前出错（如读取配置文件）时还不知道 --lang，先按环境变量选择语言
+    i18n::set_error_lang(Lang::from_env());
+    let args = git::external_diff_args(env::args_os().collect(), git::external_diff_env());
+    let config = match load_config(&args) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error: {e:?}");
+            let exit_code = args.iter().any(|a| a == "--exit-code" || a == "--silent");
+            return ExitCode::from(exit_status(exit_code, &Err(e)));
+        }
+    };
+    let mut input = Args::parse_from(config::with_defaults(
+        args,
+        &config,
+        &["apply", "get", "normalize", "merge", "keys", "help"],
+    ));
+    input.config_rules = config.rules;
+    i18n::set_error_lang(input.lang.unwrap_or_else(Lang::from_env));
+    if input.watch {
+        match watched_paths(&input) {
+            Ok(paths) => watch::watch(&paths, || {
+                if let Err(e) = execute(&input) {
+                    print_error(&input, &e);
+                }
+            }),
+            Err(e) => {
+                print_error(&input, &e);
+                return ExitCode::from(exit_status(input.exit_code || input.silent, &Err(e)));
             }
-            order => order, // 段数不同，段数少的在前
         }
     }
+    let result = execute(&input);
+    if let Err(e) = &result {
+        print_error(&input, e);
+    }
+    ExitCode::from(exit_status(input.exit_code || input.silent, &result))
 }
 
-impl PartialOrd for ConfigKey {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+/// 读取本次运行使用的配置文件，没有配置文件时为空
+fn load_config(args: &[std::ffi::OsString]) -> Result<config::Config> {
+    let cwd = env::current_dir()
+        .map_err(|e| anyhow!(i18n::error_lang().format(Msg::CurrentDirFailed, &[&e])))?;
+    let Some(path) = config::locate(args, &cwd) else {
+        return Ok(config::Config::default());
+    };
+    let command = Args::command();
+    config::load(&path, |name| {
+        command
+            .get_arguments()
+            .any(|arg| arg.get_long() == Some(name))
+    })
+}
+
+fn print_error(input: &Args, e: &anyhow::Error) {
+    // JSON 输出给程序读取，出错时同样在标准错误输出一个 JSON 对象
+    if input.format == OutputFormat::Json {
+        eprintln!("{}", error_to_json(e));
+    } else {
+        eprintln!("Error: {e:?}");
     }
 }
 
-struct ConfigDiff<'a> {
-    added: BTreeMap<ConfigKey, &'a Value>,
-    removed: BTreeMap<ConfigKey, &'a Value>,
-    modified: BTreeMap<ConfigKey, (&'a Value, &'a Value)>,
+/// --watch 监视的输入：--old、--new 以及 --base，标准输入无法监视
+fn watched_paths(input: &Args) -> Result<Vec<PathBuf>> {
+    let paths: Vec<PathBuf> = [&input.old, &input.base]
+        .into_iter()
+        .flatten()
+        .chain(&input.new)
+        .cloned()
+        .collect();
+    if paths.iter().any(|path| is_stdin(path)) {
+        return Err(anyhow!(i18n::error_lang().get(Msg::WatchStdin)));
+    }
+    Ok(paths)
 }
 
-fn main() -> Result<()> {
-    let input = Args::parse();
+/// 进程的退出码：--exit-code 时没有差异为 0，有差异为 1，出错为 2；否则成功为 0，出错为 1。
+/// 规则文件中 warn / error 级别的键发生变更时分别为 3 / 4，与 --exit-code 无关
+fn exit_status(exit_code: bool, result: &Result<bool>) -> u8 {
+    if let Err(e) = result
+        && let Some(exceeded) = e.downcast_ref::<SeverityExceeded>()
+    {
+        return exceeded.exit_code();
+    }
+    match (result, exit_code) {
+        (Ok(changed), true) => u8::from(*changed),
+        (Ok(_), false) => 0,
+        (Err(_), true) => 2,
+        (Err(_), false) => 1,
+    }
+}
 
-    let old_val = read_cfg(input.old)?;
-    let new_val = read_cfg(input.new)?;
+/// 错误的 JSON 形式：`{"error": 说明, "path": 文件路径, "location": {"line": 行, "column": 列}}`，
+/// 不是解析错误或没有位置信息时对应的字段为 null
+fn error_to_json(e: &anyhow::Error) -> serde_json::Value {
+    match e.downcast_ref::<ParseError>() {
+        Some(parse) => serde_json::json!({
+            "error": parse.message,
+            "path": parse.path.display().to_string(),
+            "location": parse.location,
+        }),
+        None => serde_json::json!({
+            "error": e.to_string(),
+            "path": null,
+            "location": null,
+        }),
+    }
+}
 
-    // 比较 YAML 内容
-    let diff = cmp_yml_vals(&old_val, &new_val);
+/// 输出报告，返回是否发现了差异
+fn execute(input: &Args) -> Result<bool> {
+    if let Some(Command::Apply(args)) = &input.command {
+        return run_apply(args).map(|()| false);
+    }
+    if let Some(Command::Get(args)) = &input.command {
+        return run_get(args).map(|()| false);
+    }
+    if let Some(Command::Normalize(args)) = &input.command {
+        return run_normalize(args).map(|()| false);
+    }
+    if let Some(Command::Merge(args)) = &input.command {
+        return run_merge(args).map(|()| false);
+    }
+    if let Some(Command::Keys(args)) = &input.command {
+        return run_keys(args).map(|()| false);
+    }
+    if input.progress {
+        progress::enable();
+    }
+    if input.output.iter().filter(|t| t.format.is_none()).count() > 1 {
+        return Err(anyhow!(i18n::error_lang().get(Msg::MultiplePlainOutputs)));
+    }
+    let mut dest: Box<dyn Write> = match input.report_path() {
+        _ if input.silent => Box::new(io::sink()),
+        Some(path) => Box::new(File::create(path).map_err(|e| {
+            let path = format!("{path:?}");
+            anyhow!(i18n::error_lang().format(Msg::CreateOutputFailed, &[&e, &path]))
+        })?),
+        None => Box::new(io::stdout().lock()),
+    };
 
-    // 输出结果
-    print_diff(&diff);
+    if input.output_encoding == UTF_8 {
+        let mut out = BufWriter::new(dest);
+        let result = run(input, &mut out);
+        out.flush()?;
+        progress::finish()?;
+        return result;
+    }
 
-    Ok(())
+    // 先把报告完整地写入缓冲区，再一次性转码输出；出错时也要输出已生成的部分
+    let mut buf = Vec::new();
+    let result = run(input, &mut buf);
+    dest.write_all(&encode_output(&buf, input.output_encoding))?;
+    progress::finish()?;
+    result
 }
 
-fn read_cfg(path: PathBuf) -> Result<Value> {
-    File::open(&path)
-        .map(BufReader::new)
-        .map_err(|e| anyhow!("读取配置文件失败！{e}: {:?}", path))
-        .and_then(|reader| {
-            serde_yaml::from_reader(reader).map_err(|e| anyhow!("解析旧版配置文件失败！{e}"))
-        })
+/// 是否输出颜色控制符；`--color always` 优先于 NO_COLOR
+fn use_color(choice: ColorChoice, format: OutputFormat, is_tty: bool, no_color: bool) -> bool {
+    // JSON、JUnit XML 和 GitHub 命令输出给程序读取，HTML 和 Markdown 有自己的格式，
+    // 都不能混入颜色控制符
+    if matches!(
+        format,
+        OutputFormat::Json
+            | OutputFormat::Html
+            | OutputFormat::Markdown
+            | OutputFormat::Junit
+            | OutputFormat::Github
+    ) {
+        return false;
+    }
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => is_tty && !no_color,
+    }
 }
 
-fn cmp_yml_vals<'a>(old: &'a Value, new: &'a Value) -> ConfigDiff<'a> {
-    let old_key_vals = extract_key_vals(old, String::new());
-    let new_key_vals = extract_key_vals(new, String::new());
-
-    let old_keys: HashSet<_> = old_key_vals.keys().collect();
-    let new_keys: HashSet<_> = new_key_vals.keys().collect();
-
-    let added_keys: Vec<&str> = new_keys
-        .difference(&old_keys)
-        .map(|&k| k.as_str())
-        .collect();
+fn parse_encoding(label: &str) -> Result<&'static Encoding> {
+    Encoding::for_label(label.as_bytes())
+        .ok_or_else(|| anyhow!(i18n::error_lang().format(Msg::UnsupportedEncoding, &[&label])))
+}
 
-    let added = added_keys
-        .into_iter()
-        .filter_map(|k| new_key_vals.get(k).map(|&v| (k.into(), v)))
-        .collect();
+/// --tag-handler 的 `TAG=KIND`
+fn parse_tag_handler(s: &str) -> Result<(String, TagHandler)> {
+    let (tag, kind) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow!(i18n::error_lang().format(Msg::InvalidTagHandler, &[&s])))?;
+    Ok((
+        tag.to_string(),
+        kind.parse().map_err(|e: String| anyhow!(e))?,
+    ))
+}
 
-    let removed_keys: Vec<&str> = old_keys
-        .difference(&new_keys)
-        .map(|&k| k.as_str())
-        .collect();
+/// 把 UTF-8 报告转换为目标编码
+fn encode_output<'a>(buf: &'a [u8], encoding: &'static Encoding) -> Cow<'a, [u8]> {
+    match String::from_utf8_lossy(buf) {
+        Cow::Borrowed(text) => encoding.encode(text).0,
+        Cow::Owned(text) => Cow::Owned(encoding.encode(&text).0.into_owned()),
+    }
+}
 
-    let removed = removed_keys
-        .into_iter()
-        .filter_map(|k| old_key_vals.get(k).map(|&v| (k.into(), v)))
-        .collect();
+fn run(input: &Args, out: &mut dyn Write) -> Result<bool> {
+    let no_color = env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    colored::control::set_override(use_color(
+        input.color,
+        input.output_format(),
+        input.report_path().is_none() && io::stdout().is_terminal(),
+        no_color,
+    ));
+    let mut rules = match &input.config_rules {
+        Some(value) => Rules::from_value(value)
+            .map_err(|e| anyhow!(i18n::error_lang().format(Msg::InvalidConfigRules, &[&e])))?,
+        None => Rules::default(),
+    };
+    if let Some(path) = &input.rules {
+        rules.extend(Rules::load(path)?);
+    }
+    for pattern in &input.ignore {
+        rules.push(pattern.clone(), RuleAction::Ignore);
+    }
+    for pattern in &input.mask {
+        rules.push(pattern.clone(), RuleAction::Redact);
+    }
+    for pattern in &input.unordered {
+        rules.push(pattern.clone(), RuleAction::Unordered);
+    }
+    let mut opts = DiffOptions {
+        rules,
+        only: input.only.clone(),
+        paths: input.path.clone(),
+        flatten: input.flatten_options(),
+        min_percent_change: input.min_percent_change,
+        ignore_keys_in_values: input.ignore_keys_in_values.clone(),
+        assume_sorted: input.assume_sorted,
+        preserve_order: input.no_sort,
+        detect_renames: input.detect_renames,
+        detect_reorders: input.detect_reorders,
+        ignore_reorders: input.ignore_reorders,
+        key_renames: match &input.renames {
+            Some(path) => KeyRenames::load(path)?,
+            None => KeyRenames::default(),
+        },
+        track_unchanged: input.context.is_some(),
+        compare_as_strings: input.compare_as_strings,
+        strict_number_types: input.strict_number_types,
+        null_is_absent: input.null_is_absent,
+        ignore_array_order: input.ignore_array_order,
+        epsilon: input.epsilon,
+        normalize_numbers: input.normalize_numbers,
+        strings: StringNormalization {
+            ignore_case: input.ignore_case,
+            trim: input.trim_strings,
+            booleans: input.normalize_booleans,
+        },
+        tags: if input.compare_tags {
+            TagOptions::builtin()
+        } else {
+            TagOptions::default()
+        },
+        #[cfg(feature = "parallel")]
+        parallel_sort: input.parallel_sort,
+        ..Default::default()
+    };
+    opts.tags.handlers.extend(input.tag_handler.iter().cloned());
+    if let Some(path) = &input.ignore_default_values {
+        opts.defaults = load_defaults(path, &opts)?;
+    }
+    let mut report = ReportOptions {
+        lang: i18n::error_lang(),
+        explain: input.explain,
+        quiet: input.quiet,
+        format: input.output_format(),
+        changed_keys_only: input.changed_keys_only,
+        count_by_type: input.count_by_type,
+        summary: input.summary,
+        stats_by_prefix: input.stats_by_prefix,
+        stats: input.stats,
+        value_length_threshold: input.show_value_lengths,
+        hashed: input.value_hash.clone(),
+        highlighted: input.highlight_key.clone(),
+        report_moved: input.report_moved,
+        context: input.context,
+        value_context: input.value_context,
+        annotate_source: input.annotate_source,
+        line_numbers: input.line_numbers,
+        width: (input.output_format() == OutputFormat::SideBySide)
+            .then(side_by_side::terminal_width),
+        ..Default::default()
+    };
+    if let Some(path) = &input.context_file {
+        report.descriptions = load_descriptions(path, &opts)?;
+    }
 
-    let modified = old_keys
-        .intersection(&new_keys)
-        .filter_map(|&k| match (old_key_vals.get(k), new_key_vals.get(k)) {
-            (Some(&old), Some(&new)) if old != new => Some((k.into(), (old, new))),
-            _ => None,
-        })
-        .collect();
+    if !input.pair.is_empty() || input.pairs_file.is_some() {
+        let mut pairs: Vec<(PathBuf, PathBuf)> = input
+            .pair
+            .chunks_exact(2)
+            .map(|pair| (pair[0].clone(), pair[1].clone()))
+            .collect();
+        if let Some(path) = &input.pairs_file {
+            pairs.extend(load_pairs_file(path)?);
+        }
+        let pairs = pairs
+            .into_iter()
+            .map(|(old, new)| FilePair {
+                label: format!("{} → {}", old.display(), new.display()),
+                old: Some(old),
+                new: Some(new),
+            })
+            .collect();
+        return run_pairs(input, &opts, &report, pairs, out);
+    }
+    if input.low_memory {
+        return run_low_memory(input, &opts, &report, out);
+    }
+    if input.new.len() > 1 {
+        return run_fan_out(input, &opts, &report, out);
+    }
+    if let (Some(old), Some(new)) = (&input.old, input.new.first())
+        && (old.is_dir() || new.is_dir())
+    {
+        return run_dirs(input, &opts, &report, old, new, out);
+    }
 
-    ConfigDiff {
-        added,
-        removed,
-        modified,
-    }
-}
-
-fn extract_key_vals(value: &Value, mut prefix: String) -> HashMap<String, &Value> {
-    let mut key_vals = HashMap::new();
-
-    match value {
-        Value::Mapping(map) => {
-            let prefix_len = prefix.len();
-            for (k, v) in map {
-                if let Some(key_str) = k.as_str() {
-                    // 重用 prefix String，避免重复分配
-                    if !prefix.is_empty() {
-                        prefix.push('.');
-                    }
-                    prefix.push_str(key_str);
-
-                    // 递归处理嵌套对象
-                    if let Value::Mapping(_) = v {
-                        let nested_keys = extract_key_vals(v, prefix.clone());
-                        key_vals.extend(nested_keys);
-                    } else {
-                        // 添加当前键值对
-                        key_vals.insert(prefix.clone(), v);
-                    }
-
-                    // 恢复 prefix 到之前的状态，重用 String
-                    prefix.truncate(prefix_len);
-                }
-            }
+    let (old_val, new_val) = if input.stdin_pair {
+        let (mut old, mut new) = read_stdin_pair(!input.no_resolve_aliases)?;
+        if input.k8s {
+            k8s::strip_server_fields(&mut old);
+            k8s::strip_server_fields(&mut new);
         }
-        _ => {
-            // 如果不是映射类型，直接添加
-            if !prefix.is_empty() {
-                key_vals.insert(prefix, value);
+        match input.sops {
+            Some(SopsMode::Mask) => {
+                sops::mask_encrypted(&mut old);
+                sops::mask_encrypted(&mut new);
             }
+            Some(SopsMode::Decrypt) => {
+                return Err(anyhow!(i18n::error_lang().get(Msg::SopsDecryptStdinPair)));
+            }
+            None => {}
         }
-    }
-
-    key_vals
-}
+        (old, new)
+    } else {
+        // clap 保证未使用 --stdin-pair 时两个路径都存在
+        let (Some(old), Some(new)) = (&input.old, input.new.first()) else {
+            unreachable!()
+        };
+        if is_stdin(old) && is_stdin(new) {
+            return Err(anyhow!(i18n::error_lang().get(Msg::BothStdin)));
+        }
+        let (old_bytes, new_bytes) = input.read_transformed(old, new)?;
 
-fn get_val_string(val: &Value) -> Cow<str> {
-    match val {
-        Value::Null => Cow::Borrowed("null"),
-        Value::Bool(b) => {
-            if *b {
-                Cow::Borrowed("true")
-            } else {
-                Cow::Borrowed("false")
+        // 字节完全相同的文件无需解析；格式不同但语义相同的文件仍走完整比较
+        if input.baseline_hash && !input.needs_full_compare() && same_hash(&old_bytes, &new_bytes) {
+            label_report(input, &mut report, out)?;
+            render(out, &ConfigDiff::default(), &opts, &report)?;
+            write_format_outputs(input, &ConfigDiff::default(), &opts, &report)?;
+            // 没有差异时也写出空的拆分文件，读取它们的工具不必区分这种情况
+            if let Some(dir) = &input.split_output {
+                split::write_split_output(dir, &ConfigDiff::default(), &opts)?;
             }
+            return Ok(false);
         }
-        Value::Number(n) => Cow::Owned(n.to_string()),
-        Value::String(s) => Cow::Borrowed(s),
-        Value::Sequence(seq) => {
-            let mut prefix = String::from("[");
-            let arr: Vec<_> = seq.iter().map(|v| get_val_string(v)).collect();
-            let arr_str = arr.join(", ");
-            prefix.push_str(&arr_str);
-            prefix.push(']');
-            Cow::Owned(prefix)
-        }
-        Value::Mapping(m) => {
-            let map: HashMap<Cow<str>, Cow<str>> = m
-                .iter()
-                .map(|(k, v)| (get_val_string(k), get_val_string(v)))
-                .collect();
-            Cow::Owned(format!("{map:?}"))
+
+        // GitHub 注释需要行号
+        if input.annotate_source || input.line_numbers || input.writes_format(OutputFormat::Github)
+        {
+            report.old_source = Some(String::from_utf8_lossy(&old_bytes).into_owned());
+            report.new_source = Some(String::from_utf8_lossy(&new_bytes).into_owned());
         }
-        Value::Tagged(t) => Cow::Owned(format!("{}:{}", t.tag, get_val_string(&t.value))),
+
+        let (old_docs, new_docs) = input.parse_pair((old, &old_bytes), (new, &new_bytes))?;
+        input.combine(old_docs, new_docs)
+    };
+
+    if input.fail_on_empty_input {
+        let lang = i18n::error_lang();
+        let (old_label, new_label) = match (&input.old, input.new.first()) {
+            (Some(old), Some(new)) => (
+                format!("{} {}", lang.get(Msg::OldFile), old.display()),
+                format!("{} {}", lang.get(Msg::NewFile), new.display()),
+            ),
+            _ => (
+                lang.get(Msg::OldStdinDocument).to_string(),
+                lang.get(Msg::NewStdinDocument).to_string(),
+            ),
+        };
+        ensure_not_empty(&old_label, &old_val, &opts)?;
+        ensure_not_empty(&new_label, &new_val, &opts)?;
     }
-}
 
-fn print_diff(diff: &ConfigDiff) {
-    println!("{}", "=== YAML 配置文件差异报告 ===".bold());
-    println!();
+    label_report(input, &mut report, out)?;
+    let (old_val, new_val) = if input.swap {
+        std::mem::swap(&mut report.old_source, &mut report.new_source);
+        (new_val, old_val)
+    } else {
+        (old_val, new_val)
+    };
+
+    if let Some(base) = &input.base {
+        let base_docs = input.parse_input(base, &read_bytes(base)?)?;
+        let (base_val, _) = input.combine(base_docs, Vec::new());
+        return run_three_way(input, &opts, &report, [&base_val, &old_val, &new_val], out);
+    }
 
-    // 统计信息
-    println!("{}", "统计信息:".blue().bold());
-    println!("  新增: {}", diff.added.len().to_string().green());
-    println!("  删除: {}", diff.removed.len().to_string().red());
-    println!("  修改: {}", diff.modified.len().to_string().yellow());
-    println!();
+    // 比较 YAML 内容
+    let diff = progress::time(Phase::Compare, || cmp_yml_vals(&old_val, &new_val, &opts));
+    warn_unsorted_input(&diff, &report);
 
-    if !diff.added.is_empty() {
-        println!("{}", "新增的配置项:".green().bold());
-        for (key, &val) in &diff.added {
-            println!("  + {}: {}", key.green(), get_val_string(val).green());
+    // 输出结果
+    if input.patch {
+        let patch = patch::merge_patch(&old_val, &new_val, &diff, &opts);
+        let text = serde_yaml::to_string(&patch)
+            .map_err(|e| anyhow!(i18n::error_lang().format(Msg::SerializePatchFailed, &[&e])))?;
+        write!(out, "{text}")?;
+    } else if input.emit_patch {
+        let ops = apply::diff_to_ops(&diff, &opts);
+        if report.format == OutputFormat::Json {
+            writeln!(out, "{}", serde_json::to_string_pretty(&ops)?)?;
+        } else {
+            let text = serde_yaml::to_string(&ops)
+                .map_err(|e| anyhow!(i18n::error_lang().format(Msg::SerializeOpsFailed, &[&e])))?;
+            write!(out, "{text}")?;
         }
-        println!();
+    } else if input.interactive {
+        review_changes(input, out, &diff, &opts, &report)?;
+    } else {
+        render(out, &diff, &opts, &report)?;
     }
+    write_format_outputs(input, &diff, &opts, &report)?;
 
-    if !diff.removed.is_empty() {
-        println!("{}", "删除的配置项:".red().bold());
-        for (key, &val) in &diff.removed {
-            println!("  - {}: {}", key.red(), get_val_string(val).red());
-        }
-        println!();
+    if let Some(dir) = &input.split_output {
+        split::write_split_output(dir, &diff, &opts)?;
+    }
+
+    if input.summarize_unchanged_subtrees {
+        let sections = unchanged_sections(&new_val, &diff, &opts);
+        print_unchanged_sections(out, &sections, report.lang)?;
+    }
+
+    if input.key_case_report {
+        let pairs = key_case_mismatches(&old_val, &new_val, &opts);
+        print_key_case_report(out, &pairs, report.lang)?;
     }
 
-    if !diff.modified.is_empty() {
-        println!("{}", "修改的配置项:".yellow().bold());
-        for (key, (old, new)) in &diff.modified {
-            println!("  ~ {}", key.yellow());
-            println!("  修改前 {}", get_val_string(old).yellow());
-            println!("  修改后 {}", get_val_string(new).yellow());
+    if input.round_trip_check {
+        round_trip_check(&old_val, &new_val, &opts)?;
+    }
+
+    #[cfg(feature = "schema")]
+    if let Some(path) = &input.validate_against {
+        let schema_val = parse_cfg(&read_bytes(path)?)?;
+        let violations = schema::validate(&schema_val, &new_val)?;
+        schema::print_violations(out, &violations, report.lang)?;
+        if !violations.is_empty() {
+            let count = violations.len();
+            return Err(anyhow!(
+                i18n::error_lang().format(Msg::SchemaViolations, &[&count])
+            ));
         }
-        println!();
     }
 
-    if diff.added.is_empty() && diff.removed.is_empty() && diff.modified.is_empty() {
-        println!("{}", "没有发现配置差异".green());
+    #[cfg(feature = "schema")]
+    if let Some(path) = &input.schema {
+        let schema_val = parse_cfg(&read_bytes(path)?)?;
+        let breaking = schema::breaking_changes(
+            &schema::validate(&schema_val, &old_val)?,
+            &schema::validate(&schema_val, &new_val)?,
+        );
+        schema::print_breaking_changes(out, &breaking, report.lang)?;
+        if !breaking.is_empty() {
+            let count = breaking.len();
+            return Err(anyhow!(
+                i18n::error_lang().format(Msg::SchemaBreaking, &[&count])
+            ));
+        }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::{cmp_yml_vals, print_diff, read_cfg};
-    use std::path::PathBuf;
+    if input.fail_on_type_change {
+        ensure_no_type_changes(&diff)?;
+    }
+    ensure_change_limits(input, std::slice::from_ref(&diff))?;
+    ensure_severity(std::slice::from_ref(&diff), &opts.rules)?;
 
-    #[test]
-    fn test_compare_yaml() {
-        // 获取项目根目录（Cargo.toml 所在的目录）
-        let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    Ok(!diff.is_empty())
+}
 
-        // 构建配置文件的完整路径
-        let old = PathBuf::from(manifest_dir).join("config_v1.yml");
-        let new = PathBuf::from(manifest_dir).join("config_v2.yml");
+/// --interactive：逐处审阅变更，按需把接受的部分写入 --accepted-patch
+fn review_changes(
+    input: &Args,
+    out: &mut dyn Write,
+    diff: &ConfigDiff,
+    opts: &DiffOptions,
+    report: &ReportOptions,
+) -> Result<()> {
+    if !io::stdin().is_terminal() {
+        return Err(anyhow!(
+            i18n::error_lang().get(Msg::InteractiveNeedsTerminal)
+        ));
+    }
+    let decisions = review::review(&mut io::stdin().lock(), out, diff, opts, report)?;
+    if let Some(path) = &input.accepted_patch {
+        let mut ops = apply::diff_to_ops(diff, opts);
+        ops.retain(|op| {
+            let (apply::PatchOp::Add { path, .. }
+            | apply::PatchOp::Remove { path }
+            | apply::PatchOp::Replace { path, .. }) = op;
+            decisions.get(path) == Some(&review::Decision::Accepted)
+        });
+        let text = serde_yaml::to_string(&ops)
+            .map_err(|e| anyhow!(i18n::error_lang().format(Msg::SerializeOpsFailed, &[&e])))?;
+        fs::write(path, text).map_err(|e| write_output_error(e, path))?;
+        let path = path.display();
+        writeln!(
+            out,
+            "{}",
+            report.lang.format(Msg::AcceptedWritten, &[&path])
+        )?;
+    }
+    Ok(())
+}
 
-        println!("Looking for files:");
-        println!("Old config: {old:?}");
-        println!("New config: {new:?}");
+/// --assume-sorted 的输入实际没有排序时在标准错误中警告；--quiet 时不输出
+fn warn_unsorted_input(diff: &ConfigDiff, report: &ReportOptions) {
+    if let Some(unsorted) = diff.unsorted_input().filter(|_| !report.quiet) {
+        let lang = report.lang;
+        let side = lang.get(if unsorted.old {
+            Msg::OldFile
+        } else {
+            Msg::NewFile
+        });
+        let (prev, key) = (unsorted.prev.as_str(), unsorted.key.as_str());
+        eprintln!("{}", lang.format(Msg::UnsortedInput, &[&side, &prev, &key]));
+    }
+}
 
-        // 检查文件是否存在
-        if !old.exists() {
-            panic!(
-                "config_v1.yml not found at {:?}. Current working directory: {:?}",
-                old,
-                std::env::current_dir().unwrap()
-            );
+/// 设置报告中的旧、新文件名；由 git 调用时在文本报告前输出仓库中的路径
+///
+/// --baseline-hash 跳过比较时也调用，保证两条路径的输出一致。
+fn label_report(input: &Args, report: &mut ReportOptions, out: &mut dyn Write) -> io::Result<()> {
+    let label = |path: &Path, rev: &Option<String>| match rev {
+        Some(rev) => format!("{rev}:{}", path.display()),
+        None => path.display().to_string(),
+    };
+    report.labels = match (&input.old, input.new.first()) {
+        (Some(old), Some(new)) => Some((label(old, &input.old_rev), label(new, &input.new_rev))),
+        _ => Some(("-".into(), "-".into())),
+    };
+    if let Some(path) = &input.git_path {
+        // 被 git 调用时 --old/--new 是临时文件，报告中改用仓库中的路径
+        report.labels = Some((
+            format!("a/{}", path.display()),
+            format!("b/{}", path.display()),
+        ));
+        if !matches!(
+            report.format,
+            OutputFormat::Json
+                | OutputFormat::Html
+                | OutputFormat::Markdown
+                | OutputFormat::Unified
+                | OutputFormat::Junit
+                | OutputFormat::Github
+        ) {
+            writeln!(out, "{}", format!(">>> {}", path.display()).bold())?;
         }
-        if !new.exists() {
+    }
+    if report.format == OutputFormat::Github
+        && let Some(labels) = input.github_labels()
+    {
+        // GitHub 按仓库中的路径放置注释
+        report.labels = Some(labels);
+    }
+    if input.swap {
+        report.labels = report.labels.take().map(|(old, new)| (new, old));
+    }
+    Ok(())
+}
+
+/// --low-memory：解析时展开两个文件，不构建文档树
+///
+/// 两个文件的全部扁平条目同时留在内存中再归并，峰值内存仍与文件大小成正比，见 [`stream`]。
+fn run_low_memory(
+    input: &Args,
+    opts: &DiffOptions,
+    report: &ReportOptions,
+    out: &mut dyn Write,
+) -> Result<bool> {
+    let (Some(old), [new]) = (&input.old, input.new.as_slice()) else {
+        return Err(anyhow!(i18n::error_lang().get(Msg::LowMemoryOnePair)));
+    };
+    let flatten_opts = compare_flatten_options(opts);
+    let lang = i18n::error_lang();
+    let read = |path: &Path| -> Result<Vec<(String, Value)>> {
+        let quoted = format!("{path:?}");
+        if path.is_dir() {
+            return Err(anyhow!(lang.format(Msg::LowMemoryNoDir, &[&quoted])));
+        }
+        if InputFormat::detect(path, &[]) == InputFormat::Toml {
+            return Err(anyhow!(lang.format(Msg::LowMemoryNoToml, &[&quoted])));
+        }
+        let entries = if is_stdin(path) {
+            let label = lang.get(Msg::StandardInput);
+            let stdin = ProgressReader::new(io::stdin().lock(), label, None);
+            stream::flatten_reader(io::BufReader::new(stdin), &flatten_opts)
+        } else {
+            let file = File::open(path)
+                .map_err(|e| anyhow!(lang.format(Msg::ReadFileFailed, &[&e, &quoted])))?;
+            let size = file.metadata().ok().map(|meta| meta.len());
+            let file = ProgressReader::new(file, path.display().to_string(), size);
+            stream::flatten_reader(io::BufReader::new(file), &flatten_opts)
+        };
+        entries.map_err(|e| match e {
+            StreamError::Yaml(e) => {
+                let message = lang.format(Msg::ParseYamlFailed, &[&e]);
+                anyhow!("{message}: {quoted}")
+            }
+            StreamError::MultipleDocuments => {
+                anyhow!(lang.format(Msg::LowMemoryMultipleDocuments, &[&quoted]))
+            }
+            StreamError::CoercedKeyConflict(key) => {
+                anyhow!(lang.format(Msg::CoercedKeyConflict, &[&key, &quoted]))
+            }
+        })
+    };
+    let mut old_entries = progress::time(Phase::ParseOld, || read(old))?;
+    let mut new_entries = progress::time(Phase::ParseNew, || read(new))?;
+    let mut labels = (old.display().to_string(), new.display().to_string());
+    if input.swap {
+        std::mem::swap(&mut old_entries, &mut new_entries);
+        std::mem::swap(&mut labels.0, &mut labels.1);
+    }
+    if input.fail_on_empty_input {
+        for (label, entries) in [(&labels.0, &old_entries), (&labels.1, &new_entries)] {
+            if entries.is_empty() {
+                return Err(anyhow!(lang.format(Msg::EmptyInput, &[label])));
+            }
+        }
+    }
+
+    let diff = progress::time(Phase::Compare, || {
+        cmp_flat_entries(&old_entries, &new_entries, opts)
+    });
+    let report = ReportOptions {
+        labels: Some(labels),
+        ..report.clone()
+    };
+    render(out, &diff, opts, &report)?;
+    write_format_outputs(input, &diff, opts, &report)?;
+    if input.fail_on_type_change {
+        ensure_no_type_changes(&diff)?;
+    }
+    ensure_change_limits(input, std::slice::from_ref(&diff))?;
+    ensure_severity(std::slice::from_ref(&diff), &opts.rules)?;
+    Ok(!diff.is_empty())
+}
+
+/// --base：三方比较，按需写出合并结果；有冲突时返回错误
+fn run_three_way(
+    input: &Args,
+    opts: &DiffOptions,
+    report: &ReportOptions,
+    [base, ours, theirs]: [&Value; 3],
+    out: &mut dyn Write,
+) -> Result<bool> {
+    let changes = three_way::three_way(base, ours, theirs, opts);
+    if report.format == OutputFormat::Json {
+        let json = serde_json::to_string_pretty(&three_way::three_way_to_json(&changes, opts))?;
+        writeln!(out, "{json}")?;
+    } else {
+        three_way::print_three_way(out, &changes, opts, report)?;
+    }
+
+    if let Some(path) = &input.merge_output {
+        let merged = three_way::merge(base, ours, theirs, opts)?;
+        let text = serde_yaml::to_string(&merged)
+            .map_err(|e| anyhow!(i18n::error_lang().format(Msg::SerializeMergeFailed, &[&e])))?;
+        fs::write(path, text).map_err(|e| write_output_error(e, path))?;
+    }
+
+    let conflicts = changes
+        .iter()
+        .filter(|c| c.kind == three_way::MergeKind::Conflict)
+        .count();
+    if conflicts > 0 {
+        return Err(anyhow!(
+            i18n::error_lang().format(Msg::MergeConflicts, &[&conflicts])
+        ));
+    }
+    Ok(!changes.is_empty())
+}
+
+/// yml-diff apply：读取操作列表和配置文件，应用后输出 YAML
+fn run_apply(args: &ApplyArgs) -> Result<()> {
+    let ops: Vec<apply::PatchOp> = parse_file(&args.patch, &read_bytes(&args.patch)?, None, true)?
+        .into_iter()
+        .next()
+        .map(serde_yaml::from_value)
+        .transpose()
+        .map_err(|e| {
+            let path = format!("{:?}", args.patch);
+            anyhow!(i18n::error_lang().format(Msg::ParseOpsFailed, &[&e, &path]))
+        })?
+        .unwrap_or_default();
+    let doc = parse_file(&args.file, &read_bytes(&args.file)?, None, true)?
+        .into_iter()
+        .next()
+        .unwrap_or(Value::Null);
+    let doc = apply::apply_ops(&doc, &ops, &args.separator)?;
+    let text = serde_yaml::to_string(&doc).map_err(serialize_result_error)?;
+    match &args.output {
+        Some(path) => fs::write(path, text).map_err(|e| write_output_error(e, path)),
+        None => Ok(io::stdout().write_all(text.as_bytes())?),
+    }
+}
+
+fn run_get(args: &GetArgs) -> Result<()> {
+    let docs = parse_file(&args.file, &read_bytes(&args.file)?, None, true)?;
+    // 多文档文件与比较时一样合并，键路径为 `doc.Service/web.spec.port` 或 `doc[0].spec.port`
+    let (doc, _) = combine_documents(docs, Vec::new());
+    let key = ConfigKey::new(&args.key, &args.separator);
+    let segments: Vec<&str> = key.segments().collect();
+    let value = apply::get_path(&doc, &segments).ok_or_else(|| {
+        let file = format!("{:?}", args.file);
+        anyhow!(i18n::error_lang().format(Msg::KeyNotFound, &[&args.key, &file]))
+    })?;
+    io::stdout().write_all(render_value(value, args.json)?.as_bytes())?;
+    Ok(())
+}
+
+fn run_keys(args: &KeysArgs) -> Result<()> {
+    let docs = parse_file(&args.file, &read_bytes(&args.file)?, None, true)?;
+    // 多文档文件与比较时一样合并，列出的键可以直接用于 --path、--ignore 和 get
+    let (doc, _) = combine_documents(docs, Vec::new());
+    let opts = DiffOptions {
+        paths: args.path.clone(),
+        flatten: FlattenOptions {
+            separator: args.separator.clone(),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut out = BufWriter::new(io::stdout().lock());
+    let result = key_lines(&doc, &opts, args.values, args.sort)
+        .iter()
+        .try_for_each(|line| writeln!(out, "{line}"))
+        .and_then(|()| out.flush());
+    match result {
+        // 输出被 head 等提前关闭时不算错误
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => Ok(result?),
+    }
+}
+
+/// keys 子命令输出的各行：范围内的扁平键，`values` 时附上值
+fn key_lines(doc: &Value, opts: &DiffOptions, values: bool, sort: bool) -> Vec<String> {
+    let mut entries: Vec<(ConfigKey, &Value)> = extract_key_vals(doc, opts)
+        .into_iter()
+        .filter(|(key, _)| opts.includes(key))
+        .map(|(key, val)| (ConfigKey::new(key, &opts.flatten.separator), val))
+        .collect();
+    if sort {
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+    }
+    entries
+        .into_iter()
+        .map(|(key, val)| match values {
+            true => format!("{}: {}", key.as_str(), get_val_string(val)),
+            false => key.to_string(),
+        })
+        .collect()
+}
+
+fn run_merge(args: &MergeArgs) -> Result<()> {
+    let first_doc = |path: &PathBuf| -> Result<Value> {
+        Ok(parse_file(path, &read_bytes(path)?, None, true)?
+            .into_iter()
+            .next()
+            .unwrap_or(Value::Null))
+    };
+    let (old, new) = (first_doc(&args.old)?, first_doc(&args.new)?);
+    let opts = DiffOptions::default();
+    let (merged, diff, skipped) = merge::merge_selected(&old, &new, &args.only, &opts);
+    merge::print_preview(
+        &mut io::stderr(),
+        &diff,
+        skipped,
+        &opts,
+        &ReportOptions {
+            lang: i18n::error_lang(),
+            ..Default::default()
+        },
+    )?;
+    if args.dry_run {
+        return Ok(());
+    }
+    let text = serde_yaml::to_string(&merged)
+        .map_err(|e| anyhow!(i18n::error_lang().format(Msg::SerializeMergeFailed, &[&e])))?;
+    match &args.output {
+        Some(path) => fs::write(path, text).map_err(|e| write_output_error(e, path)),
+        None => Ok(io::stdout().write_all(text.as_bytes())?),
+    }
+}
+
+fn run_normalize(args: &NormalizeArgs) -> Result<()> {
+    let bytes = read_bytes(&args.file)?;
+    let docs = parse_file(&args.file, &bytes, None, true)?;
+    let text = normalize::to_string(docs).map_err(serialize_result_error)?;
+    if args.check {
+        if text.as_bytes() != bytes {
+            let file = format!("{:?}", args.file);
+            return Err(anyhow!(
+                i18n::error_lang().format(Msg::NotNormalized, &[&file])
+            ));
+        }
+        return Ok(());
+    }
+    match &args.output {
+        Some(path) => fs::write(path, text).map_err(|e| write_output_error(e, path)),
+        None => Ok(io::stdout().write_all(text.as_bytes())?),
+    }
+}
+
+/// get 子命令的输出：字符串、数字和布尔值直接输出原文，便于 `$(yml-diff get ...)` 取值
+fn render_value(value: &Value, json: bool) -> Result<String> {
+    if json {
+        let text = serde_json::to_string_pretty(value).map_err(serialize_result_error)?;
+        return Ok(text + "\n");
+    }
+    Ok(match value {
+        Value::String(s) => format!("{s}\n"),
+        Value::Number(n) => format!("{n}\n"),
+        Value::Bool(b) => format!("{b}\n"),
+        _ => serde_yaml::to_string(value).map_err(serialize_result_error)?,
+    })
+}
+
+fn serialize_result_error(e: impl fmt::Display) -> anyhow::Error {
+    anyhow!(i18n::error_lang().format(Msg::SerializeResultFailed, &[&e]))
+}
+
+/// 用不带任何过滤的差异重建新文件并与实际的新文件比较
+///
+/// 过滤类选项（规则、默认值、百分比阈值）会有意丢弃差异，因此这里只沿用扁平化选项。
+fn round_trip_check(old: &Value, new: &Value, opts: &DiffOptions) -> Result<()> {
+    let raw_opts = DiffOptions {
+        flatten: opts.flatten.clone(),
+        ..Default::default()
+    };
+    let diff = cmp_yml_vals(old, new, &raw_opts);
+    let rebuilt = apply::apply_diff(old, &diff, raw_opts.flatten.index_style.is_some());
+    if rebuilt == *new {
+        eprintln!("{}", i18n::error_lang().get(Msg::RoundTripPassed).green());
+        return Ok(());
+    }
+
+    let mismatch = cmp_yml_vals(&rebuilt, new, &raw_opts);
+    let keys: Vec<&str> = mismatch
+        .added()
+        .keys()
+        .chain(mismatch.removed().keys())
+        .chain(mismatch.modified().keys())
+        .map(|k| k.as_str())
+        .collect();
+    if keys.is_empty() {
+        // 不一致之处无法扁平化，例如非字符串的键
+        return Err(anyhow!(i18n::error_lang().get(Msg::RoundTripUnflattenable)));
+    }
+    let keys = keys.join(", ");
+    Err(anyhow!(
+        i18n::error_lang().format(Msg::RoundTripFailed, &[&keys])
+    ))
+}
+
+/// 多组比较中的一组文件；目录比较时只存在于一侧的文件另一侧为 `None`
+struct FilePair {
+    label: String,
+    old: Option<PathBuf>,
+    new: Option<PathBuf>,
+}
+
+/// 一个基线与多个目标比较：--old 与每个 --new 各组成一对，文本和 JSON 报告为矩阵形式，
+/// 其余格式逐对输出
+fn run_fan_out(
+    input: &Args,
+    opts: &DiffOptions,
+    report: &ReportOptions,
+    out: &mut dyn Write,
+) -> Result<bool> {
+    let old = input.old.as_ref().expect("clap 保证有 --new 时也有 --old");
+    if input.old_rev.is_some()
+        || input.new_rev.is_some()
+        || input.base.is_some()
+        || input.interactive
+    {
+        return Err(anyhow!(i18n::error_lang().get(Msg::FanOutOptions)));
+    }
+    if let Some(path) = [old]
+        .into_iter()
+        .chain(&input.new)
+        .find(|p| is_stdin(p) || p.is_dir())
+    {
+        let path = path.display();
+        return Err(anyhow!(
+            i18n::error_lang().format(Msg::FanOutFilesOnly, &[&path])
+        ));
+    }
+    let pairs = input
+        .new
+        .iter()
+        .map(|new| FilePair {
+            label: new.display().to_string(),
+            old: Some(old.clone()),
+            new: Some(new.clone()),
+        })
+        .collect();
+    run_pairs(input, opts, report, pairs, out)
+}
+
+/// 比较两个目录：按相对路径配对其中的 YAML 文件，逐个文件输出差异
+fn run_dirs(
+    input: &Args,
+    opts: &DiffOptions,
+    report: &ReportOptions,
+    old: &Path,
+    new: &Path,
+    out: &mut dyn Write,
+) -> Result<bool> {
+    if !(old.is_dir() && new.is_dir()) {
+        let (old, new) = (format!("{old:?}"), format!("{new:?}"));
+        return Err(anyhow!(
+            i18n::error_lang().format(Msg::DirMismatch, &[&old, &new])
+        ));
+    }
+    if input.patch
+        || input.emit_patch
+        || input.split_output.is_some()
+        || input.key_case_report
+        || input.base.is_some()
+        || input.old_rev.is_some()
+        || input.new_rev.is_some()
+        || input.interactive
+    {
+        return Err(anyhow!(i18n::error_lang().get(Msg::DirUnsupportedOptions)));
+    }
+    let pairs = dir::pair_dirs(old, new)?
+        .into_iter()
+        .map(|pair| {
+            let relative = pair.relative.display();
+            let label = match (&pair.old, &pair.new) {
+                (Some(_), None) => report.lang.format(Msg::OnlyInOldDir, &[&relative]),
+                (None, Some(_)) => report.lang.format(Msg::OnlyInNewDir, &[&relative]),
+                _ => relative.to_string(),
+            };
+            FilePair {
+                label,
+                old: pair.old,
+                new: pair.new,
+            }
+        })
+        .collect();
+    run_pairs(input, opts, report, pairs, out)
+}
+
+/// 依次比较 `--pair`、`--pairs-file` 或目录比较得到的多组文件
+///
+/// 某一组读取或解析失败时不影响其余各组，该组按无差异处理并在标准错误中说明，
+/// 最后只要有一组失败就返回错误。
+fn run_pairs(
+    input: &Args,
+    opts: &DiffOptions,
+    report: &ReportOptions,
+    pairs: Vec<FilePair>,
+    out: &mut dyn Write,
+) -> Result<bool> {
+    if report.format == OutputFormat::Html {
+        return Err(anyhow!(i18n::error_lang().get(Msg::HtmlOnePair)));
+    }
+    if input.output.iter().any(|target| target.format.is_some()) {
+        return Err(anyhow!(i18n::error_lang().get(Msg::OutputTargetOnePair)));
+    }
+    // 各组的读取、解析和比较并行执行，报告和错误信息仍按组的顺序输出
+    let jobs = input.jobs.unwrap_or_else(jobs::default_jobs);
+    let labels: Vec<String> = pairs.iter().map(|pair| pair.label.clone()).collect();
+    let mut values = Vec::new();
+    let mut failures = Vec::new();
+    let counter = progress::Counter::new(pairs.len());
+    let loaded = jobs::map(&pairs, jobs, |pair| {
+        let loaded = load_pair(input, opts, pair);
+        counter.tick();
+        loaded
+    });
+    for (pair, loaded) in pairs.iter().zip(loaded) {
+        match loaded {
+            Ok(pair) => values.push(pair),
+            Err(e) => {
+                eprintln!("{}", format!("{}: {e}", pair.label).red());
+                failures.push(pair.label.clone());
+                values.push(None);
+            }
+        }
+    }
+
+    let diffs: Vec<ConfigDiff> = progress::time(Phase::Compare, || {
+        jobs::map(&values, jobs, |pair| match pair {
+            Some((old, new)) if input.swap => cmp_yml_vals(new, old, opts),
+            Some((old, new)) => cmp_yml_vals(old, new, opts),
+            None => ConfigDiff::default(),
+        })
+    });
+    for diff in &diffs {
+        warn_unsorted_input(diff, report);
+    }
+
+    let baseline = input.old.as_ref().map(|old| old.display().to_string());
+    if input.merge_report {
+        merge::print_merged(out, &labels, &diffs, opts, report)?;
+    } else if let Some(baseline) = baseline.filter(|_| input.new.len() > 1)
+        && matches!(report.format, OutputFormat::Text | OutputFormat::Json)
+    {
+        if report.format == OutputFormat::Json {
+            let json = matrix::matrix_to_json(&baseline, &labels, &diffs, opts);
+            writeln!(out, "{}", serde_json::to_string_pretty(&json)?)?;
+        } else {
+            matrix::print_matrix(out, &baseline, &labels, &diffs, opts, report)?;
+        }
+    } else if report.format == OutputFormat::Junit {
+        // 每组文件是一个测试套件，整体仍是一个 XML 文档；比较失败的一组没有结果
+        let suites: Vec<(String, &ConfigDiff)> = labels
+            .iter()
+            .zip(&diffs)
+            .filter(|(label, _)| !failures.contains(label))
+            .map(|(label, diff)| (label.clone(), diff))
+            .collect();
+        junit::print_junit(out, &suites, opts, report)?;
+    } else if report.format == OutputFormat::Json {
+        // 多组结果合成一个 JSON 数组，保证整体输出仍是合法的 JSON
+        let results: Vec<serde_json::Value> = labels
+            .iter()
+            .zip(&diffs)
+            .map(|(label, diff)| {
+                let mut result = diff_to_json(diff, opts);
+                result["pair"] = label.as_str().into();
+                result["failed"] = failures.contains(label).into();
+                result
+            })
+            .collect();
+        let json = serde_json::to_string_pretty(&results)?;
+        writeln!(out, "{json}")?;
+    } else if matches!(report.format, OutputFormat::Unified | OutputFormat::Github) {
+        // 各组依次输出，不加分隔行和汇总，整体仍是可以交给 diff 查看工具的统一差异格式；
+        // GitHub 注释同样需要每组各自的文件名
+        let name = |path: &Option<PathBuf>| match path {
+            Some(path) => path.display().to_string(),
+            None => unified::NULL_LABEL.to_string(),
+        };
+        for (pair, diff) in pairs.iter().zip(&diffs) {
+            let (old, new) = (name(&pair.old), name(&pair.new));
+            let report = ReportOptions {
+                labels: Some(if input.swap { (new, old) } else { (old, new) }),
+                ..report.clone()
+            };
+            render(out, diff, opts, &report)?;
+        }
+    } else {
+        for (label, diff) in labels.iter().zip(&diffs) {
+            writeln!(out, "{}", format!(">>> {label}").bold())?;
+            if failures.contains(label) {
+                writeln!(out, "{}", report.lang.get(Msg::PairFailedSeeErrors).red())?;
+            } else {
+                render(out, diff, opts, report)?;
+            }
+            writeln!(out)?;
+        }
+        if !report.quiet && pairs.len() > 1 {
+            print_pairs_summary(out, &pairs, &diffs, &failures, report.lang)?;
+        }
+    }
+
+    if !failures.is_empty() {
+        let (failed, total, labels) = (failures.len(), pairs.len(), failures.join(", "));
+        return Err(anyhow!(
+            i18n::error_lang().format(Msg::PairsFailed, &[&failed, &total, &labels])
+        ));
+    }
+    if input.fail_on_type_change {
+        diffs.iter().try_for_each(ensure_no_type_changes)?;
+    }
+    ensure_change_limits(input, &diffs)?;
+    ensure_severity(&diffs, &opts.rules)?;
+    Ok(diffs.iter().any(|diff| !diff.is_empty()))
+}
+
+/// 多组比较的汇总：每组文件一行变更数量，最后是全部文件的合计
+fn print_pairs_summary(
+    out: &mut dyn Write,
+    pairs: &[FilePair],
+    diffs: &[ConfigDiff],
+    failures: &[String],
+    lang: Lang,
+) -> io::Result<()> {
+    writeln!(out, "{}", lang.get(Msg::SummaryTitle).bold())?;
+    let (mut added, mut removed, mut modified) = (0, 0, 0);
+    let mut changed = 0;
+    for (pair, diff) in pairs.iter().zip(diffs) {
+        if failures.contains(&pair.label) {
+            writeln!(out, "  {}  {}", pair.label, lang.get(Msg::PairFailed).red())?;
+            continue;
+        }
+        if diff.is_empty() {
+            writeln!(out, "  {}  {}", pair.label, lang.get(Msg::PairUnchanged))?;
+            continue;
+        }
+        changed += 1;
+        added += diff.added().len();
+        removed += diff.removed().len();
+        modified += diff.modified().len();
+        writeln!(
+            out,
+            "  {}  {} {} {}",
+            pair.label,
+            format!("+{}", diff.added().len()).green(),
+            format!("-{}", diff.removed().len()).red(),
+            format!("~{}", diff.modified().len()).yellow(),
+        )?;
+    }
+
+    let only_old = pairs.iter().filter(|p| p.new.is_none()).count();
+    let only_new = pairs.iter().filter(|p| p.old.is_none()).count();
+    let same = pairs.len() - changed - failures.len();
+    let totals = [&pairs.len() as &dyn fmt::Display, &changed, &same];
+    write!(out, "{}", lang.format(Msg::SummaryTotals, &totals))?;
+    if only_old + only_new > 0 {
+        write!(
+            out,
+            "{}",
+            lang.format(Msg::SummaryOnlyInDirs, &[&only_old, &only_new])
+        )?;
+    }
+    if !failures.is_empty() {
+        write!(
+            out,
+            "{}",
+            lang.format(Msg::SummaryFailures, &[&failures.len()])
+        )?;
+    }
+    let (added, removed, modified) = (
+        format!("+{added}").green(),
+        format!("-{removed}").red(),
+        format!("~{modified}").yellow(),
+    );
+    writeln!(
+        out,
+        "{}",
+        lang.format(Msg::SummaryChanges, &[&added, &removed, &modified])
+    )
+}
+
+/// 读取并解析一组文件；字节完全相同的文件对返回 `None`，直接视为无差异
+fn load_pair(input: &Args, opts: &DiffOptions, pair: &FilePair) -> Result<Option<(Value, Value)>> {
+    let (Some(old), Some(new)) = (&pair.old, &pair.new) else {
+        // 只存在于一侧的文件，另一侧视为没有文档，其中的键全部记为新增或删除
+        let docs = |path: &Option<PathBuf>, transform| match path {
+            Some(path) => {
+                input.parse_input(path, &input.apply_transform(read_bytes(path)?, transform)?)
+            }
+            None => Ok(Vec::new()),
+        };
+        let old_docs = docs(&pair.old, &input.transform_old)?;
+        let new_docs = docs(&pair.new, &input.transform_new)?;
+        return Ok(Some(input.combine(old_docs, new_docs)));
+    };
+    let (old_bytes, new_bytes) = input.read_transformed(old, new)?;
+    if input.baseline_hash && !input.needs_full_compare() && same_hash(&old_bytes, &new_bytes) {
+        return Ok(None);
+    }
+    let (old_docs, new_docs) = input.parse_pair((old, &old_bytes), (new, &new_bytes))?;
+    let (old_val, new_val) = input.combine(old_docs, new_docs);
+    if input.fail_on_empty_input {
+        let lang = i18n::error_lang();
+        let old_label = format!("{} {}", lang.get(Msg::OldFile), old.display());
+        let new_label = format!("{} {}", lang.get(Msg::NewFile), new.display());
+        ensure_not_empty(&old_label, &old_val, opts)?;
+        ensure_not_empty(&new_label, &new_val, opts)?;
+    }
+    Ok(Some((old_val, new_val)))
+}
+
+/// --fail-on-empty-input：输入扁平化后一个键都没有时报错，`side` 说明是哪一侧的输入
+fn ensure_not_empty(side: &str, val: &Value, opts: &DiffOptions) -> Result<()> {
+    if flatten(val, &opts.flatten).next().is_none() {
+        return Err(anyhow!(
+            i18n::error_lang().format(Msg::EmptyInput, &[&side])
+        ));
+    }
+    Ok(())
+}
+
+/// --fail-on-type-change：列出所有改变了类型的键
+fn ensure_no_type_changes(diff: &ConfigDiff) -> Result<()> {
+    let lang = i18n::error_lang();
+    let changed: Vec<String> = diff
+        .modified()
+        .iter()
+        .filter(|&(_, &(old, new))| classify_modification(old, new) == ModificationKind::Type)
+        .map(|(key, &(old, new))| {
+            let (old, new) = (kind_name(old, lang), kind_name(new, lang));
+            lang.format(Msg::TypeChangeEntry, &[&key.as_str(), &old, &new])
+        })
+        .collect();
+    if !changed.is_empty() {
+        let (count, keys) = (changed.len(), changed.join(", "));
+        return Err(anyhow!(
+            lang.format(Msg::TypeChangesFound, &[&count, &keys])
+        ));
+    }
+    Ok(())
+}
+
+/// --max-changes / --max-added / --max-removed / --max-modified：所有文件的变更数量合计后与上限比较，
+/// 列出所有超出的项
+fn ensure_change_limits(input: &Args, diffs: &[ConfigDiff]) -> Result<()> {
+    let count = |f: fn(&ConfigDiff) -> usize| diffs.iter().map(f).sum::<usize>();
+    let added = count(|diff| diff.added().len());
+    let removed = count(|diff| diff.removed().len());
+    let modified = count(|diff| diff.modified().len());
+    let lang = i18n::error_lang();
+    let exceeded: Vec<String> = [
+        (
+            Msg::Changes,
+            added + removed + modified,
+            input.max_changes,
+            "--max-changes",
+        ),
+        (Msg::Added, added, input.max_added, "--max-added"),
+        (Msg::Removed, removed, input.max_removed, "--max-removed"),
+        (
+            Msg::Modified,
+            modified,
+            input.max_modified,
+            "--max-modified",
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(name, n, max, flag)| {
+        max.filter(|&max| n > max)
+            .map(|max| lang.format(Msg::LimitExceeded, &[&lang.get(name), &n, &flag, &max]))
+    })
+    .collect();
+    if !exceeded.is_empty() {
+        let exceeded = exceeded.join(lang.get(Msg::LimitSeparator));
+        return Err(anyhow!(
+            lang.format(Msg::ChangeLimitsExceeded, &[&exceeded])
+        ));
+    }
+    Ok(())
+}
+
+/// 规则文件中 warn 或 error 级别的键发生了变更；进程以 [`SeverityExceeded::exit_code`] 结束
+#[derive(Debug)]
+struct SeverityExceeded {
+    severity: Severity,
+    keys: Vec<String>,
+}
+
+impl SeverityExceeded {
+    fn exit_code(&self) -> u8 {
+        match self.severity {
+            Severity::Error => 4,
+            _ => 3,
+        }
+    }
+}
+
+impl fmt::Display for SeverityExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (count, keys) = (self.keys.len(), self.keys.join(", "));
+        let message = i18n::error_lang().format(
+            Msg::SeverityChanged,
+            &[&count, &self.severity.as_str(), &keys],
+        );
+        f.write_str(&message)
+    }
+}
+
+impl std::error::Error for SeverityExceeded {}
+
+/// 列出最高严重程度的变更；最高只有 info 级别时不算失败
+fn ensure_severity(diffs: &[ConfigDiff], rules: &Rules) -> Result<()> {
+    let rated: Vec<(Severity, &str)> = diffs
+        .iter()
+        .flat_map(|diff| diff.changes())
+        .filter_map(|change| Some((rules.severity(change.key)?, change.key)))
+        .collect();
+    let Some(highest) = rated.iter().map(|&(severity, _)| severity).max() else {
+        return Ok(());
+    };
+    if highest == Severity::Info {
+        return Ok(());
+    }
+    let keys = rated
+        .into_iter()
+        .filter(|&(severity, _)| severity == highest)
+        .map(|(_, key)| key.to_string())
+        .collect();
+    Err(SeverityExceeded {
+        severity: highest,
+        keys,
+    }
+    .into())
+}
+
+/// 读取 --pairs-file 清单
+fn load_pairs_file(path: &Path) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let text = String::from_utf8(read_bytes(path)?).map_err(|e| {
+        let path = format!("{path:?}");
+        anyhow!(i18n::error_lang().format(Msg::PairsFileNotUtf8, &[&e, &path]))
+    })?;
+    let base = path.parent().unwrap_or(Path::new(""));
+    parse_pairs(&text)
+        .map_err(|e| anyhow!("{e}: {:?}", path))
+        .map(|pairs| {
+            pairs
+                .into_iter()
+                .map(|(old, new)| (base.join(old), base.join(new)))
+                .collect()
+        })
+}
+
+fn parse_pairs(text: &str) -> Result<Vec<(&str, &str)>> {
+    let mut pairs = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let sep = if line.contains('\t') { '\t' } else { ',' };
+        let fields: Vec<&str> = line.split(sep).map(str::trim).collect();
+        match fields[..] {
+            [old, new] if !old.is_empty() && !new.is_empty() => pairs.push((old, new)),
+            _ => {
+                let line = i + 1;
+                return Err(anyhow!(
+                    i18n::error_lang().format(Msg::PairsFileLine, &[&line])
+                ));
+            }
+        }
+    }
+    Ok(pairs)
+}
+
+impl Args {
+    /// 读取一对文件并执行指定的预处理
+    fn read_transformed(&self, old: &Path, new: &Path) -> Result<(Vec<u8>, Vec<u8>)> {
+        let read = |path, rev: &Option<String>| match rev {
+            Some(rev) => git::show(rev, path),
+            None => read_bytes(path),
+        };
+        Ok((
+            progress::time(Phase::ParseOld, || {
+                self.apply_transform(read(old, &self.old_rev)?, &self.transform_old)
+            })?,
+            progress::time(Phase::ParseNew, || {
+                self.apply_transform(read(new, &self.new_rev)?, &self.transform_new)
+            })?,
+        ))
+    }
+
+    /// --max-depth 与 --no-recurse 对应的最大展开深度
+    fn max_depth(&self) -> Option<usize> {
+        self.max_depth.or(self.no_recurse.then_some(0))
+    }
+
+    fn flatten_options(&self) -> FlattenOptions {
+        FlattenOptions {
+            index_style: (!self.sequences_as_values).then_some(self.index_style.into()),
+            coerce_key_types: self.coerce_key_types,
+            max_depth: self.max_depth(),
+            separator: self.separator.clone(),
+            list_keys: self.list_key.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// 文件内容相同时也必须解析并比较：输出不是差异报告，或需要用到解析后的文档，
+    /// 不能走 --baseline-hash 的捷径；新增这类输出时要加到这里
+    fn needs_full_compare(&self) -> bool {
+        self.patch
+            || self.emit_patch
+            || self.interactive
+            || self.summarize_unchanged_subtrees
+            || self.key_case_report
+            || self.base.is_some()
+            || self.validates_schema()
+            || self.fail_on_empty_input
+    }
+
+    /// 代替标准输出的报告文件，即不带格式的 --output
+    fn report_path(&self) -> Option<&Path> {
+        (self.output.iter())
+            .find(|target| target.format.is_none())
+            .map(|target| target.path.as_path())
+    }
+
+    /// 标准输出的报告或 --output FORMAT:PATH 中是否有该格式
+    fn writes_format(&self, format: OutputFormat) -> bool {
+        self.output_format() == format
+            || (self.output.iter()).any(|target| target.format == Some(format))
+    }
+
+    /// GitHub 注释的文件名：仓库中的路径，不能带提交或 a/、b/ 前缀
+    fn github_labels(&self) -> Option<(String, String)> {
+        let path = self.git_path.as_ref().or(self.new.first())?;
+        let path = path.display().to_string();
+        Some((path.clone(), path))
+    }
+
+    /// --format 指定的输出格式，--tree 和 --side-by-side 是其中之一的简写
+    fn output_format(&self) -> OutputFormat {
+        if self.tree {
+            OutputFormat::Tree
+        } else if self.side_by_side {
+            OutputFormat::SideBySide
+        } else {
+            self.format
+        }
+    }
+
+    /// 按 --input-format 或文件自身的格式解析输入；--sops decrypt 时先解密，
+    /// --k8s 时去掉集群填充的字段，--sops mask 时替换密文
+    fn parse_input(&self, path: &Path, bytes: &[u8]) -> Result<Vec<Value>> {
+        let decrypted;
+        let bytes = if self.sops == Some(SopsMode::Decrypt) {
+            let format = self
+                .input_format
+                .unwrap_or_else(|| InputFormat::detect(path, bytes));
+            decrypted = sops::decrypt(path, bytes, format)?;
+            &decrypted
+        } else {
+            bytes
+        };
+        let mut docs = parse_file(path, bytes, self.input_format, !self.no_resolve_aliases)?;
+        if self.k8s {
+            docs.iter_mut().for_each(k8s::strip_server_fields);
+        }
+        if self.sops == Some(SopsMode::Mask) {
+            docs.iter_mut().for_each(sops::mask_encrypted);
+        }
+        if self.coerce_key_types {
+            let flatten_opts = self.flatten_options();
+            if let Some(key) = docs
+                .iter()
+                .find_map(|doc| coerced_key_conflict(doc, &flatten_opts))
+            {
+                let path = format!("{path:?}");
+                return Err(anyhow!(
+                    i18n::error_lang().format(Msg::CoercedKeyConflict, &[&key, &path])
+                ));
+            }
+        }
+        Ok(docs)
+    }
+
+    /// 解析一对文件；--strict 时两边都解析完再一起报告错误，错误中标明是旧文件还是新文件，
+    /// 并附上出错位置的源文本
+    fn parse_pair(
+        &self,
+        (old, old_bytes): (&Path, &[u8]),
+        (new, new_bytes): (&Path, &[u8]),
+    ) -> Result<(Vec<Value>, Vec<Value>)> {
+        let old_docs = progress::time(Phase::ParseOld, || self.parse_input(old, old_bytes));
+        if !self.strict {
+            let old_docs = old_docs?;
+            let new_docs = progress::time(Phase::ParseNew, || self.parse_input(new, new_bytes))?;
+            return Ok((old_docs, new_docs));
+        }
+        let new_docs = progress::time(Phase::ParseNew, || self.parse_input(new, new_bytes));
+        let (old_err, new_err) = match (old_docs, new_docs) {
+            (Ok(old_docs), Ok(new_docs)) => return Ok((old_docs, new_docs)),
+            (old_docs, new_docs) => (old_docs.err(), new_docs.err()),
+        };
+        let lang = i18n::error_lang();
+        let diagnostics: Vec<String> = [
+            (Msg::OldFile, old, old_bytes, old_err),
+            (Msg::NewFile, new, new_bytes, new_err),
+        ]
+        .into_iter()
+        .filter_map(|(side, path, bytes, err)| {
+            let err = err?;
+            let side = lang.get(side);
+            Some(match err.downcast_ref::<ParseError>() {
+                Some(parse) => parse.diagnostic(side, bytes),
+                None => format!("{side} {:?}: {err}", path),
+            })
+        })
+        .collect();
+        Err(anyhow!(
+            "{}\n{}",
+            lang.format(Msg::FilesFailedToParse, &[&diagnostics.len()]),
+            diagnostics.join("\n\n")
+        ))
+    }
+
+    /// 把一对文件的文档合成两个可比较的值，--k8s 时按资源标识配对文档
+    fn combine(&self, old: Vec<Value>, new: Vec<Value>) -> (Value, Value) {
+        if self.k8s {
+            combine_documents_by(old, new, k8s::resource_identity)
+        } else {
+            combine_documents(old, new)
+        }
+    }
+
+    /// 解析前的预处理：依次执行 --transform 和 --transform-old / --transform-new，再替换占位符
+    fn apply_transform(&self, bytes: Vec<u8>, transform: &Option<Transform>) -> Result<Vec<u8>> {
+        let bytes = (self.transform.iter().chain(transform))
+            .try_fold(bytes, |bytes, t| t.apply(bytes, self.allow_exec))?;
+        match self.vars()? {
+            Some(vars) => Ok(match String::from_utf8(bytes) {
+                Ok(text) => vars.interpolate(&text).into_bytes(),
+                // 其他编码的文件没有可以替换的文本，原样交给解析
+                Err(e) => e.into_bytes(),
+            }),
+            None => Ok(bytes),
+        }
+    }
+
+    /// --resolve-env / --vars / --vars-file 给出的变量；三者都没有使用时不替换占位符
+    fn vars(&self) -> Result<Option<Vars>> {
+        if !self.resolve_env && self.vars.is_empty() && self.vars_file.is_none() {
+            return Ok(None);
+        }
+        let mut vars = Vars::new(self.resolve_env);
+        if let Some(path) = &self.vars_file {
+            vars.extend_from_file(&parse_cfg(&read_bytes(path)?)?);
+        }
+        for pair in &self.vars {
+            vars.push(pair)?;
+        }
+        Ok(Some(vars))
+    }
+
+    fn validates_schema(&self) -> bool {
+        #[cfg(feature = "schema")]
+        return self.validate_against.is_some() || self.schema.is_some();
+        #[cfg(not(feature = "schema"))]
+        return false;
+    }
+}
+
+/// 读取文件内容，路径为 `-` 时读取标准输入
+///
+/// 文件按流一直读到结尾，不依赖文件的大小，因此 `--old <(kubectl get cm foo -o yaml)`
+/// 这样的进程替换（管道）与普通文件一样可用。
+fn read_bytes(path: &Path) -> Result<Vec<u8>> {
+    if is_stdin(path) {
+        let label = i18n::error_lang().get(Msg::StandardInput);
+        return read_all(ProgressReader::new(io::stdin().lock(), label, None))
+            .map_err(|e| anyhow!(i18n::error_lang().format(Msg::ReadStdinFailed, &[&e])));
+    }
+    if let Some(source) = remote::Source::parse(path)? {
+        return source.fetch();
+    }
+    File::open(path)
+        .and_then(|file| {
+            let size = file.metadata().ok().map(|meta| meta.len());
+            read_all(ProgressReader::new(file, path.display().to_string(), size))
+        })
+        .map_err(|e| {
+            let path = format!("{path:?}");
+            anyhow!(i18n::error_lang().format(Msg::ReadFileFailed, &[&e, &path]))
+        })
+}
+
+fn write_output_error(e: io::Error, path: &Path) -> anyhow::Error {
+    let path = format!("{path:?}");
+    anyhow!(i18n::error_lang().format(Msg::WriteOutputFailed, &[&e, &path]))
+}
+
+fn read_all(mut reader: impl Read) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn is_stdin(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+fn parse_cfg(bytes: &[u8]) -> Result<Value> {
+    serde_yaml::from_slice(bytes)
+        .map_err(|e| anyhow!(i18n::error_lang().format(Msg::ParseConfigFailed, &[&e])))
+}
+
+/// 解析标准输入中以 `---` 分隔的多文档 YAML
+fn parse_documents(bytes: &[u8], resolve_merge_keys: bool) -> Result<Vec<Value>> {
+    parse_file(
+        Path::new("-"),
+        bytes,
+        Some(InputFormat::Yaml),
+        resolve_merge_keys,
+    )
+}
+
+/// 从标准输入读取一对文档，多余的文档会被忽略
+fn read_stdin_pair(resolve_merge_keys: bool) -> Result<(Value, Value)> {
+    let bytes = read_all(io::stdin().lock())
+        .map_err(|e| anyhow!(i18n::error_lang().format(Msg::ReadStdinFailed, &[&e])))?;
+    split_pair(parse_documents(&bytes, resolve_merge_keys)?)
+}
+
+fn split_pair(docs: Vec<Value>) -> Result<(Value, Value)> {
+    let mut docs = docs.into_iter();
+    match (docs.next(), docs.next()) {
+        (Some(old), Some(new)) => Ok((old, new)),
+        (first, _) => {
+            let count = usize::from(first.is_some());
+            Err(anyhow!(
+                i18n::error_lang().format(Msg::StdinPairCount, &[&count])
+            ))
+        }
+    }
+}
+
+/// 用 xxh3 比较两份原始内容，只能识别逐字节相同的文件
+fn same_hash(old: &[u8], new: &[u8]) -> bool {
+    old.len() == new.len() && xxh3_64(old) == xxh3_64(new)
+}
+
+/// 读取默认值文件，按比较时相同的方式扁平化；嵌套写法和扁平写法都可以
+fn load_defaults(path: &Path, opts: &DiffOptions) -> Result<HashMap<String, Value>> {
+    let value = parse_cfg(&read_bytes(path)?)?;
+    Ok(extract_key_vals(&value, opts)
+        .into_iter()
+        .map(|(k, v)| (flat_sidecar_key(k), v.clone()))
+        .collect())
+}
+
+/// 读取键说明文件，嵌套写法和扁平写法都按扁平键查找
+fn load_descriptions(path: &Path, opts: &DiffOptions) -> Result<HashMap<String, String>> {
+    let value = parse_cfg(&read_bytes(path)?)?;
+    Ok(flatten(&value, &opts.flatten)
+        .map(|(k, v)| (flat_sidecar_key(k), get_val_string(v).into_owned()))
+        .collect())
+}
+
+/// 默认值、键说明等辅助文件顶层的键含有分隔符时是扁平写法的路径：`"db.pool": 10` 即 `db.pool`，
+/// 而不是名字中带分隔符的一个键。扁平化时这样的键被加上了引号，这里去掉顶层那一段的引号
+fn flat_sidecar_key(key: String) -> String {
+    let Some(rest) = key.strip_prefix('"') else {
+        return key;
+    };
+    let mut path = String::with_capacity(rest.len());
+    let mut chars = rest.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => path.extend(chars.next().map(|(_, c)| c)),
+            '"' => {
+                path.push_str(&rest[i + 1..]);
+                return path;
+            }
+            _ => path.push(c),
+        }
+    }
+    key
+}
+
+/// 找出旧文件与新文件中只有大小写不同的键，返回 (旧写法, 新写法)
+///
+/// 只报告跨文件的不一致：某个写法在两个文件中都存在时不算。
+fn key_case_mismatches(old: &Value, new: &Value, opts: &DiffOptions) -> Vec<(String, String)> {
+    let old_keys: HashSet<String> = extract_key_vals(old, opts).into_keys().collect();
+    let new_keys: HashSet<String> = extract_key_vals(new, opts).into_keys().collect();
+
+    let mut old_by_lower: HashMap<String, Vec<&String>> = HashMap::new();
+    for key in old_keys.difference(&new_keys) {
+        old_by_lower
+            .entry(key.to_lowercase())
+            .or_default()
+            .push(key);
+    }
+
+    let mut pairs: Vec<(String, String)> = new_keys
+        .difference(&old_keys)
+        .flat_map(|new_key| {
+            let old_spellings = old_by_lower.get(&new_key.to_lowercase());
+            old_spellings
+                .into_iter()
+                .flatten()
+                .map(move |&old_key| (old_key.clone(), new_key.clone()))
+        })
+        .collect();
+    let sep = &opts.flatten.separator;
+    pairs.sort_by(|a, b| {
+        ConfigKey::new(a.0.as_str(), sep)
+            .cmp(&ConfigKey::new(b.0.as_str(), sep))
+            .then(a.1.cmp(&b.1))
+    });
+    pairs
+}
+
+/// 没有任何变更的顶层配置段及其扁平键数量，键数按新文件统计
+fn unchanged_sections(new: &Value, diff: &ConfigDiff, opts: &DiffOptions) -> Vec<(String, usize)> {
+    let changed = section_stats(diff, 1);
+    let mut counts: BTreeMap<ConfigKey, usize> = BTreeMap::new();
+    for key in extract_key_vals(new, opts).into_keys() {
+        if !opts.includes(&key) {
+            continue;
+        }
+        let key = ConfigKey::new(key, &opts.flatten.separator);
+        let section = key.section(1);
+        if !changed.contains_key(section) {
+            *counts
+                .entry(ConfigKey::new(section, &opts.flatten.separator))
+                .or_default() += 1;
+        }
+    }
+    counts.into_iter().map(|(k, n)| (k.into(), n)).collect()
+}
+
+fn print_unchanged_sections(
+    out: &mut dyn Write,
+    sections: &[(String, usize)],
+    lang: Lang,
+) -> io::Result<()> {
+    if sections.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "{}", lang.get(Msg::UnchangedSections).blue().bold())?;
+    for (section, keys) in sections {
+        let line = lang.format(Msg::UnchangedSection, &[section, keys]);
+        writeln!(out, "  {} {line}", "✓".green())?;
+    }
+    writeln!(out)
+}
+
+fn print_key_case_report(
+    out: &mut dyn Write,
+    pairs: &[(String, String)],
+    lang: Lang,
+) -> io::Result<()> {
+    writeln!(out, "{}", lang.get(Msg::KeyCaseSection).blue().bold())?;
+    if pairs.is_empty() {
+        writeln!(out, "  {}", lang.get(Msg::NoKeyCaseMismatch).green())?;
+    }
+    for (old, new) in pairs {
+        writeln!(out, "  {} {} → {}", "!".yellow(), old.red(), new.green())?;
+    }
+    writeln!(out)
+}
+
+/// 输出用的值：命中 redact 规则的键只显示占位符，命中 --value-hash 的键只显示哈希
+fn display_val<'a>(
+    key: &str,
+    val: &'a Value,
+    opts: &DiffOptions,
+    report: &ReportOptions,
+) -> Cow<'a, str> {
+    if opts.rules.is_redacted(key) {
+        return Cow::Borrowed("***");
+    }
+    if report.hashed.iter().any(|p| p.matches(key)) {
+        return Cow::Owned(value_hash(val));
+    }
+    if let Some(desc) = opts.tags.describe_binary(val) {
+        return Cow::Owned(desc);
+    }
+    if let (Some(threshold), Value::String(s)) = (report.value_length_threshold, val) {
+        let len = s.chars().count();
+        if len > threshold {
+            return Cow::Owned(format!("<string, {len} chars>"));
+        }
+    }
+    get_val_string(val)
+}
+
+/// 两边都是较长的字符串且原样显示时，给出只标出变化部分的字符级差异，任一边有多行时给出逐行差异
+/// （以换行开头）；按多重集比较的序列给出删除和新增的元素；其余情况返回 `None`，
+/// 仍按修改前 / 修改后分别显示
+fn inline_diff(
+    key: &str,
+    old: &Value,
+    new: &Value,
+    opts: &DiffOptions,
+    report: &ReportOptions,
+) -> Option<String> {
+    if let (Value::Sequence(a), Value::Sequence(b)) = (old, new)
+        && opts.is_unordered(key)
+        && display_val(key, old, opts, report) == get_val_string(old)
+    {
+        let (removed, added) = multiset_changes(a, b);
+        let segments: Vec<String> = (removed.iter())
+            .map(|v| format!("[-{}-]", get_val_string(v)).red().to_string())
+            .chain(
+                (added.iter()).map(|v| format!("{{+{}+}}", get_val_string(v)).green().to_string()),
+            )
+            .collect();
+        return Some(segments.join(" "));
+    }
+    let (Value::String(a), Value::String(b)) = (old, new) else {
+        return None;
+    };
+    let long = a.chars().count().max(b.chars().count()) >= inline::INLINE_DIFF_MIN_LEN;
+    // 被隐藏、显示哈希或只显示长度的值不能暴露内容
+    let shown_as_is = display_val(key, old, opts, report) == a.as_str()
+        && display_val(key, new, opts, report) == b.as_str();
+    if !shown_as_is {
+        return None;
+    }
+    if a.trim_end().contains('\n') || b.trim_end().contains('\n') {
+        let lines = inline::render_lines(a, b, report.value_context, "    ");
+        return Some(format!("\n{lines}"));
+    }
+    long.then(|| inline::render(&inline::char_diff(a, b)))
+}
+
+/// 值内容的短哈希，形如 `sha256:1a2b3c4d`
+fn value_hash(val: &Value) -> String {
+    let digest = Sha256::digest(get_val_string(val).as_bytes());
+    let hex: String = digest[..4].iter().map(|b| format!("{b:02x}")).collect();
+    format!("sha256:{hex}")
+}
+
+/// 值的类型名称，用于解释差异
+///
+/// 带标签的值以标签区分类型，`!secret` 与 `!plain` 是不同的类型
+fn kind_name(val: &Value, lang: Lang) -> Cow<'static, str> {
+    Cow::Borrowed(match val {
+        Value::Null => "null",
+        Value::Bool(_) => lang.get(Msg::KindBool),
+        Value::Number(_) => lang.get(Msg::KindNumber),
+        Value::String(_) => lang.get(Msg::KindString),
+        Value::Sequence(_) => lang.get(Msg::KindSequence),
+        Value::Mapping(_) => lang.get(Msg::KindMapping),
+        Value::Tagged(t) => return Cow::Owned(lang.format(Msg::KindTagged, &[&t.tag])),
+    })
+}
+
+/// 一处修改属于哪一类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ModificationKind {
+    /// 类型相同，值不同
+    Value,
+    /// 类型变了，例如数值变成字符串
+    Type,
+    /// 两个字符串只有空白不同：按空白切分后的各段完全相同
+    Whitespace,
+}
+
+fn classify_modification(old: &Value, new: &Value) -> ModificationKind {
+    if kind_name(old, Lang::Zh) != kind_name(new, Lang::Zh) {
+        return ModificationKind::Type;
+    }
+    match (old, new) {
+        // 标签相同，按标签内的值分类
+        (Value::Tagged(a), Value::Tagged(b)) => classify_modification(&a.value, &b.value),
+        (Value::String(a), Value::String(b)) if a.split_whitespace().eq(b.split_whitespace()) => {
+            ModificationKind::Whitespace
+        }
+        _ => ModificationKind::Value,
+    }
+}
+
+/// 各类变更的数量，--count-by-type 的输出
+#[derive(Debug, Default, PartialEq, Serialize)]
+struct DiffCounts {
+    added: usize,
+    removed: usize,
+    modified: ModifiedCounts,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+struct ModifiedCounts {
+    total: usize,
+    value: usize,
+    r#type: usize,
+    whitespace: usize,
+}
+
+impl DiffCounts {
+    fn new(diff: &ConfigDiff) -> Self {
+        let mut modified = ModifiedCounts {
+            total: diff.modified().len(),
+            ..Default::default()
+        };
+        for &(old, new) in diff.modified().values() {
+            match classify_modification(old, new) {
+                ModificationKind::Value => modified.value += 1,
+                ModificationKind::Type => modified.r#type += 1,
+                ModificationKind::Whitespace => modified.whitespace += 1,
+            }
+        }
+        DiffCounts {
+            added: diff.added().len(),
+            removed: diff.removed().len(),
+            modified,
+        }
+    }
+}
+
+/// 说明某个键为什么被判定为修改
+fn explain_modified(key: &str, old: &Value, new: &Value, opts: &DiffOptions, lang: Lang) -> String {
+    let old_kind = kind_name(old, lang);
+    let new_kind = kind_name(new, lang);
+    if old_kind != new_kind {
+        return lang.format(Msg::DifferentKinds, &[&old_kind, &new_kind]);
+    }
+    if opts.strict_number_types
+        && let (Some(a), Some(b)) = (number_kind(old), number_kind(new))
+        && a != b
+    {
+        return lang.format(Msg::DifferentNumberKinds, &[&a, &b]);
+    }
+    if let Some(tolerance) = opts.rules.tolerance(key)
+        && let (Some(a), Some(b)) = (old.as_f64(), new.as_f64())
+    {
+        return lang.format(Msg::BeyondTolerance, &[&(a - b).abs(), &tolerance]);
+    }
+    if let Some(epsilon) = opts.epsilon
+        && let Some((a, b)) = float_pair(old, new)
+    {
+        return lang.format(Msg::BeyondEpsilon, &[&(a - b).abs(), &epsilon]);
+    }
+    if opts.rules.is_unordered(key) && matches!(old, Value::Sequence(_)) {
+        return lang.get(Msg::UnorderedDiffers).to_string();
+    }
+    lang.format(Msg::SameKindDiffers, &[&old_kind])
+}
+
+fn print_reason(
+    out: &mut dyn Write,
+    reason: &str,
+    key: &str,
+    opts: &DiffOptions,
+    lang: Lang,
+) -> io::Result<()> {
+    let msg = if opts.rules.is_redacted(key) {
+        Msg::ReasonRedacted
+    } else {
+        Msg::Reason
+    };
+    let line = lang.format(msg, &[&reason]);
+    writeln!(out, "{}", line.dimmed())
+}
+
+/// 按 --output FORMAT:PATH 把报告另外写入文件；文件中不带颜色，其余报告选项与标准输出相同
+fn write_format_outputs(
+    input: &Args,
+    diff: &ConfigDiff,
+    opts: &DiffOptions,
+    report: &ReportOptions,
+) -> Result<()> {
+    let colored = colored::control::SHOULD_COLORIZE.should_colorize();
+    let result = input.output.iter().try_for_each(|target| {
+        let Some(format) = target.format else {
+            return Ok(());
+        };
+        let mut report = ReportOptions {
+            format,
+            width: report.width.or_else(|| {
+                (format == OutputFormat::SideBySide).then(side_by_side::terminal_width)
+            }),
+            ..report.clone()
+        };
+        if format == OutputFormat::Github && report.format != OutputFormat::Github {
+            report.labels = input.github_labels().or(report.labels);
+        }
+        let path = format!("{:?}", target.path);
+        let lang = i18n::error_lang();
+        let file = File::create(&target.path)
+            .map_err(|e| anyhow!(lang.format(Msg::CreateOutputFailed, &[&e, &path])))?;
+        colored::control::set_override(false);
+        let mut out = BufWriter::new(file);
+        render(&mut out, diff, opts, &report)
+            .and_then(|()| out.flush())
+            .map_err(|e| anyhow!(lang.format(Msg::WriteOutputFailed, &[&e, &path])))
+    });
+    colored::control::set_override(colored);
+    result
+}
+
+/// 按选定的格式输出差异
+fn render(
+    out: &mut dyn Write,
+    diff: &ConfigDiff,
+    opts: &DiffOptions,
+    report: &ReportOptions,
+) -> io::Result<()> {
+    progress::time(Phase::Render, || render_report(out, diff, opts, report))
+}
+
+fn render_report(
+    out: &mut dyn Write,
+    diff: &ConfigDiff,
+    opts: &DiffOptions,
+    report: &ReportOptions,
+) -> io::Result<()> {
+    if report.count_by_type {
+        let json =
+            serde_json::to_string_pretty(&DiffCounts::new(diff)).map_err(io::Error::other)?;
+        return writeln!(out, "{json}");
+    }
+    if let Some(style) = report.changed_keys_only {
+        return print_changed_keys(out, diff, style);
+    }
+    if report.summary {
+        return print_summary(out, diff, report);
+    }
+    if let Some(depth) = report.stats_by_prefix {
+        return print_prefix_stats(out, diff, depth, report.format);
+    }
+    if report.stats {
+        return print_section_summary(out, diff, opts, report);
+    }
+    match report.format {
+        OutputFormat::Text => print_diff(out, diff, opts, report),
+        OutputFormat::Diffstat => print_diffstat(out, diff, report),
+        OutputFormat::Json => print_json(out, diff, opts, report),
+        OutputFormat::Unified => unified::print_unified(out, diff, opts, report),
+        OutputFormat::Html => html::print_html(out, diff, opts, report),
+        OutputFormat::Markdown => markdown::print_markdown(out, diff, opts, report),
+        OutputFormat::Tree => tree::print_tree(out, diff, opts, report),
+        OutputFormat::SideBySide => side_by_side::print_side_by_side(out, diff, opts, report),
+        OutputFormat::Github => github::print_github(out, diff, opts, report),
+        OutputFormat::Junit => {
+            junit::print_junit(out, &[(junit::suite_name(report), diff)], opts, report)
+        }
+    }
+}
+
+/// 某个配置段内各类差异的数量
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+struct SectionStats {
+    added: usize,
+    removed: usize,
+    modified: usize,
+}
+
+impl SectionStats {
+    fn total(&self) -> usize {
+        self.added + self.removed + self.modified
+    }
+}
+
+/// 按键的前 `depth` 段分组统计差异数量
+fn section_stats(diff: &ConfigDiff, depth: usize) -> BTreeMap<String, SectionStats> {
+    let section = |key: &ConfigKey| key.section(depth).to_string();
+
+    let mut stats: BTreeMap<String, SectionStats> = BTreeMap::new();
+    for key in diff.added().keys() {
+        stats.entry(section(key)).or_default().added += 1;
+    }
+    for key in diff.removed().keys() {
+        stats.entry(section(key)).or_default().removed += 1;
+    }
+    for key in diff.modified().keys() {
+        stats.entry(section(key)).or_default().modified += 1;
+    }
+    stats
+}
+
+/// 柱状图的最大宽度，超出时按比例缩放
+const DIFFSTAT_WIDTH: usize = 40;
+
+/// 以 JSON 输出差异
+///
+/// 顶层对象固定包含 `added`、`removed`、`modified` 和 `stats` 四个字段，前三者以扁平键为键；
+/// 修改的键对应 `{"old": 旧值, "new": 新值}`。值按原始类型输出，命中 redact 规则的值输出为 `"***"`。
+/// 规则文件指定了严重程度时另有 `severity` 字段，给出每个命中规则的变更键的级别；
+/// --line-numbers 时另有 `lines` 字段，每个变更键对应 `{"old": 行号, "new": 行号}`，定位不到的一侧为 `null`。
+fn print_json(
+    out: &mut dyn Write,
+    diff: &ConfigDiff,
+    opts: &DiffOptions,
+    report: &ReportOptions,
+) -> io::Result<()> {
+    let mut json = diff_to_json(diff, opts);
+    if report.line_numbers {
+        json["lines"] = diff
+            .changes()
+            .map(|change| {
+                let (old, new) = change_lines(change.key, &change.kind, opts, report);
+                (
+                    change.key.to_string(),
+                    serde_json::json!({"old": old, "new": new}),
+                )
+            })
+            .collect::<serde_json::Map<_, _>>()
+            .into();
+    }
+    let json = serde_json::to_string_pretty(&json).map_err(io::Error::other)?;
+    writeln!(out, "{json}")
+}
+
+fn diff_to_json(diff: &ConfigDiff, opts: &DiffOptions) -> serde_json::Value {
+    let value = |key: &str, val: &Value| {
+        if opts.rules.is_redacted(key) {
+            return serde_json::Value::String("***".into());
+        }
+        // 无法用 JSON 表示的值（如非字符串的映射键）退回显示用的字符串
+        serde_json::to_value(val).unwrap_or_else(|_| get_val_string(val).into_owned().into())
+    };
+    let added: serde_json::Map<_, _> = diff
+        .added()
+        .iter()
+        .map(|(k, &v)| (k.to_string(), value(k, v)))
+        .collect();
+    let removed: serde_json::Map<_, _> = diff
+        .removed()
+        .iter()
+        .map(|(k, &v)| (k.to_string(), value(k, v)))
+        .collect();
+    let modified: serde_json::Map<_, _> = diff
+        .modified()
+        .iter()
+        .map(|(k, &(old, new))| {
+            let mut change = serde_json::json!({ "old": value(k, old), "new": value(k, new) });
+            // 按多重集比较的序列另外给出真正删除和新增的元素
+            if let (Value::Sequence(a), Value::Sequence(b)) = (old, new)
+                && opts.is_unordered(k)
+                && !opts.rules.is_redacted(k)
+            {
+                let (removed, added) = multiset_changes(a, b);
+                let list = |vals: Vec<&Value>| -> Vec<serde_json::Value> {
+                    vals.into_iter().map(|v| value(k, v)).collect()
+                };
+                change["elements"] =
+                    serde_json::json!({ "removed": list(removed), "added": list(added) });
+            }
+            (k.to_string(), change)
+        })
+        .collect();
+    let mut json = serde_json::json!({
+        "added": added,
+        "removed": removed,
+        "modified": modified,
+        "stats": DiffCounts::new(diff),
+    });
+    if opts.detect_renames {
+        json["renamed"] = diff
+            .renamed()
+            .iter()
+            .map(|r| serde_json::json!({"from": r.from.as_str(), "to": r.to.as_str(), "keys": r.moved.len()}))
+            .collect();
+    }
+    if opts.detect_reorders {
+        json["reordered"] = diff
+            .reordered()
+            .iter()
+            .map(|r| {
+                serde_json::json!({
+                    "sequence": r.sequence.as_str(),
+                    "from": r.from,
+                    "to": r.to,
+                    "value": value(&r.sequence, r.value),
+                })
+            })
+            .collect();
+    }
+    if !opts.key_renames.is_empty() {
+        json["migrated"] = diff
+            .migrated()
+            .iter()
+            .map(|m| {
+                serde_json::json!({
+                    "from": m.from.as_str(),
+                    "to": m.to.as_str(),
+                    "old": value(&m.from, m.old),
+                    "new": value(&m.to, m.new),
+                    "changed": m.changed,
+                })
+            })
+            .collect();
+    }
+    if opts.rules.has_severities() {
+        json["severity"] = diff
+            .changes()
+            .filter_map(|change| {
+                let severity = opts.rules.severity(change.key)?;
+                Some((change.key.to_string(), severity.as_str().into()))
+            })
+            .collect::<serde_json::Map<_, _>>()
+            .into();
+    }
+    json
+}
+
+/// 按键的排序（--no-sort 时按文档顺序）把三类变更合在一起，逐行输出键路径
+fn print_changed_keys(
+    out: &mut dyn Write,
+    diff: &ConfigDiff,
+    style: KeyListStyle,
+) -> io::Result<()> {
+    for change in diff.changes() {
+        let mark = match change.kind {
+            ChangeKind::Added(_) => '+',
+            ChangeKind::Removed(_) => '-',
+            ChangeKind::Modified(..) => '~',
+        };
+        match style {
+            KeyListStyle::Plain => writeln!(out, "{}", change.key)?,
+            KeyListStyle::Marked => writeln!(out, "{mark} {}", change.key)?,
+        }
+    }
+    Ok(())
+}
+
+fn print_diffstat(
+    out: &mut dyn Write,
+    diff: &ConfigDiff,
+    report: &ReportOptions,
+) -> io::Result<()> {
+    let stats = section_stats(diff, 1);
+    let name_width = stats.keys().map(|k| k.chars().count()).max().unwrap_or(0);
+    let max_total = stats.values().map(SectionStats::total).max().unwrap_or(0);
+    let count_width = max_total.to_string().len();
+
+    // 非零的数量至少画一格
+    let scale = |n: usize| {
+        if max_total <= DIFFSTAT_WIDTH || n == 0 {
+            n
+        } else {
+            (n * DIFFSTAT_WIDTH / max_total).max(1)
+        }
+    };
+
+    for (section, s) in &stats {
+        writeln!(
+            out,
+            " {section:<name_width$} | {:>count_width$} {}{}{}",
+            s.total(),
+            "+".repeat(scale(s.added)).green(),
+            "-".repeat(scale(s.removed)).red(),
+            "~".repeat(scale(s.modified)).yellow(),
+        )?;
+    }
+    let counts = [
+        &stats.len() as &dyn fmt::Display,
+        &diff.added().len(),
+        &diff.removed().len(),
+        &diff.modified().len(),
+    ];
+    writeln!(out, "{}", report.lang.format(Msg::DiffstatSummary, &counts))
+}
+
+/// 键后面附加的说明文字，没有说明时为空
+fn describe(key: &str, report: &ReportOptions) -> String {
+    match report.descriptions.get(key) {
+        Some(desc) => format!(" — {desc}").dimmed().to_string(),
+        None => String::new(),
+    }
+}
+
+/// 键在源文件中定义所在的行号（从 1 开始）；没有读取源文件或找不到时为 `None`
+fn source_line(source: Option<&str>, key: &str, opts: &DiffOptions) -> Option<usize> {
+    let key = ConfigKey::new(key, &opts.flatten.separator);
+    source::locate_key(source?, &key).map(|line| line + 1)
+}
+
+/// --line-numbers：变更的键在旧、新文件中的行号，只存在于一侧的键另一侧为 `None`
+fn change_lines(
+    key: &str,
+    kind: &ChangeKind,
+    opts: &DiffOptions,
+    report: &ReportOptions,
+) -> (Option<usize>, Option<usize>) {
+    let old = || source_line(report.old_source.as_deref(), key, opts);
+    let new = || source_line(report.new_source.as_deref(), key, opts);
+    match kind {
+        ChangeKind::Added(_) => (None, new()),
+        ChangeKind::Removed(_) => (old(), None),
+        ChangeKind::Modified(..) => (old(), new()),
+    }
+}
+
+/// 把行号写成 `old.yml:42 → new.yml:45`，只有一侧时只写一个位置；没有开启 --line-numbers 或两侧都
+/// 定位不到时为 `None`
+fn source_location(
+    old: Option<usize>,
+    new: Option<usize>,
+    report: &ReportOptions,
+) -> Option<String> {
+    if !report.line_numbers {
+        return None;
+    }
+    let (old_label, new_label) = match &report.labels {
+        Some((old, new)) => (old.as_str(), new.as_str()),
+        None => ("old", "new"),
+    };
+    let old = old.map(|line| format!("{old_label}:{line}"));
+    let new = new.map(|line| format!("{new_label}:{line}"));
+    match (old, new) {
+        (Some(old), Some(new)) => Some(format!("{old} → {new}")),
+        (old, new) => old.or(new),
+    }
+}
+
+/// 一处变更的 [`source_location`]
+fn change_location(
+    key: &str,
+    kind: &ChangeKind,
+    opts: &DiffOptions,
+    report: &ReportOptions,
+) -> Option<String> {
+    if !report.line_numbers {
+        return None;
+    }
+    let (old, new) = change_lines(key, kind, opts, report);
+    source_location(old, new, report)
+}
+
+/// 文本类报告中键后面的位置标注，如 ` (old.yml:42 → new.yml:45)`
+fn location_suffix(location: Option<String>) -> String {
+    match location {
+        Some(location) => format!(" ({location})").dimmed().to_string(),
+        None => String::new(),
+    }
+}
+
+/// 打印键在源文件中的原始行，找不到时不输出
+fn print_source(
+    out: &mut dyn Write,
+    label: &str,
+    source: Option<&str>,
+    key: &ConfigKey,
+) -> io::Result<()> {
+    let Some(source) = source else {
+        return Ok(());
+    };
+    let Some(line) = source::locate_key(source, key) else {
+        return Ok(());
+    };
+    for (no, text) in source::snippet(source, line) {
+        writeln!(out, "{}", format!("    {label} {no:>4} | {text}").dimmed())?;
+    }
+    Ok(())
+}
+
+/// 在删除和新增的键之间找出被移动的键，返回 (旧路径, 新路径)
+///
+/// 删除的键与新增的键最后一段相同、值也相同，但父路径不同时视为移动。
+/// 有多个候选时按键的顺序一一配对，每个键最多参与一次移动；已随子树重命名的键不再参与。
+fn find_moves<'d>(diff: &'d ConfigDiff) -> Vec<(&'d ConfigKey, &'d ConfigKey)> {
+    let leaf = |key: &'d ConfigKey| key.segments().next_back().unwrap_or_default();
+    let (renamed_from, mut used) = renamed_keys(diff);
+    let mut moves = Vec::new();
+    for (old_key, &old_val) in diff.removed() {
+        if renamed_from.contains(old_key.as_str()) {
+            continue;
+        }
+        let found = diff.added().iter().find(|&(new_key, &new_val)| {
+            !used.contains(new_key.as_str()) && leaf(new_key) == leaf(old_key) && new_val == old_val
+        });
+        if let Some((new_key, _)) = found {
+            used.insert(new_key.as_str());
+            moves.push((old_key, new_key));
+        }
+    }
+    moves
+}
+
+/// 随子树重命名的 (旧键, 新键) 集合
+fn renamed_keys<'d>(diff: &'d ConfigDiff) -> (HashSet<&'d str>, HashSet<&'d str>) {
+    let pairs = diff.renamed().iter().flat_map(|r| &r.moved);
+    (
+        pairs.clone().map(|(from, _)| from.as_str()).collect(),
+        pairs.map(|(_, to)| to.as_str()).collect(),
+    )
+}
+
+/// 报告中键名的样式：命中 --highlight-key 的键在变更颜色的基础上再加粗、下划线并反色
+fn styled_key(key: &str, color: Color, report: &ReportOptions) -> ColoredString {
+    let styled = key.color(color);
+    if report.highlighted.iter().any(|p| p.matches(key)) {
+        styled.bold().underline().reversed()
+    } else {
+        styled
+    }
+}
+
+/// 统计信息块；`moves` 为合并成移动的删除 + 新增对数，它们和随子树重命名的键都不计入新增和删除
+fn print_stats(
+    out: &mut dyn Write,
+    diff: &ConfigDiff,
+    moves: usize,
+    report: &ReportOptions,
+) -> io::Result<()> {
+    let lang = report.lang;
+    writeln!(out, "{}", lang.get(Msg::Stats).blue().bold())?;
+    let renamed: usize = diff.renamed().iter().map(|r| r.moved.len()).sum();
+    let added = diff.added().len() - moves - renamed;
+    let removed = diff.removed().len() - moves - renamed;
+    let count = |msg, n: usize, color| lang.format(msg, &[&n.to_string().color(color)]);
+    writeln!(out, "{}", count(Msg::StatAdded, added, Color::Green))?;
+    writeln!(out, "{}", count(Msg::StatRemoved, removed, Color::Red))?;
+    if report.report_moved {
+        writeln!(out, "{}", count(Msg::StatMoved, moves, Color::Cyan))?;
+    }
+    if !diff.renamed().is_empty() {
+        let renamed = diff.renamed().len();
+        writeln!(out, "{}", count(Msg::StatRenamed, renamed, Color::Cyan))?;
+    }
+    if !diff.reordered().is_empty() {
+        let reordered = diff.reordered().len();
+        writeln!(out, "{}", count(Msg::StatReordered, reordered, Color::Cyan))?;
+    }
+    if !diff.migrated().is_empty() {
+        let migrated = diff.migrated().len();
+        writeln!(out, "{}", count(Msg::StatMigrated, migrated, Color::Cyan))?;
+    }
+    let counts = DiffCounts::new(diff).modified;
+    let mut details = Vec::new();
+    if counts.r#type > 0 {
+        details.push(lang.format(Msg::TypeChanges, &[&counts.r#type]));
+    }
+    if counts.whitespace > 0 {
+        details.push(lang.format(Msg::WhitespaceOnly, &[&counts.whitespace]));
+    }
+    let details = if details.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "{}{}{}",
+            lang.get(Msg::DetailsOpen),
+            details.join(lang.get(Msg::DetailsSeparator)),
+            lang.get(Msg::DetailsClose)
+        )
+    };
+    let modified = diff.modified().len().to_string().yellow();
+    writeln!(
+        out,
+        "{}",
+        lang.format(Msg::StatModified, &[&modified, &details])
+    )?;
+    writeln!(out)
+}
+
+/// --stats-by-prefix：按键的前 `depth` 段分组，变更总数多的组在前，总数相同时按前缀排序
+fn prefix_stats(diff: &ConfigDiff, depth: usize) -> Vec<(String, SectionStats)> {
+    let mut stats: Vec<_> = section_stats(diff, depth).into_iter().collect();
+    stats.sort_by_key(|(_, s)| std::cmp::Reverse(s.total()));
+    stats
+}
+
+fn print_prefix_stats(
+    out: &mut dyn Write,
+    diff: &ConfigDiff,
+    depth: usize,
+    format: OutputFormat,
+) -> io::Result<()> {
+    let stats = prefix_stats(diff, depth);
+    if format == OutputFormat::Json {
+        let rows: Vec<serde_json::Value> = stats
+            .iter()
+            .map(|(prefix, s)| {
+                let mut row = serde_json::to_value(s).expect("计数总能序列化");
+                row["prefix"] = prefix.as_str().into();
+                row
+            })
+            .collect();
+        let json = serde_json::to_string_pretty(&rows).map_err(io::Error::other)?;
+        return writeln!(out, "{json}");
+    }
+    let name_width = stats
+        .iter()
+        .map(|(prefix, _)| prefix.chars().count() + 1)
+        .max()
+        .unwrap_or(0);
+    for (prefix, s) in &stats {
+        writeln!(
+            out,
+            "{:<name_width$} {} {} {}",
+            format!("{prefix}:"),
+            format!("+{}", s.added).green(),
+            format!("-{}", s.removed).red(),
+            format!("~{}", s.modified).yellow(),
+        )?;
+    }
+    Ok(())
+}
+
+/// --summary：只输出各类变更的数量，JSON 格式为 `{"added": N, "removed": N, "modified": N}`
+fn print_summary(out: &mut dyn Write, diff: &ConfigDiff, report: &ReportOptions) -> io::Result<()> {
+    if report.format == OutputFormat::Json {
+        let summary = serde_json::json!({
+            "added": diff.added().len(),
+            "removed": diff.removed().len(),
+            "modified": diff.modified().len(),
+        });
+        let json = serde_json::to_string_pretty(&summary).map_err(io::Error::other)?;
+        return writeln!(out, "{json}");
+    }
+    let moves = if report.report_moved {
+        find_moves(diff).len()
+    } else {
+        0
+    };
+    print_stats(out, diff, moves, report)
+}
+
+/// --stats：总数、按顶层配置段的数量和层级最深的变更键
+///
+/// JSON 格式为 `{"added": N, "removed": N, "modified": N, "sections": [...], "deepest": {"key": 键, "depth": 段数}}`，
+/// `sections` 与 `--stats-by-prefix --format json` 相同；没有变更时 `deepest` 为 null。
+fn print_section_summary(
+    out: &mut dyn Write,
+    diff: &ConfigDiff,
+    opts: &DiffOptions,
+    report: &ReportOptions,
+) -> io::Result<()> {
+    let deepest = deepest_change(diff, &opts.flatten.separator);
+    if report.format == OutputFormat::Json {
+        let sections: Vec<serde_json::Value> = prefix_stats(diff, 1)
+            .iter()
+            .map(|(prefix, s)| {
+                let mut row = serde_json::to_value(s).expect("计数总能序列化");
+                row["prefix"] = prefix.as_str().into();
+                row
+            })
+            .collect();
+        let stats = serde_json::json!({
+            "added": diff.added().len(),
+            "removed": diff.removed().len(),
+            "modified": diff.modified().len(),
+            "sections": sections,
+            "deepest": deepest.map(|(key, depth)| serde_json::json!({"key": key, "depth": depth})),
+        });
+        let json = serde_json::to_string_pretty(&stats).map_err(io::Error::other)?;
+        return writeln!(out, "{json}");
+    }
+    print_stats(out, diff, 0, report)?;
+    if let Some((key, depth)) = deepest {
+        writeln!(out, "{}", report.lang.get(Msg::BySection).blue().bold())?;
+        print_prefix_stats(out, diff, 1, report.format)?;
+        writeln!(out)?;
+        let key = key.bold();
+        writeln!(
+            out,
+            "{}",
+            report.lang.format(Msg::DeepestChange, &[&key, &depth])
+        )?;
+    }
+    Ok(())
+}
+
+/// 段数最多的变更键及其段数，段数相同时取排在前面的键
+fn deepest_change<'d>(diff: &'d ConfigDiff, separator: &str) -> Option<(&'d str, usize)> {
+    diff.changes()
+        .map(|change| {
+            let depth = ConfigKey::new(change.key, separator).segments().count();
+            (change.key, depth)
+        })
+        .reduce(|deepest, next| if next.1 > deepest.1 { next } else { deepest })
+}
+
+/// 作为上下文显示的未变化键及其值
+type ContextKeys<'d, 'a> = Vec<(&'d ConfigKey, &'a Value)>;
+
+/// --context：与 `key` 同一父级、值没有变化的键中排在它之前和之后的各最多 `n` 个
+fn unchanged_siblings<'d, 'a>(
+    diff: &'d ConfigDiff<'a>,
+    key: &ConfigKey,
+    n: Option<usize>,
+) -> (ContextKeys<'d, 'a>, ContextKeys<'d, 'a>) {
+    let Some(n) = n else {
+        return (Vec::new(), Vec::new());
+    };
+    let depth = key.segments().count();
+    let parent = key.section(depth - 1);
+    let (before, after): (Vec<_>, Vec<_>) = diff
+        .unchanged()
+        .iter()
+        .filter(|(k, _)| k.segments().count() == depth && k.section(depth - 1) == parent)
+        .map(|(k, &v)| (k, v))
+        .partition(|&(k, _)| k < key);
+    let skip = before.len().saturating_sub(n);
+    (
+        before.into_iter().skip(skip).collect(),
+        after.into_iter().take(n).collect(),
+    )
+}
+
+/// 以暗色输出作为上下文的未变化键，跳过已经显示过的
+fn print_context<'d>(
+    out: &mut dyn Write,
+    keys: &[(&'d ConfigKey, &Value)],
+    shown: &mut HashSet<&'d str>,
+    opts: &DiffOptions,
+    report: &ReportOptions,
+) -> io::Result<()> {
+    for &(key, val) in keys {
+        if shown.insert(key.as_str()) {
+            let line = format!(
+                "    {}: {}",
+                key.as_str(),
+                display_val(key, val, opts, report)
+            );
+            writeln!(out, "{}", line.dimmed())?;
+        }
+    }
+    Ok(())
+}
+
+fn print_diff(
+    out: &mut dyn Write,
+    diff: &ConfigDiff,
+    opts: &DiffOptions,
+    report: &ReportOptions,
+) -> io::Result<()> {
+    let moves = if report.report_moved {
+        find_moves(diff)
+    } else {
+        Vec::new()
+    };
+    let (mut moved_from, mut moved_to) = renamed_keys(diff);
+    moved_from.extend(moves.iter().map(|(old, _)| old.as_str()));
+    moved_to.extend(moves.iter().map(|(_, new)| new.as_str()));
+    let added: Vec<_> = diff
+        .added()
+        .iter()
+        .filter(|(k, _)| !moved_to.contains(k.as_str()))
+        .collect();
+    let removed: Vec<_> = diff
+        .removed()
+        .iter()
+        .filter(|(k, _)| !moved_from.contains(k.as_str()))
+        .collect();
+    let lang = report.lang;
+    let (old_label, new_label) = (lang.get(Msg::OldSide), lang.get(Msg::NewSide));
+
+    if !report.quiet {
+        writeln!(out, "{}", lang.get(Msg::ReportTitle).bold())?;
+        writeln!(out)?;
+        print_stats(out, diff, moves.len(), report)?;
+    }
+    // 每一节中已经作为上下文显示过的键，相邻的变更共享同级键时不重复显示
+    let mut shown = HashSet::new();
+    // 源文件文本也用于 --line-numbers，只有 --annotate-source 时才附上原始行
+    let old_line = |key: &str| source_line(report.old_source.as_deref(), key, opts);
+    let new_line = |key: &str| source_line(report.new_source.as_deref(), key, opts);
+    let (old_source, new_source) = if report.annotate_source {
+        (report.old_source.as_deref(), report.new_source.as_deref())
+    } else {
+        (None, None)
+    };
+
+    if !added.is_empty() {
+        writeln!(out, "{}", lang.get(Msg::AddedSection).green().bold())?;
+        shown.clear();
+        for (key, &val) in added {
+            let (before, after) = unchanged_siblings(diff, key, report.context);
+            print_context(out, &before, &mut shown, opts, report)?;
+            writeln!(
+                out,
+                "  + {}{}{}: {}",
+                styled_key(key, Color::Green, report),
+                location_suffix(source_location(None, new_line(key), report)),
+                describe(key, report),
+                display_val(key, val, opts, report).green()
+            )?;
+            print_source(out, new_label, new_source, key)?;
+            if report.explain {
+                print_reason(out, lang.get(Msg::OnlyInNew), key, opts, lang)?;
+            }
+            print_context(out, &after, &mut shown, opts, report)?;
+        }
+        writeln!(out)?;
+    }
+
+    if !removed.is_empty() {
+        writeln!(out, "{}", lang.get(Msg::RemovedSection).red().bold())?;
+        shown.clear();
+        for (key, &val) in removed {
+            let (before, after) = unchanged_siblings(diff, key, report.context);
+            print_context(out, &before, &mut shown, opts, report)?;
+            writeln!(
+                out,
+                "  - {}{}{}: {}",
+                styled_key(key, Color::Red, report),
+                location_suffix(source_location(old_line(key), None, report)),
+                describe(key, report),
+                display_val(key, val, opts, report).red()
+            )?;
+            print_source(out, old_label, old_source, key)?;
+            if report.explain {
+                print_reason(out, lang.get(Msg::OnlyInOld), key, opts, lang)?;
+            }
+            print_context(out, &after, &mut shown, opts, report)?;
+        }
+        writeln!(out)?;
+    }
+
+    if !diff.renamed().is_empty() {
+        writeln!(out, "{}", lang.get(Msg::RenamedSection).cyan().bold())?;
+        for rename in diff.renamed() {
+            writeln!(
+                out,
+                "  > {} → {}{}",
+                styled_key(&rename.from, Color::Cyan, report),
+                styled_key(&rename.to, Color::Cyan, report),
+                lang.format(Msg::MovedAlong, &[&rename.moved.len()])
+            )?;
+            if report.explain {
+                let reason = lang.get(Msg::RenameReason);
+                print_reason(out, reason, &rename.to, opts, lang)?;
+            }
+        }
+        writeln!(out)?;
+    }
+
+    if !diff.reordered().is_empty() {
+        writeln!(out, "{}", lang.get(Msg::ReorderedSection).cyan().bold())?;
+        for reorder in diff.reordered() {
+            let sequence = reorder.sequence.as_str();
+            writeln!(
+                out,
+                "  ↕ {}[{}] → [{}]  {}",
+                styled_key(sequence, Color::Cyan, report),
+                reorder.from,
+                reorder.to,
+                display_val(sequence, reorder.value, opts, report).cyan()
+            )?;
+        }
+        writeln!(out)?;
+    }
+
+    if !diff.migrated().is_empty() {
+        writeln!(out, "{}", lang.get(Msg::MigratedSection).cyan().bold())?;
+        for migration in diff.migrated() {
+            let (from, to) = (&migration.from, &migration.to);
+            let keys = format!(
+                "  ⇒ {} → {}{}",
+                styled_key(from, Color::Cyan, report),
+                styled_key(to, Color::Cyan, report),
+                location_suffix(source_location(old_line(from), new_line(to), report))
+            );
+            if migration.changed {
+                writeln!(out, "{keys}")?;
+                let (old, new) = (
+                    display_val(from, migration.old, opts, report).yellow(),
+                    display_val(to, migration.new, opts, report).yellow(),
+                );
+                writeln!(out, "{}", lang.format(Msg::Before, &[&old]))?;
+                writeln!(out, "{}", lang.format(Msg::After, &[&new]))?;
+            } else {
+                let val = display_val(to, migration.new, opts, report).cyan();
+                writeln!(out, "{keys}: {val}")?;
+            }
+            print_source(out, old_label, old_source, from)?;
+            print_source(out, new_label, new_source, to)?;
+            if report.explain {
+                print_reason(out, lang.get(Msg::MigrateReason), to, opts, lang)?;
+            }
+        }
+        writeln!(out)?;
+    }
+
+    if !moves.is_empty() {
+        writeln!(out, "{}", lang.get(Msg::MovedSection).cyan().bold())?;
+        for (old_key, new_key) in &moves {
+            writeln!(
+                out,
+                "  > {} → {}{}{}: {}",
+                styled_key(old_key, Color::Cyan, report),
+                styled_key(new_key, Color::Cyan, report),
+                location_suffix(source_location(
+                    old_line(old_key),
+                    new_line(new_key),
+                    report
+                )),
+                describe(new_key, report),
+                display_val(new_key, diff.added()[*new_key], opts, report).cyan()
+            )?;
+            print_source(out, old_label, old_source, old_key)?;
+            print_source(out, new_label, new_source, new_key)?;
+            if report.explain {
+                print_reason(out, lang.get(Msg::MoveReason), new_key, opts, lang)?;
+            }
+        }
+        writeln!(out)?;
+    }
+
+    if !diff.modified().is_empty() {
+        writeln!(out, "{}", lang.get(Msg::ModifiedSection).yellow().bold())?;
+        shown.clear();
+        for (key, (old, new)) in diff.modified() {
+            let (before, after) = unchanged_siblings(diff, key, report.context);
+            print_context(out, &before, &mut shown, opts, report)?;
+            // 类型变化（如带引号的端口号变成数值）是常见的错误来源，单独标出
+            let type_change = if classify_modification(old, new) == ModificationKind::Type {
+                lang.format(
+                    Msg::TypeChange,
+                    &[&kind_name(old, lang), &kind_name(new, lang)],
+                )
+                .magenta()
+                .bold()
+                .to_string()
+            } else {
+                String::new()
+            };
+            writeln!(
+                out,
+                "  ~ {}{}{}{type_change}",
+                styled_key(key, Color::Yellow, report),
+                location_suffix(source_location(old_line(key), new_line(key), report)),
+                describe(key, report)
+            )?;
+            if let Some(line) = inline_diff(key, old, new, opts, report) {
+                let label = lang.get(Msg::InlineDiff);
+                match line.strip_prefix('\n') {
+                    Some(lines) => writeln!(out, "{label}\n{lines}")?,
+                    None => writeln!(out, "{label}   {line}")?,
+                }
+            } else {
+                let (old, new) = (
+                    display_val(key, old, opts, report).yellow(),
+                    display_val(key, new, opts, report).yellow(),
+                );
+                writeln!(out, "{}", lang.format(Msg::Before, &[&old]))?;
+                writeln!(out, "{}", lang.format(Msg::After, &[&new]))?;
+            }
+            print_source(out, old_label, old_source, key)?;
+            print_source(out, new_label, new_source, key)?;
+            if report.explain {
+                let reason = explain_modified(key, old, new, opts, lang);
+                print_reason(out, &reason, key, opts, lang)?;
+            }
+            print_context(out, &after, &mut shown, opts, report)?;
+        }
+        writeln!(out)?;
+    }
+
+    if opts.rules.has_severities() {
+        print_severity_groups(out, diff, opts, lang)?;
+    }
+
+    if diff.is_empty() && !report.quiet {
+        writeln!(out, "{}", lang.get(Msg::NoDifferences).green())?;
+    }
+    Ok(())
+}
+
+/// 规则文件指定了严重程度时，按级别从高到低列出命中规则的变更
+fn print_severity_groups(
+    out: &mut dyn Write,
+    diff: &ConfigDiff,
+    opts: &DiffOptions,
+    lang: Lang,
+) -> io::Result<()> {
+    let changes: Vec<_> = diff
+        .changes()
+        .filter_map(|change| Some((opts.rules.severity(change.key)?, change)))
+        .collect();
+    if changes.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "{}", lang.get(Msg::BySeverity).bold())?;
+    for severity in Severity::ALL {
+        let group: Vec<_> = changes.iter().filter(|(s, _)| *s == severity).collect();
+        if group.is_empty() {
+            continue;
+        }
+        let color = match severity {
+            Severity::Error => Color::Red,
+            Severity::Warn => Color::Yellow,
+            Severity::Info => Color::Blue,
+        };
+        let title = format!("  {} ({})", severity.as_str(), group.len());
+        writeln!(out, "{}", title.color(color).bold())?;
+        for (_, change) in group {
+            let mark = match change.kind {
+                ChangeKind::Added(_) => '+',
+                ChangeKind::Removed(_) => '-',
+                ChangeKind::Modified(..) => '~',
+            };
+            writeln!(out, "    {mark} {}", change.key)?;
+        }
+    }
+    writeln!(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_yaml() {
+        // 获取项目根目录（Cargo.toml 所在的目录）
+        let manifest_dir = env!("CARGO_MANIFEST_DIR");
+
+        // 构建配置文件的完整路径
+        let old = PathBuf::from(manifest_dir).join("config_v1.yml");
+        let new = PathBuf::from(manifest_dir).join("config_v2.yml");
+
+        println!("Looking for files:");
+        println!("Old config: {old:?}");
+        println!("New config: {new:?}");
+
+        // 检查文件是否存在
+        if !old.exists() {
+            panic!(
+                "config_v1.yml not found at {:?}. Current working directory: {:?}",
+                old,
+                std::env::current_dir().unwrap()
+            );
+        }
+        if !new.exists() {
             panic!(
                 "config_v2.yml not found at {:?}. Current working directory: {:?}",
                 new,
@@ -326,13 +3881,1297 @@ mod tests {
             );
         }
 
-        let old_val = read_cfg(old).unwrap();
-        let new_val = read_cfg(new).unwrap();
+        let old_bytes = read_bytes(&old).unwrap();
+        let new_bytes = read_bytes(&new).unwrap();
+        assert!(same_hash(&old_bytes, &old_bytes));
+        assert!(!same_hash(&old_bytes, &new_bytes));
+
+        let old_val = parse_cfg(&old_bytes).unwrap();
+        let new_val = parse_cfg(&new_bytes).unwrap();
 
         // 比较配置
-        let diff = cmp_yml_vals(&old_val, &new_val);
+        let opts = DiffOptions::default();
+        let diff = cmp_yml_vals(&old_val, &new_val, &opts);
 
         // 输出结果
-        print_diff(&diff);
+        print_diff(
+            &mut io::sink(),
+            &diff,
+            &opts,
+            &ReportOptions {
+                explain: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let stats = section_stats(&diff, 1);
+        assert_eq!(
+            stats["app"],
+            SectionStats {
+                added: 4,
+                removed: 2,
+                modified: 4
+            }
+        );
+    }
+
+    #[test]
+    fn test_stats_by_prefix() {
+        let old: Value =
+            serde_yaml::from_str("db:\n  a: {x: 1}\n  b: 1\napp:\n  port: 1\nz: 1\n").unwrap();
+        let new: Value =
+            serde_yaml::from_str("db:\n  a: {x: 2, y: 1}\n  c: 1\napp:\n  port: 1\nz: 2\nq: 1\n")
+                .unwrap();
+        let diff = cmp_yml_vals(&old, &new, &DiffOptions::default());
+        let prefixes = |depth| -> Vec<(String, usize)> {
+            (prefix_stats(&diff, depth).into_iter())
+                .map(|(prefix, s)| (prefix, s.total()))
+                .collect()
+        };
+        assert_eq!(
+            prefixes(1),
+            vec![("db".into(), 4), ("q".into(), 1), ("z".into(), 1)]
+        );
+        assert_eq!(prefixes(2)[0], ("db.a".into(), 2));
+
+        let args = Args::parse_from(["yml-diff", "-o", "a", "-n", "b", "--stats-by-prefix"]);
+        assert_eq!(args.stats_by_prefix, Some(1));
+        assert!(
+            Args::try_parse_from(["yml-diff", "-o", "a", "-n", "b", "--stats-by-prefix", "0"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_stats() {
+        let old: Value = serde_yaml::from_str(
+            "spring:\n  db: {pool: {max: 1}}\n  name: a\nlogging: {level: info}\n",
+        )
+        .unwrap();
+        let new: Value = serde_yaml::from_str(
+            "spring:\n  db: {pool: {max: 2}}\n  name: b\nlogging: {level: debug, file: x}\n",
+        )
+        .unwrap();
+        let opts = DiffOptions::default();
+        let diff = cmp_yml_vals(&old, &new, &opts);
+
+        let mut report = ReportOptions {
+            stats: true,
+            format: OutputFormat::Json,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        render(&mut out, &diff, &opts, &report).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(json["added"], 1);
+        assert_eq!(json["modified"], 3);
+        assert_eq!(
+            json["sections"][0],
+            serde_json::json!({"prefix": "logging", "added": 1, "removed": 0, "modified": 1})
+        );
+        assert_eq!(
+            json["deepest"],
+            serde_json::json!({"key": "spring.db.pool.max", "depth": 4})
+        );
+
+        colored::control::set_override(false);
+        report.format = OutputFormat::Text;
+        let mut out = Vec::new();
+        render(&mut out, &diff, &opts, &report).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(
+            text.contains("按配置段:\nlogging: +1 -0 ~1\nspring:  +0 -0 ~2\n"),
+            "{text}"
+        );
+        assert!(
+            text.ends_with("最深的变更: spring.db.pool.max (4 层)\n"),
+            "{text}"
+        );
+    }
+
+    #[test]
+    fn test_change_limits() {
+        let old: Value = serde_yaml::from_str("a: 1\nb: 1\nc: 1\n").unwrap();
+        let new: Value = serde_yaml::from_str("a: 2\nd: 1\n").unwrap();
+        let diff = cmp_yml_vals(&old, &new, &DiffOptions::default());
+        let limits = |extra: &[&str]| {
+            let args = ["yml-diff", "-o", "a", "-n", "b"].iter().chain(extra);
+            ensure_change_limits(&Args::parse_from(args), std::slice::from_ref(&diff))
+        };
+        assert!(limits(&[]).is_ok());
+        assert!(limits(&["--max-changes", "4", "--max-added", "1"]).is_ok());
+        assert_eq!(
+            limits(&["--max-changes", "3", "--max-removed", "0"])
+                .unwrap_err()
+                .to_string(),
+            "变更数量超出上限: 变更 4 处，超过 --max-changes 3；删除 2 处，超过 --max-removed 0"
+        );
+    }
+
+    #[test]
+    fn test_explain_modified() {
+        let opts = DiffOptions::default();
+        let port_str = Value::String("8080".into());
+        let port_num: Value = serde_yaml::from_str("8080").unwrap();
+        let other_num: Value = serde_yaml::from_str("9090").unwrap();
+
+        assert_eq!(
+            explain_modified("port", &port_str, &port_num, &opts, Lang::Zh),
+            "值不同（字符串 → 数值）"
+        );
+        assert_eq!(
+            explain_modified("port", &port_num, &other_num, &opts, Lang::Zh),
+            "值不同（同为数值）"
+        );
+        assert_eq!(
+            explain_modified("port", &port_str, &port_num, &opts, Lang::En),
+            "values differ (string → number)"
+        );
+    }
+
+    #[test]
+    fn test_english_report() {
+        let old: Value = serde_yaml::from_str("a: 1\nb: '80'\n").unwrap();
+        let new: Value = serde_yaml::from_str("b: 80\nc: x\n").unwrap();
+        let opts = DiffOptions::default();
+        let diff = cmp_yml_vals(&old, &new, &opts);
+        let report = ReportOptions {
+            lang: Lang::En,
+            explain: true,
+            ..Default::default()
+        };
+
+        colored::control::set_override(false);
+        let mut out = Vec::new();
+        print_diff(&mut out, &diff, &opts, &report).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(
+            text.starts_with(
+                "=== YAML configuration diff report ===\n\nStatistics:\n  Added: 1\n  Removed: 1\n  \
+                 Modified: 1 (type changes 1)\n"
+            ),
+            "{text}"
+        );
+        assert!(
+            text.contains("Added keys:\n  + c: x\n    reason: only in the new file\n"),
+            "{text}"
+        );
+        assert!(
+            text.contains("  ~ b [type change: string → number]\n  before 80\n  after  80\n"),
+            "{text}"
+        );
+    }
+
+    #[test]
+    fn test_english_output_formats() {
+        let old: Value = serde_yaml::from_str("db: {host: a, port: 1}\nx: 1\n").unwrap();
+        let new: Value = serde_yaml::from_str("db: {host: b, port: 1}\ny: 2\n").unwrap();
+        let opts = DiffOptions::default();
+        let diff = cmp_yml_vals(&old, &new, &opts);
+        let han = |text: &str| text.chars().any(|c| ('\u{4e00}'..='\u{9fff}').contains(&c));
+
+        colored::control::set_override(false);
+        for format in <OutputFormat as clap::ValueEnum>::value_variants() {
+            let report = ReportOptions {
+                lang: Lang::En,
+                format: *format,
+                ..Default::default()
+            };
+            for diff in [&diff, &ConfigDiff::default()] {
+                let mut out = Vec::new();
+                render(&mut out, diff, &opts, &report).unwrap();
+                let text = String::from_utf8(out).unwrap();
+                assert!(!han(&text), "{format:?}: {text}");
+            }
+        }
+        for report in [
+            ReportOptions {
+                lang: Lang::En,
+                summary: true,
+                ..Default::default()
+            },
+            ReportOptions {
+                lang: Lang::En,
+                stats: true,
+                ..Default::default()
+            },
+        ] {
+            let mut out = Vec::new();
+            render(&mut out, &diff, &opts, &report).unwrap();
+            let text = String::from_utf8(out).unwrap();
+            assert!(!han(&text), "{text}");
+        }
+
+        let render_format = |format| {
+            let report = ReportOptions {
+                lang: Lang::En,
+                format,
+                ..Default::default()
+            };
+            let mut out = Vec::new();
+            render(&mut out, &diff, &opts, &report).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        let text = render_format(OutputFormat::Diffstat);
+        assert!(
+            text.ends_with(" 3 section(s) changed, 1 added, 1 removed, 1 modified\n"),
+            "{text}"
+        );
+        let text = render_format(OutputFormat::Markdown);
+        assert!(
+            text.starts_with("## YAML configuration diff report"),
+            "{text}"
+        );
+        let text = render_format(OutputFormat::Html);
+        assert!(text.contains("<html lang=\"en\">"), "{text}");
+        let text = render_format(OutputFormat::Github);
+        assert!(text.contains("added y: 2"), "{text}");
+
+        let mut out = Vec::new();
+        print_unchanged_sections(&mut out, &[("db".into(), 2)], Lang::En).unwrap();
+        print_key_case_report(&mut out, &[], Lang::En).unwrap();
+        assert!(!han(&String::from_utf8(out).unwrap()));
+    }
+
+    #[test]
+    fn test_stdin_pair_documents() {
+        let docs = parse_documents(b"a: 1\n---\na: 2\n", true).unwrap();
+        let (old, new) = split_pair(docs).unwrap();
+        let diff = cmp_yml_vals(&old, &new, &DiffOptions::default());
+        assert_eq!(diff.modified().len(), 1);
+
+        let docs = parse_documents(b"a: 1\n", true).unwrap();
+        assert!(split_pair(docs).is_err());
+    }
+
+    #[test]
+    fn test_show_value_lengths() {
+        let opts = DiffOptions::default();
+        let report = ReportOptions {
+            value_length_threshold: Some(4),
+            ..Default::default()
+        };
+        let long = Value::String("abcdef".into());
+        let short = Value::String("abc".into());
+        assert_eq!(display_val("k", &long, &opts, &report), "<string, 6 chars>");
+        assert_eq!(display_val("k", &short, &opts, &report), "abc");
+    }
+
+    #[test]
+    fn test_encode_output() {
+        let gbk = parse_encoding("gbk").unwrap();
+        assert_eq!(&*encode_output("新增".as_bytes(), gbk), b"\xd0\xc2\xd4\xf6");
+        assert_eq!(&*encode_output(b"abc", gbk), b"abc");
+        assert!(parse_encoding("no-such-encoding").is_err());
+    }
+
+    #[test]
+    fn test_summary_only() {
+        let old: Value = serde_yaml::from_str("a: 1\nb: 1\n").unwrap();
+        let new: Value = serde_yaml::from_str("a: 2\nc: 1\nd: 1\n").unwrap();
+        let opts = DiffOptions::default();
+        let diff = cmp_yml_vals(&old, &new, &opts);
+
+        let mut report = ReportOptions {
+            summary: true,
+            format: OutputFormat::Json,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        render(&mut out, &diff, &opts, &report).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"added": 2, "removed": 1, "modified": 1})
+        );
+
+        report.format = OutputFormat::Text;
+        let mut out = Vec::new();
+        render(&mut out, &diff, &opts, &report).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains("统计信息"));
+        assert!(!text.contains("新增的配置项") && !text.contains("c: 1"));
+    }
+
+    #[test]
+    fn test_renamed_subtrees_in_report() {
+        let old: Value =
+            serde_yaml::from_str("db: {host: a, port: 1, user: u, name: n}\n").unwrap();
+        let new: Value =
+            serde_yaml::from_str("database: {host: a, port: 1, user: u, name: m}\n").unwrap();
+        let opts = DiffOptions {
+            detect_renames: true,
+            ..Default::default()
+        };
+        let diff = cmp_yml_vals(&old, &new, &opts);
+
+        colored::control::set_override(false);
+        let mut out = Vec::new();
+        print_diff(&mut out, &diff, &opts, &ReportOptions::default()).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains("  > db → database（3 项随之移动）"), "{text}");
+        assert!(
+            text.contains("  新增: 1\n  删除: 1\n  重命名: 1\n"),
+            "{text}"
+        );
+        // 值不同的键仍然显示为删除 + 新增，随子树移动的键不再逐个显示
+        assert!(
+            text.contains("  - db.name: n") && !text.contains("db.host"),
+            "{text}"
+        );
+    }
+
+    #[test]
+    fn test_reordered_elements_in_report() {
+        let old: Value = serde_yaml::from_str("hosts: [a, b, c]\n").unwrap();
+        let new: Value = serde_yaml::from_str("hosts: [c, a, b, d]\n").unwrap();
+        let opts = DiffOptions {
+            detect_reorders: true,
+            ..Default::default()
+        };
+        let diff = cmp_yml_vals(&old, &new, &opts);
+
+        colored::control::set_override(false);
+        let mut out = Vec::new();
+        print_diff(&mut out, &diff, &opts, &ReportOptions::default()).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(
+            text.contains("位置变化的元素:\n  ↕ hosts[2] → [0]  c\n"),
+            "{text}"
+        );
+        assert!(
+            text.contains("  + hosts[3]: d") && !text.contains("hosts[1]"),
+            "{text}"
+        );
+        assert!(text.contains("  重排: 1\n"), "{text}");
+        let json = diff_to_json(&diff, &opts);
+        assert_eq!(
+            json["reordered"],
+            serde_json::json!([{"sequence": "hosts", "from": 2, "to": 0, "value": "c"}])
+        );
+    }
+
+    #[test]
+    fn test_output_fan_out() {
+        assert_eq!(
+            parse_output_target("json:diff.json").unwrap(),
+            OutputTarget {
+                format: Some(OutputFormat::Json),
+                path: "diff.json".into(),
+            }
+        );
+        // 冒号前不是格式名时整个值都是路径
+        assert_eq!(parse_output_target("C:report.txt").unwrap().format, None);
+        assert!(parse_output_target("html:").is_err());
+
+        let dir = std::env::temp_dir().join(format!("yml-diff-output-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (old, new) = (dir.join("old.yml"), dir.join("new.yml"));
+        fs::write(&old, "a: 1\nb: x\n").unwrap();
+        fs::write(&new, "a: 2\nb: x\n").unwrap();
+        let (json, markdown) = (dir.join("diff.json"), dir.join("diff.md"));
+        let input = Args::parse_from([
+            "yml-diff".as_ref(),
+            "--old".as_ref(),
+            old.as_os_str(),
+            "--new".as_ref(),
+            new.as_os_str(),
+            format!("--output=json:{}", json.display()).as_ref(),
+            format!("--output=markdown:{}", markdown.display()).as_ref(),
+        ]);
+        let mut out = Vec::new();
+        assert!(run(&input, &mut out).unwrap());
+        // 标准输出仍是文本报告，文件中是各自的格式
+        assert!(String::from_utf8(out).unwrap().contains("修改的配置项:"));
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&json).unwrap()).unwrap();
+        assert_eq!(json["modified"]["a"]["old"], 1);
+        assert!(fs::read_to_string(&markdown).unwrap().contains("`a`"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// 在临时目录中写入两个内容相同的文件，加上 `--baseline-hash` 和 `extra` 比较它们；
+    /// 返回标准输出和临时目录，由调用方检查后删除
+    fn run_identical(name: &str, extra: &[&str]) -> (String, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("yml-diff-{name}-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (old, new) = (dir.join("old.yml"), dir.join("new.yml"));
+        fs::write(&old, "db: {host: a, port: 80}\napp: {name: x}\n").unwrap();
+        fs::copy(&old, &new).unwrap();
+        let mut args = vec![
+            "yml-diff".to_string(),
+            "--baseline-hash".into(),
+            "--old".into(),
+            old.display().to_string(),
+            "--new".into(),
+            new.display().to_string(),
+        ];
+        args.extend(
+            extra
+                .iter()
+                .map(|arg| arg.replace("{dir}", &dir.display().to_string())),
+        );
+        let mut out = Vec::new();
+        run(&Args::parse_from(args), &mut out).unwrap();
+        (String::from_utf8(out).unwrap(), dir)
+    }
+
+    #[test]
+    fn test_baseline_hash_emit_patch() {
+        colored::control::set_override(false);
+        let (out, dir) = run_identical("emit-patch", &["--emit-patch"]);
+        assert_eq!(out, "[]\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_baseline_hash_split_output() {
+        let (_, dir) = run_identical("split", &["--split-output", "{dir}/split"]);
+        for name in ["added.yml", "removed.yml", "modified.yml"] {
+            assert_eq!(
+                fs::read_to_string(dir.join("split").join(name)).unwrap(),
+                "{}\n"
+            );
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_baseline_hash_unchanged_sections() {
+        colored::control::set_override(false);
+        let (out, dir) = run_identical("unchanged", &["--summarize-unchanged-subtrees"]);
+        assert!(
+            out.contains("  ✓ app (未变化，1 个键)\n  ✓ db (未变化，2 个键)\n"),
+            "{out}"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_baseline_hash_matches_full_compare() {
+        colored::control::set_override(false);
+        let dir = std::env::temp_dir().join(format!("yml-diff-same-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (old, new) = (dir.join("a.yml"), dir.join("b.yml"));
+        fs::write(&old, "db: {host: a, port: 80}\n").unwrap();
+        fs::copy(&old, &new).unwrap();
+        let output = |extra: &[&str]| {
+            let mut args = vec![
+                "yml-diff".to_string(),
+                "--old".into(),
+                old.display().to_string(),
+                "--new".into(),
+                new.display().to_string(),
+            ];
+            args.extend(extra.iter().map(|arg| arg.to_string()));
+            let mut out = Vec::new();
+            run(&Args::parse_from(args), &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        for format in <OutputFormat as clap::ValueEnum>::value_variants() {
+            let format = clap::ValueEnum::to_possible_value(format).unwrap();
+            let format = format.get_name();
+            for extra in [
+                &["--format", format][..],
+                &["--format", format, "--git-path", "x.yml"],
+            ] {
+                let hashed = output(&[extra, &["--baseline-hash"]].concat());
+                assert_eq!(hashed, output(extra), "{extra:?}");
+            }
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_baseline_hash_needs_full_compare() {
+        let parse = |extra: &[&str]| {
+            let mut args = vec!["yml-diff", "--old", "a.yml", "--new", "b.yml"];
+            args.extend(extra);
+            Args::parse_from(args)
+        };
+        assert!(!parse(&[]).needs_full_compare());
+        assert!(parse(&["--interactive"]).needs_full_compare());
+        assert!(parse(&["--key-case-report"]).needs_full_compare());
+
+        colored::control::set_override(false);
+        let (out, dir) = run_identical("key-case", &["--key-case-report"]);
+        assert!(out.contains("没有只有大小写不同的键"), "{out}");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_sidecar_files_accept_flat_keys() {
+        let dir = std::env::temp_dir().join(format!("yml-diff-sidecar-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (defaults, context) = (dir.join("defaults.yml"), dir.join("context.yml"));
+        fs::write(
+            &defaults,
+            "\"db.pool\": 10\n\"servers[0]\": {port: 80}\napp: {debug: false}\n",
+        )
+        .unwrap();
+        fs::write(
+            &context,
+            "\"db.pool_size\": 连接池大小\ndb: {host: 数据库地址}\n",
+        )
+        .unwrap();
+        let opts = DiffOptions::default();
+
+        let defaults = load_defaults(&defaults, &opts).unwrap();
+        assert_eq!(defaults["db.pool"], Value::from(10));
+        assert_eq!(defaults["servers[0].port"], Value::from(80));
+        assert_eq!(defaults["app.debug"], Value::from(false));
+        let descriptions = load_descriptions(&context, &opts).unwrap();
+        assert_eq!(descriptions["db.pool_size"], "连接池大小");
+        assert_eq!(descriptions["db.host"], "数据库地址");
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(flat_sidecar_key(r#""a.\"b\"".c"#.into()), r#"a."b".c"#);
+        assert_eq!(flat_sidecar_key("a.b".into()), "a.b");
+    }
+
+    #[test]
+    fn test_coerce_key_types_conflict() {
+        let dir = std::env::temp_dir().join(format!("yml-diff-coerce-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (old, new) = (dir.join("old.yml"), dir.join("new.yml"));
+        fs::write(&old, "ports: {8080: http}\n").unwrap();
+        fs::write(&new, "ports: {8080: http, \"8080\": admin}\n").unwrap();
+        let args = |extra: &[&str]| {
+            let (old, new) = (old.to_str().unwrap(), new.to_str().unwrap());
+            let mut args = vec!["yml-diff", "-o", old, "-n", new];
+            args.extend(extra);
+            Args::parse_from(args)
+        };
+
+        let err = run(&args(&["--coerce-key-types"]), &mut Vec::new()).unwrap_err();
+        assert!(err.to_string().contains("键 ports.8080 重名"), "{err}");
+        let err = run(
+            &args(&["--coerce-key-types", "--low-memory"]),
+            &mut Vec::new(),
+        );
+        let err = err.unwrap_err().to_string();
+        assert!(err.contains("键 ports.8080 重名"), "{err}");
+        // 不规范化时数值键被跳过，不会重名
+        assert!(run(&args(&[]), &mut Vec::new()).is_ok());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_migrated_keys_in_report() {
+        let old: Value = serde_yaml::from_str("server: {max_threads: 200, port: 80}\n").unwrap();
+        let new: Value =
+            serde_yaml::from_str("server: {thread_pool: {max: 100}, port: 80}\n").unwrap();
+        let mut opts = DiffOptions::default();
+        opts.key_renames
+            .push("server.max_threads", "server.thread_pool.max");
+        let diff = cmp_yml_vals(&old, &new, &opts);
+
+        colored::control::set_override(false);
+        let mut out = Vec::new();
+        print_diff(&mut out, &diff, &opts, &ReportOptions::default()).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(
+            text.contains(
+                "按改名表迁移的配置项:\n  ⇒ server.max_threads → server.thread_pool.max\n  修改前 200\n  修改后 100\n"
+            ),
+            "{text}"
+        );
+        assert!(text.contains("  迁移: 1\n  修改: 0"), "{text}");
+        assert!(
+            !text.contains("新增的配置项") && !text.contains("删除的配置项"),
+            "{text}"
+        );
+        let json = diff_to_json(&diff, &opts);
+        assert_eq!(
+            json["migrated"],
+            serde_json::json!([{
+                "from": "server.max_threads",
+                "to": "server.thread_pool.max",
+                "old": 200,
+                "new": 100,
+                "changed": true,
+            }])
+        );
+    }
+
+    #[test]
+    fn test_context_shows_unchanged_siblings() {
+        let old: Value = serde_yaml::from_str(
+            "db: {a: 1, b: 2, c: 3, d: 4, e: 5}
+x: 1
+",
+        )
+        .unwrap();
+        let new: Value = serde_yaml::from_str(
+            "db: {a: 1, b: 2, c: 30, d: 4, e: 50}
+x: 1
+",
+        )
+        .unwrap();
+        let opts = DiffOptions {
+            track_unchanged: true,
+            ..Default::default()
+        };
+        let diff = cmp_yml_vals(&old, &new, &opts);
+        assert_eq!(diff.unchanged().len(), 4);
+
+        colored::control::set_override(false);
+        let mut out = Vec::new();
+        let report = ReportOptions {
+            quiet: true,
+            context: Some(1),
+            ..Default::default()
+        };
+        print_diff(&mut out, &diff, &opts, &report).unwrap();
+        // 两处修改共享的 db.d 只显示一次，不同父级的 x 不算同级键
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "修改的配置项:\n    db.b: 2\n  ~ db.c\n  修改前 3\n  修改后 30\n    db.d: 4\n\
+             \x20 ~ db.e\n  修改前 5\n  修改后 50\n\n"
+        );
+    }
+
+    #[test]
+    fn test_unordered_sequences_show_element_changes() {
+        let old: Value = serde_yaml::from_str("tags: [a, b, c]\nports: [1, 2]\n").unwrap();
+        let new: Value = serde_yaml::from_str("tags: [c, d, a]\nports: [2, 1]\n").unwrap();
+        let mut opts = DiffOptions::default();
+        opts.rules
+            .push("tags".parse().unwrap(), RuleAction::Unordered);
+        opts.rules
+            .push("ports".parse().unwrap(), RuleAction::Unordered);
+        let diff = cmp_yml_vals(&old, &new, &opts);
+
+        colored::control::set_override(false);
+        let mut out = Vec::new();
+        let report = ReportOptions {
+            quiet: true,
+            ..Default::default()
+        };
+        print_diff(&mut out, &diff, &opts, &report).unwrap();
+        // 只是顺序不同的 ports 不算差异
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "修改的配置项:\n  ~ tags\n  差异   [-b-] {+d+}\n\n"
+        );
+
+        let json = diff_to_json(&diff, &opts);
+        assert_eq!(json["modified"]["tags"]["elements"]["removed"][0], "b");
+        assert_eq!(json["modified"]["tags"]["elements"]["added"][0], "d");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_read_bytes_from_pipe() {
+        // 进程替换传入的是命名管道，内容只能按流读取
+        let fifo = std::env::temp_dir().join(format!("yml-diff-fifo-{}", std::process::id()));
+        let status = std::process::Command::new("mkfifo")
+            .arg(&fifo)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let writer = {
+            let fifo = fifo.clone();
+            std::thread::spawn(move || fs::write(fifo, "a: 1\n").unwrap())
+        };
+        assert_eq!(read_bytes(&fifo).unwrap(), b"a: 1\n");
+        writer.join().unwrap();
+        fs::remove_file(&fifo).unwrap();
+    }
+
+    #[test]
+    fn test_pairs_summary() {
+        colored::control::set_override(false);
+        let opts = DiffOptions::default();
+        let pair = |label: &str, old: bool, new: bool| FilePair {
+            label: label.into(),
+            old: old.then(|| PathBuf::from("old").join(label)),
+            new: new.then(|| PathBuf::from("new").join(label)),
+        };
+        let pairs = [
+            pair("app.yml", true, true),
+            pair("db.yml", true, true),
+            pair("gone.yml", true, false),
+            pair("broken.yml", true, true),
+        ];
+        let yaml = |s: &str| serde_yaml::from_str::<Value>(s).unwrap();
+        let (old, new, gone) = (yaml("a: 1\nb: 1\n"), yaml("a: 2\nc: 1\n"), yaml("x: 1\n"));
+        let diffs = [
+            cmp_yml_vals(&old, &new, &opts),
+            ConfigDiff::default(),
+            cmp_yml_vals(&gone, &Value::Null, &opts),
+            ConfigDiff::default(),
+        ];
+        let mut out = Vec::new();
+        print_pairs_summary(&mut out, &pairs, &diffs, &["broken.yml".into()], Lang::Zh).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "=== 汇总 ===\n  app.yml  +1 -1 ~1\n  db.yml  无差异\n  gone.yml  +0 -1 ~0\n  \
+             broken.yml  失败\n共 4 组文件，2 组有差异，1 组相同，仅存在于旧目录 1 个，\
+             仅存在于新目录 0 个，1 组失败；合计 +1 -2 ~1\n"
+        );
+
+        let mut out = Vec::new();
+        print_pairs_summary(&mut out, &pairs, &diffs, &["broken.yml".into()], Lang::En).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains("db.yml  no differences\n"), "{text}");
+        assert!(text.is_ascii(), "{text}");
+    }
+
+    #[test]
+    fn test_use_color() {
+        use ColorChoice::*;
+        assert!(use_color(Auto, OutputFormat::Text, true, false));
+        assert!(!use_color(Auto, OutputFormat::Text, false, false));
+        assert!(!use_color(Auto, OutputFormat::Text, true, true));
+        assert!(use_color(Always, OutputFormat::Text, false, true));
+        assert!(!use_color(Never, OutputFormat::Diffstat, true, false));
+        assert!(!use_color(Always, OutputFormat::Json, true, false));
+        // 有自己格式的输出从不带颜色，终端中查看的新格式与文本报告相同
+        assert!(!use_color(Always, OutputFormat::Html, true, false));
+        assert!(!use_color(Always, OutputFormat::Markdown, true, false));
+        assert!(!use_color(Always, OutputFormat::Junit, true, false));
+        assert!(!use_color(Always, OutputFormat::Github, true, false));
+        assert!(use_color(Auto, OutputFormat::Tree, true, false));
+        assert!(!use_color(Auto, OutputFormat::SideBySide, false, false));
+    }
+
+    #[test]
+    fn test_key_case_mismatches() {
+        let old: Value = serde_yaml::from_str("db:\n  maxConns: 1\n  host: a\nApp: x\n").unwrap();
+        let new: Value =
+            serde_yaml::from_str("db:\n  MaxConns: 1\n  host: a\nApp: x\napp: y\n").unwrap();
+        let pairs = key_case_mismatches(&old, &new, &DiffOptions::default());
+        assert_eq!(pairs, vec![("db.maxConns".into(), "db.MaxConns".into())]);
+    }
+
+    #[test]
+    fn test_value_hash_is_stable() {
+        let opts = DiffOptions::default();
+        let report = ReportOptions {
+            hashed: vec!["blob.*".parse().unwrap()],
+            ..Default::default()
+        };
+        let a = Value::String("generated".into());
+        let b = Value::String("generated!".into());
+        let shown = display_val("blob.data", &a, &opts, &report);
+        assert!(shown.starts_with("sha256:") && shown.len() == 15);
+        assert_eq!(shown, display_val("blob.data", &a.clone(), &opts, &report));
+        assert_ne!(shown, display_val("blob.data", &b, &opts, &report));
+        assert_eq!(display_val("other", &a, &opts, &report), "generated");
+    }
+
+    #[test]
+    fn test_print_changed_keys() {
+        let old: Value = serde_yaml::from_str("a: 1\nb:\n  c: 1\n  d: 1\n").unwrap();
+        let new: Value = serde_yaml::from_str("a: 2\nb:\n  c: 1\n  e: 1\n").unwrap();
+        let diff = cmp_yml_vals(&old, &new, &DiffOptions::default());
+
+        let mut out = Vec::new();
+        print_changed_keys(&mut out, &diff, KeyListStyle::Marked).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "~ a\n- b.d\n+ b.e\n");
+
+        let mut out = Vec::new();
+        print_changed_keys(&mut out, &diff, KeyListStyle::Plain).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "a\nb.d\nb.e\n");
+    }
+
+    #[test]
+    fn test_count_by_type() {
+        let old: Value = serde_yaml::from_str("a: 1\nb: 1\nc: \"x  y\"\nd: 1\n").unwrap();
+        let new: Value = serde_yaml::from_str("a: 2\nb: \"1\"\nc: \" x y\"\ne: 1\n").unwrap();
+        let diff = cmp_yml_vals(&old, &new, &DiffOptions::default());
+
+        let json = serde_json::to_value(DiffCounts::new(&diff)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "added": 1,
+                "removed": 1,
+                "modified": {"total": 3, "value": 1, "type": 1, "whitespace": 1}
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_pairs() {
+        let text = "# 环境配置\nbase.yml\tdev.yml\n\n base.yml , prod.yml \n";
+        assert_eq!(
+            parse_pairs(text).unwrap(),
+            vec![("base.yml", "dev.yml"), ("base.yml", "prod.yml")]
+        );
+        assert!(parse_pairs("only-one.yml\n").is_err());
+        assert!(parse_pairs("a,b,c\n").is_err());
+    }
+
+    #[test]
+    fn test_styled_key_highlight() {
+        let report = ReportOptions {
+            highlighted: vec!["*.password".parse().unwrap()],
+            ..Default::default()
+        };
+        let plain = styled_key("db.host", Color::Green, &report);
+        let highlighted = styled_key("db.password", Color::Green, &report);
+        assert_eq!(plain.fgcolor, Some(Color::Green));
+        assert!(!plain.style.contains(Styles::Bold));
+        assert_eq!(highlighted.fgcolor, Some(Color::Green));
+        assert!(highlighted.style.contains(Styles::Bold));
+        assert!(highlighted.style.contains(Styles::Underline));
+    }
+
+    #[test]
+    fn test_no_recurse_compares_top_level_values() {
+        let args = Args::parse_from(["yml-diff", "-o", "a.yml", "-n", "b.yml", "--no-recurse"]);
+        let opts = DiffOptions {
+            flatten: FlattenOptions {
+                max_depth: args.max_depth(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let old: Value = serde_yaml::from_str("a:\n  b: 1\n  c: 1\nd: 1\n").unwrap();
+        let new: Value = serde_yaml::from_str("a:\n  b: 2\n  c: 1\nd: 1\n").unwrap();
+        let diff = cmp_yml_vals(&old, &new, &opts);
+        let modified: Vec<&str> = diff.modified().keys().map(|k| k.as_str()).collect();
+        assert_eq!(modified, vec!["a"]);
+    }
+
+    #[test]
+    fn test_max_depth_treats_deeper_subtrees_as_values() {
+        let args = Args::parse_from(["yml-diff", "-o", "a.yml", "-n", "b.yml", "--max-depth", "1"]);
+        let opts = DiffOptions {
+            flatten: FlattenOptions {
+                max_depth: args.max_depth(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let old: Value =
+            serde_yaml::from_str("db:\n  conn: {host: a, port: 1}\n  pool: 1\n").unwrap();
+        let new: Value =
+            serde_yaml::from_str("db:\n  conn: {host: b, port: 1}\n  pool: 1\nx: {y: {z: 1}}\n")
+                .unwrap();
+        let diff = cmp_yml_vals(&old, &new, &opts);
+        let modified: Vec<&str> = diff.modified().keys().map(|k| k.as_str()).collect();
+        assert_eq!(modified, vec!["db.conn"]);
+        let added: Vec<&str> = diff.added().keys().map(|k| k.as_str()).collect();
+        assert_eq!(added, vec!["x.y"]);
+        assert_eq!(get_val_string(diff.modified()[0].1), "{host: b, port: 1}");
+    }
+
+    #[test]
+    fn test_find_moves() {
+        let old: Value =
+            serde_yaml::from_str("logging:\n  level: info\n  file: a.log\nport: 1\n").unwrap();
+        let new: Value =
+            serde_yaml::from_str("app:\n  logging:\n    level: info\n    file: b.log\n  port: 2\n")
+                .unwrap();
+        let diff = cmp_yml_vals(&old, &new, &DiffOptions::default());
+        let moves: Vec<(&str, &str)> = find_moves(&diff)
+            .into_iter()
+            .map(|(a, b)| (a.as_str(), b.as_str()))
+            .collect();
+        assert_eq!(moves, vec![("logging.level", "app.logging.level")]);
+    }
+
+    #[test]
+    fn test_strict_number_types() {
+        let old: Value = serde_yaml::from_str("a: 1\nb: 2\n").unwrap();
+        let new: Value = serde_yaml::from_str("a: 1.0\nb: 2\n").unwrap();
+        let mut opts = DiffOptions {
+            rules: Rules::from_value(&serde_yaml::from_str("a: float-tolerance=0.5").unwrap())
+                .unwrap(),
+            ..Default::default()
+        };
+        assert!(cmp_yml_vals(&old, &new, &opts).is_empty());
+
+        opts.strict_number_types = true;
+        let diff = cmp_yml_vals(&old, &new, &opts);
+        let modified: Vec<&str> = diff.modified().keys().map(|k| k.as_str()).collect();
+        assert_eq!(modified, vec!["a"]);
+        assert_eq!(
+            explain_modified("a", &old["a"], &new["a"], &opts, Lang::Zh),
+            "数值类型不同（整数 → 浮点数）"
+        );
+        assert_eq!(get_val_string(&new["a"]), "1.0");
+    }
+
+    #[test]
+    fn test_unchanged_sections() {
+        let old: Value =
+            serde_yaml::from_str("app:\n  a: 1\ndb:\n  host: x\n  port: 1\nlog: info\n").unwrap();
+        let new: Value =
+            serde_yaml::from_str("app:\n  a: 2\ndb:\n  host: x\n  port: 1\nlog: info\n").unwrap();
+        let opts = DiffOptions::default();
+        let diff = cmp_yml_vals(&old, &new, &opts);
+        assert_eq!(
+            unchanged_sections(&new, &diff, &opts),
+            vec![("db".to_string(), 2), ("log".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_ensure_not_empty() {
+        let opts = DiffOptions::default();
+        for text in ["", "# 只有注释\n", "~\n", "{}\n"] {
+            let val: Value = serde_yaml::from_str(text).unwrap_or(Value::Null);
+            let err = ensure_not_empty("旧文件 a.yml", &val, &opts).unwrap_err();
+            assert!(err.to_string().starts_with("旧文件 a.yml 没有任何配置项"));
+        }
+        let val: Value = serde_yaml::from_str("a: 1\n").unwrap();
+        assert!(ensure_not_empty("新文件 b.yml", &val, &opts).is_ok());
+    }
+
+    #[test]
+    fn test_ensure_no_type_changes() {
+        let old: Value = serde_yaml::from_str(
+            "port: 8080
+host: a
+",
+        )
+        .unwrap();
+        let new: Value = serde_yaml::from_str(
+            "port: '8080'
+host: b
+",
+        )
+        .unwrap();
+        let opts = DiffOptions::default();
+        let err = ensure_no_type_changes(&cmp_yml_vals(&old, &new, &opts)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "1 处配置项的类型发生了变化: port（数值 → 字符串）"
+        );
+
+        let new: Value = serde_yaml::from_str(
+            "port: 8081
+host: b
+",
+        )
+        .unwrap();
+        assert!(ensure_no_type_changes(&cmp_yml_vals(&old, &new, &opts)).is_ok());
+    }
+
+    #[test]
+    fn test_severity_rules() {
+        let rules: Value = serde_yaml::from_str(
+            "\"security.**\": severity=error\nlogging.level: info\n\"db.*\": [warn, redact]\n",
+        )
+        .unwrap();
+        let opts = DiffOptions {
+            rules: Rules::from_value(&rules).unwrap(),
+            ..Default::default()
+        };
+        let old: Value = serde_yaml::from_str(
+            "security: {tls: {enabled: true}}\nlogging: {level: info}\ndb: {pool: 5}\nport: 1\n",
+        )
+        .unwrap();
+        let new: Value = serde_yaml::from_str(
+            "security: {tls: {enabled: false}}\nlogging: {level: debug}\ndb: {pool: 8}\nport: 2\n",
+        )
+        .unwrap();
+        let diff = cmp_yml_vals(&old, &new, &opts);
+
+        colored::control::set_override(false);
+        let mut out = Vec::new();
+        print_severity_groups(&mut out, &diff, &opts, Lang::Zh).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "按严重程度:\n  error (1)\n    ~ security.tls.enabled\n  warn (1)\n    ~ db.pool\n  info (1)\n    ~ logging.level\n\n"
+        );
+        assert_eq!(diff_to_json(&diff, &opts)["severity"]["db.pool"], "warn");
+
+        let result = ensure_severity(std::slice::from_ref(&diff), &opts.rules).map(|()| true);
+        assert_eq!(
+            result.as_ref().unwrap_err().to_string(),
+            "1 处 error 级别的配置项发生了变化: security.tls.enabled"
+        );
+        assert_eq!(exit_status(false, &result), 4);
+
+        // 只有 info 级别的变更时不算失败
+        let new: Value = serde_yaml::from_str(
+            "security: {tls: {enabled: true}}\nlogging: {level: debug}\ndb: {pool: 5}\nport: 2\n",
+        )
+        .unwrap();
+        let diff = cmp_yml_vals(&old, &new, &opts);
+        assert!(ensure_severity(std::slice::from_ref(&diff), &opts.rules).is_ok());
+        assert!(Rules::from_value(&serde_yaml::from_str("a: severity=fatal").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_diff_to_json() {
+        let old: Value = serde_yaml::from_str("a: 1\nb: true\nc: x\n").unwrap();
+        let new: Value = serde_yaml::from_str("a: 2\nb: true\nd: [1, 2]\n").unwrap();
+        let opts = DiffOptions::default();
+        let json = diff_to_json(&cmp_yml_vals(&old, &new, &opts), &opts);
+        assert_eq!(json["added"], serde_json::json!({"d[0]": 1, "d[1]": 2}));
+        assert_eq!(json["removed"], serde_json::json!({"c": "x"}));
+        assert_eq!(
+            json["modified"],
+            serde_json::json!({"a": {"old": 1, "new": 2}})
+        );
+
+        let empty = diff_to_json(&ConfigDiff::default(), &opts);
+        assert_eq!(empty["added"], serde_json::json!({}));
+        assert_eq!(empty["modified"], serde_json::json!({}));
+    }
+
+    #[test]
+    fn test_json_preserves_value_types() {
+        // 值按原始类型输出，字符串 "80" 与数值 80 在 JSON 中仍可区分，整体比较的子树输出为对象
+        let old: Value =
+            serde_yaml::from_str("port: \"80\"\nratio: 0.5\nx: null\ndb: {a: 1}\n").unwrap();
+        let new: Value =
+            serde_yaml::from_str("port: 80\nratio: 1.5\nx: false\ndb: {a: 2}\n").unwrap();
+        let opts = DiffOptions {
+            flatten: FlattenOptions {
+                max_depth: Some(0),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let json = diff_to_json(&cmp_yml_vals(&old, &new, &opts), &opts);
+        assert_eq!(
+            json["modified"],
+            serde_json::json!({
+                "port": {"old": "80", "new": 80},
+                "ratio": {"old": 0.5, "new": 1.5},
+                "x": {"old": null, "new": false},
+                "db": {"old": {"a": 1}, "new": {"a": 2}},
+            })
+        );
+    }
+
+    #[test]
+    fn test_stdin_path_and_parse_errors() {
+        assert!(is_stdin(Path::new("-")));
+        assert!(!is_stdin(Path::new("./-")));
+
+        let err = parse_file(Path::new("new.yml"), b"a: [1\n", None, true)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("new.yml") && !err.contains("旧版"), "{err}");
+        let err = parse_cfg(b"a: [1\n").unwrap_err().to_string();
+        assert!(!err.contains("旧版"), "{err}");
+    }
+
+    #[test]
+    fn test_strict_parse_reports_both_files() {
+        let input = Args::parse_from(["yml-diff", "--strict", "--old", "a.yml", "--new", "b.json"]);
+        let err = input
+            .parse_pair(
+                (Path::new("a.yml"), b"db:\n  hosts: [a, b\n"),
+                (Path::new("b.json"), b"{\"a\": 1,}"),
+            )
+            .unwrap_err()
+            .to_string();
+        let lines: Vec<&str> = err.lines().collect();
+        assert_eq!(lines[0], "2 个文件解析失败:");
+        assert!(lines[1].starts_with("旧文件 \"a.yml\" 第 3 行第 1 列: 解析 YAML 失败！"));
+        // 文件意外结束时指向最后一行的末尾
+        assert_eq!(
+            lines[2..5],
+            ["  1 | db:", "  2 |   hosts: [a, b", "    |               ^"]
+        );
+        assert!(err.contains("\n\n新文件 \"b.json\" 第 1 行第 9 列: 解析 JSON 失败！"));
+        assert!(err.ends_with("  1 | {\"a\": 1,}\n    |         ^"), "{err}");
+
+        // 只有一边出错时另一边照常解析，错误中只有出错的一边
+        let err = input
+            .parse_pair((Path::new("a.yml"), b"a: 1\n"), (Path::new("b.json"), b"{"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("1 个文件解析失败:\n新文件"), "{err}");
+    }
+
+    #[test]
+    fn test_exit_status() {
+        assert_eq!(exit_status(true, &Ok(false)), 0);
+        assert_eq!(exit_status(true, &Ok(true)), 1);
+        assert_eq!(exit_status(true, &Err(anyhow!("解析失败"))), 2);
+        assert_eq!(exit_status(false, &Ok(true)), 0);
+        assert_eq!(exit_status(false, &Err(anyhow!("解析失败"))), 1);
+    }
+
+    #[test]
+    fn test_parse_error_json() {
+        let err = parse_file(
+            Path::new("conf/app.toml"),
+            b"[a]\nx = 1\ny = \n",
+            None,
+            true,
+        )
+        .unwrap_err();
+        let json = error_to_json(&err);
+        assert_eq!(json["path"], "conf/app.toml");
+        assert_eq!(
+            json["location"],
+            serde_json::json!({"line": 3, "column": 5})
+        );
+        assert!(
+            json["error"]
+                .as_str()
+                .unwrap()
+                .starts_with("解析 TOML 失败！")
+        );
+
+        let err = parse_file(Path::new("a.yml"), b"a: 1\nb: [\n", None, true).unwrap_err();
+        assert_eq!(error_to_json(&err)["location"]["line"], 3);
+
+        let json = error_to_json(&anyhow!("其他错误"));
+        assert_eq!(
+            json,
+            serde_json::json!({"error": "其他错误", "path": null, "location": null})
+        );
+    }
+
+    #[test]
+    fn test_compare_multi_document_files() {
+        let manifest_dir = env!("CARGO_MANIFEST_DIR");
+        let old = PathBuf::from(manifest_dir).join("manifest_v1.yml");
+        let new = PathBuf::from(manifest_dir).join("manifest_v2.yml");
+        let (old_val, new_val) = combine_documents(
+            parse_file(&old, &read_bytes(&old).unwrap(), None, true).unwrap(),
+            parse_file(&new, &read_bytes(&new).unwrap(), None, true).unwrap(),
+        );
+
+        let diff = cmp_yml_vals(&old_val, &new_val, &DiffOptions::default());
+        let modified: Vec<&str> = diff.modified().keys().map(|k| k.as_str()).collect();
+        // 清单中的文档都有 kind 和 metadata.name，按 kind/name 配对
+        assert_eq!(
+            modified,
+            vec![
+                "doc.Deployment/web.spec.replicas",
+                "doc.Service/web.spec.ports[0].port"
+            ]
+        );
+        // 新文件多出的文档整体作为新增
+        let added: Vec<&str> = diff.added().keys().map(|k| k.as_str()).collect();
+        assert_eq!(
+            added,
+            vec![
+                "doc.ConfigMap/web-config.apiVersion",
+                "doc.ConfigMap/web-config.kind",
+                "doc.ConfigMap/web-config.metadata.name"
+            ]
+        );
+        assert!(diff.removed().is_empty());
+    }
+
+    #[test]
+    fn test_anchors_compare_equal_to_inlined() {
+        let manifest_dir = env!("CARGO_MANIFEST_DIR");
+        let load = |name: &str| {
+            let path = PathBuf::from(manifest_dir).join(name);
+            parse_file(&path, &read_bytes(&path).unwrap(), None, true).unwrap()
+        };
+        let (old_val, new_val) = combine_documents(load("anchors_v1.yml"), load("anchors_v2.yml"));
+        assert!(cmp_yml_vals(&old_val, &new_val, &DiffOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn test_type_change_is_labelled() {
+        let old: Value = serde_yaml::from_str("port: \"8080\"\nhost: a\n").unwrap();
+        let new: Value = serde_yaml::from_str("port: 8080\nhost: b\n").unwrap();
+        let opts = DiffOptions::default();
+        let diff = cmp_yml_vals(&old, &new, &opts);
+        assert_eq!(
+            classify_modification(&old["port"], &new["port"]),
+            ModificationKind::Type
+        );
+
+        let mut out = Vec::new();
+        print_diff(&mut out, &diff, &opts, &ReportOptions::default()).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains("[类型变更: 字符串 → 数值]"));
+        assert_eq!(text.matches("类型变更").count(), 1);
+    }
+
+    #[test]
+    fn test_tagged_values() {
+        let manifest_dir = env!("CARGO_MANIFEST_DIR");
+        let load = |name: &str| {
+            let path = PathBuf::from(manifest_dir).join(name);
+            parse_file(&path, &read_bytes(&path).unwrap(), None, true).unwrap()
+        };
+        let (old, new) = combine_documents(load("tags_v1.yml"), load("tags_v2.yml"));
+        let opts = DiffOptions::default();
+        let diff = cmp_yml_vals(&old, &new, &opts);
+
+        let kinds: Vec<(&str, ModificationKind)> = (diff.modified().iter())
+            .map(|(key, &(old, new))| (key.as_str(), classify_modification(old, new)))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("api_key", ModificationKind::Value),
+                ("db_password", ModificationKind::Type),
+                ("replicas", ModificationKind::Type),
+            ]
+        );
+
+        let mut out = Vec::new();
+        print_diff(&mut out, &diff, &opts, &ReportOptions::default()).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(
+            text.contains("[类型变更: 标签 !secret → 标签 !plain]"),
+            "{text}"
+        );
+        assert!(text.contains("[类型变更: 标签 !int → 字符串]"), "{text}");
+        assert!(text.contains("修改前 !secret abc123"), "{text}");
+    }
+
+    #[test]
+    fn test_key_lines() {
+        let doc: Value =
+            serde_yaml::from_str("z: 1\ndb: {port: 80, hosts: [a, b]}\napp: {db: {x: y}}\n")
+                .unwrap();
+        let opts = DiffOptions::default();
+        assert_eq!(
+            key_lines(&doc, &opts, false, false),
+            ["z", "db.port", "db.hosts[0]", "db.hosts[1]", "app.db.x"]
+        );
+        assert_eq!(
+            key_lines(&doc, &opts, false, true),
+            ["app.db.x", "db.port", "db.hosts[0]", "db.hosts[1]", "z"]
+        );
+        let opts = DiffOptions {
+            paths: vec![KeyPattern::new("db")],
+            ..Default::default()
+        };
+        assert_eq!(
+            key_lines(&doc, &opts, true, false),
+            ["db.port: 80", "db.hosts[0]: a", "db.hosts[1]: b"]
+        );
+    }
+
+    #[test]
+    fn test_get_renders_values() {
+        let doc: Value = serde_yaml::from_str(
+            "spring:\n  datasource: {url: 'jdbc:mysql://db', pool: 8}\nservers: [{host: a}]\n",
+        )
+        .unwrap();
+        let get = |key: &str| {
+            let key = ConfigKey::new(key, ".");
+            apply::get_path(&doc, &key.segments().collect::<Vec<_>>()).cloned()
+        };
+        let url = get("spring.datasource.url").unwrap();
+        assert_eq!(render_value(&url, false).unwrap(), "jdbc:mysql://db\n");
+        assert_eq!(render_value(&url, true).unwrap(), "\"jdbc:mysql://db\"\n");
+        assert_eq!(
+            render_value(&get("servers[0].host").unwrap(), false).unwrap(),
+            "a\n"
+        );
+        let subtree = get("spring.datasource").unwrap();
+        assert_eq!(
+            render_value(&subtree, false).unwrap(),
+            "url: jdbc:mysql://db\npool: 8\n"
+        );
+        assert_eq!(
+            render_value(&subtree, true).unwrap(),
+            "{\n  \"url\": \"jdbc:mysql://db\",\n  \"pool\": 8\n}\n"
+        );
+        assert!(get("spring.missing").is_none());
     }
 }
@@ -0,0 +1,132 @@
+use crate::i18n::Msg;
+use crate::{ReportOptions, change_location, display_val};
+use std::io::{self, Write};
+use yml_diff::{ChangeKind, ConfigDiff, DiffOptions};
+
+/// --format markdown：GitHub 风格的 Markdown，先是各类变更的数量，再按类别列出每个键，
+/// 键和值都写在代码片段中，可以直接作为 pull request 的评论发布
+pub fn print_markdown(
+    out: &mut dyn Write,
+    diff: &ConfigDiff,
+    opts: &DiffOptions,
+    report: &ReportOptions,
+) -> io::Result<()> {
+    let value = |key: &str, val| code(&display_val(key, val, opts, report));
+    // --line-numbers 时位置写在键的后面
+    let key_cell = |key: &str, kind: ChangeKind| match change_location(key, &kind, opts, report) {
+        Some(location) => format!("{} ({})", code(key), location.replace('|', "\\|")),
+        None => code(key),
+    };
+
+    let lang = report.lang;
+    writeln!(out, "## {}", lang.get(Msg::ReportName))?;
+    writeln!(out)?;
+    if let Some((old, new)) = &report.labels {
+        writeln!(out, "{} → {}", code(old), code(new))?;
+        writeln!(out)?;
+    }
+    writeln!(out, "{}", lang.get(Msg::CountsHeader))?;
+    writeln!(out, "| ---: | ---: | ---: |")?;
+    writeln!(
+        out,
+        "| {} | {} | {} |",
+        diff.added().len(),
+        diff.removed().len(),
+        diff.modified().len()
+    )?;
+    writeln!(out)?;
+
+    if diff.is_empty() {
+        return writeln!(out, "{}", lang.get(Msg::NoDifferences));
+    }
+
+    if !diff.added().is_empty() {
+        writeln!(out, "### {}", lang.get(Msg::AddedHeading))?;
+        writeln!(out)?;
+        writeln!(out, "{}", lang.get(Msg::AddedTableHeader))?;
+        writeln!(out, "| --- | --- |")?;
+        for (key, &val) in diff.added() {
+            let cell = key_cell(key, ChangeKind::Added(val));
+            writeln!(out, "| {cell} | {} |", value(key, val))?;
+        }
+        writeln!(out)?;
+    }
+
+    if !diff.removed().is_empty() {
+        writeln!(out, "### {}", lang.get(Msg::RemovedHeading))?;
+        writeln!(out)?;
+        writeln!(out, "{}", lang.get(Msg::RemovedTableHeader))?;
+        writeln!(out, "| --- | --- |")?;
+        for (key, &val) in diff.removed() {
+            let cell = key_cell(key, ChangeKind::Removed(val));
+            writeln!(out, "| {cell} | {} |", value(key, val))?;
+        }
+        writeln!(out)?;
+    }
+
+    if !diff.modified().is_empty() {
+        writeln!(out, "### {}", lang.get(Msg::ModifiedHeading))?;
+        writeln!(out)?;
+        writeln!(out, "{}", lang.get(Msg::ModifiedTableHeader))?;
+        writeln!(out, "| --- | --- |")?;
+        for (key, &(old, new)) in diff.modified() {
+            let cell = key_cell(key, ChangeKind::Modified(old, new));
+            writeln!(
+                out,
+                "| {cell} | {} → {} |",
+                value(key, old),
+                value(key, new)
+            )?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// 写成表格单元格中的代码片段
+///
+/// 内容含有反引号时用更长的反引号串包围；换行写作 `↵`，因为表格的一行不能跨行；
+/// `|` 即使在代码片段中也会被当作列分隔符，需要转义。
+fn code(text: &str) -> String {
+    let text = text.replace('\n', "↵").replace('|', "\\|");
+    let longest_run = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest_run + 1);
+    // 以反引号开头或结尾的内容需要空格隔开，否则会与包围的反引号连在一起
+    let pad = if text.starts_with('`') || text.ends_with('`') {
+        " "
+    } else {
+        ""
+    };
+    format!("{fence}{pad}{text}{pad}{fence}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{code, print_markdown};
+    use crate::ReportOptions;
+    use serde_yaml::Value;
+    use yml_diff::{DiffOptions, cmp_yml_vals};
+
+    #[test]
+    fn test_print_markdown() {
+        assert_eq!(code("a|b"), "`a\\|b`");
+        assert_eq!(code("x `y` z"), "``x `y` z``");
+        assert_eq!(code("`q`"), "`` `q` ``");
+        assert_eq!(code("a\nb"), "`a↵b`");
+
+        let old: Value = serde_yaml::from_str("a: 1\nb: x\n").unwrap();
+        let new: Value = serde_yaml::from_str("a: 2\nc: [1]\n").unwrap();
+        let opts = DiffOptions::default();
+        let diff = cmp_yml_vals(&old, &new, &opts);
+        let mut out = Vec::new();
+        print_markdown(&mut out, &diff, &opts, &ReportOptions::default()).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "## YAML 配置文件差异报告\n\n\
+             | 新增 | 删除 | 修改 |\n| ---: | ---: | ---: |\n| 1 | 1 | 1 |\n\n\
+             ### 新增的配置项\n\n| 键 | 新值 |\n| --- | --- |\n| `c[0]` | `1` |\n\n\
+             ### 删除的配置项\n\n| 键 | 旧值 |\n| --- | --- |\n| `b` | `x` |\n\n\
+             ### 修改的配置项\n\n| 键 | 旧值 → 新值 |\n| --- | --- |\n| `a` | `1` → `2` |\n\n"
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use crate::i18n::Msg;
+use crate::side_by_side::{pad, text_width};
+use crate::{ReportOptions, display_val};
+use colored::*;
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use yml_diff::{ChangeKind, ConfigDiff, ConfigKey, DiffOptions, get_val_string};
+
+/// 矩阵中一格最多显示的宽度，更长的值截断并以 `…` 结尾
+const MAX_CELL: usize = 32;
+
+/// 与基线相同的格子
+const SAME: &str = "·";
+
+/// 在任一目标中有变化的键 → 它在各个目标中的变更（与基线相同时为 `None`），按键排序
+fn build<'a>(diffs: &[ConfigDiff<'a>]) -> BTreeMap<ConfigKey, Vec<Option<ChangeKind<'a>>>> {
+    let mut rows: BTreeMap<ConfigKey, Vec<Option<ChangeKind<'a>>>> = BTreeMap::new();
+    for (i, diff) in diffs.iter().enumerate() {
+        let changes = (diff.added().iter())
+            .map(|(k, &v)| (k, ChangeKind::Added(v)))
+            .chain((diff.removed().iter()).map(|(k, &v)| (k, ChangeKind::Removed(v))))
+            .chain(
+                (diff.modified().iter())
+                    .map(|(k, &(old, new))| (k, ChangeKind::Modified(old, new))),
+            );
+        for (key, kind) in changes {
+            let row = rows
+                .entry(key.clone())
+                .or_insert_with(|| vec![None; diffs.len()]);
+            row[i] = Some(kind);
+        }
+    }
+    rows
+}
+
+/// 键在基线中的值：取自任一目标中删除或修改前的值，基线中没有该键时为 `None`
+fn baseline_value<'a>(row: &[Option<ChangeKind<'a>>]) -> Option<&'a Value> {
+    row.iter().flatten().find_map(|kind| match *kind {
+        ChangeKind::Removed(old) | ChangeKind::Modified(old, _) => Some(old),
+        ChangeKind::Added(_) => None,
+    })
+}
+
+/// 一个基线与多个目标比较（`--old base.yml --new dev.yml --new prod.yml`）时的矩阵报告
+///
+/// 每行是在至少一个目标中有变化的键，先是基线中的值，再是各个目标中的情况：`·` 与基线相同，
+/// `~ 值` 修改、`+ 值` 新增、`-` 删除。最后是每个目标的差异数量。
+pub fn print_matrix(
+    out: &mut dyn Write,
+    baseline: &str,
+    targets: &[String],
+    diffs: &[ConfigDiff],
+    opts: &DiffOptions,
+    report: &ReportOptions,
+) -> io::Result<()> {
+    if !report.quiet {
+        let title = (report.lang).format(Msg::MatrixTitle, &[&baseline, &targets.len()]);
+        writeln!(out, "{}", title.bold())?;
+        writeln!(out)?;
+    }
+    let rows = build(diffs);
+    if rows.is_empty() {
+        if !report.quiet {
+            writeln!(out, "{}", report.lang.get(Msg::MatrixAllSame).green())?;
+        }
+        return Ok(());
+    }
+
+    let value = |key: &str, val| truncate(&display_val(key, val, opts, report).replace('\n', "↵"));
+    let mut table: Vec<Vec<(String, Option<Color>)>> = Vec::new();
+    let mut header = vec![
+        (report.lang.get(Msg::MatrixKey).to_string(), None),
+        (report.lang.get(Msg::MatrixBaseline).to_string(), None),
+    ];
+    header.extend(targets.iter().map(|t| (t.clone(), None)));
+    table.push(header);
+    for (key, row) in &rows {
+        let key = key.as_str();
+        let mut line = vec![
+            (key.to_string(), None),
+            (
+                baseline_value(row).map_or("—".into(), |v| value(key, v)),
+                None,
+            ),
+        ];
+        line.extend(row.iter().map(|kind| match *kind {
+            None => (SAME.to_string(), None),
+            Some(ChangeKind::Added(new)) => (format!("+ {}", value(key, new)), Some(Color::Green)),
+            Some(ChangeKind::Removed(_)) => ("-".to_string(), Some(Color::Red)),
+            Some(ChangeKind::Modified(_, new)) => {
+                (format!("~ {}", value(key, new)), Some(Color::Yellow))
+            }
+        }));
+        table.push(line);
+    }
+
+    let widths: Vec<usize> = (0..table[0].len())
+        .map(|col| {
+            table
+                .iter()
+                .map(|row| text_width(&row[col].0))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    for (i, row) in table.iter().enumerate() {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|((text, color), &width)| {
+                let cell = pad(text, width);
+                match color {
+                    Some(color) => cell.color(*color).to_string(),
+                    None if i == 0 => cell.bold().to_string(),
+                    None => cell,
+                }
+            })
+            .collect();
+        writeln!(out, "{}", cells.join("  ").trim_end())?;
+    }
+
+    if !report.quiet {
+        writeln!(out)?;
+        for (target, diff) in targets.iter().zip(diffs) {
+            let count = diff.changes().count();
+            writeln!(
+                out,
+                "{}",
+                report
+                    .lang
+                    .format(Msg::MatrixTargetCount, &[target, &count])
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// 超过 [`MAX_CELL`] 列的内容截断
+fn truncate(text: &str) -> String {
+    if text_width(text) <= MAX_CELL {
+        return text.to_string();
+    }
+    let mut cell = String::new();
+    for c in text.chars() {
+        if text_width(&cell) + text_width(&c.to_string()) >= MAX_CELL {
+            break;
+        }
+        cell.push(c);
+    }
+    cell.push('…');
+    cell
+}
+
+/// 矩阵报告的 JSON 形式：`{"baseline": 基线, "targets": [目标, ...], "keys": {键: {...}}}`
+///
+/// 每个键对应 `{"baseline": 基线中的值, 目标: 变更, ...}`，变更写作 `{"old": 旧值, "new": 新值}`，
+/// 新增没有 `old`、删除没有 `new`，与基线相同时为 `null`。命中 redact 规则的值输出为 `"***"`。
+pub fn matrix_to_json(
+    baseline: &str,
+    targets: &[String],
+    diffs: &[ConfigDiff],
+    opts: &DiffOptions,
+) -> serde_json::Value {
+    let value = |key: &str, val: &Value| {
+        if opts.rules.is_redacted(key) {
+            return serde_json::Value::String("***".into());
+        }
+        serde_json::to_value(val).unwrap_or_else(|_| get_val_string(val).into_owned().into())
+    };
+    let keys: serde_json::Map<_, _> = build(diffs)
+        .iter()
+        .map(|(key, row)| {
+            let key = key.as_str();
+            let mut cells = serde_json::Map::new();
+            cells.insert(
+                "baseline".into(),
+                baseline_value(row).map_or(serde_json::Value::Null, |v| value(key, v)),
+            );
+            for (target, kind) in targets.iter().zip(row) {
+                let cell = match *kind {
+                    None => serde_json::Value::Null,
+                    Some(ChangeKind::Added(new)) => serde_json::json!({ "new": value(key, new) }),
+                    Some(ChangeKind::Removed(old)) => serde_json::json!({ "old": value(key, old) }),
+                    Some(ChangeKind::Modified(old, new)) => {
+                        serde_json::json!({ "old": value(key, old), "new": value(key, new) })
+                    }
+                };
+                cells.insert(target.clone(), cell);
+            }
+            (key.to_string(), cells.into())
+        })
+        .collect();
+    serde_json::json!({ "baseline": baseline, "targets": targets, "keys": keys })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{matrix_to_json, print_matrix};
+    use crate::ReportOptions;
+    use serde_yaml::Value;
+    use yml_diff::{DiffOptions, cmp_yml_vals};
+
+    #[test]
+    fn test_print_matrix() {
+        let yaml = |text: &str| serde_yaml::from_str::<Value>(text).unwrap();
+        let base = yaml("db: {host: localhost, pool: 5}\ndebug: true\n");
+        let dev = yaml("db: {host: localhost, pool: 5}\ndebug: true\n");
+        let prod = yaml("db: {host: db.prod, pool: 20}\ncache: on\n");
+        let opts = DiffOptions::default();
+        let diffs = vec![
+            cmp_yml_vals(&base, &dev, &opts),
+            cmp_yml_vals(&base, &prod, &opts),
+        ];
+        let targets = vec!["dev.yml".to_string(), "prod.yml".to_string()];
+
+        colored::control::set_override(false);
+        let mut out = Vec::new();
+        let report = ReportOptions {
+            quiet: true,
+            ..Default::default()
+        };
+        print_matrix(&mut out, "base.yml", &targets, &diffs, &opts, &report).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "键       基线       dev.yml  prod.yml\n\
+             cache    —          ·        + on\n\
+             db.host  localhost  ·        ~ db.prod\n\
+             db.pool  5          ·        ~ 20\n\
+             debug    true       ·        -\n"
+        );
+
+        let mut out = Vec::new();
+        let report = ReportOptions {
+            lang: crate::i18n::Lang::En,
+            ..report
+        };
+        print_matrix(&mut out, "base.yml", &targets, &diffs, &opts, &report).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(
+            text.starts_with("Key      Baseline   dev.yml  prod.yml\n"),
+            "{text}"
+        );
+
+        let json = matrix_to_json("base.yml", &targets, &diffs, &opts);
+        assert_eq!(json["keys"]["db.pool"]["baseline"], 5);
+        assert_eq!(json["keys"]["db.pool"]["prod.yml"]["new"], 20);
+        assert!(json["keys"]["db.pool"]["dev.yml"].is_null());
+    }
+}
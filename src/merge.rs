@@ -0,0 +1,234 @@
+use crate::apply::apply_diff;
+use crate::i18n::Msg;
+use crate::{ReportOptions, display_val};
+use colored::*;
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use yml_diff::{ChangeKind, ConfigDiff, ConfigKey, DiffOptions, cmp_yml_vals};
+
+/// 合并后的一处变更，以及它出现在哪些文件对中（下标从 0 开始）
+#[derive(Debug)]
+pub struct MergedChange<'a> {
+    pub kind: ChangeKind<'a>,
+    pub pairs: Vec<usize>,
+}
+
+/// 把多组差异按 (类型, 键, 值) 去重合并
+pub fn merge_diffs<'a>(diffs: &[ConfigDiff<'a>]) -> BTreeMap<ConfigKey, Vec<MergedChange<'a>>> {
+    let mut merged: BTreeMap<ConfigKey, Vec<MergedChange<'a>>> = BTreeMap::new();
+    for (i, diff) in diffs.iter().enumerate() {
+        let changes = diff
+            .added()
+            .iter()
+            .map(|(k, &v)| (k, ChangeKind::Added(v)))
+            .chain(
+                diff.removed()
+                    .iter()
+                    .map(|(k, &v)| (k, ChangeKind::Removed(v))),
+            )
+            .chain(
+                diff.modified()
+                    .iter()
+                    .map(|(k, &(old, new))| (k, ChangeKind::Modified(old, new))),
+            );
+
+        for (key, kind) in changes {
+            let entries = merged.entry(key.clone()).or_default();
+            match entries.iter_mut().find(|e| e.kind == kind) {
+                Some(entry) => entry.pairs.push(i),
+                None => entries.push(MergedChange {
+                    kind,
+                    pairs: vec![i],
+                }),
+            }
+        }
+    }
+    merged
+}
+
+pub fn print_merged(
+    out: &mut dyn Write,
+    labels: &[String],
+    diffs: &[ConfigDiff],
+    opts: &DiffOptions,
+    report: &ReportOptions,
+) -> io::Result<()> {
+    let merged = merge_diffs(diffs);
+    let total = labels.len();
+
+    let lang = report.lang;
+    writeln!(out, "{}", lang.get(Msg::MergedTitle).bold())?;
+    writeln!(out)?;
+    writeln!(out, "{}", lang.get(Msg::FilePairs).blue().bold())?;
+    for (i, label) in labels.iter().enumerate() {
+        writeln!(out, "  [{}] {label}", i + 1)?;
+    }
+    writeln!(out)?;
+
+    if merged.is_empty() {
+        writeln!(out, "{}", lang.get(Msg::NoDifferences).green())?;
+        return Ok(());
+    }
+
+    for (key, changes) in &merged {
+        let key = key.as_str();
+        for change in changes {
+            let pairs = (change.pairs.iter())
+                .map(|i| (i + 1).to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            let seen = lang
+                .format(Msg::SeenInPairs, &[&change.pairs.len(), &total, &pairs])
+                .dimmed();
+            match change.kind {
+                ChangeKind::Added(v) => {
+                    let line = format!("  + {key}: {}", display_val(key, v, opts, report));
+                    writeln!(out, "{} {seen}", line.green())?;
+                }
+                ChangeKind::Removed(v) => {
+                    let line = format!("  - {key}: {}", display_val(key, v, opts, report));
+                    writeln!(out, "{} {seen}", line.red())?;
+                }
+                ChangeKind::Modified(old, new) => {
+                    let line = format!(
+                        "  ~ {key}: {} → {}",
+                        display_val(key, old, opts, report),
+                        display_val(key, new, opts, report)
+                    );
+                    writeln!(out, "{} {seen}", line.yellow())?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// merge 子命令从新文件中取用的变更类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum MergeKind {
+    /// 只在新文件中的键
+    Added,
+    /// 两边都有但值不同的键，取新值
+    Modified,
+    /// 新文件中已经没有的键
+    Removed,
+}
+
+impl MergeKind {
+    fn matches(self, kind: &ChangeKind) -> bool {
+        matches!(
+            (self, kind),
+            (MergeKind::Added, ChangeKind::Added(_))
+                | (MergeKind::Modified, ChangeKind::Modified(..))
+                | (MergeKind::Removed, ChangeKind::Removed(_))
+        )
+    }
+}
+
+/// 以旧文档为基础，只把 `only` 中类型的变更从新文档合并进来
+///
+/// 返回合并结果、实际合并的变更，以及没有选择而未合并的变更数。
+pub fn merge_selected<'a>(
+    old: &'a Value,
+    new: &'a Value,
+    only: &[MergeKind],
+    opts: &DiffOptions,
+) -> (Value, ConfigDiff<'a>, usize) {
+    let mut diff = cmp_yml_vals(old, new, opts);
+    let total = diff.changes().count();
+    diff.retain(|change| only.iter().any(|kind| kind.matches(&change.kind)));
+    let skipped = total - diff.changes().count();
+    let merged = apply_diff(old, &diff, opts.flatten.index_style.is_some());
+    (merged, diff, skipped)
+}
+
+/// 列出 merge 子命令将要合并的变更
+pub fn print_preview(
+    out: &mut dyn Write,
+    diff: &ConfigDiff,
+    skipped: usize,
+    opts: &DiffOptions,
+    report: &ReportOptions,
+) -> io::Result<()> {
+    let lang = report.lang;
+    let count = diff.changes().count();
+    if count == 0 {
+        writeln!(out, "{}", lang.get(Msg::NothingToMerge).green())?;
+    } else {
+        writeln!(
+            out,
+            "{}",
+            lang.format(Msg::WillMerge, &[&count]).blue().bold()
+        )?;
+    }
+    for change in diff.changes() {
+        let key = change.key;
+        match change.kind {
+            ChangeKind::Added(v) => {
+                let line = format!("  + {key}: {}", display_val(key, v, opts, report));
+                writeln!(out, "{}", line.green())?;
+            }
+            ChangeKind::Removed(v) => {
+                let line = format!("  - {key}: {}", display_val(key, v, opts, report));
+                writeln!(out, "{}", line.red())?;
+            }
+            ChangeKind::Modified(old, new) => {
+                let line = format!(
+                    "  ~ {key}: {} → {}",
+                    display_val(key, old, opts, report),
+                    display_val(key, new, opts, report)
+                );
+                writeln!(out, "{}", line.yellow())?;
+            }
+        }
+    }
+    if skipped > 0 {
+        let note = lang.format(Msg::MergeSkipped, &[&skipped]);
+        writeln!(out, "{}", note.dimmed())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MergeKind, merge_diffs, merge_selected};
+    use crate::{DiffOptions, cmp_yml_vals};
+    use serde_yaml::Value;
+
+    #[test]
+    fn test_merge_deduplicates_identical_changes() {
+        let base: Value = serde_yaml::from_str("a: 1\nb: 1\n").unwrap();
+        let dev: Value = serde_yaml::from_str("a: 2\nb: 1\n").unwrap();
+        let prod: Value = serde_yaml::from_str("a: 2\nb: 3\n").unwrap();
+
+        let opts = DiffOptions::default();
+        let diffs = vec![
+            cmp_yml_vals(&base, &dev, &opts),
+            cmp_yml_vals(&base, &prod, &opts),
+        ];
+        let merged = merge_diffs(&diffs);
+
+        let a = &merged[&"a".into()];
+        assert_eq!(a.len(), 1);
+        assert_eq!(a[0].pairs, vec![0, 1]);
+        assert_eq!(merged[&"b".into()][0].pairs, vec![1]);
+    }
+
+    #[test]
+    fn test_merge_selected_changes() {
+        let old: Value = serde_yaml::from_str("a: 1\nb: 1\nc: 1\n").unwrap();
+        let new: Value = serde_yaml::from_str("a: 2\nc: 1\nd: {e: 1}\nlist: [x]\n").unwrap();
+        let opts = DiffOptions::default();
+        let yaml = |s: &str| serde_yaml::from_str::<Value>(s).unwrap();
+
+        let (merged, diff, skipped) = merge_selected(&old, &new, &[MergeKind::Added], &opts);
+        assert_eq!(merged, yaml("a: 1\nb: 1\nc: 1\nd: {e: 1}\nlist: [x]\n"));
+        assert_eq!((diff.added().len(), skipped), (2, 2));
+
+        let only = [MergeKind::Modified, MergeKind::Removed];
+        let (merged, _, skipped) = merge_selected(&old, &new, &only, &opts);
+        assert_eq!(merged, yaml("a: 2\nc: 1\n"));
+        assert_eq!(skipped, 2);
+    }
+}
@@ -0,0 +1,151 @@
+use crate::diff::ConfigKey;
+use anyhow::{Result, anyhow};
+use indexmap::IndexMap;
+use serde_yaml::Value;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// 已知的键改名表，例如框架升级时 `server.max_threads` 改为 `server.thread_pool.max`
+///
+/// 文件是 `旧键: 新键` 的映射，键的写法与报告中的扁平键相同：
+///
+/// ```yaml
+/// server.max_threads: server.thread_pool.max
+/// spring.redis: spring.data.redis
+/// ```
+///
+/// 旧键是一个子树时，其下的叶子按相对路径对应到新键下；一个键命中多条时取文件中靠前的一条。
+#[derive(Debug, Clone, Default)]
+pub struct KeyRenames {
+    renames: Vec<(String, String)>,
+}
+
+impl KeyRenames {
+    pub fn load(path: &Path) -> Result<Self> {
+        let reader = File::open(path)
+            .map(BufReader::new)
+            .map_err(|e| anyhow!("读取键改名表失败！{e}: {:?}", path))?;
+        let value: Value =
+            serde_yaml::from_reader(reader).map_err(|e| anyhow!("解析键改名表失败！{e}"))?;
+        Self::from_value(&value)
+    }
+
+    pub fn from_value(value: &Value) -> Result<Self> {
+        let map = match value {
+            Value::Mapping(map) => map,
+            Value::Null => return Ok(KeyRenames::default()),
+            _ => return Err(anyhow!("键改名表必须是一个映射")),
+        };
+        let mut renames = KeyRenames::default();
+        for (k, v) in map {
+            let (Some(from), Some(to)) = (k.as_str(), v.as_str()) else {
+                return Err(anyhow!("键改名表的键和值都必须是字符串: {k:?}"));
+            };
+            if from.is_empty() || to.is_empty() {
+                return Err(anyhow!("键改名表中不能有空键: {from:?} → {to:?}"));
+            }
+            renames.push(from, to);
+        }
+        Ok(renames)
+    }
+
+    pub fn push(&mut self, from: impl Into<String>, to: impl Into<String>) {
+        self.renames.push((from.into(), to.into()));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.renames.is_empty()
+    }
+
+    /// 旧文档中的扁平键按改名表在新文档中对应的键
+    fn target(&self, key: &str, separator: &str) -> Option<String> {
+        self.renames.iter().find_map(|(from, to)| {
+            let rest = key.strip_prefix(from.as_str())?;
+            (rest.is_empty() || rest.starts_with(separator) || rest.starts_with('['))
+                .then(|| format!("{to}{rest}"))
+        })
+    }
+}
+
+/// 按改名表配对的一对键，见 [`ConfigDiff::migrated`](crate::ConfigDiff::migrated)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Migration<'a> {
+    pub from: ConfigKey,
+    pub to: ConfigKey,
+    pub old: &'a Value,
+    pub new: &'a Value,
+    /// 按比较选项，迁移前后的值是否不同
+    pub changed: bool,
+}
+
+/// 在删除和新增的键之间找出改名表中的配对 (旧键, 新键)，按删除的键的顺序排列
+///
+/// 只有旧键仅在旧文档中、新键仅在新文档中时才配对；两边都有的键照常比较。
+pub(crate) fn pair_renamed_keys(
+    removed: &IndexMap<ConfigKey, &Value>,
+    added: &IndexMap<ConfigKey, &Value>,
+    renames: &KeyRenames,
+    separator: &str,
+) -> Vec<(ConfigKey, ConfigKey)> {
+    let mut used = HashSet::new();
+    removed
+        .keys()
+        .filter_map(|from| {
+            let target = renames.target(from.as_str(), separator)?;
+            let (to, _) = added.get_key_value(&ConfigKey::new(target, separator))?;
+            used.insert(to.as_str()).then(|| (from.clone(), to.clone()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KeyRenames;
+    use crate::{DiffOptions, cmp_yml_vals};
+    use serde_yaml::Value;
+
+    #[test]
+    fn test_migrations() {
+        let yaml = |s: &str| serde_yaml::from_str::<Value>(s).unwrap();
+        let renames = KeyRenames::from_value(&yaml(
+            "server.max_threads: server.thread_pool.max\nspring.redis: spring.data.redis\n",
+        ))
+        .unwrap();
+        let opts = DiffOptions {
+            key_renames: renames,
+            ..Default::default()
+        };
+        let old =
+            yaml("server: {max_threads: 200, port: 80}\nspring: {redis: {host: a, port: 6379}}\n");
+        let new = yaml(
+            "server: {thread_pool: {max: 100}, port: 80}\nspring: {data: {redis: {host: a, port: 6379}}}\nextra: 1\n",
+        );
+        let diff = cmp_yml_vals(&old, &new, &opts);
+        assert!(diff.removed().is_empty() && diff.modified().is_empty());
+        assert_eq!(diff.added().len(), 1);
+        let migrated: Vec<_> = diff
+            .migrated()
+            .iter()
+            .map(|m| (m.from.as_str(), m.to.as_str(), m.changed))
+            .collect();
+        assert_eq!(
+            migrated,
+            [
+                ("server.max_threads", "server.thread_pool.max", true),
+                ("spring.redis.host", "spring.data.redis.host", false),
+                ("spring.redis.port", "spring.data.redis.port", false),
+            ]
+        );
+
+        // 新键在旧文档中已经存在时不配对
+        let old = yaml("server: {max_threads: 1, thread_pool: {max: 1}}\n");
+        let new = yaml("server: {thread_pool: {max: 2}}\n");
+        let diff = cmp_yml_vals(&old, &new, &opts);
+        assert!(diff.migrated().is_empty());
+        assert_eq!((diff.removed().len(), diff.modified().len()), (1, 1));
+
+        assert!(KeyRenames::from_value(&yaml("a: [b]\n")).is_err());
+    }
+}
@@ -0,0 +1,93 @@
+use serde_yaml::{Mapping, Value};
+use std::cmp::Ordering;
+use yml_diff::ConfigKey;
+use yml_diff::flatten::{coerce_key, quote_key};
+
+const SEPARATOR: &str = ".";
+
+/// normalize 子命令：按差异报告中键的顺序（[`ConfigKey::hierarchical_cmp`]）重排所有映射的键
+///
+/// 同一映射中的键按完整的键路径比较，嵌套映射中的数字键按数值排序（`2` 在 `10` 之前）；
+/// 无法作为键路径的键（如映射作为键）保持原有的相对顺序，排在最后。序列的元素顺序有意义，不做改动。
+pub fn normalize(value: &mut Value) {
+    normalize_at(value, None);
+}
+
+fn normalize_at(value: &mut Value, path: Option<&ConfigKey>) {
+    match value {
+        Value::Mapping(map) => {
+            let mut entries: Vec<(Option<ConfigKey>, Value, Value)> = std::mem::take(map)
+                .into_iter()
+                .map(|(k, v)| (coerce_key(&k).map(|seg| child(path, &seg)), k, v))
+                .collect();
+            entries.sort_by(|(a, ..), (b, ..)| match (a, b) {
+                (Some(a), Some(b)) => a.hierarchical_cmp(b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            });
+            *map = entries
+                .into_iter()
+                .map(|(key, k, mut v)| {
+                    normalize_at(&mut v, key.as_ref().or(path));
+                    (k, v)
+                })
+                .collect::<Mapping>();
+        }
+        Value::Sequence(seq) => {
+            for (i, item) in seq.iter_mut().enumerate() {
+                normalize_at(item, Some(&child(path, &i.to_string())));
+            }
+        }
+        Value::Tagged(tagged) => normalize_at(&mut tagged.value, path),
+        _ => {}
+    }
+}
+
+fn child(path: Option<&ConfigKey>, segment: &str) -> ConfigKey {
+    let segment = quote_key(segment, SEPARATOR);
+    match path {
+        Some(path) => ConfigKey::new(format!("{}{SEPARATOR}{segment}", path.as_str()), SEPARATOR),
+        None => ConfigKey::new(segment, SEPARATOR),
+    }
+}
+
+/// 把各文档规范化后重新序列化，多个文档之间以 `---` 分隔
+pub fn to_string(docs: Vec<Value>) -> Result<String, serde_yaml::Error> {
+    let mut text = String::new();
+    for (i, mut doc) in docs.into_iter().enumerate() {
+        normalize(&mut doc);
+        if i > 0 {
+            text.push_str("---\n");
+        }
+        text.push_str(&serde_yaml::to_string(&doc)?);
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_string;
+    use serde_yaml::Value;
+
+    #[test]
+    fn test_normalize() {
+        let doc = |text: &str| serde_yaml::from_str::<Value>(text).unwrap();
+        let text = to_string(vec![
+            doc("server: {port: 80, host: a}\napp:\n  10: x\n  2: y\n  list: [{b: 1, a: 2}, c]\n"),
+            doc("{z: 1, y: 2}"),
+        ])
+        .unwrap();
+        assert_eq!(
+            text,
+            "app:\n  2: y\n  10: x\n  list:\n  - a: 2\n    b: 1\n  - c\nserver:\n  host: a\n  port: 80\n\
+             ---\ny: 2\nz: 1\n"
+        );
+        // 已经规范化的文件再次规范化不变
+        let again: Vec<Value> = text
+            .split("---\n")
+            .map(|part| serde_yaml::from_str(part).unwrap())
+            .collect();
+        assert_eq!(to_string(again).unwrap(), text);
+    }
+}
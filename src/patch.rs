@@ -0,0 +1,130 @@
+use serde_yaml::{Mapping, Value};
+use yml_diff::{ConfigDiff, ConfigKey, DiffOptions};
+
+/// 把差异转换成 RFC 7386 JSON Merge Patch 形式的 YAML 文档，把它合并到旧文档上即可得到新文档
+///
+/// 扁平键会还原成嵌套的映射：
+///
+/// - 新增和修改的键写出新值
+/// - 删除的键写作 `null`，这是 merge patch 表示删除的唯一方式；因此值本身为 null 的新增键
+///   在 patch 中与删除无法区分，合并后该键不会出现
+/// - merge patch 无法描述序列中的单个元素，序列内有任何变化时整个序列写出新值，
+///   新文件中不存在的序列写作 `null`
+/// - 命中 redact 规则的值写作 `***`，这样的 patch 不能直接用于重建新文件
+pub fn merge_patch(old: &Value, new: &Value, diff: &ConfigDiff, opts: &DiffOptions) -> Value {
+    let mut patch = Value::Mapping(Mapping::new());
+    let redacted = |key: &ConfigKey, val: Value| {
+        if opts.rules.is_redacted(key) {
+            Value::String("***".into())
+        } else {
+            val
+        }
+    };
+
+    // 先写删除，这样同一路径上后写入的新值会覆盖删除
+    for key in diff.removed().keys() {
+        let segments: Vec<&str> = key.segments().collect();
+        let (path, val) = patch_entry(old, new, &segments);
+        set_path(&mut patch, path, redacted(key, val));
+    }
+    for key in diff.added().keys().chain(diff.modified().keys()) {
+        let segments: Vec<&str> = key.segments().collect();
+        let (path, val) = patch_entry(old, new, &segments);
+        set_path(&mut patch, path, redacted(key, val));
+    }
+    patch
+}
+
+/// 找出一个扁平键在 patch 中的位置和值
+///
+/// 沿路径同时在两个文档中向下查找，任一边在途中遇到序列时，在序列所在的位置整体写入新文档中的序列。
+fn patch_entry<'s>(old: &Value, new: &Value, segments: &'s [&'s str]) -> (&'s [&'s str], Value) {
+    let (mut old_cur, mut new_cur) = (Some(old), Some(new));
+    for (i, seg) in segments.iter().enumerate() {
+        if [old_cur, new_cur]
+            .into_iter()
+            .flatten()
+            .any(Value::is_sequence)
+        {
+            return (&segments[..i], new_cur.cloned().unwrap_or(Value::Null));
+        }
+        old_cur = old_cur.and_then(|v| v.get(seg));
+        new_cur = new_cur.and_then(|v| v.get(seg));
+    }
+    (segments, new_cur.cloned().unwrap_or(Value::Null))
+}
+
+fn set_path(patch: &mut Value, path: &[&str], val: Value) {
+    let mut cur = patch;
+    for seg in path {
+        if !cur.is_mapping() {
+            *cur = Value::Mapping(Mapping::new());
+        }
+        let Value::Mapping(map) = cur else {
+            unreachable!()
+        };
+        cur = map
+            .entry(Value::String(seg.to_string()))
+            .or_insert(Value::Null);
+    }
+    *cur = val;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::merge_patch;
+    use serde_yaml::Value;
+    use yml_diff::{DiffOptions, cmp_yml_vals};
+
+    /// RFC 7386 的合并算法
+    fn apply(target: &Value, patch: &Value) -> Value {
+        let Value::Mapping(patch) = patch else {
+            return patch.clone();
+        };
+        let mut target = match target {
+            Value::Mapping(map) => map.clone(),
+            _ => Default::default(),
+        };
+        for (k, v) in patch {
+            if v.is_null() {
+                target.remove(k);
+            } else {
+                let merged = apply(target.get(k).unwrap_or(&Value::Null), v);
+                target.insert(k.clone(), merged);
+            }
+        }
+        Value::Mapping(target)
+    }
+
+    #[test]
+    fn test_merge_patch_rebuilds_new() {
+        let cases = [
+            (
+                "a: 1\nb:\n  c: 2\n  d: 3\n",
+                "a: 2\nb:\n  c: 2\ne:\n  f: [1]\n",
+            ),
+            ("a:\n  b: 1\n", "a: 3\n"),
+            ("a: 3\n", "a:\n  b: 1\n"),
+            ("s: [1, 2, 3]\nt: [x]\n", "s: [1, {k: v}]\n"),
+        ];
+        let opts = DiffOptions::default();
+        for (old, new) in cases {
+            let old: Value = serde_yaml::from_str(old).unwrap();
+            let new: Value = serde_yaml::from_str(new).unwrap();
+            let patch = merge_patch(&old, &new, &cmp_yml_vals(&old, &new, &opts), &opts);
+            assert_eq!(apply(&old, &patch), new, "{patch:?}");
+        }
+    }
+
+    #[test]
+    fn test_merge_patch_is_nested() {
+        let old: Value = serde_yaml::from_str("db:\n  host: a\n  user: x\n").unwrap();
+        let new: Value = serde_yaml::from_str("db:\n  host: b\n").unwrap();
+        let opts = DiffOptions::default();
+        let patch = merge_patch(&old, &new, &cmp_yml_vals(&old, &new, &opts), &opts);
+        assert_eq!(
+            patch,
+            serde_yaml::from_str::<Value>("db:\n  host: b\n  user: null\n").unwrap()
+        );
+    }
+}
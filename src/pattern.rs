@@ -0,0 +1,177 @@
+use regex::Regex;
+use std::fmt;
+use std::str::FromStr;
+
+/// 匹配扁平化键路径的 glob 模式
+///
+/// - `*` 匹配单个段内的任意字符（不跨越 `.`）
+/// - `**` 匹配任意字符，可以跨越 `.`
+/// - `?` 匹配单个段内的任意一个字符
+///
+/// 模式与完整的键路径进行匹配，例如 `*.password` 匹配 `db.password`，
+/// 但不匹配 `services.db.password`；后者需要写成 `**.password`。
+///
+/// 通过 [`FromStr`] 解析时，以 `re:` 开头的模式是正则表达式，在键路径中搜索匹配，
+/// 需要匹配整个键时用 `^` 和 `$` 锚定，例如 `re:^image\.(tag|digest)$`。
+#[derive(Debug, Clone)]
+pub struct KeyPattern {
+    raw: String,
+    matcher: Matcher,
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    Glob(Vec<Token>),
+    Regex(Regex),
+}
+
+/// 正则表达式模式的前缀
+const REGEX_PREFIX: &str = "re:";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token {
+    Literal(char),
+    AnyChar,
+    Star,
+    DoubleStar,
+}
+
+const SEPARATOR: char = '.';
+
+impl KeyPattern {
+    /// glob 模式，`raw` 总是按 glob 解释，不识别 `re:` 前缀
+    pub fn new(raw: &str) -> Self {
+        let mut tokens = Vec::new();
+        let mut chars = raw.chars().peekable();
+        while let Some(c) = chars.next() {
+            let token = match c {
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    Token::DoubleStar
+                }
+                '*' => Token::Star,
+                '?' => Token::AnyChar,
+                c => Token::Literal(c),
+            };
+            tokens.push(token);
+        }
+        KeyPattern {
+            raw: raw.to_string(),
+            matcher: Matcher::Glob(tokens),
+        }
+    }
+
+    /// 正则表达式模式
+    pub fn regex(re: &str) -> Result<Self, regex::Error> {
+        Ok(KeyPattern {
+            raw: format!("{REGEX_PREFIX}{re}"),
+            matcher: Matcher::Regex(Regex::new(re)?),
+        })
+    }
+
+    pub fn matches(&self, key: &str) -> bool {
+        match &self.matcher {
+            Matcher::Glob(tokens) => {
+                let chars: Vec<char> = key.chars().collect();
+                match_tokens(tokens, &chars)
+            }
+            Matcher::Regex(re) => re.is_match(key),
+        }
+    }
+}
+
+/// 两个模式的原文相同即相等
+impl PartialEq for KeyPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.raw == other.raw
+    }
+}
+
+fn match_tokens(tokens: &[Token], s: &[char]) -> bool {
+    match tokens.first() {
+        None => s.is_empty(),
+        Some(Token::Literal(c)) => s.first() == Some(c) && match_tokens(&tokens[1..], &s[1..]),
+        Some(Token::AnyChar) => {
+            matches!(s.first(), Some(&c) if c != SEPARATOR) && match_tokens(&tokens[1..], &s[1..])
+        }
+        Some(Token::Star) => {
+            for i in 0..=s.len() {
+                if match_tokens(&tokens[1..], &s[i..]) {
+                    return true;
+                }
+                // 单个 `*` 不能越过段分隔符
+                if s.get(i) == Some(&SEPARATOR) {
+                    break;
+                }
+            }
+            false
+        }
+        Some(Token::DoubleStar) => (0..=s.len()).any(|i| match_tokens(&tokens[1..], &s[i..])),
+    }
+}
+
+impl FromStr for KeyPattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix(REGEX_PREFIX) {
+            Some(re) => KeyPattern::regex(re).map_err(|e| format!("无效的正则表达式 {re:?}: {e}")),
+            None => Ok(KeyPattern::new(s)),
+        }
+    }
+}
+
+impl fmt::Display for KeyPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KeyPattern;
+
+    #[test]
+    fn test_single_star_stays_within_segment() {
+        let p = KeyPattern::new("*.password");
+        assert!(p.matches("db.password"));
+        assert!(!p.matches("services.db.password"));
+        assert!(!p.matches("password"));
+    }
+
+    #[test]
+    fn test_double_star_crosses_segments() {
+        let p = KeyPattern::new("**.password");
+        assert!(p.matches("db.password"));
+        assert!(p.matches("services.db.password"));
+
+        let p = KeyPattern::new("secrets.**");
+        assert!(p.matches("secrets.a"));
+        assert!(p.matches("secrets.a.b"));
+        assert!(!p.matches("app.secrets.a"));
+    }
+
+    #[test]
+    fn test_literal_and_any_char() {
+        assert!(KeyPattern::new("build.time").matches("build.time"));
+        assert!(!KeyPattern::new("build.time").matches("build.timestamp"));
+        assert!(KeyPattern::new("port?").matches("port1"));
+        assert!(!KeyPattern::new("a?b").matches("a.b"));
+    }
+
+    #[test]
+    fn test_regex_pattern() {
+        let p: KeyPattern = r"re:^(image\.tag|metadata\.timestamp)$".parse().unwrap();
+        assert!(p.matches("image.tag"));
+        assert!(p.matches("metadata.timestamp"));
+        assert!(!p.matches("image.tags"));
+        assert_eq!(p.to_string(), r"re:^(image\.tag|metadata\.timestamp)$");
+
+        // 不加锚点时在键路径中搜索
+        let p: KeyPattern = "re:time".parse().unwrap();
+        assert!(p.matches("build.timestamp"));
+
+        assert!("re:(".parse::<KeyPattern>().is_err());
+        assert!(!KeyPattern::new("re:a").matches("a"));
+    }
+}
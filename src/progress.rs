@@ -0,0 +1,223 @@
+use crate::i18n::{Msg, error_lang};
+use std::io::{self, IsTerminal, Read, Write};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// 两次重绘进度条之间的最短间隔，避免大文件逐块读取时不停地写终端
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// --progress：是否显示进度并统计各阶段的耗时
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// 各阶段累计的耗时，按阶段第一次出现的顺序排列
+static TIMINGS: Mutex<Vec<(Phase, Duration)>> = Mutex::new(Vec::new());
+
+/// 耗时统计中的阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// 读取、预处理并解析旧文件
+    ParseOld,
+    /// 读取、预处理并解析新文件
+    ParseNew,
+    Compare,
+    Render,
+}
+
+impl Phase {
+    fn label(self) -> &'static str {
+        error_lang().get(match self {
+            Phase::ParseOld => Msg::PhaseParseOld,
+            Phase::ParseNew => Msg::PhaseParseNew,
+            Phase::Compare => Msg::PhaseCompare,
+            Phase::Render => Msg::PhaseRender,
+        })
+    }
+}
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// 执行 `f` 并把耗时累加到 `phase` 上；未启用 --progress 时直接执行
+///
+/// 多组文件并行比较时累加的是各组之和，可能超过实际经过的时间。
+pub fn time<T>(phase: Phase, f: impl FnOnce() -> T) -> T {
+    if !is_enabled() {
+        return f();
+    }
+    let start = Instant::now();
+    let result = f();
+    let elapsed = start.elapsed();
+    let mut timings = TIMINGS.lock().unwrap_or_else(|e| e.into_inner());
+    match timings.iter_mut().find(|(p, _)| *p == phase) {
+        Some((_, total)) => *total += elapsed,
+        None => timings.push((phase, elapsed)),
+    }
+    result
+}
+
+/// 启用了 --progress 时在标准错误中输出各阶段的耗时
+pub fn finish() -> io::Result<()> {
+    if !is_enabled() {
+        return Ok(());
+    }
+    print_timings(&mut io::stderr().lock())
+}
+
+/// 输出各阶段的耗时；没有统计到任何阶段时不输出
+pub fn print_timings(out: &mut dyn Write) -> io::Result<()> {
+    let timings = TIMINGS.lock().unwrap_or_else(|e| e.into_inner());
+    if timings.is_empty() {
+        return Ok(());
+    }
+    let lang = error_lang();
+    writeln!(out, "{}", lang.get(Msg::Timings))?;
+    for (phase, elapsed) in timings.iter() {
+        writeln!(out, "  {}: {}", phase.label(), format_duration(*elapsed))?;
+    }
+    let total: Duration = timings.iter().map(|(_, elapsed)| *elapsed).sum();
+    let total = format_duration(total);
+    writeln!(out, "{}", lang.format(Msg::TimingsTotal, &[&total]))
+}
+
+fn format_duration(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs_f64();
+    if secs < 1.0 {
+        format!("{:.1} ms", secs * 1000.0)
+    } else {
+        format!("{secs:.2} s")
+    }
+}
+
+/// 读取时在标准错误中显示进度条的包装；标准错误不是终端时只透传数据
+pub struct ProgressReader<R> {
+    inner: R,
+    label: String,
+    /// 文件大小，读取管道等无法预知大小时为 `None`，只显示已读取的字节数
+    total: Option<u64>,
+    read: u64,
+    last_draw: Instant,
+    drawn: bool,
+    visible: bool,
+}
+
+impl<R: Read> ProgressReader<R> {
+    pub fn new(inner: R, label: impl Into<String>, total: Option<u64>) -> Self {
+        ProgressReader {
+            inner,
+            label: label.into(),
+            total,
+            read: 0,
+            last_draw: Instant::now(),
+            drawn: false,
+            visible: is_enabled() && io::stderr().is_terminal(),
+        }
+    }
+
+    /// 读取超过 [`REDRAW_INTERVAL`] 才开始显示，很快读完的小文件不会闪出进度条
+    fn draw(&mut self, done: bool) {
+        if done && !self.drawn || !done && self.last_draw.elapsed() < REDRAW_INTERVAL {
+            return;
+        }
+        self.last_draw = Instant::now();
+        self.drawn = true;
+        let mut err = io::stderr().lock();
+        let _ = if done {
+            // 读取结束后清掉进度条，不留在报告之前
+            write!(err, "\r\x1b[2K")
+        } else {
+            write!(
+                err,
+                "\r\x1b[2K{}",
+                progress_line(&self.label, self.read, self.total)
+            )
+        };
+        let _ = err.flush();
+    }
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if self.visible {
+            self.read += n as u64;
+            self.draw(n == 0 && !buf.is_empty());
+        }
+        Ok(n)
+    }
+}
+
+/// 进度条的一行文字，如 `读取 a.yml [########------------] 40% 12.0/30.0 MB`
+fn progress_line(label: &str, read: u64, total: Option<u64>) -> String {
+    const WIDTH: usize = 20;
+    let mb = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+    match total {
+        Some(total) if total > 0 => {
+            let ratio = (read as f64 / total as f64).min(1.0);
+            let filled = (ratio * WIDTH as f64) as usize;
+            let (done, todo) = ("#".repeat(filled), "-".repeat(WIDTH - filled));
+            let percent = format!("{:.0}", ratio * 100.0);
+            let (read, total) = (format!("{:.1}", mb(read)), format!("{:.1}", mb(total)));
+            error_lang().format(
+                Msg::ProgressBar,
+                &[&label, &done, &todo, &percent, &read, &total],
+            )
+        }
+        _ => {
+            let read = format!("{:.1}", mb(read));
+            error_lang().format(Msg::ProgressBytes, &[&label, &read])
+        }
+    }
+}
+
+/// 多组文件比较时已完成的组数，每完成一组在标准错误中刷新一次
+pub struct Counter {
+    total: usize,
+    done: AtomicUsize,
+    visible: bool,
+}
+
+impl Counter {
+    pub fn new(total: usize) -> Self {
+        Counter {
+            total,
+            done: AtomicUsize::new(0),
+            visible: is_enabled() && io::stderr().is_terminal(),
+        }
+    }
+
+    pub fn tick(&self) {
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        if !self.visible {
+            return;
+        }
+        let mut err = io::stderr().lock();
+        let _ = if done >= self.total {
+            write!(err, "\r\x1b[2K")
+        } else {
+            let line = error_lang().format(Msg::ProgressPairs, &[&done, &self.total]);
+            write!(err, "\r\x1b[2K{line}")
+        };
+        let _ = err.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::progress_line;
+
+    #[test]
+    fn test_progress_line() {
+        let mb = 1024 * 1024;
+        assert_eq!(
+            progress_line("a.yml", 3 * mb, Some(12 * mb)),
+            "读取 a.yml [#####---------------] 25% 3.0/12.0 MB"
+        );
+        assert_eq!(progress_line("-", mb / 2, None), "读取 - 0.5 MB");
+    }
+}
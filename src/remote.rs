@@ -0,0 +1,159 @@
+use crate::i18n::{Msg, error_lang};
+use anyhow::{Result, anyhow};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+use yml_diff::tags::decode_base64;
+
+/// 请求 `http(s)://` 地址时附加的请求头，每行一个 `名称: 值`，如 `Authorization: Bearer xxx`
+pub const HEADERS_ENV: &str = "YML_DIFF_HTTP_HEADERS";
+
+/// 不在本地磁盘上的输入
+#[derive(Debug, PartialEq, Eq)]
+pub enum Source<'a> {
+    /// 用 curl 下载的地址
+    Http(&'a str),
+    /// `k8s://命名空间/ConfigMap 名/键`，用 kubectl 从当前上下文的集群中读取
+    ConfigMap {
+        namespace: &'a str,
+        name: &'a str,
+        key: &'a str,
+    },
+}
+
+impl<'a> Source<'a> {
+    /// 识别 `http://`、`https://` 和 `k8s://` 开头的路径；其余路径返回 `None`，按本地文件读取
+    pub fn parse(path: &'a Path) -> Result<Option<Self>> {
+        let Some(path) = path.to_str() else {
+            return Ok(None);
+        };
+        if path.starts_with("http://") || path.starts_with("https://") {
+            return Ok(Some(Source::Http(path)));
+        }
+        let Some(rest) = path.strip_prefix("k8s://") else {
+            return Ok(None);
+        };
+        match rest.split('/').collect::<Vec<_>>()[..] {
+            [namespace, name, key]
+                if !namespace.is_empty() && !name.is_empty() && !key.is_empty() =>
+            {
+                Ok(Some(Source::ConfigMap {
+                    namespace,
+                    name,
+                    key,
+                }))
+            }
+            _ => Err(anyhow!(
+                error_lang().format(Msg::InvalidConfigMapUrl, &[&path])
+            )),
+        }
+    }
+
+    pub fn fetch(&self) -> Result<Vec<u8>> {
+        match *self {
+            Source::Http(url) => fetch_http(url),
+            Source::ConfigMap {
+                namespace,
+                name,
+                key,
+            } => fetch_config_map(namespace, name, key),
+        }
+    }
+}
+
+/// 请求头通过标准输入交给 curl（`-H @-`），令牌不会出现在进程列表中
+fn fetch_http(url: &str) -> Result<Vec<u8>> {
+    let headers = std::env::var(HEADERS_ENV).unwrap_or_default();
+    let mut command = Command::new("curl");
+    command.args(["--fail", "--silent", "--show-error", "--location"]);
+    command.args(["--proto", "=http,https"]);
+    if !headers.trim().is_empty() {
+        command.args(["--header", "@-"]);
+    }
+    let mut child = command
+        .arg("--")
+        .arg(url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow!(error_lang().format(Msg::RunCurlFailed, &[&e])))?;
+    let mut stdin = child.stdin.take().expect("已设置为管道");
+    let writer = thread::spawn(move || stdin.write_all(headers.as_bytes()));
+    let output = child
+        .wait_with_output()
+        .map_err(|e| anyhow!(error_lang().format(Msg::DownloadFailed, &[&e, &url])))?;
+    // curl 不读取标准输入时写入会失败，这不是错误
+    let _ = writer.join();
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!(
+            error_lang().format(Msg::DownloadFailed, &[&stderr.trim_end(), &url])
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// 读取 ConfigMap 中的一个键；`binaryData` 中的键先解码 base64
+fn fetch_config_map(namespace: &str, name: &str, key: &str) -> Result<Vec<u8>> {
+    let location = format!("k8s://{namespace}/{name}/{key}");
+    let output = Command::new("kubectl")
+        .args([
+            "get",
+            "configmap",
+            name,
+            "--namespace",
+            namespace,
+            "--output",
+            "json",
+        ])
+        .output()
+        .map_err(|e| anyhow!(error_lang().format(Msg::RunKubectlFailed, &[&e])))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!(error_lang().format(
+            Msg::ReadConfigMapFailed,
+            &[&stderr.trim_end(), &location]
+        )));
+    }
+    let map: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| anyhow!(error_lang().format(Msg::ParseKubectlFailed, &[&e, &location])))?;
+    if let Some(text) = map["data"][key].as_str() {
+        return Ok(text.as_bytes().to_vec());
+    }
+    if let Some(encoded) = map["binaryData"][key].as_str() {
+        return decode_base64(encoded)
+            .ok_or_else(|| anyhow!(error_lang().format(Msg::InvalidBase64, &[&location])));
+    }
+    Err(anyhow!(
+        error_lang().format(Msg::ConfigMapKeyMissing, &[&location])
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Source;
+    use std::path::Path;
+
+    #[test]
+    fn test_parse_source() {
+        let parse = |s: &'static str| Source::parse(Path::new(s));
+        assert_eq!(
+            parse("https://example.com/app.yml").unwrap(),
+            Some(Source::Http("https://example.com/app.yml"))
+        );
+        assert_eq!(
+            parse("k8s://prod/app-config/application.yml").unwrap(),
+            Some(Source::ConfigMap {
+                namespace: "prod",
+                name: "app-config",
+                key: "application.yml",
+            })
+        );
+        assert_eq!(parse("config/app.yml").unwrap(), None);
+        assert_eq!(parse("-").unwrap(), None);
+        assert!(parse("k8s://prod/app-config").is_err());
+        assert!(parse("k8s://prod//app.yml").is_err());
+    }
+}
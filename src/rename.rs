@@ -0,0 +1,165 @@
+use crate::diff::{ConfigDiff, ConfigKey, DiffOptions};
+use serde_yaml::Value;
+use std::collections::HashMap;
+
+/// 被判定为重命名的子树至少有这个比例的叶子（按两边较大的叶子数计）在新位置上值相同
+pub const RENAME_MIN_SIMILARITY: f64 = 0.75;
+
+/// 一个被重命名或移动的子树，例如 `db` → `database`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rename {
+    pub from: ConfigKey,
+    pub to: ConfigKey,
+    /// 随子树一起移动、值没有变化的叶子 (旧键, 新键)；其余叶子仍然是普通的删除和新增
+    pub moved: Vec<(ConfigKey, ConfigKey)>,
+}
+
+/// 在删除和新增的键之间找出被重命名的子树
+///
+/// 旧路径必须在新文档中已不存在，新路径也必须在旧文档中不存在；两棵子树中相对路径和值都相同的叶子
+/// 不少于 [`RENAME_MIN_SIMILARITY`] 时视为重命名。候选之间有嵌套时取层级最浅的一对，
+/// 同一棵子树最多参与一次重命名。只比较子树，单个叶子键的改名不在此列。
+pub fn find_renames(
+    diff: &ConfigDiff,
+    old: &Value,
+    new: &Value,
+    opts: &DiffOptions,
+) -> Vec<Rename> {
+    let separator = &opts.flatten.separator;
+    let removed = subtrees(diff.removed().iter().map(|(k, &v)| (k, v)), new);
+    let added = subtrees(diff.added().iter().map(|(k, &v)| (k, v)), old);
+
+    // (相对路径, 值) → 含有这个叶子的新子树，用来只比较至少有一个叶子相同的子树对
+    let mut index: HashMap<(&str, &Value), Vec<&str>> = HashMap::new();
+    for (prefix, leaves) in &added {
+        for &(suffix, val) in leaves {
+            index.entry((suffix, val)).or_default().push(prefix);
+        }
+    }
+
+    let mut candidates = Vec::new();
+    for (from, leaves) in &removed {
+        let mut matches: HashMap<&str, usize> = HashMap::new();
+        for &(suffix, val) in leaves {
+            for &to in index.get(&(suffix, val)).into_iter().flatten() {
+                *matches.entry(to).or_default() += 1;
+            }
+        }
+        for (to, matched) in matches {
+            let total = leaves.len().max(added[to].len());
+            if matched as f64 / total as f64 >= RENAME_MIN_SIMILARITY {
+                candidates.push((
+                    ConfigKey::new(*from, separator),
+                    ConfigKey::new(to, separator),
+                ));
+            }
+        }
+    }
+    // 层级浅的在前，同一层级按键排序，保证结果稳定
+    candidates
+        .sort_by(|(a, b), (c, d)| (a.segments().count(), a, b).cmp(&(c.segments().count(), c, d)));
+
+    let mut renames: Vec<Rename> = Vec::new();
+    for (from, to) in candidates {
+        let overlaps = renames
+            .iter()
+            .any(|r| nested(&r.from, &from, separator) || nested(&r.to, &to, separator));
+        if overlaps {
+            continue;
+        }
+        let new_leaves: HashMap<&str, &Value> = added[to.as_str()].iter().copied().collect();
+        let moved = removed[from.as_str()]
+            .iter()
+            .filter(|&&(suffix, val)| new_leaves.get(suffix) == Some(&val))
+            .map(|&(suffix, _)| {
+                (
+                    ConfigKey::new(format!("{}{suffix}", from.as_str()), separator),
+                    ConfigKey::new(format!("{}{suffix}", to.as_str()), separator),
+                )
+            })
+            .collect();
+        renames.push(Rename { from, to, moved });
+    }
+    renames
+}
+
+/// 把键按每个真前缀分组：前缀 → [(前缀之后的相对路径, 值)]；`other` 中仍然存在的前缀不算
+fn subtrees<'k, 'a>(
+    keys: impl Iterator<Item = (&'k ConfigKey, &'a Value)>,
+    other: &Value,
+) -> HashMap<&'k str, Vec<(&'k str, &'a Value)>> {
+    let mut groups: HashMap<&str, Vec<(&str, &Value)>> = HashMap::new();
+    for (key, val) in keys {
+        let segments: Vec<&str> = key.segments().collect();
+        for depth in 1..segments.len() {
+            let prefix = key.section(depth);
+            if value_at(other, &segments[..depth]).is_some() {
+                continue;
+            }
+            groups
+                .entry(prefix)
+                .or_default()
+                .push((&key[prefix.len()..], val));
+        }
+    }
+    groups
+}
+
+/// `a` 与 `b` 相同，或其中一个是另一个的祖先
+fn nested(a: &str, b: &str, separator: &str) -> bool {
+    let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    long.strip_prefix(short)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(separator) || rest.starts_with('['))
+}
+
+/// 按段沿路径查找文档中的值，数字段在序列中作为下标
+fn value_at<'v>(doc: &'v Value, segments: &[&str]) -> Option<&'v Value> {
+    segments.iter().try_fold(doc, |cur, seg| match cur {
+        Value::Sequence(seq) => seg.parse::<usize>().ok().and_then(|i| seq.get(i)),
+        _ => cur.get(seg),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diff::{DiffOptions, cmp_yml_vals};
+    use serde_yaml::Value;
+
+    fn renames(old: &str, new: &str) -> Vec<(String, String, usize)> {
+        let old: Value = serde_yaml::from_str(old).unwrap();
+        let new: Value = serde_yaml::from_str(new).unwrap();
+        let opts = DiffOptions {
+            detect_renames: true,
+            ..Default::default()
+        };
+        let diff = cmp_yml_vals(&old, &new, &opts);
+        diff.renamed()
+            .iter()
+            .map(|r| (r.from.to_string(), r.to.to_string(), r.moved.len()))
+            .collect()
+    }
+
+    #[test]
+    fn test_find_renames() {
+        // 整个子树改名，嵌套的 db.pool → database.pool 不再单独报告
+        assert_eq!(
+            renames(
+                "db:\n  host: a\n  port: 5432\n  pool: {min: 1, max: 8}\n",
+                "database:\n  host: a\n  port: 5432\n  pool: {min: 1, max: 8}\n"
+            ),
+            [("db".into(), "database".into(), 4)]
+        );
+        // 移到另一个父级下，且有一个值改变
+        assert_eq!(
+            renames(
+                "cache: {host: r, port: 6379, ttl: 60, db: 0}\n",
+                "infra:\n  cache: {host: r, port: 6379, ttl: 60, db: 1}\n"
+            ),
+            [("cache".into(), "infra.cache".into(), 3)]
+        );
+        // 相同的值不够多，不算重命名
+        assert!(renames("a: {x: 1, y: 2}\n", "b: {x: 1, y: 3}\n").is_empty());
+        // 旧路径在新文档中仍然存在
+        assert!(renames("a: {x: 1}\n", "a: 2\nb: {x: 1}\n").is_empty());
+    }
+}
@@ -0,0 +1,216 @@
+use crate::compare::ValueComparator;
+use crate::diff::{ConfigKey, DiffOptions, compare_flatten_options, values_equal};
+use crate::flatten::indexed_sequences;
+use serde_yaml::Value;
+use std::collections::HashMap;
+
+/// 两个序列的元素数之积超过这个值时不做对齐，按下标比较；对齐的开销与它成正比
+pub const MAX_ALIGNMENT_CELLS: usize = 1_000_000;
+
+/// 序列中内容不变、只是位置改变的元素，例如 `[a, b, c]` → `[c, a, b]` 中的 `c`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reorder<'a> {
+    /// 序列的扁平键，根节点本身是序列时为空
+    pub sequence: ConfigKey,
+    /// 元素在旧序列中的下标
+    pub from: usize,
+    /// 元素在新序列中的下标
+    pub to: usize,
+    pub value: &'a Value,
+}
+
+/// 同一路径上的新旧序列按元素内容对齐的结果
+#[derive(Debug)]
+pub(crate) struct Alignment<'a> {
+    pub sequence: String,
+    pub old: &'a [Value],
+    pub new: &'a [Value],
+    /// 每个旧元素配对的新下标
+    pub old_to_new: Vec<Option<usize>>,
+    /// 每个新元素配对的旧下标
+    pub new_to_old: Vec<Option<usize>>,
+    /// 不在公共子序列中、但在另一边找到了相同元素的配对 (旧下标, 新下标)
+    pub moves: Vec<(usize, usize)>,
+}
+
+impl Alignment<'_> {
+    /// 需要重新计算变更的下标：这个位置上至少一边的元素与另一个位置上的元素配对
+    pub fn shifted(&self) -> Vec<usize> {
+        let len = self.old.len().max(self.new.len());
+        (0..len)
+            .filter(|&i| {
+                let moved =
+                    |pair: Option<&Option<usize>>| pair.is_some_and(|&j| j.is_some_and(|j| j != i));
+                moved(self.old_to_new.get(i)) || moved(self.new_to_old.get(i))
+            })
+            .collect()
+    }
+}
+
+/// 找出两个文档中同一路径上、元素需要对齐的序列
+///
+/// 先用最长公共子序列对齐，公共子序列中的元素只是因为前面插入或删除了元素而改变下标，不算位置变化；
+/// 剩下的元素再在另一边找内容相同、尚未配对的元素，找到的作为 [`Reorder`]。
+/// 一个序列的元素被整体配对到别处之后，其内部的序列不再单独对齐。
+/// 元素都在原位、或元素数之积超过 [`MAX_ALIGNMENT_CELLS`] 的序列不在结果中。
+pub(crate) fn align_sequences<'a>(
+    old: &'a Value,
+    new: &'a Value,
+    opts: &DiffOptions,
+    cmp: &dyn ValueComparator,
+) -> Vec<Alignment<'a>> {
+    let flatten_opts = compare_flatten_options(opts);
+    let new_sequences: HashMap<String, &[Value]> =
+        indexed_sequences(new, &flatten_opts).into_iter().collect();
+    let separator = &opts.flatten.separator;
+    let mut shifted_prefixes: Vec<String> = Vec::new();
+    let mut alignments = Vec::new();
+    for (path, old_seq) in indexed_sequences(old, &flatten_opts) {
+        let Some(&new_seq) = new_sequences.get(&path) else {
+            continue;
+        };
+        if old_seq == new_seq
+            || old_seq.len() * new_seq.len() > MAX_ALIGNMENT_CELLS
+            || shifted_prefixes
+                .iter()
+                .any(|prefix| is_under(&path, prefix, separator))
+        {
+            continue;
+        }
+        let eq = |i: usize, j: usize| {
+            let key = flatten_opts.index_key(&path, j);
+            values_equal(&key, &old_seq[i], &new_seq[j], opts, cmp)
+        };
+        let (mut old_to_new, mut new_to_old) = lcs_pairs(old_seq.len(), new_seq.len(), eq);
+        let mut moves = Vec::new();
+        let unmatched: Vec<usize> = (0..new_seq.len())
+            .filter(|&j| new_to_old[j].is_none())
+            .collect();
+        for j in unmatched {
+            if let Some(i) = (0..old_seq.len()).find(|&i| old_to_new[i].is_none() && eq(i, j)) {
+                old_to_new[i] = Some(j);
+                new_to_old[j] = Some(i);
+                moves.push((i, j));
+            }
+        }
+        let alignment = Alignment {
+            sequence: path,
+            old: old_seq,
+            new: new_seq,
+            old_to_new,
+            new_to_old,
+            moves,
+        };
+        let shifted = alignment.shifted();
+        if shifted.is_empty() {
+            continue;
+        }
+        shifted_prefixes.extend(
+            shifted
+                .into_iter()
+                .map(|i| flatten_opts.index_key(&alignment.sequence, i)),
+        );
+        alignments.push(alignment);
+    }
+    alignments
+}
+
+/// 扁平键 `key` 是否就是 `prefix` 或位于它之下
+pub(crate) fn is_under(key: &str, prefix: &str, separator: &str) -> bool {
+    match key.strip_prefix(prefix) {
+        Some("") => true,
+        Some(rest) => prefix.is_empty() || rest.starts_with(separator) || rest.starts_with('['),
+        None => false,
+    }
+}
+
+/// 最长公共子序列中的元素配对，返回每个旧元素配对的新下标和每个新元素配对的旧下标
+fn lcs_pairs(
+    n: usize,
+    m: usize,
+    eq: impl Fn(usize, usize) -> bool,
+) -> (Vec<Option<usize>>, Vec<Option<usize>>) {
+    // lengths[i][j]：old[i..] 与 new[j..] 的最长公共子序列长度
+    let mut lengths = vec![vec![0usize; m + 1]; n + 1];
+    let mut equal = vec![vec![false; m]; n];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            equal[i][j] = eq(i, j);
+            lengths[i][j] = if equal[i][j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+    let (mut old_to_new, mut new_to_old) = (vec![None; n], vec![None; m]);
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if equal[i][j] {
+            old_to_new[i] = Some(j);
+            new_to_old[j] = Some(i);
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    (old_to_new, new_to_old)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ConfigKey, DiffOptions, cmp_yml_vals};
+    use serde_yaml::Value;
+
+    #[test]
+    fn test_reorders() {
+        let yaml = |s: &str| serde_yaml::from_str::<Value>(s).unwrap();
+        let opts = DiffOptions {
+            detect_reorders: true,
+            ..Default::default()
+        };
+        let keys =
+            |map: Vec<&ConfigKey>| map.into_iter().map(|k| k.to_string()).collect::<Vec<_>>();
+
+        // c 移到最前，a、b 只是随之后移
+        let (old, new) = (yaml("list: [a, b, c]\n"), yaml("list: [c, a, b]\n"));
+        let diff = cmp_yml_vals(&old, &new, &opts);
+        assert!(diff.added().is_empty() && diff.removed().is_empty() && diff.modified().is_empty());
+        let reorder = &diff.reordered()[0];
+        assert_eq!(
+            (reorder.sequence.as_str(), reorder.from, reorder.to),
+            ("list", 2, 0)
+        );
+        assert_eq!(reorder.value, &Value::from("c"));
+
+        // 插入和删除不再让后面的元素都显示为修改；原位替换的元素照常比较
+        let old = yaml("list: [{n: a, v: 1}, x, b, c]\n");
+        let new = yaml("list: [{n: a, v: 2}, y, z, b, c]\n");
+        let diff = cmp_yml_vals(&old, &new, &opts);
+        assert!(diff.reordered().is_empty());
+        assert_eq!(
+            keys(diff.modified().keys().collect()),
+            ["list[1]", "list[0].v"]
+        );
+        assert_eq!(keys(diff.added().keys().collect()), ["list[2]"]);
+        assert!(diff.removed().is_empty());
+
+        // 只是顺序变化的序列可以完全忽略
+        let (old, new) = (yaml("[{a: 1}, {b: 2}]\n"), yaml("[{b: 2}, {a: 1}]\n"));
+        let ignore = DiffOptions {
+            ignore_reorders: true,
+            ..Default::default()
+        };
+        assert!(cmp_yml_vals(&old, &new, &ignore).is_empty());
+        assert_eq!(cmp_yml_vals(&old, &new, &opts).reordered().len(), 1);
+        assert_eq!(
+            cmp_yml_vals(&old, &new, &DiffOptions::default())
+                .added()
+                .len(),
+            2
+        );
+    }
+}
@@ -0,0 +1,291 @@
+use crate::diff::{ChangeKind, ConfigDiff, DiffOptions, get_val_string};
+use serde::Serialize;
+use serde_yaml::Value;
+
+/// HTML 报告使用的样式；[`DiffReport::to_html`] 只生成片段，嵌入页面时另外引用这段样式
+pub const HTML_STYLE: &str = "\
+body { font-family: -apple-system, 'Segoe UI', 'PingFang SC', 'Microsoft YaHei', sans-serif; margin: 2em; }
+.stats span { margin-right: 1.5em; font-weight: bold; }
+ul.tree, ul.tree ul { list-style: none; padding-left: 1.2em; }
+ul.tree > li { margin: 0.1em 0; }
+summary { cursor: pointer; font-family: monospace; }
+.count { color: #888; font-family: sans-serif; font-size: 0.85em; }
+.location { color: #888; font-family: monospace; font-size: 0.85em; }
+.key { font-family: monospace; }
+code { padding: 0 0.3em; border-radius: 3px; white-space: pre-wrap; }
+.added, .added code { color: #1a7f37; }
+.added code { background: #dafbe1; }
+.removed, .removed code { color: #cf222e; }
+.removed code { background: #ffebe9; }
+.modified { color: #9a6700; }
+code.old { color: #cf222e; background: #ffebe9; text-decoration: line-through; }
+code.new { color: #1a7f37; background: #dafbe1; }
+";
+
+/// [`DiffReport::to_html`] 和 `diff_str` 中说明文字的语言
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Lang {
+    #[default]
+    Zh,
+    En,
+}
+
+impl Lang {
+    fn pick(self, zh: &'static str, en: &'static str) -> &'static str {
+        match self {
+            Lang::Zh => zh,
+            Lang::En => en,
+        }
+    }
+}
+
+/// 不依赖文件系统和终端的差异报告，值已转换为 JSON，被规则隐藏的值为 `***`
+///
+/// 变更按 [`ConfigDiff::changes`] 的顺序排列，可以直接序列化，也可以用 [`to_json`](Self::to_json)
+/// 和 [`to_html`](Self::to_html) 渲染成字符串。
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DiffReport {
+    pub changes: Vec<ReportChange>,
+    pub stats: ReportStats,
+}
+
+/// 报告中的一处变更；新增没有 `old`，删除没有 `new`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReportChange {
+    pub key: String,
+    pub kind: ReportKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ReportStats {
+    pub added: usize,
+    pub removed: usize,
+    pub modified: usize,
+}
+
+impl DiffReport {
+    pub fn new(diff: &ConfigDiff, opts: &DiffOptions) -> Self {
+        let value = |key: &str, val: &Value| {
+            if opts.rules.is_redacted(key) {
+                return serde_json::Value::String("***".into());
+            }
+            // 无法用 JSON 表示的值（如非字符串的映射键）退回显示用的字符串
+            serde_json::to_value(val).unwrap_or_else(|_| get_val_string(val).into_owned().into())
+        };
+        let changes = diff
+            .changes()
+            .map(|change| {
+                let key = change.key;
+                let (kind, old, new) = match change.kind {
+                    ChangeKind::Added(val) => (ReportKind::Added, None, Some(value(key, val))),
+                    ChangeKind::Removed(val) => (ReportKind::Removed, Some(value(key, val)), None),
+                    ChangeKind::Modified(old, new) => (
+                        ReportKind::Modified,
+                        Some(value(key, old)),
+                        Some(value(key, new)),
+                    ),
+                };
+                ReportChange {
+                    key: key.to_string(),
+                    kind,
+                    old,
+                    new,
+                }
+            })
+            .collect();
+        DiffReport {
+            changes,
+            stats: ReportStats {
+                added: diff.added().len(),
+                removed: diff.removed().len(),
+                modified: diff.modified().len(),
+            },
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("报告中只有字符串键和 JSON 值")
+    }
+
+    /// 可以嵌入页面的 HTML 片段：统计信息和逐行的变更列表，样式见 [`HTML_STYLE`]
+    pub fn to_html(&self, lang: Lang) -> String {
+        let code = |val: &Option<serde_json::Value>, class: &str| {
+            let text = match val {
+                Some(serde_json::Value::String(s)) => s.clone(),
+                Some(val) => val.to_string(),
+                None => String::new(),
+            };
+            format!("<code{class}>{}</code>", escape_html(&text))
+        };
+        let mut html = String::from("<div class=\"yml-diff\">\n");
+        html.push_str(&format!(
+            "<p class=\"stats\"><span class=\"added\">{} {}</span><span class=\"removed\">{} {}</span>\
+             <span class=\"modified\">{} {}</span></p>\n",
+            lang.pick("新增", "Added"),
+            self.stats.added,
+            lang.pick("删除", "Removed"),
+            self.stats.removed,
+            lang.pick("修改", "Modified"),
+            self.stats.modified
+        ));
+        if self.is_empty() {
+            let message = lang.pick("没有发现配置差异", "No configuration differences found");
+            html.push_str(&format!("<p class=\"added\">{message}</p>\n"));
+        } else {
+            html.push_str("<ul class=\"tree\">\n");
+            for change in &self.changes {
+                let (class, mark, detail) = match change.kind {
+                    ReportKind::Added => ("added", '+', code(&change.new, "")),
+                    ReportKind::Removed => ("removed", '-', code(&change.old, "")),
+                    ReportKind::Modified => (
+                        "modified",
+                        '~',
+                        format!(
+                            "{} → {}",
+                            code(&change.old, " class=\"old\""),
+                            code(&change.new, " class=\"new\"")
+                        ),
+                    ),
+                };
+                html.push_str(&format!(
+                    "<li class=\"{class}\">{mark} <span class=\"key\">{}</span>: {detail}</li>\n",
+                    escape_html(&change.key)
+                ));
+            }
+            html.push_str("</ul>\n");
+        }
+        html.push_str("</div>\n");
+        html
+    }
+}
+
+/// 比较两段 YAML（或 JSON）文本，不读写文件、不输出颜色，供编译到 wasm32 的页面调用
+///
+/// 两段文本都只能有一个文档，合并键 `<<` 会先展开；`lang` 是错误信息的语言。
+#[cfg(feature = "wasm")]
+pub fn diff_str(
+    old: &str,
+    new: &str,
+    options: &DiffOptions,
+    lang: Lang,
+) -> anyhow::Result<DiffReport> {
+    let parse = |text: &str, old: bool| -> anyhow::Result<Value> {
+        let side = match (lang, old) {
+            (Lang::Zh, true) => "旧",
+            (Lang::Zh, false) => "新",
+            (Lang::En, true) => "old",
+            (Lang::En, false) => "new",
+        };
+        let mut value: Value = serde_yaml::from_str(text).map_err(|e| match lang {
+            Lang::Zh => anyhow::anyhow!("解析{side}文本失败！{e}"),
+            Lang::En => anyhow::anyhow!("Failed to parse the {side} text! {e}"),
+        })?;
+        value.apply_merge().map_err(|e| match lang {
+            Lang::Zh => anyhow::anyhow!("展开{side}文本的合并键失败！{e}"),
+            Lang::En => anyhow::anyhow!("Failed to expand merge keys in the {side} text! {e}"),
+        })?;
+        Ok(value)
+    };
+    let (old, new) = (parse(old, true)?, parse(new, false)?);
+    let diff = crate::cmp_yml_vals(&old, &new, options);
+    Ok(DiffReport::new(&diff, options))
+}
+
+/// 转义 HTML 特殊字符，值和键名都可能包含 `<`、`&` 和引号
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DiffReport, Lang, ReportKind};
+    use crate::rules::RuleAction;
+    use crate::{DiffOptions, cmp_yml_vals};
+    use serde_yaml::Value;
+
+    #[test]
+    fn test_diff_report() {
+        let yaml = |s: &str| serde_yaml::from_str::<Value>(s).unwrap();
+        let (old, new) = (
+            yaml("db: {host: a, password: x}\nx: '<b>'\n"),
+            yaml("db: {host: b, password: y}\nport: 80\n"),
+        );
+        let mut opts = DiffOptions::default();
+        opts.rules
+            .push("*.password".parse().unwrap(), RuleAction::Redact);
+        let report = DiffReport::new(&cmp_yml_vals(&old, &new, &opts), &opts);
+
+        let kinds: Vec<_> = (report.changes.iter())
+            .map(|c| (c.key.as_str(), c.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                ("db.host", ReportKind::Modified),
+                ("db.password", ReportKind::Modified),
+                ("port", ReportKind::Added),
+                ("x", ReportKind::Removed),
+            ]
+        );
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(
+            json["changes"][1],
+            serde_json::json!({"key": "db.password", "kind": "modified", "old": "***", "new": "***"})
+        );
+        assert_eq!(json["stats"]["modified"], 2);
+        let html = report.to_html(Lang::Zh);
+        assert!(html.contains(
+            "<li class=\"removed\">- <span class=\"key\">x</span>: <code>&lt;b&gt;</code></li>"
+        ));
+        assert!(html.contains("<span class=\"added\">新增 1</span>"));
+        let html = report.to_html(Lang::En);
+        assert!(
+            html.contains("<span class=\"added\">Added 1</span>"),
+            "{html}"
+        );
+        assert!(
+            DiffReport::default()
+                .to_html(Lang::En)
+                .contains("No configuration differences")
+        );
+
+        #[cfg(feature = "wasm")]
+        {
+            let opts = DiffOptions::default();
+            let report = super::diff_str("a: 1\n", "{\"a\": 2}", &opts, Lang::Zh).unwrap();
+            assert_eq!(report.stats.modified, 1);
+            let err = super::diff_str("a: [", "", &opts, Lang::En).unwrap_err();
+            assert!(
+                err.to_string().starts_with("Failed to parse the old text!"),
+                "{err}"
+            );
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use crate::i18n::Msg;
+use crate::{ReportOptions, display_val};
+use colored::*;
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Write};
+use yml_diff::{ChangeKind, ConfigDiff, ConfigKey, DiffOptions};
+
+/// 审阅时对一处变更的决定
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Accepted,
+    Rejected,
+}
+
+/// --interactive：在终端中逐处审阅变更，与 `git add -p` 类似
+///
+/// 变更按顶层段分组，进入新的一段时显示段名和其中的变更数量；每处变更显示旧值和新值，
+/// 从 `input` 逐行读取命令。输入结束时视同 `q`。返回做出了决定的键，没有决定的键不在其中。
+pub fn review(
+    input: &mut dyn BufRead,
+    out: &mut dyn Write,
+    diff: &ConfigDiff,
+    opts: &DiffOptions,
+    report: &ReportOptions,
+) -> io::Result<BTreeMap<String, Decision>> {
+    let changes: Vec<_> = diff.changes().collect();
+    let sections: Vec<String> = changes
+        .iter()
+        .map(|change| {
+            let key = ConfigKey::new(change.key, &opts.flatten.separator);
+            key.section(1).to_string()
+        })
+        .collect();
+    let lang = report.lang;
+    let mut decisions = BTreeMap::new();
+    let mut i = 0;
+    let mut line = String::new();
+    while i < changes.len() {
+        let change = &changes[i];
+        if i == 0 || sections[i] != sections[i - 1] {
+            let count = sections.iter().filter(|s| **s == sections[i]).count();
+            let title = lang.format(Msg::ReviewSection, &[&sections[i], &count]);
+            writeln!(out, "{}", title.bold())?;
+        }
+        let value = |val| display_val(change.key, val, opts, report);
+        let state = match decisions.get(change.key) {
+            Some(Decision::Accepted) => lang.get(Msg::ReviewAccepted).green(),
+            Some(Decision::Rejected) => lang.get(Msg::ReviewRejected).red(),
+            None => "".normal(),
+        };
+        writeln!(
+            out,
+            "[{}/{}] {}{state}",
+            i + 1,
+            changes.len(),
+            change.key.bold()
+        )?;
+        match change.kind {
+            ChangeKind::Added(new) => print_value(out, "+", &value(new), Color::Green)?,
+            ChangeKind::Removed(old) => print_value(out, "-", &value(old), Color::Red)?,
+            ChangeKind::Modified(old, new) => {
+                print_value(out, "-", &value(old), Color::Red)?;
+                print_value(out, "+", &value(new), Color::Green)?;
+            }
+        }
+
+        loop {
+            write!(out, "{}", lang.get(Msg::ReviewPrompt).cyan())?;
+            out.flush()?;
+            line.clear();
+            if input.read_line(&mut line)? == 0 {
+                writeln!(out)?;
+                i = changes.len();
+                break;
+            }
+            let section = |j: &usize| sections[*j] == sections[i];
+            i = match line.trim() {
+                "y" => {
+                    decisions.insert(change.key.to_string(), Decision::Accepted);
+                    i + 1
+                }
+                "n" => {
+                    decisions.insert(change.key.to_string(), Decision::Rejected);
+                    i + 1
+                }
+                "s" | "" => i + 1,
+                command @ ("a" | "d") => {
+                    let decision = if command == "a" {
+                        Decision::Accepted
+                    } else {
+                        Decision::Rejected
+                    };
+                    let rest: Vec<usize> = (i..changes.len()).take_while(section).collect();
+                    for &j in &rest {
+                        decisions.insert(changes[j].key.to_string(), decision);
+                    }
+                    i + rest.len()
+                }
+                "p" if i == 0 => {
+                    writeln!(out, "{}", lang.get(Msg::ReviewFirst))?;
+                    continue;
+                }
+                "p" => i - 1,
+                "q" => changes.len(),
+                "?" => {
+                    writeln!(out, "{}", lang.get(Msg::ReviewHelp))?;
+                    continue;
+                }
+                other => {
+                    writeln!(out, "{}", lang.format(Msg::ReviewUnknown, &[&other]))?;
+                    continue;
+                }
+            };
+            break;
+        }
+    }
+
+    let accepted = decisions
+        .values()
+        .filter(|&&d| d == Decision::Accepted)
+        .count();
+    let (rejected, pending) = (decisions.len() - accepted, changes.len() - decisions.len());
+    writeln!(
+        out,
+        "{}",
+        lang.format(Msg::ReviewDone, &[&accepted, &rejected, &pending])
+    )?;
+    Ok(decisions)
+}
+
+/// 逐行输出一个值，多行的值每行都带前缀
+fn print_value(out: &mut dyn Write, sign: &str, text: &str, color: Color) -> io::Result<()> {
+    for line in text.lines() {
+        writeln!(out, "  {}", format!("{sign} {line}").color(color))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Decision, review};
+    use crate::ReportOptions;
+    use serde_yaml::Value;
+    use yml_diff::{DiffOptions, cmp_yml_vals};
+
+    #[test]
+    fn test_review() {
+        let old: Value =
+            serde_yaml::from_str("db: {host: a, port: 80, user: u}\nname: x\nmode: m\n").unwrap();
+        let new: Value =
+            serde_yaml::from_str("db: {host: b, port: 81, user: v}\nname: y\nmode: n\n").unwrap();
+        let opts = DiffOptions::default();
+        let diff = cmp_yml_vals(&old, &new, &opts);
+
+        colored::control::set_override(false);
+        // db.host 先拒绝再回来接受，db 段其余部分整体拒绝，mode 跳过，name 输入结束时未决定
+        let mut input = "n\np\n?\ny\nd\n\n".as_bytes();
+        let mut out = Vec::new();
+        let decisions = review(
+            &mut input,
+            &mut out,
+            &diff,
+            &opts,
+            &ReportOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(decisions.len(), 3);
+        assert_eq!(decisions["db.host"], Decision::Accepted);
+        assert_eq!(decisions["db.port"], Decision::Rejected);
+        assert_eq!(decisions["db.user"], Decision::Rejected);
+
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("── db (3 处) ──\n[1/5] db.host\n  - a\n  + b\n"));
+        assert!(out.contains("[1/5] db.host [已拒绝]\n"));
+        assert!(out.contains("── mode (1 处) ──\n[4/5] mode\n"));
+        assert!(out.ends_with("已接受 1 处，已拒绝 2 处，未审阅 2 处\n"));
+    }
+}
@@ -0,0 +1,219 @@
+use crate::pattern::KeyPattern;
+use anyhow::{Result, anyhow};
+use serde_yaml::Value;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::str::FromStr;
+
+/// 规则文件中针对某一类键的比较行为
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleAction {
+    /// 完全忽略该键，不出现在任何差异中
+    Ignore,
+    /// 照常比较，但输出时隐藏具体值
+    Redact,
+    /// 数值在给定绝对误差内视为相等
+    FloatTolerance(f64),
+    /// 序列按多重集比较，忽略元素顺序
+    Unordered,
+    /// 该键发生变更时的严重程度，决定报告中的分组和进程的退出码
+    Severity(Severity),
+}
+
+/// 变更的严重程度，按 info < warn < error 排序
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Info,
+    Warn,
+    Error,
+}
+
+impl Severity {
+    /// 从高到低排列的所有级别
+    pub const ALL: [Severity; 3] = [Severity::Error, Severity::Warn, Severity::Info];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warn => "warn",
+            Severity::Error => "error",
+        }
+    }
+}
+
+impl FromStr for Severity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "info" => Ok(Severity::Info),
+            "warn" | "warning" => Ok(Severity::Warn),
+            "error" => Ok(Severity::Error),
+            other => Err(anyhow!(
+                "未知的严重程度: {other:?}（可选 info / warn / error）"
+            )),
+        }
+    }
+}
+
+impl RuleAction {
+    fn parse(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if let Some(tolerance) = s.strip_prefix("float-tolerance=") {
+            let tolerance: f64 = tolerance
+                .trim()
+                .parse()
+                .map_err(|e| anyhow!("无效的浮点误差 {tolerance:?}: {e}"))?;
+            return Ok(RuleAction::FloatTolerance(tolerance));
+        }
+        if let Some(severity) = s.strip_prefix("severity=") {
+            return severity.parse().map(RuleAction::Severity);
+        }
+        match s {
+            "ignore" => Ok(RuleAction::Ignore),
+            "redact" => Ok(RuleAction::Redact),
+            "unordered" => Ok(RuleAction::Unordered),
+            "info" | "warn" | "error" => s.parse().map(RuleAction::Severity),
+            other => Err(anyhow!("未知的规则行为: {other:?}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    pattern: KeyPattern,
+    actions: Vec<RuleAction>,
+}
+
+/// 从规则文件加载的一组「键模式 → 比较行为」映射
+///
+/// 规则文件是一个 YAML 映射，键为 glob 模式，值为单个行为或行为列表：
+///
+/// ```yaml
+/// "*.password": redact
+/// "timeouts.*": float-tolerance=0.01
+/// hosts: unordered
+/// build.time: ignore
+/// "secrets.**": [redact, unordered]
+/// "security.**": severity=error
+/// logging.level: info
+/// ```
+///
+/// 一个键可以同时命中多条规则，所有命中规则的行为都会生效；命中多个严重程度时取最高的。
+#[derive(Debug, Clone, Default)]
+pub struct Rules {
+    rules: Vec<Rule>,
+}
+
+impl Rules {
+    pub fn load(path: &Path) -> Result<Self> {
+        let reader = File::open(path)
+            .map(BufReader::new)
+            .map_err(|e| anyhow!("读取规则文件失败！{e}: {:?}", path))?;
+        let value: Value =
+            serde_yaml::from_reader(reader).map_err(|e| anyhow!("解析规则文件失败！{e}"))?;
+        Self::from_value(&value)
+    }
+
+    pub fn from_value(value: &Value) -> Result<Self> {
+        let map = match value {
+            Value::Mapping(map) => map,
+            Value::Null => return Ok(Rules::default()),
+            _ => return Err(anyhow!("规则文件必须是一个映射")),
+        };
+
+        let mut rules = Vec::with_capacity(map.len());
+        for (k, v) in map {
+            let pattern = k
+                .as_str()
+                .ok_or_else(|| anyhow!("规则的键必须是字符串: {k:?}"))?;
+            let actions = match v {
+                Value::String(s) => vec![RuleAction::parse(s)?],
+                Value::Sequence(seq) => seq
+                    .iter()
+                    .map(|a| {
+                        a.as_str()
+                            .ok_or_else(|| anyhow!("规则 {pattern:?} 的行为必须是字符串"))
+                            .and_then(RuleAction::parse)
+                    })
+                    .collect::<Result<_>>()?,
+                _ => return Err(anyhow!("规则 {pattern:?} 的行为必须是字符串或字符串列表")),
+            };
+            rules.push(Rule {
+                pattern: pattern.parse().map_err(|e: String| anyhow!("{e}"))?,
+                actions,
+            });
+        }
+
+        Ok(Rules { rules })
+    }
+
+    /// 追加一条规则，例如命令行 `--ignore` 指定的模式
+    pub fn push(&mut self, pattern: KeyPattern, action: RuleAction) {
+        self.rules.push(Rule {
+            pattern,
+            actions: vec![action],
+        });
+    }
+
+    /// 追加另一组规则，例如配置文件中的规则之后再加上 `--rules` 指定的规则文件
+    pub fn extend(&mut self, other: Rules) {
+        self.rules.extend(other.rules);
+    }
+
+    fn actions_for<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a RuleAction> {
+        self.rules
+            .iter()
+            .filter(move |r| r.pattern.matches(key))
+            .flat_map(|r| &r.actions)
+    }
+
+    pub fn is_ignored(&self, key: &str) -> bool {
+        self.actions_for(key).any(|a| *a == RuleAction::Ignore)
+    }
+
+    pub fn is_redacted(&self, key: &str) -> bool {
+        self.actions_for(key).any(|a| *a == RuleAction::Redact)
+    }
+
+    pub fn is_unordered(&self, key: &str) -> bool {
+        self.actions_for(key).any(|a| *a == RuleAction::Unordered)
+    }
+
+    /// 带有 unordered 行为的所有模式
+    pub fn unordered_patterns(&self) -> impl Iterator<Item = KeyPattern> + '_ {
+        self.rules
+            .iter()
+            .filter(|r| r.actions.contains(&RuleAction::Unordered))
+            .map(|r| r.pattern.clone())
+    }
+
+    /// 多条规则都指定误差时，取最大的那个
+    pub fn tolerance(&self, key: &str) -> Option<f64> {
+        self.actions_for(key)
+            .filter_map(|a| match a {
+                RuleAction::FloatTolerance(t) => Some(*t),
+                _ => None,
+            })
+            .reduce(f64::max)
+    }
+
+    /// 键发生变更时的严重程度，多条规则都指定时取最高的
+    pub fn severity(&self, key: &str) -> Option<Severity> {
+        self.actions_for(key)
+            .filter_map(|a| match a {
+                RuleAction::Severity(s) => Some(*s),
+                _ => None,
+            })
+            .max()
+    }
+
+    /// 是否有规则指定了严重程度
+    pub fn has_severities(&self) -> bool {
+        self.rules
+            .iter()
+            .flat_map(|r| &r.actions)
+            .any(|a| matches!(a, RuleAction::Severity(_)))
+    }
+}
@@ -0,0 +1,185 @@
+use crate::i18n::{Lang, Msg, error_lang};
+use anyhow::{Result, anyhow};
+use colored::*;
+use jsonschema::error::ValidationErrorKind;
+use serde_yaml::Value;
+use std::io::{self, Write};
+
+/// 一条 schema 违规：出错位置（JSON Pointer）、类别和说明
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub path: String,
+    pub kind: ViolationKind,
+    pub message: String,
+}
+
+/// 迁移时需要特别注意的几类违规
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViolationKind {
+    /// 缺少 `required` 中的键，`path` 指向缺少的键
+    Required,
+    /// 值的类型不符合 `type`
+    Type,
+    /// `additionalProperties` 不允许的键，`path` 指向多出的键
+    NotAllowed,
+    Other,
+}
+
+impl ViolationKind {
+    fn describe(self, lang: Lang) -> &'static str {
+        lang.get(match self {
+            ViolationKind::Required => Msg::SchemaRequired,
+            ViolationKind::Type => Msg::SchemaType,
+            ViolationKind::NotAllowed => Msg::SchemaNotAllowed,
+            ViolationKind::Other => Msg::SchemaOther,
+        })
+    }
+}
+
+/// 用 JSON Schema 校验一个 YAML 值
+///
+/// schema 本身可以用 JSON 或 YAML 书写；两者都先转换成 JSON 再校验，
+/// 带标签的 YAML 值会按 serde_yaml 的序列化方式变成 `{"!tag": value}`。
+pub fn validate(schema: &Value, instance: &Value) -> Result<Vec<Violation>> {
+    let schema = to_json(schema)?;
+    let instance = to_json(instance)?;
+    let validator = jsonschema::validator_for(&schema)
+        .map_err(|e| anyhow!(error_lang().format(Msg::InvalidSchema, &[&e])))?;
+
+    let mut violations = Vec::new();
+    for e in validator.iter_errors(&instance) {
+        let path = e.instance_path().to_string();
+        let message = e.to_string();
+        match e.kind() {
+            ValidationErrorKind::Required { property } => violations.push(Violation {
+                path: child_path(&path, property.as_str().unwrap_or_default()),
+                kind: ViolationKind::Required,
+                message,
+            }),
+            ValidationErrorKind::AdditionalProperties { unexpected } => {
+                violations.extend(unexpected.iter().map(|key| Violation {
+                    path: child_path(&path, key),
+                    kind: ViolationKind::NotAllowed,
+                    message: message.clone(),
+                }))
+            }
+            ValidationErrorKind::Type { .. } => violations.push(Violation {
+                path,
+                kind: ViolationKind::Type,
+                message,
+            }),
+            _ => violations.push(Violation {
+                path,
+                kind: ViolationKind::Other,
+                message,
+            }),
+        }
+    }
+    Ok(violations)
+}
+
+/// JSON Pointer 中的子路径，键中的 `~` 和 `/` 按 RFC 6901 转义
+fn child_path(parent: &str, key: &str) -> String {
+    format!("{parent}/{}", key.replace('~', "~0").replace('/', "~1"))
+}
+
+/// --schema：新文件中有、旧文件中没有的违规，即这次修改引入的破坏
+///
+/// 旧文件中已经存在的违规不算在内，这样 schema 收紧之前遗留的问题不会让每次比较都失败。
+pub fn breaking_changes(old: &[Violation], new: &[Violation]) -> Vec<Violation> {
+    new.iter().filter(|v| !old.contains(v)).cloned().collect()
+}
+
+pub fn print_breaking_changes(
+    out: &mut dyn Write,
+    breaking: &[Violation],
+    lang: Lang,
+) -> io::Result<()> {
+    writeln!(out, "{}", lang.get(Msg::BreakingSection).blue().bold())?;
+    if breaking.is_empty() {
+        writeln!(out, "  {}", lang.get(Msg::NoBreaking).green())?;
+    }
+    for v in breaking {
+        let path = if v.path.is_empty() { "/" } else { &v.path };
+        writeln!(
+            out,
+            "  ! {} [{}]: {}",
+            path.red(),
+            v.kind.describe(lang),
+            v.message
+        )?;
+    }
+    writeln!(out)
+}
+
+fn to_json(value: &Value) -> Result<serde_json::Value> {
+    serde_json::to_value(value)
+        .map_err(|e| anyhow!(error_lang().format(Msg::YamlToJsonFailed, &[&e])))
+}
+
+pub fn print_violations(
+    out: &mut dyn Write,
+    violations: &[Violation],
+    lang: Lang,
+) -> io::Result<()> {
+    writeln!(out, "{}", lang.get(Msg::SchemaSection).blue().bold())?;
+    if violations.is_empty() {
+        writeln!(out, "  {}", lang.get(Msg::SchemaValid).green())?;
+    }
+    for v in violations {
+        let path = if v.path.is_empty() { "/" } else { &v.path };
+        writeln!(out, "  ! {}: {}", path.red(), v.message)?;
+    }
+    writeln!(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ViolationKind, breaking_changes, validate};
+    use serde_yaml::Value;
+
+    #[test]
+    fn test_validate_reports_violations() {
+        let schema: Value = serde_yaml::from_str(
+            "type: object\nrequired: [port]\nproperties:\n  port:\n    type: integer\n",
+        )
+        .unwrap();
+
+        let ok: Value = serde_yaml::from_str("port: 80\n").unwrap();
+        assert!(validate(&schema, &ok).unwrap().is_empty());
+
+        let bad: Value = serde_yaml::from_str("port: \"80\"\n").unwrap();
+        let violations = validate(&schema, &bad).unwrap();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].path, "/port");
+    }
+
+    #[test]
+    fn test_breaking_changes() {
+        let schema: Value = serde_yaml::from_str(
+            "type: object\nrequired: [db]\nadditionalProperties: false\nproperties:\n  \
+               db:\n    type: object\n    required: [host, port]\n    \
+               properties:\n      port: {type: integer}\n  name: {type: string}\n",
+        )
+        .unwrap();
+        // 旧文件中已经缺少 host，不算这次引入的破坏
+        let old: Value = serde_yaml::from_str("db: {port: 80}\nname: a\n").unwrap();
+        let new: Value = serde_yaml::from_str("db: {}\nname: 1\ncache: on\n").unwrap();
+        let old = validate(&schema, &old).unwrap();
+        let new = validate(&schema, &new).unwrap();
+
+        let mut breaking: Vec<_> = breaking_changes(&old, &new)
+            .into_iter()
+            .map(|v| (v.path, v.kind))
+            .collect();
+        breaking.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            breaking,
+            vec![
+                ("/cache".to_string(), ViolationKind::NotAllowed),
+                ("/db/port".to_string(), ViolationKind::Required),
+                ("/name".to_string(), ViolationKind::Type),
+            ]
+        );
+    }
+}
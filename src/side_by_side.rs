@@ -0,0 +1,193 @@
+use crate::i18n::Msg;
+use crate::{ReportOptions, change_lines, display_val, source_location};
+use colored::*;
+use std::env;
+use std::io::{self, Write};
+use yml_diff::{ChangeKind, ConfigDiff, DiffOptions};
+
+/// 无法得知终端宽度（例如输出被重定向且没有设置 COLUMNS）时使用的总宽度
+pub const DEFAULT_WIDTH: usize = 120;
+
+/// 每一栏至少这么宽，终端过窄时宁可超出终端宽度
+const MIN_COLUMN: usize = 20;
+
+/// 左右两栏之间的标记列：` | ` 修改、` < ` 删除、` > ` 新增
+const GUTTER: usize = 3;
+
+/// 报告的总宽度：优先使用 COLUMNS 环境变量，其次查询标准输出所在的终端
+pub fn terminal_width() -> usize {
+    env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .filter(|&width| width > 0)
+        .or_else(query_width)
+        .unwrap_or(DEFAULT_WIDTH)
+}
+
+#[cfg(unix)]
+fn query_width() -> Option<usize> {
+    // SAFETY: TIOCGWINSZ 只向传入的 winsize 结构写入终端尺寸；标准输出不是终端时返回错误
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let ok = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0;
+    (ok && size.ws_col > 0).then_some(usize::from(size.ws_col))
+}
+
+#[cfg(not(unix))]
+fn query_width() -> Option<usize> {
+    None
+}
+
+/// --format side-by-side：像 `diff -y` 一样左栏是旧值、右栏是新值，每处变更一行，
+/// 超出栏宽的内容折到下一行
+pub fn print_side_by_side(
+    out: &mut dyn Write,
+    diff: &ConfigDiff,
+    opts: &DiffOptions,
+    report: &ReportOptions,
+) -> io::Result<()> {
+    let width = report.width.unwrap_or(DEFAULT_WIDTH);
+    let column = (width.saturating_sub(GUTTER) / 2).max(MIN_COLUMN);
+
+    if !report.quiet {
+        let (old, new) = match &report.labels {
+            Some((old, new)) => (old.as_str(), new.as_str()),
+            None => (report.lang.get(Msg::OldSide), report.lang.get(Msg::NewSide)),
+        };
+        let header = format!("{}   {new}", pad(old, column));
+        writeln!(out, "{}", header.trim_end().bold())?;
+        writeln!(out, "{}", "─".repeat(column * 2 + GUTTER))?;
+    }
+
+    for change in diff.changes() {
+        // --line-numbers 时每一栏的开头是该侧文件中的位置
+        let (old_line, new_line) = change_lines(change.key, &change.kind, opts, report);
+        let entry = |val, location: Option<String>| {
+            let val = display_val(change.key, val, opts, report);
+            let entry = format!("{}: {}", change.key, val.replace('\n', "↵"));
+            match location {
+                Some(location) => format!("{location} {entry}"),
+                None => entry,
+            }
+        };
+        let old_location = || source_location(old_line, None, report);
+        let new_location = || source_location(None, new_line, report);
+        let (left, mark, right, color) = match change.kind {
+            ChangeKind::Added(val) => {
+                (String::new(), '>', entry(val, new_location()), Color::Green)
+            }
+            ChangeKind::Removed(val) => {
+                (entry(val, old_location()), '<', String::new(), Color::Red)
+            }
+            ChangeKind::Modified(old, new) => (
+                entry(old, old_location()),
+                '|',
+                entry(new, new_location()),
+                Color::Yellow,
+            ),
+        };
+        let (left, right) = (wrap(&left, column), wrap(&right, column));
+        for row in 0..left.len().max(right.len()) {
+            let l = left.get(row).map_or("", String::as_str);
+            let r = right.get(row).map_or("", String::as_str);
+            let mark = if row == 0 { mark } else { ' ' };
+            let line = if r.is_empty() {
+                format!("{} {mark}", pad(l, column))
+            } else {
+                format!("{} {mark} {r}", pad(l, column))
+            };
+            writeln!(out, "{}", line.trim_end().color(color))?;
+        }
+    }
+
+    if diff.is_empty() && !report.quiet {
+        writeln!(out, "{}", report.lang.get(Msg::NoDifferences).green())?;
+    }
+    Ok(())
+}
+
+/// 字符在等宽终端中占的列数：东亚宽字符和全角字符占两列
+fn char_width(c: char) -> usize {
+    match u32::from(c) {
+        0x1100..=0x115F
+        | 0x2E80..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1FAFF
+        | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+pub fn text_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+/// 在右侧补空格到 `width` 列
+pub fn pad(text: &str, width: usize) -> String {
+    format!(
+        "{text}{}",
+        " ".repeat(width.saturating_sub(text_width(text)))
+    )
+}
+
+/// 按列宽把文本切成若干行；空文本没有任何行
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let w = char_width(c);
+        if used + w > width && !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+            used = 0;
+        }
+        line.push(c);
+        used += w;
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{print_side_by_side, wrap};
+    use crate::ReportOptions;
+    use serde_yaml::Value;
+    use yml_diff::{DiffOptions, cmp_yml_vals};
+
+    #[test]
+    fn test_print_side_by_side() {
+        assert_eq!(wrap("abcdef", 4), ["abcd", "ef"]);
+        assert_eq!(wrap("中文ab", 3), ["中", "文a", "b"]);
+        assert!(wrap("", 4).is_empty());
+
+        let old: Value =
+            serde_yaml::from_str("a: 1\nb: x\nlong: abcdefghijklmnopqrstuvwxyz\n").unwrap();
+        let new: Value =
+            serde_yaml::from_str("a: 2\nc: y\nlong: abcdefghijklmnopqrstuvwxyz!\n").unwrap();
+        let opts = DiffOptions::default();
+        let diff = cmp_yml_vals(&old, &new, &opts);
+
+        colored::control::set_override(false);
+        let mut out = Vec::new();
+        let report = ReportOptions {
+            quiet: true,
+            width: Some(43),
+            ..Default::default()
+        };
+        print_side_by_side(&mut out, &diff, &opts, &report).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "a: 1                 | a: 2\n\
+             b: x                 <\n\
+             \x20                    > c: y\n\
+             long: abcdefghijklmn | long: abcdefghijklmn\n\
+             opqrstuvwxyz           opqrstuvwxyz!\n"
+        );
+    }
+}
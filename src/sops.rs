@@ -0,0 +1,123 @@
+use crate::format::InputFormat;
+use crate::i18n::{Msg, error_lang};
+use anyhow::{Result, anyhow};
+use regex::Regex;
+use serde_yaml::Value;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::sync::LazyLock;
+
+/// SOPS 加密后的值：`ENC[AES256_GCM,data:...,iv:...,tag:...,type:str]`
+static ENCRYPTED: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^ENC\[AES256_GCM,data:[^,\]]*,iv:[^,\]]*,tag:[^,\]]*,type:([a-z]+)\]$")
+        .expect("合法的正则表达式")
+});
+
+/// SOPS 写在文档顶层的元数据块
+pub const METADATA_KEY: &str = "sops";
+
+/// --sops 处理加密文件的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SopsMode {
+    /// 去掉元数据块，密文统一显示为 `ENC[类型]`，只比较键和未加密的值
+    Mask,
+    /// 先用 sops 命令解密再比较
+    Decrypt,
+}
+
+/// 去掉 SOPS 的元数据块，并把每个密文替换成 `ENC[类型]`
+///
+/// 每次加密的随机数不同，同一个明文重新加密后密文也不同；替换之后重新加密不再产生差异，
+/// 但加密的值被修改同样无法发现。只有带 `mac` 字段的 `sops` 映射才被当作元数据，
+/// 避免误删名为 `sops` 的普通配置。
+pub fn mask_encrypted(doc: &mut Value) {
+    if let Value::Mapping(map) = doc
+        && map
+            .get(METADATA_KEY)
+            .is_some_and(|meta| meta.get("mac").is_some())
+    {
+        map.remove(METADATA_KEY);
+    }
+    mask_values(doc);
+}
+
+fn mask_values(value: &mut Value) {
+    match value {
+        Value::String(s) => {
+            if let Some(caps) = ENCRYPTED.captures(s) {
+                *s = format!("ENC[{}]", &caps[1]);
+            }
+        }
+        Value::Mapping(map) => map.values_mut().for_each(mask_values),
+        Value::Sequence(seq) => seq.iter_mut().for_each(mask_values),
+        Value::Tagged(tagged) => mask_values(&mut tagged.value),
+        _ => {}
+    }
+}
+
+/// 用 sops 命令解密文件内容，密钥的查找（age、PGP、KMS 等）完全交给 sops
+///
+/// 内容可能来自 git 的某个提交或经过了 --transform，因此先写入临时文件再解密；临时文件中只有密文。
+pub fn decrypt(path: &Path, bytes: &[u8], format: InputFormat) -> Result<Vec<u8>> {
+    let kind = match format {
+        InputFormat::Yaml => "yaml",
+        InputFormat::Json => "json",
+        InputFormat::Toml => {
+            let path = format!("{path:?}");
+            return Err(anyhow!(error_lang().format(Msg::SopsToml, &[&path])));
+        }
+    };
+    let temp = std::env::temp_dir().join(format!(
+        "yml-diff-sops-{}-{}.{kind}",
+        std::process::id(),
+        path.file_name().unwrap_or_default().to_string_lossy()
+    ));
+    fs::write(&temp, bytes).map_err(|e| {
+        let temp = format!("{temp:?}");
+        anyhow!(error_lang().format(Msg::WriteTempFailed, &[&e, &temp]))
+    })?;
+    let output = Command::new("sops")
+        .args(["--decrypt", "--input-type", kind, "--output-type", kind])
+        .arg(&temp)
+        .output();
+    let _ = fs::remove_file(&temp);
+    let output = output.map_err(|e| anyhow!(error_lang().format(Msg::RunSopsFailed, &[&e])))?;
+    if !output.status.success() {
+        let (path, stderr) = (format!("{path:?}"), String::from_utf8_lossy(&output.stderr));
+        return Err(anyhow!(
+            error_lang().format(Msg::SopsDecryptFailed, &[&path, &stderr.trim_end()])
+        ));
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mask_encrypted;
+    use serde_yaml::Value;
+
+    #[test]
+    fn test_mask_encrypted() {
+        let encrypted = |data: &str| {
+            let mut doc: Value = serde_yaml::from_str(&format!(
+                "db:\n  password: ENC[AES256_GCM,data:{data},iv:aXY=,tag:dGFn,type:str]\n  port: 5432\n\
+                 sops:\n  mac: ENC[AES256_GCM,data:bWFj,iv:aXY=,tag:dGFn,type:str]\n  version: 3.8.1\n"
+            ))
+            .unwrap();
+            mask_encrypted(&mut doc);
+            doc
+        };
+        let doc = encrypted("c2VjcmV0");
+        assert_eq!(doc["db"]["password"], "ENC[str]");
+        assert_eq!(doc["db"]["port"], 5432);
+        assert!(doc.get("sops").is_none());
+        // 重新加密只改变密文，替换后两边相同
+        assert_eq!(doc, encrypted("b3RoZXI="));
+
+        // 没有 mac 的 `sops` 是普通配置
+        let mut plain: Value = serde_yaml::from_str("sops: {enabled: true}\n").unwrap();
+        mask_encrypted(&mut plain);
+        assert_eq!(plain["sops"]["enabled"], true);
+    }
+}
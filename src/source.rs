@@ -0,0 +1,131 @@
+use yml_diff::ConfigKey;
+
+/// 在 YAML 源文本中启发式地查找扁平键定义所在的行（从 0 开始）
+///
+/// 逐段查找 `段名:` 形式的行，后一段只在前一段的缩进块内查找。限制：
+///
+/// - 同一个块内重复出现的键只会命中第一次出现的位置
+/// - 序列下标段无法定位，结果停留在序列所在的键上
+/// - flow 风格（`{a: 1}`）、锚点引用展开出的键以及多文档文件都无法准确定位
+pub fn locate_key(source: &str, key: &ConfigKey) -> Option<usize> {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut start = 0;
+    let mut parent_indent: Option<usize> = None;
+    let mut found = None;
+
+    for segment in key.segments() {
+        if segment.parse::<usize>().is_ok() {
+            continue;
+        }
+
+        let mut hit = None;
+        for (i, line) in lines.iter().enumerate().skip(start) {
+            let (indent, content) = split_indent(line);
+            if content.is_empty() || content.starts_with('#') {
+                continue;
+            }
+            // 缩进回到父级或更外层，说明已经离开父级的块
+            if parent_indent.is_some_and(|p| indent <= p) {
+                break;
+            }
+            if is_key_line(content, segment) {
+                hit = Some((i, indent));
+                break;
+            }
+        }
+
+        let (i, indent) = hit?;
+        found = Some(i);
+        start = i + 1;
+        parent_indent = Some(indent);
+    }
+
+    found
+}
+
+/// 返回键所在行以及紧挨在它上方的注释行，行号从 1 开始
+pub fn snippet(source: &str, line: usize) -> Vec<(usize, &str)> {
+    let lines: Vec<&str> = source.lines().collect();
+    let Some(&target) = lines.get(line) else {
+        return Vec::new();
+    };
+
+    let first = lines[..line]
+        .iter()
+        .rposition(|l| !l.trim_start().starts_with('#'))
+        .map_or(0, |i| i + 1);
+
+    lines[first..line]
+        .iter()
+        .enumerate()
+        .map(|(i, l)| (first + i + 1, *l))
+        .chain(std::iter::once((line + 1, target)))
+        .collect()
+}
+
+/// 拆出缩进宽度和内容；序列项的 `- ` 前缀计入缩进
+fn split_indent(line: &str) -> (usize, &str) {
+    let mut content = line.trim_start();
+    let mut indent = line.len() - content.len();
+    while let Some(rest) = content.strip_prefix("- ") {
+        let rest_trimmed = rest.trim_start();
+        indent += content.len() - rest_trimmed.len();
+        content = rest_trimmed;
+    }
+    (indent, content)
+}
+
+fn is_key_line(content: &str, segment: &str) -> bool {
+    let rest = [
+        content.strip_prefix(segment),
+        content
+            .strip_prefix('"')
+            .and_then(|c| c.strip_prefix(segment))
+            .and_then(|c| c.strip_prefix('"')),
+        content
+            .strip_prefix('\'')
+            .and_then(|c| c.strip_prefix(segment))
+            .and_then(|c| c.strip_prefix('\'')),
+    ];
+    rest.into_iter()
+        .flatten()
+        .filter_map(|r| r.strip_prefix(':'))
+        .any(|r| r.is_empty() || r.starts_with(char::is_whitespace))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{locate_key, snippet};
+
+    const SOURCE: &str = "\
+app:
+  name: demo
+db:
+  # 连接信息
+  name: main
+  credentials:
+    password: secret
+servers:
+  - host: a
+";
+
+    #[test]
+    fn test_locate_nested_key() {
+        assert_eq!(locate_key(SOURCE, &"app.name".into()), Some(1));
+        assert_eq!(locate_key(SOURCE, &"db.name".into()), Some(4));
+        assert_eq!(
+            locate_key(SOURCE, &"db.credentials.password".into()),
+            Some(6)
+        );
+        assert_eq!(locate_key(SOURCE, &"servers[0].host".into()), Some(8));
+        assert_eq!(locate_key(SOURCE, &"app.password".into()), None);
+    }
+
+    #[test]
+    fn test_snippet_includes_leading_comments() {
+        assert_eq!(
+            snippet(SOURCE, 4),
+            vec![(4, "  # 连接信息"), (5, "  name: main")]
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use crate::i18n::{Msg, error_lang};
+use anyhow::{Result, anyhow};
+use serde_yaml::{Mapping, Value};
+use std::fs;
+use std::path::Path;
+use yml_diff::{ConfigDiff, DiffOptions};
+
+/// 把三类变更分别写入目录下的 `added.yml`、`removed.yml` 和 `modified.yml`
+///
+/// 每个文件都是以扁平键为键的映射：新增和删除的键对应其值，修改的键对应
+/// `{old: 旧值, new: 新值}`。命中 redact 规则的值写作 `***`。
+pub fn write_split_output(dir: &Path, diff: &ConfigDiff, opts: &DiffOptions) -> Result<()> {
+    fs::create_dir_all(dir).map_err(|e| {
+        let dir = format!("{dir:?}");
+        anyhow!(error_lang().format(Msg::CreateDirFailed, &[&e, &dir]))
+    })?;
+
+    let value = |key: &str, val: &Value| {
+        if opts.rules.is_redacted(key) {
+            Value::String("***".into())
+        } else {
+            val.clone()
+        }
+    };
+
+    let added: Mapping = diff
+        .added()
+        .iter()
+        .map(|(k, &v)| (Value::String(k.to_string()), value(k, v)))
+        .collect();
+    let removed: Mapping = diff
+        .removed()
+        .iter()
+        .map(|(k, &v)| (Value::String(k.to_string()), value(k, v)))
+        .collect();
+    let modified: Mapping = diff
+        .modified()
+        .iter()
+        .map(|(k, &(old, new))| {
+            let mut change = Mapping::new();
+            change.insert("old".into(), value(k, old));
+            change.insert("new".into(), value(k, new));
+            (Value::String(k.to_string()), Value::Mapping(change))
+        })
+        .collect();
+
+    for (name, changes) in [
+        ("added.yml", added),
+        ("removed.yml", removed),
+        ("modified.yml", modified),
+    ] {
+        let path = dir.join(name);
+        let text = serde_yaml::to_string(&changes)
+            .map_err(|e| anyhow!(error_lang().format(Msg::SerializeDiffFailed, &[&e])))?;
+        fs::write(&path, text).map_err(|e| {
+            let path = format!("{path:?}");
+            anyhow!(error_lang().format(Msg::WriteDiffFileFailed, &[&e, &path]))
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write_split_output;
+    use crate::{DiffOptions, cmp_yml_vals};
+    use serde_yaml::Value;
+    use std::fs;
+
+    #[test]
+    fn test_split_output_writes_three_files() {
+        let old: Value = serde_yaml::from_str("a: 1\nb: 1\n").unwrap();
+        let new: Value = serde_yaml::from_str("a: 2\nc: 1\n").unwrap();
+        let opts = DiffOptions::default();
+        let diff = cmp_yml_vals(&old, &new, &opts);
+
+        let dir = std::env::temp_dir().join(format!("yml-diff-split-{}", std::process::id()));
+        write_split_output(&dir.join("out"), &diff, &opts).unwrap();
+
+        let read = |name: &str| -> Value {
+            serde_yaml::from_str(&fs::read_to_string(dir.join("out").join(name)).unwrap()).unwrap()
+        };
+        assert_eq!(
+            read("added.yml"),
+            serde_yaml::from_str::<Value>("c: 1").unwrap()
+        );
+        assert_eq!(
+            read("removed.yml"),
+            serde_yaml::from_str::<Value>("b: 1").unwrap()
+        );
+        assert_eq!(
+            read("modified.yml"),
+            serde_yaml::from_str::<Value>("a: {old: 1, new: 2}").unwrap()
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
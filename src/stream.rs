@@ -0,0 +1,316 @@
+//! 不构建完整文档树的扁平化，用于几百 MB 的生成文件
+//!
+//! [`flatten_reader`] 在反序列化的同时展开键，只保留叶子值和扁平键，不保留嵌套的 [`Value`] 树；
+//! [`cmp_flat_entries`](crate::diff::cmp_flat_entries) 再把两份按键排序的条目归并比较，
+//! 不需要 [`extract_key_vals`](crate::extract_key_vals) 那样为每个键建立哈希表。
+//!
+//! 这不是流式比较：峰值内存约为两个文件的扁平条目之和，比完整的值树加哈希表小一个常数倍，
+//! 但仍与文件大小成正比，而不是与差异大小成正比。serde_yaml 在反序列化前会读入整个输入并缓存
+//! 一个文档的全部事件，即使把条目分批写到临时文件再归并，这部分内存也无法避免。
+
+use crate::flatten::{FlattenOptions, IndexStyle, coerce_key, quote_key};
+use serde::Deserialize;
+use serde::de::value::{EnumAccessDeserializer, SeqAccessDeserializer};
+use serde::de::{self, DeserializeSeed, EnumAccess, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde_yaml::Value;
+use std::collections::HashSet;
+use std::fmt;
+use std::io::Read;
+
+/// 从 `reader` 读取一个 YAML 文档，直接展开成 `(扁平键, 叶子值)`，按文档顺序返回
+///
+/// 展开方式与 [`flatten`](crate::flatten::flatten) 相同，但不支持 `list_keys`，序列总是按下标展开。
+/// 别名在解析时替换成锚点处的值，合并键 `<<` 不展开，作为普通的键。
+/// 空文档没有任何条目；包含多个文档时返回错误。
+pub fn flatten_reader(
+    reader: impl Read,
+    opts: &FlattenOptions,
+) -> Result<Vec<(String, Value)>, StreamError> {
+    let mut sink = Sink::default();
+    let mut documents = serde_yaml::Deserializer::from_reader(reader);
+    if let Some(document) = documents.next() {
+        let node = Node {
+            key: String::new(),
+            depth: 0,
+            opts,
+            sink: &mut sink,
+        };
+        if let Err(e) = node.deserialize(document) {
+            return Err(match sink.conflict {
+                Some(key) => StreamError::CoercedKeyConflict(key),
+                None => StreamError::Yaml(e),
+            });
+        }
+    }
+    if documents.next().is_some() {
+        return Err(StreamError::MultipleDocuments);
+    }
+    Ok(sink.entries)
+}
+
+/// [`flatten_reader`] 的错误；除 YAML 本身的错误外只给出结构化的原因，说明文字由调用方决定
+#[derive(Debug)]
+pub enum StreamError {
+    Yaml(serde_yaml::Error),
+    /// 输入包含多个文档
+    MultipleDocuments,
+    /// 设置了 `coerce_key_types` 后同一映射中重名的键（扁平键），见
+    /// [`coerced_key_conflict`](crate::flatten::coerced_key_conflict)
+    CoercedKeyConflict(String),
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StreamError::Yaml(e) => e.fmt(f),
+            StreamError::MultipleDocuments => f.write_str("只支持单个 YAML 文档"),
+            StreamError::CoercedKeyConflict(key) => write!(f, "规范化后重名的键: {key}"),
+        }
+    }
+}
+
+impl std::error::Error for StreamError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StreamError::Yaml(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// 展开的结果；`conflict` 记下重名的键，反序列化随即以错误结束
+#[derive(Default)]
+struct Sink {
+    entries: Vec<(String, Value)>,
+    conflict: Option<String>,
+}
+
+/// 文档中的一个节点；根节点的 `key` 为空，其子节点的深度为 0
+struct Node<'s, 'o> {
+    key: String,
+    depth: usize,
+    opts: &'o FlattenOptions,
+    sink: &'s mut Sink,
+}
+
+impl Node<'_, '_> {
+    fn is_root(&self) -> bool {
+        self.key.is_empty()
+    }
+
+    /// 与 `flatten` 相同：超过最大深度或命中 opaque 的子树整体作为一个值
+    fn is_expandable(&self) -> bool {
+        self.is_root()
+            || (self.opts.max_depth.is_none_or(|max| self.depth < max)
+                && !self.opts.opaque.iter().any(|p| p.matches(&self.key)))
+    }
+
+    fn child(&mut self, key: String) -> Node<'_, '_> {
+        Node {
+            depth: if self.is_root() { 0 } else { self.depth + 1 },
+            key,
+            opts: self.opts,
+            sink: self.sink,
+        }
+    }
+
+    fn join(&self, key: &str) -> String {
+        let key = quote_key(key, &self.opts.separator);
+        if self.is_root() {
+            key.into_owned()
+        } else {
+            format!("{}{}{key}", self.key, self.opts.separator)
+        }
+    }
+
+    fn index(&self, i: usize) -> String {
+        match self.opts.index_style {
+            Some(IndexStyle::Bracket) => format!("{}[{i}]", self.key),
+            _ => self.join(&i.to_string()),
+        }
+    }
+
+    /// 叶子值；根节点本身不是映射或序列时不产生任何键
+    fn leaf(self, value: Value) {
+        if !self.is_root() {
+            self.sink.entries.push((self.key, value));
+        }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for Node<'_, '_> {
+    type Value = ();
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        if self.is_expandable() {
+            deserializer.deserialize_any(self)
+        } else {
+            let value = Value::deserialize(deserializer)?;
+            self.leaf(value);
+            Ok(())
+        }
+    }
+}
+
+impl<'de> Visitor<'de> for Node<'_, '_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("任意 YAML 值")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<(), E> {
+        self.leaf(Value::Bool(v));
+        Ok(())
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<(), E> {
+        self.leaf(Value::from(v));
+        Ok(())
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<(), E> {
+        self.leaf(Value::from(v));
+        Ok(())
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<(), E> {
+        self.leaf(Value::from(v));
+        Ok(())
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<(), E> {
+        self.leaf(Value::String(v.to_string()));
+        Ok(())
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<(), E> {
+        self.leaf(Value::String(v));
+        Ok(())
+    }
+
+    fn visit_unit<E>(self) -> Result<(), E> {
+        self.leaf(Value::Null);
+        Ok(())
+    }
+
+    fn visit_none<E>(self) -> Result<(), E> {
+        self.leaf(Value::Null);
+        Ok(())
+    }
+
+    fn visit_some<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        self.deserialize(deserializer)
+    }
+
+    /// 带标签的值作为整体
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<(), A::Error> {
+        let value = Value::deserialize(EnumAccessDeserializer::new(data))?;
+        self.leaf(value);
+        Ok(())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(mut self, mut seq: A) -> Result<(), A::Error> {
+        if self.opts.index_style.is_none() {
+            let value = Value::deserialize(SeqAccessDeserializer::new(seq))?;
+            self.leaf(value);
+            return Ok(());
+        }
+        let mut len = 0;
+        loop {
+            let key = self.index(len);
+            if seq.next_element_seed(self.child(key))?.is_none() {
+                break;
+            }
+            len += 1;
+        }
+        if len == 0 {
+            self.leaf(Value::Sequence(Vec::new()));
+        }
+        Ok(())
+    }
+
+    fn visit_map<A: MapAccess<'de>>(mut self, mut map: A) -> Result<(), A::Error> {
+        let mut len = 0;
+        let mut seen = HashSet::new();
+        while let Some(key) = map.next_key::<Value>()? {
+            len += 1;
+            let key = if self.opts.coerce_key_types {
+                coerce_key(&key).map(|k| k.into_owned())
+            } else {
+                key.as_str().map(String::from)
+            };
+            // 与 flatten 相同，无法作为键路径的键连同其值一起跳过
+            let Some(key) = key else {
+                map.next_value::<IgnoredAny>()?;
+                continue;
+            };
+            // 规范化后重名的键（如 `8080` 与 `"8080"`）会互相覆盖，见 coerced_key_conflict
+            if self.opts.coerce_key_types && !seen.insert(key.clone()) {
+                let key = self.join(&key);
+                let error = de::Error::custom(&key);
+                self.sink.conflict = Some(key);
+                return Err(error);
+            }
+            let key = self.join(&key);
+            map.next_value_seed(self.child(key))?;
+        }
+        if len == 0 {
+            self.leaf(Value::Mapping(Default::default()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StreamError, flatten_reader};
+    use crate::flatten::{FlattenOptions, flatten};
+    use serde_yaml::Value;
+
+    #[test]
+    fn test_flatten_reader_matches_flatten() {
+        let text = "db:\n  hosts: [a, b]\n  port: 5432\n  \"a.b\": {}\nempty: []\ntag: !secret x\n\
+                    8080: http\nroot: &r {x: 1}\nalias: *r\n";
+        for opts in [
+            FlattenOptions::default(),
+            FlattenOptions {
+                max_depth: Some(0),
+                coerce_key_types: true,
+                ..Default::default()
+            },
+            FlattenOptions {
+                index_style: None,
+                opaque: vec!["root".parse().unwrap()],
+                ..Default::default()
+            },
+        ] {
+            let value: Value = serde_yaml::from_str(text).unwrap();
+            let mut expected: Vec<_> = flatten(&value, &opts)
+                .map(|(k, v)| (k, v.clone()))
+                .collect();
+            let mut entries = flatten_reader(text.as_bytes(), &opts).unwrap();
+            expected.sort_by(|a, b| a.0.cmp(&b.0));
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            assert_eq!(entries, expected);
+        }
+
+        assert!(matches!(
+            flatten_reader("a: 1\n---\nb: 2\n".as_bytes(), &FlattenOptions::default()),
+            Err(StreamError::MultipleDocuments)
+        ));
+        let coerce = FlattenOptions {
+            coerce_key_types: true,
+            ..Default::default()
+        };
+        assert!(matches!(
+            flatten_reader("8080: a\n\"8080\": b\n".as_bytes(), &coerce),
+            Err(StreamError::CoercedKeyConflict(key)) if key == "8080"
+        ));
+        assert!(
+            flatten_reader("".as_bytes(), &FlattenOptions::default())
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
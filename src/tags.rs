@@ -0,0 +1,330 @@
+//! 按标签的含义比较带标签的值
+//!
+//! serde_yaml 解析时会去掉 YAML 核心标签：`!!timestamp 2024-01-01` 与 `!!binary aGk=` 读出来
+//! 都是普通字符串，只有本地标签（如 `!timestamp`、`!secret`）保留为 [`Value::Tagged`]。
+//! 因此 [`TagOptions`] 一方面把能解析为 YAML 时间戳的字符串按时刻比较，另一方面按标签名
+//! 为本地标签选择 [`TagHandler`]。
+
+use regex::Regex;
+use serde_yaml::Value;
+use serde_yaml::value::{Tag, TaggedValue};
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, LazyLock};
+
+/// YAML 1.1 的时间戳写法：只有日期，或日期加时间，时间后可以带小数秒和时区
+static TIMESTAMP: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(\d{4})-(\d{1,2})-(\d{1,2})(?:(?:[Tt]|[ \t]+)(\d{1,2}):(\d{2}):(\d{2})(?:\.(\d*))?(?:[ \t]*(Z|[+-]\d{1,2}(?::?\d{2})?))?)?$",
+    )
+    .expect("合法的正则表达式")
+});
+
+/// 带某个标签的值在比较前如何规范化
+#[derive(Clone)]
+pub enum TagHandler {
+    /// 值是时间戳，换算成 UTC 后比较；`2024-01-01T08:00:00+08:00` 与 `2024-01-01 00:00:00Z` 相等
+    Timestamp,
+    /// 值是 base64 编码的二进制数据，解码后比较字节内容，忽略换行等排版差异
+    Binary,
+    /// 忽略标签，只比较其中的值
+    Untagged,
+    /// 自定义的规范化，参数是标签内的值，返回用于比较的值
+    Custom(Arc<dyn Fn(&Value) -> Value + Send + Sync>),
+}
+
+impl fmt::Debug for TagHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TagHandler::Timestamp => f.write_str("Timestamp"),
+            TagHandler::Binary => f.write_str("Binary"),
+            TagHandler::Untagged => f.write_str("Untagged"),
+            TagHandler::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+impl FromStr for TagHandler {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "timestamp" => Ok(TagHandler::Timestamp),
+            "binary" => Ok(TagHandler::Binary),
+            "untagged" => Ok(TagHandler::Untagged),
+            _ => Err(format!(
+                "未知的标签处理方式 {s:?}，可选 timestamp、binary、untagged"
+            )),
+        }
+    }
+}
+
+/// 比较前对带标签的值和时间戳字符串做的规范化，默认全部关闭
+///
+/// 与 [`StringNormalization`](crate::compare::StringNormalization) 一样只影响比较，
+/// 报告中显示的仍然是原值。
+#[derive(Debug, Clone, Default)]
+pub struct TagOptions {
+    /// 能解析为 YAML 时间戳的字符串换算成 UTC 后比较；没有时区的时间按 UTC 处理，只有日期时为当天零点
+    pub timestamps: bool,
+    /// 标签 → 处理方式，标签可以带或不带前导 `!`；同一个标签出现多次时后面的生效
+    pub handlers: Vec<(String, TagHandler)>,
+}
+
+impl TagOptions {
+    /// 内置的处理方式：时间戳字符串按时刻比较，`!timestamp` 和 `!binary` 按内容比较
+    pub fn builtin() -> Self {
+        TagOptions {
+            timestamps: true,
+            handlers: vec![
+                ("!timestamp".into(), TagHandler::Timestamp),
+                ("!binary".into(), TagHandler::Binary),
+            ],
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.timestamps || !self.handlers.is_empty()
+    }
+
+    /// 标签对应的处理方式
+    pub fn handler(&self, tag: &Tag) -> Option<&TagHandler> {
+        (self.handlers.iter().rev())
+            .find(|(name, _)| tag == name)
+            .map(|(_, handler)| handler)
+    }
+
+    /// 规范化值中的每个时间戳和带标签的值，序列和映射逐层处理；没有开启任何规范化时不复制
+    pub fn normalize<'a>(&self, val: &'a Value) -> Cow<'a, Value> {
+        if !self.is_active() {
+            return Cow::Borrowed(val);
+        }
+        Cow::Owned(self.normalize_owned(val))
+    }
+
+    fn normalize_owned(&self, val: &Value) -> Value {
+        match val {
+            Value::String(s) if self.timestamps => {
+                canonical_timestamp(s).map_or_else(|| val.clone(), Value::String)
+            }
+            Value::Sequence(seq) => seq.iter().map(|v| self.normalize_owned(v)).collect(),
+            Value::Mapping(m) => Value::Mapping(
+                m.iter()
+                    .map(|(k, v)| (k.clone(), self.normalize_owned(v)))
+                    .collect(),
+            ),
+            Value::Tagged(t) => match self.handler(&t.tag) {
+                // 按时刻比较后与等价的普通时间戳字符串相等
+                Some(TagHandler::Timestamp) => match t.value.as_str().and_then(canonical_timestamp)
+                {
+                    Some(ts) => Value::String(ts),
+                    None => val.clone(),
+                },
+                Some(TagHandler::Binary) => match t.value.as_str().and_then(decode_base64) {
+                    Some(bytes) => Value::Tagged(Box::new(TaggedValue {
+                        tag: t.tag.clone(),
+                        value: Value::String(hex(&Sha256::digest(&bytes))),
+                    })),
+                    None => val.clone(),
+                },
+                Some(TagHandler::Untagged) => self.normalize_owned(&t.value),
+                Some(TagHandler::Custom(f)) => f(&t.value),
+                None => {
+                    let mut t = t.clone();
+                    t.value = self.normalize_owned(&t.value);
+                    Value::Tagged(t)
+                }
+            },
+            _ => val.clone(),
+        }
+    }
+
+    /// 由 [`TagHandler::Binary`] 处理的值的简短描述，如 `!binary <1024 字节, sha256:1a2b3c4d>`，
+    /// 用来代替整段 base64；其他值或无法解码时返回 `None`
+    pub fn describe_binary(&self, val: &Value) -> Option<String> {
+        let Value::Tagged(t) = val else {
+            return None;
+        };
+        if !matches!(self.handler(&t.tag), Some(TagHandler::Binary)) {
+            return None;
+        }
+        let bytes = decode_base64(t.value.as_str()?)?;
+        let digest = Sha256::digest(&bytes);
+        Some(format!(
+            "{} <{} 字节, sha256:{}>",
+            t.tag,
+            bytes.len(),
+            hex(&digest[..4])
+        ))
+    }
+}
+
+/// 把 YAML 时间戳换算成 UTC 的规范写法 `YYYY-MM-DDTHH:MM:SS[.小数]Z`，小数秒去掉末尾的 0；
+/// 不是时间戳或日期、时间超出范围时返回 `None`
+pub fn canonical_timestamp(s: &str) -> Option<String> {
+    let caps = TIMESTAMP.captures(s.trim())?;
+    let num = |i: usize| {
+        caps.get(i)
+            .map_or(Some(0), |m| m.as_str().parse::<i64>().ok())
+    };
+    let (year, month, day) = (num(1)?, num(2)?, num(3)?);
+    let (hour, minute, second) = (num(4)?, num(5)?, num(6)?);
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+    let offset = match caps.get(8).map(|m| m.as_str()) {
+        None | Some("Z") => 0,
+        Some(tz) => {
+            let sign = if tz.starts_with('-') { -1 } else { 1 };
+            let digits = tz[1..].replace(':', "");
+            let (h, m) = match digits.len() {
+                1 | 2 => (digits.parse::<i64>().ok()?, 0),
+                _ => {
+                    let (h, m) = digits.split_at(digits.len() - 2);
+                    (h.parse::<i64>().ok()?, m.parse::<i64>().ok()?)
+                }
+            };
+            sign * (h * 3600 + m * 60)
+        }
+    };
+    let secs =
+        days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second - offset;
+    let (year, month, day) = civil_from_days(secs.div_euclid(86400));
+    let time = secs.rem_euclid(86400);
+    let fraction = caps.get(7).map_or("", |m| m.as_str().trim_end_matches('0'));
+    let fraction = if fraction.is_empty() {
+        String::new()
+    } else {
+        format!(".{fraction}")
+    };
+    Some(format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}{fraction}Z",
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    ))
+}
+
+/// 公历日期距 1970-01-01 的天数
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// [`days_from_civil`] 的逆运算
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// 解码标准 base64，忽略其中的空白；不是合法的 base64 时返回 `None`
+pub fn decode_base64(s: &str) -> Option<Vec<u8>> {
+    let digits: Vec<u8> = s.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    let data = digits
+        .strip_suffix(b"==")
+        .or_else(|| digits.strip_suffix(b"="))
+        .unwrap_or(&digits);
+    if !digits.len().is_multiple_of(4) {
+        return None;
+    }
+    let mut bytes = Vec::with_capacity(data.len() * 3 / 4);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for &c in data {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        buffer = buffer << 6 | u32::from(value);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TagHandler, TagOptions, canonical_timestamp, decode_base64};
+    use serde_yaml::Value;
+    use std::borrow::Cow;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_tag_normalization() {
+        assert_eq!(
+            canonical_timestamp("2024-01-01T08:30:00.500+08:00").as_deref(),
+            Some("2024-01-01T00:30:00.5Z")
+        );
+        assert_eq!(
+            canonical_timestamp("2024-1-1").as_deref(),
+            Some("2024-01-01T00:00:00Z")
+        );
+        assert_eq!(
+            canonical_timestamp("2023-12-31 21:00:00 -3").as_deref(),
+            Some("2024-01-01T00:00:00Z")
+        );
+        assert_eq!(canonical_timestamp("2024-13-01"), None);
+        assert_eq!(canonical_timestamp("v2024-01-01"), None);
+        assert_eq!(decode_base64("aGVs\nbG8=").as_deref(), Some(&b"hello"[..]));
+        assert_eq!(decode_base64("aGk"), None);
+
+        let yaml = |s: &str| serde_yaml::from_str::<Value>(s).unwrap();
+        let mut tags = TagOptions::builtin();
+        let eq = |tags: &TagOptions, a: &str, b: &str| {
+            tags.normalize(&yaml(a)) == tags.normalize(&yaml(b))
+        };
+        assert!(eq(&tags, "!timestamp 2024-01-01", "2024-01-01T00:00:00Z"));
+        assert!(eq(&tags, "[2024-01-01 08:00:00+08:00]", "[2024-01-01]"));
+        assert!(eq(&tags, "!binary aGVs bG8=", "!binary aGVsbG8="));
+        assert!(!eq(&tags, "!binary aGVsbG8=", "!binary aGk="));
+        assert!(!eq(&tags, "!secret a", "a"));
+        assert_eq!(
+            tags.describe_binary(&yaml("!binary aGVsbG8=")).as_deref(),
+            Some("!binary <5 字节, sha256:2cf24dba>")
+        );
+
+        tags.handlers
+            .push(("secret".into(), "untagged".parse().unwrap()));
+        tags.handlers.push((
+            "!upper".into(),
+            TagHandler::Custom(Arc::new(|v| {
+                Value::String(v.as_str().unwrap_or_default().to_lowercase())
+            })),
+        ));
+        assert!(eq(&tags, "!secret a", "a"));
+        assert!(eq(&tags, "{x: !upper ABC}", "{x: abc}"));
+        assert!(matches!(
+            TagOptions::default().normalize(&yaml("!secret a")),
+            Cow::Borrowed(_)
+        ));
+    }
+}
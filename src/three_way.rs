@@ -0,0 +1,284 @@
+use crate::apply::{PatchOp, apply_ops};
+use crate::i18n::Msg;
+use crate::{ReportOptions, display_val};
+use anyhow::Result;
+use colored::*;
+use serde::Serialize;
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use yml_diff::{ChangeKind, ConfigKey, DiffOptions, cmp_yml_vals};
+
+/// 三方比较中一处变更的归类；旧版本相当于 git 的 ours，新版本相当于 theirs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeKind {
+    /// 只有旧版本相对基准有变化
+    Ours,
+    /// 只有新版本相对基准有变化
+    Theirs,
+    /// 两边改成了相同的值
+    Both,
+    /// 两边改成了不同的值
+    Conflict,
+}
+
+/// 一个扁平键在三个版本中的值，`None` 表示该版本中没有这个键
+#[derive(Debug, PartialEq)]
+pub struct ThreeWayChange<'a> {
+    pub key: ConfigKey,
+    pub kind: MergeKind,
+    pub base: Option<&'a Value>,
+    pub ours: Option<&'a Value>,
+    pub theirs: Option<&'a Value>,
+}
+
+/// 分别比较基准与旧版本、基准与新版本，按键合并两边的变更并归类，结果按键排序
+pub fn three_way<'a>(
+    base: &'a Value,
+    ours: &'a Value,
+    theirs: &'a Value,
+    opts: &DiffOptions,
+) -> Vec<ThreeWayChange<'a>> {
+    // 键 → (基准中的值, 旧版本中的值, 新版本中的值)，两边都以基准为准，未变化的一边与基准相同
+    type Entry<'a> = (Option<&'a Value>, Option<&'a Value>, Option<&'a Value>);
+    let mut entries: BTreeMap<ConfigKey, (Entry<'a>, bool, bool)> = BTreeMap::new();
+
+    let ours_diff = cmp_yml_vals(base, ours, opts);
+    let theirs_diff = cmp_yml_vals(base, theirs, opts);
+    for (diff, is_ours) in [(&ours_diff, true), (&theirs_diff, false)] {
+        for change in diff.changes() {
+            let (before, after) = match change.kind {
+                ChangeKind::Added(new) => (None, Some(new)),
+                ChangeKind::Removed(old) => (Some(old), None),
+                ChangeKind::Modified(old, new) => (Some(old), Some(new)),
+            };
+            let ((_, ours_val, theirs_val), changed_ours, changed_theirs) = entries
+                .entry(ConfigKey::new(change.key, &opts.flatten.separator))
+                .or_insert(((before, before, before), false, false));
+            if is_ours {
+                (*ours_val, *changed_ours) = (after, true);
+            } else {
+                (*theirs_val, *changed_theirs) = (after, true);
+            }
+        }
+    }
+
+    entries
+        .into_iter()
+        .map(
+            |(key, ((base, ours, theirs), changed_ours, changed_theirs))| {
+                let kind = match (changed_ours, changed_theirs) {
+                    (true, false) => MergeKind::Ours,
+                    (false, true) => MergeKind::Theirs,
+                    _ if ours == theirs => MergeKind::Both,
+                    _ => MergeKind::Conflict,
+                };
+                ThreeWayChange {
+                    key,
+                    kind,
+                    base,
+                    ours,
+                    theirs,
+                }
+            },
+        )
+        .collect()
+}
+
+/// 合并三个版本：以旧版本为底，加上只有新版本做出的变更；冲突的键保留旧版本的值
+///
+/// 合并不应用规则和其他过滤选项，只沿用扁平化选项，保证新版本的每一处变更都被合并。
+pub fn merge(base: &Value, ours: &Value, theirs: &Value, opts: &DiffOptions) -> Result<Value> {
+    let raw_opts = DiffOptions {
+        flatten: opts.flatten.clone(),
+        ..Default::default()
+    };
+    let mut removes = Vec::new();
+    let mut sets = Vec::new();
+    for change in three_way(base, ours, theirs, &raw_opts) {
+        if change.kind != MergeKind::Theirs {
+            continue;
+        }
+        let path = change.key.to_string();
+        match (change.base, change.theirs) {
+            (_, None) => removes.push(PatchOp::Remove { path }),
+            (None, Some(value)) => sets.push(PatchOp::Add {
+                path,
+                value: value.clone(),
+            }),
+            (Some(_), Some(value)) => sets.push(PatchOp::Replace {
+                path,
+                value: value.clone(),
+            }),
+        }
+    }
+    // 倒序删除，保证同一序列中先删除较大的下标
+    removes.reverse();
+    removes.extend(sets);
+    apply_ops(ours, &removes, &raw_opts.flatten.separator)
+}
+
+/// 按归类分组输出三方比较的结果
+pub fn print_three_way(
+    out: &mut dyn Write,
+    changes: &[ThreeWayChange],
+    opts: &DiffOptions,
+    report: &ReportOptions,
+) -> io::Result<()> {
+    let lang = report.lang;
+    let show = |key: &str, val: Option<&Value>| match val {
+        Some(val) => display_val(key, val, opts, report).into_owned(),
+        None => lang.get(Msg::Missing).to_string(),
+    };
+
+    if !report.quiet {
+        writeln!(out, "{}", lang.get(Msg::ThreeWayTitle).bold())?;
+        writeln!(out)?;
+    }
+    let sections = [
+        (MergeKind::Ours, Msg::OursOnlySection),
+        (MergeKind::Theirs, Msg::TheirsOnlySection),
+        (MergeKind::Both, Msg::BothSameSection),
+    ];
+    for (kind, title) in sections {
+        let title = lang.get(title);
+        let mut section = changes.iter().filter(|c| c.kind == kind).peekable();
+        if section.peek().is_none() {
+            continue;
+        }
+        writeln!(out, "{}", title.blue().bold())?;
+        for change in section {
+            let key = change.key.as_str();
+            let after = if kind == MergeKind::Theirs {
+                change.theirs
+            } else {
+                change.ours
+            };
+            let line = format!(
+                "  ~ {key}: {} → {}",
+                show(key, change.base),
+                show(key, after)
+            );
+            writeln!(out, "{}", line.yellow())?;
+        }
+        writeln!(out)?;
+    }
+
+    let conflicts: Vec<_> = changes
+        .iter()
+        .filter(|c| c.kind == MergeKind::Conflict)
+        .collect();
+    if !conflicts.is_empty() {
+        writeln!(out, "{}", lang.get(Msg::ConflictSection).red().bold())?;
+        for change in conflicts {
+            let key = change.key.as_str();
+            writeln!(out, "{}", format!("  ! {key}").red())?;
+            for (msg, val) in [
+                (Msg::ConflictBase, change.base),
+                (Msg::ConflictOurs, change.ours),
+                (Msg::ConflictTheirs, change.theirs),
+            ] {
+                writeln!(out, "{}", lang.format(msg, &[&show(key, val)]))?;
+            }
+        }
+        writeln!(out)?;
+    }
+
+    if changes.is_empty() && !report.quiet {
+        writeln!(out, "{}", lang.get(Msg::NoDifferences).green())?;
+    }
+    Ok(())
+}
+
+/// 三方比较结果的 JSON 形式：每处变更一个对象，不存在的值为 null，命中 redact 规则的值为 `"***"`
+pub fn three_way_to_json(changes: &[ThreeWayChange], opts: &DiffOptions) -> serde_json::Value {
+    changes
+        .iter()
+        .map(|change| {
+            let key = change.key.as_str();
+            let value = |val: Option<&Value>| match val {
+                Some(_) if opts.rules.is_redacted(key) => serde_json::json!("***"),
+                val => serde_json::json!(val),
+            };
+            serde_json::json!({
+                "key": key,
+                "kind": change.kind,
+                "base": value(change.base),
+                "ours": value(change.ours),
+                "theirs": value(change.theirs),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MergeKind, merge, print_three_way, three_way, three_way_to_json};
+    use crate::ReportOptions;
+    use crate::i18n::Lang;
+    use serde_yaml::Value;
+    use yml_diff::DiffOptions;
+    use yml_diff::rules::RuleAction;
+
+    #[test]
+    fn test_three_way() {
+        let yaml = |s: &str| serde_yaml::from_str::<Value>(s).unwrap();
+        let base = yaml("a: 1\nb: 1\nc: 1\nd: 1\ns: [1, 2]\n");
+        let ours = yaml("a: 2\nb: 1\nc: 3\nd: 2\ns: [1, 2]\n");
+        let theirs = yaml("a: 1\nb: 2\nc: 3\nd: 4\ns: [1]\ne: 1\n");
+        let opts = DiffOptions::default();
+
+        let changes = three_way(&base, &ours, &theirs, &opts);
+        let kinds: Vec<(&str, MergeKind)> =
+            changes.iter().map(|c| (c.key.as_str(), c.kind)).collect();
+        assert_eq!(
+            kinds,
+            [
+                ("a", MergeKind::Ours),
+                ("b", MergeKind::Theirs),
+                ("c", MergeKind::Both),
+                ("d", MergeKind::Conflict),
+                ("e", MergeKind::Theirs),
+                ("s[1]", MergeKind::Theirs),
+            ]
+        );
+
+        // 冲突的 d 保留旧版本的值，其余两边的变更都合并进来
+        assert_eq!(
+            merge(&base, &ours, &theirs, &opts).unwrap(),
+            yaml("a: 2\nb: 2\nc: 3\nd: 2\ns: [1]\ne: 1\n")
+        );
+
+        colored::control::set_override(false);
+        let report = ReportOptions {
+            lang: Lang::En,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        print_three_way(&mut out, &changes, &opts, &report).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(
+            text.starts_with("=== YAML configuration three-way diff report ===\n"),
+            "{text}"
+        );
+        assert!(text.contains("Conflicting keys:\n"), "{text}");
+        assert!(text.contains("  ~ e: <missing> → 1\n"), "{text}");
+    }
+
+    #[test]
+    fn test_three_way_json_is_redacted() {
+        let yaml = |s: &str| serde_yaml::from_str::<Value>(s).unwrap();
+        let mut opts = DiffOptions::default();
+        opts.rules
+            .push("token".parse().unwrap(), RuleAction::Redact);
+        let (base, ours, theirs) = (yaml("token: a\n"), yaml("token: b\n"), yaml("{}"));
+        let changes = three_way(&base, &ours, &theirs, &opts);
+        assert_eq!(
+            three_way_to_json(&changes, &opts),
+            serde_json::json!([
+                {"key": "token", "kind": "conflict", "base": "***", "ours": "***", "theirs": null}
+            ])
+        );
+    }
+}
@@ -0,0 +1,267 @@
+use crate::i18n::{Msg, error_lang};
+use anyhow::{Result, anyhow};
+use serde_yaml::{Mapping, Value};
+use std::cmp::Ordering;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::thread;
+use yml_diff::flatten::{coerce_key, quote_key};
+use yml_diff::pattern::KeyPattern;
+use yml_diff::tags::decode_base64;
+
+/// 解析前对输入文本做的预处理
+///
+/// 内置转换直接在进程内完成；其余写法都被当作 shell 命令，文件内容通过标准输入传入，
+/// 命令的标准输出作为新的文件内容。执行外部命令意味着配置文件的比较结果取决于
+/// 任意命令的行为，命令本身也以当前用户的权限运行，因此必须显式指定 `--allow-exec`。
+#[derive(Debug, Clone)]
+pub enum Transform {
+    /// 去掉每行末尾的空白以及文件首尾的空行
+    Trim,
+    /// 解析后把所有映射按键排序，再重新序列化
+    SortKeys,
+    /// 解析后把所有映射的字符串键转成小写，再重新序列化；转换后重名的键保留后出现的值
+    LowercaseKeys,
+    /// 解析后去掉当作注释使用的键：以 `#` 或 `//` 开头的键，以及 `_comment`、`$comment`
+    StripCommentKeys,
+    /// `base64-decode:模式`：扁平键命中模式的字符串值按 base64 解码，如 Kubernetes Secret 的 `data.*`；
+    /// 解码结果不是 UTF-8 文本或值不是合法的 base64 时保持原样
+    Base64Decode(KeyPattern),
+    /// 通过 `sh -c` 执行的外部命令
+    Command(String),
+}
+
+impl FromStr for Transform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(pattern) = s.strip_prefix("base64-decode:") {
+            return Ok(Transform::Base64Decode(pattern.parse()?));
+        }
+        Ok(match s {
+            "trim" => Transform::Trim,
+            "sort-keys" => Transform::SortKeys,
+            "lowercase-keys" => Transform::LowercaseKeys,
+            "strip-comment-keys" => Transform::StripCommentKeys,
+            cmd => Transform::Command(cmd.to_string()),
+        })
+    }
+}
+
+impl Transform {
+    /// 对文件内容执行转换；`allow_exec` 为假时拒绝执行外部命令
+    pub fn apply(&self, bytes: Vec<u8>, allow_exec: bool) -> Result<Vec<u8>> {
+        match self {
+            Transform::Trim => Ok(trim(&String::from_utf8_lossy(&bytes)).into_bytes()),
+            Transform::SortKeys => rewrite(&bytes, "sort-keys", sort_keys),
+            Transform::LowercaseKeys => rewrite(&bytes, "lowercase-keys", lowercase_keys),
+            Transform::StripCommentKeys => {
+                rewrite(&bytes, "strip-comment-keys", strip_comment_keys)
+            }
+            Transform::Base64Decode(pattern) => rewrite(&bytes, "base64-decode", |value| {
+                decode_matching(value, "", pattern)
+            }),
+            Transform::Command(cmd) if !allow_exec => {
+                let cmd = format!("{cmd:?}");
+                Err(anyhow!(
+                    error_lang().format(Msg::TransformNeedsExec, &[&cmd])
+                ))
+            }
+            Transform::Command(cmd) => run_command(cmd, bytes),
+        }
+    }
+}
+
+fn trim(text: &str) -> String {
+    let mut out: String = text
+        .trim_matches('\n')
+        .lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n");
+    out.push('\n');
+    out
+}
+
+/// 内置的结构转换：解析后修改值，再重新序列化
+fn rewrite(bytes: &[u8], name: &str, f: impl FnOnce(&mut Value)) -> Result<Vec<u8>> {
+    let mut value: Value = serde_yaml::from_slice(bytes)
+        .map_err(|e| anyhow!(error_lang().format(Msg::TransformParseFailed, &[&name, &e])))?;
+    f(&mut value);
+    serde_yaml::to_string(&value)
+        .map(String::into_bytes)
+        .map_err(|e| anyhow!(error_lang().format(Msg::TransformSerializeFailed, &[&name, &e])))
+}
+
+fn sort_keys(value: &mut Value) {
+    match value {
+        Value::Mapping(map) => {
+            let mut entries: Vec<(Value, Value)> = std::mem::take(map).into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+            *map = entries
+                .into_iter()
+                .map(|(k, mut v)| {
+                    sort_keys(&mut v);
+                    (k, v)
+                })
+                .collect::<Mapping>();
+        }
+        Value::Sequence(seq) => seq.iter_mut().for_each(sort_keys),
+        Value::Tagged(tagged) => sort_keys(&mut tagged.value),
+        _ => {}
+    }
+}
+
+fn lowercase_keys(value: &mut Value) {
+    match value {
+        Value::Mapping(map) => {
+            *map = std::mem::take(map)
+                .into_iter()
+                .map(|(k, mut v)| {
+                    lowercase_keys(&mut v);
+                    match k {
+                        Value::String(s) => (Value::String(s.to_lowercase()), v),
+                        k => (k, v),
+                    }
+                })
+                .collect();
+        }
+        Value::Sequence(seq) => seq.iter_mut().for_each(lowercase_keys),
+        Value::Tagged(tagged) => lowercase_keys(&mut tagged.value),
+        _ => {}
+    }
+}
+
+fn strip_comment_keys(value: &mut Value) {
+    match value {
+        Value::Mapping(map) => {
+            map.retain(|k, _| {
+                !k.as_str().is_some_and(|k| {
+                    k.starts_with('#') || k.starts_with("//") || k == "_comment" || k == "$comment"
+                })
+            });
+            map.values_mut().for_each(strip_comment_keys);
+        }
+        Value::Sequence(seq) => seq.iter_mut().for_each(strip_comment_keys),
+        Value::Tagged(tagged) => strip_comment_keys(&mut tagged.value),
+        _ => {}
+    }
+}
+
+/// 按默认的扁平化方式（`.` 分隔，序列按下标）计算键路径，解码命中模式的字符串值
+fn decode_matching(value: &mut Value, path: &str, pattern: &KeyPattern) {
+    let join = |seg: &str| {
+        let seg = quote_key(seg, ".");
+        if path.is_empty() {
+            seg.into_owned()
+        } else {
+            format!("{path}.{seg}")
+        }
+    };
+    match value {
+        Value::String(s) if !path.is_empty() && pattern.matches(path) => {
+            if let Some(text) = decode_base64(s).and_then(|bytes| String::from_utf8(bytes).ok()) {
+                *s = text;
+            }
+        }
+        Value::Mapping(map) => {
+            for (k, v) in map.iter_mut() {
+                if let Some(seg) = coerce_key(k) {
+                    decode_matching(v, &join(&seg), pattern);
+                }
+            }
+        }
+        Value::Sequence(seq) => {
+            for (i, v) in seq.iter_mut().enumerate() {
+                decode_matching(v, &join(&i.to_string()), pattern);
+            }
+        }
+        Value::Tagged(tagged) => decode_matching(&mut tagged.value, path, pattern),
+        _ => {}
+    }
+}
+
+fn run_command(cmd: &str, input: Vec<u8>) -> Result<Vec<u8>> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(cmd)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            let cmd = format!("{cmd:?}");
+            anyhow!(error_lang().format(Msg::StartCommandFailed, &[&e, &cmd]))
+        })?;
+
+    // 在单独的线程中写入，避免命令输出较多时双方互相等待
+    let mut stdin = child.stdin.take().expect("已设置为管道");
+    let writer = thread::spawn(move || stdin.write_all(&input));
+
+    let output = child.wait_with_output().map_err(|e| {
+        let cmd = format!("{cmd:?}");
+        anyhow!(error_lang().format(Msg::WaitCommandFailed, &[&e, &cmd]))
+    })?;
+    // 命令可能不读取输入就退出，此时写入失败是正常的
+    let _ = writer.join();
+
+    if !output.status.success() {
+        let cmd = format!("{cmd:?}");
+        return Err(anyhow!(
+            error_lang().format(Msg::CommandFailed, &[&output.status, &cmd])
+        ));
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Transform;
+
+    #[test]
+    fn test_builtin_transforms() {
+        let trim: Transform = "trim".parse().unwrap();
+        let out = trim
+            .apply(b"\na: 1   \nb: 2\t\n\n".to_vec(), false)
+            .unwrap();
+        assert_eq!(out, b"a: 1\nb: 2\n");
+
+        let sort: Transform = "sort-keys".parse().unwrap();
+        let out = sort
+            .apply(b"b: 1\na:\n  d: 1\n  c: 2\n".to_vec(), false)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "a:\n  c: 2\n  d: 1\nb: 1\n"
+        );
+
+        let apply = |name: &str, text: &str| {
+            let t: Transform = name.parse().unwrap();
+            String::from_utf8(t.apply(text.as_bytes().to_vec(), false).unwrap()).unwrap()
+        };
+        assert_eq!(
+            apply("lowercase-keys", "Server:\n  Port: 80\nlist: [{A: B}]\n"),
+            "server:\n  port: 80\nlist:\n- a: B\n"
+        );
+        assert_eq!(
+            apply(
+                "strip-comment-keys",
+                "_comment: x\na:\n  '#note': y\n  '// todo': z\n  b: 1\n"
+            ),
+            "a:\n  b: 1\n"
+        );
+        assert_eq!(
+            apply(
+                "base64-decode:data.*",
+                "data: {user: YWRtaW4=, bin: //79, raw: x}\nkind: YWRtaW4=\n"
+            ),
+            "data:\n  user: admin\n  bin: //79\n  raw: x\nkind: YWRtaW4=\n"
+        );
+    }
+
+    #[test]
+    fn test_command_requires_allow_exec() {
+        let cmd: Transform = "cat".parse().unwrap();
+        assert!(cmd.apply(b"a: 1\n".to_vec(), false).is_err());
+    }
+}
@@ -0,0 +1,180 @@
+use crate::i18n::Msg;
+use crate::{ReportOptions, change_location, display_val, location_suffix, print_stats};
+use colored::*;
+use indexmap::IndexMap;
+use std::borrow::Cow;
+use std::io::{self, Write};
+use yml_diff::{ChangeKind, ConfigDiff, ConfigKey, DiffOptions};
+
+/// 差异树的一个节点：键路径上的一段，可以同时带有自身的变更和下一层的节点
+#[derive(Default)]
+pub struct Node<'a> {
+    /// 完整的扁平键及其变更
+    pub change: Option<(String, ChangeKind<'a>)>,
+    pub children: IndexMap<String, Node<'a>>,
+}
+
+impl<'a> Node<'a> {
+    /// 按键路径的各段把差异组织成树
+    ///
+    /// 变更按 [`ConfigKey`] 的层级顺序给出，同一个父级下的键总是相邻，因此每一层子节点的顺序
+    /// 与平铺报告中的顺序一致。
+    pub fn build(diff: &ConfigDiff<'a>, opts: &DiffOptions) -> Self {
+        let mut root = Node::default();
+        for change in diff.changes() {
+            let key = ConfigKey::new(change.key, &opts.flatten.separator);
+            let node = key.segments().fold(&mut root, |node, seg| {
+                node.children.entry(seg.to_string()).or_default()
+            });
+            node.change = Some((change.key.to_string(), change.kind));
+        }
+        root
+    }
+
+    /// 节点及其所有下层的变更数
+    pub fn count(&self) -> usize {
+        usize::from(self.change.is_some()) + self.children.values().map(Node::count).sum::<usize>()
+    }
+}
+
+/// --format tree：按公共前缀缩进分组的报告，每个分组后面是其中的变更数
+///
+/// 没有自身变更、只有一个子节点的各层合并成一行，例如 `spring.datasource.hikari (2)`。
+pub fn print_tree(
+    out: &mut dyn Write,
+    diff: &ConfigDiff,
+    opts: &DiffOptions,
+    report: &ReportOptions,
+) -> io::Result<()> {
+    if !report.quiet {
+        writeln!(out, "{}", report.lang.get(Msg::ReportTitle).bold())?;
+        writeln!(out)?;
+        print_stats(out, diff, 0, report)?;
+    }
+    if diff.is_empty() {
+        if !report.quiet {
+            writeln!(out, "{}", report.lang.get(Msg::NoDifferences).green())?;
+        }
+        return Ok(());
+    }
+    let root = Node::build(diff, opts);
+    for (seg, node) in &root.children {
+        let label = segment_label(seg, &opts.flatten.separator).into_owned();
+        print_node(out, label, node, 0, opts, report)?;
+    }
+    Ok(())
+}
+
+/// 显示用的段名：含有分隔符、`[` 或转义的段像扁平键中一样加上引号，合并后的多段标签才不会有歧义
+///
+/// [`ConfigKey::segments`] 返回的段不含引号，但引号内的转义保持原样，因此直接加上引号即可。
+pub fn segment_label<'s>(seg: &'s str, separator: &str) -> Cow<'s, str> {
+    if seg.contains(separator) || seg.contains('[') || seg.contains('\\') {
+        Cow::Owned(format!("\"{seg}\""))
+    } else {
+        Cow::Borrowed(seg)
+    }
+}
+
+fn print_node(
+    out: &mut dyn Write,
+    mut label: String,
+    mut node: &Node,
+    depth: usize,
+    opts: &DiffOptions,
+    report: &ReportOptions,
+) -> io::Result<()> {
+    while node.change.is_none() && node.children.len() == 1 {
+        let (seg, child) = node.children.first().expect("只有一个子节点");
+        let sep = &opts.flatten.separator;
+        label = format!("{label}{sep}{}", segment_label(seg, sep));
+        node = child;
+    }
+
+    let indent = "  ".repeat(depth);
+    if let Some((key, kind)) = &node.change {
+        let value = |val| display_val(key, val, opts, report);
+        let line = match *kind {
+            ChangeKind::Added(val) => format!("+ {label}: {}", value(val)).green(),
+            ChangeKind::Removed(val) => format!("- {label}: {}", value(val)).red(),
+            ChangeKind::Modified(old, new) => {
+                format!("~ {label}: {} → {}", value(old), value(new)).yellow()
+            }
+        };
+        let location = location_suffix(change_location(key, kind, opts, report));
+        writeln!(out, "{indent}{line}{location}")?;
+    }
+    if node.children.is_empty() {
+        return Ok(());
+    }
+    if node.change.is_none() {
+        let count = format!("({})", node.count()).dimmed();
+        writeln!(out, "{indent}{} {count}", label.bold())?;
+    }
+    for (seg, child) in &node.children {
+        let label = segment_label(seg, &opts.flatten.separator).into_owned();
+        print_node(out, label, child, depth + 1, opts, report)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::print_tree;
+    use crate::ReportOptions;
+    use serde_yaml::Value;
+    use yml_diff::{DiffOptions, cmp_yml_vals};
+
+    #[test]
+    fn test_print_tree() {
+        let old: Value = serde_yaml::from_str(
+            "spring:\n  datasource:\n    hikari: {max: 8, idle: 1}\n    url: a\nport: 1\n",
+        )
+        .unwrap();
+        let new: Value = serde_yaml::from_str(
+            "spring:\n  datasource:\n    hikari: {max: 10, idle: 1, min: 2}\n    url: b\nport: 2\n",
+        )
+        .unwrap();
+        let opts = DiffOptions::default();
+        let diff = cmp_yml_vals(&old, &new, &opts);
+
+        colored::control::set_override(false);
+        let mut out = Vec::new();
+        let report = ReportOptions {
+            quiet: true,
+            ..Default::default()
+        };
+        print_tree(&mut out, &diff, &opts, &report).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "~ port: 1 → 2\n\
+             spring.datasource (3)\n\
+             \x20 ~ url: a → b\n\
+             \x20 hikari (2)\n\
+             \x20   ~ max: 8 → 10\n\
+             \x20   + min: 2\n"
+        );
+    }
+
+    #[test]
+    fn test_segments_containing_separator_are_quoted() {
+        let old: Value =
+            serde_yaml::from_str("metadata:\n  labels:\n    app.kubernetes.io/name: a\n").unwrap();
+        let new: Value =
+            serde_yaml::from_str("metadata:\n  labels:\n    app.kubernetes.io/name: b\n").unwrap();
+        let opts = DiffOptions::default();
+        let diff = cmp_yml_vals(&old, &new, &opts);
+
+        colored::control::set_override(false);
+        let mut out = Vec::new();
+        let report = ReportOptions {
+            quiet: true,
+            ..Default::default()
+        };
+        print_tree(&mut out, &diff, &opts, &report).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "~ metadata.labels.\"app.kubernetes.io/name\": a → b\n"
+        );
+    }
+}
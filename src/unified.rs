@@ -0,0 +1,113 @@
+use crate::{ReportOptions, change_location, display_val};
+use colored::*;
+use std::io::{self, Write};
+use yml_diff::{ChangeKind, ConfigDiff, DiffOptions};
+
+/// 只存在于一侧的文件，另一侧在头部写作 `/dev/null`，与 `diff -N` 相同
+pub const NULL_LABEL: &str = "/dev/null";
+
+/// 统一差异格式的输出：先是 `--- 旧文件` / `+++ 新文件` 两行头部，之后三类变更按键的顺序交错排列，
+/// 每个键以 `@@ 键路径 @@` 开头，删除和修改输出 `-` 旧值，新增和修改输出 `+` 新值；
+/// --line-numbers 时键在源文件中的位置写在 `@@` 之后，如 `@@ a.b @@ old.yml:3 → new.yml:4`
+///
+/// 没有差异时什么也不输出，与 `diff -u` 相同。
+pub fn print_unified(
+    out: &mut dyn Write,
+    diff: &ConfigDiff,
+    opts: &DiffOptions,
+    report: &ReportOptions,
+) -> io::Result<()> {
+    if diff.is_empty() {
+        return Ok(());
+    }
+    let (old_label, new_label) = match &report.labels {
+        Some((old, new)) => (old.as_str(), new.as_str()),
+        None => ("old", "new"),
+    };
+    writeln!(out, "{}", format!("--- {old_label}").bold())?;
+    writeln!(out, "{}", format!("+++ {new_label}").bold())?;
+
+    for change in diff.changes() {
+        let key = change.key;
+        let header = match change_location(key, &change.kind, opts, report) {
+            Some(location) => format!("@@ {key} @@ {location}"),
+            None => format!("@@ {key} @@"),
+        };
+        writeln!(out, "{}", header.cyan())?;
+        let (old, new) = match change.kind {
+            ChangeKind::Added(new) => (None, Some(new)),
+            ChangeKind::Removed(old) => (Some(old), None),
+            ChangeKind::Modified(old, new) => (Some(old), Some(new)),
+        };
+        if let Some(old) = old {
+            let line = format!("-{}", display_val(key, old, opts, report));
+            writeln!(out, "{}", line.red())?;
+        }
+        if let Some(new) = new {
+            let line = format!("+{}", display_val(key, new, opts, report));
+            writeln!(out, "{}", line.green())?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::print_unified;
+    use crate::ReportOptions;
+    use serde_yaml::Value;
+    use yml_diff::{ConfigDiff, DiffOptions, cmp_yml_vals};
+
+    #[test]
+    fn test_print_unified() {
+        let old: Value = serde_yaml::from_str("a: 1\nb:\n  c: x\n  d: 1\n").unwrap();
+        let new: Value = serde_yaml::from_str("a: 2\nb:\n  c: x\n  e: [1]\n").unwrap();
+        let opts = DiffOptions::default();
+        let diff = cmp_yml_vals(&old, &new, &opts);
+
+        colored::control::set_override(false);
+        let report = ReportOptions {
+            labels: Some(("config/dev.yml".into(), "config/prod.yml".into())),
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        print_unified(&mut out, &diff, &opts, &report).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "--- config/dev.yml\n+++ config/prod.yml\n\
+             @@ a @@\n-1\n+2\n@@ b.d @@\n-1\n@@ b.e[0] @@\n+1\n"
+        );
+
+        let mut out = Vec::new();
+        print_unified(&mut out, &ConfigDiff::default(), &opts, &report).unwrap();
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_line_numbers() {
+        let old_text = "a: 1\nb:\n  c: x\n  d: 1\n";
+        let new_text = "# 注释\nb:\n  c: x\n  d: 2\ne: 1\n";
+        let old: Value = serde_yaml::from_str(old_text).unwrap();
+        let new: Value = serde_yaml::from_str(new_text).unwrap();
+        let opts = DiffOptions::default();
+        let diff = cmp_yml_vals(&old, &new, &opts);
+
+        colored::control::set_override(false);
+        let report = ReportOptions {
+            line_numbers: true,
+            labels: Some(("old.yml".into(), "new.yml".into())),
+            old_source: Some(old_text.into()),
+            new_source: Some(new_text.into()),
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        print_unified(&mut out, &diff, &opts, &report).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "--- old.yml\n+++ new.yml\n\
+             @@ a @@ old.yml:1\n-1\n\
+             @@ b.d @@ old.yml:4 → new.yml:4\n-1\n+2\n\
+             @@ e @@ new.yml:5\n+1\n"
+        );
+    }
+}
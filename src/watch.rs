@@ -0,0 +1,100 @@
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// 两次检查文件状态之间的间隔
+pub const POLL_INTERVAL: Duration = Duration::from_millis(300);
+
+/// 清屏并把光标移到左上角；标准输出不是终端时不输出，以免在日志或管道中留下控制符
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+/// 被监视的路径在某一时刻的状态：每个文件的路径、修改时间和大小；目录展开为其中的所有文件，
+/// 不存在的路径记为 `None`，这样文件被删除或重新创建也算变化
+type Snapshot = Vec<(PathBuf, Option<(SystemTime, u64)>)>;
+
+fn snapshot(paths: &[PathBuf]) -> Snapshot {
+    let mut files = Vec::new();
+    for path in paths {
+        collect(path, &mut files);
+    }
+    files
+}
+
+fn collect(path: &Path, files: &mut Snapshot) {
+    let Ok(meta) = fs::metadata(path) else {
+        files.push((path.to_path_buf(), None));
+        return;
+    };
+    if meta.is_dir() {
+        let mut entries: Vec<PathBuf> = fs::read_dir(path)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .collect();
+        entries.sort();
+        entries.iter().for_each(|entry| collect(entry, files));
+    } else {
+        let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        files.push((path.to_path_buf(), Some((modified, meta.len()))));
+    }
+}
+
+/// --watch：先运行一次 `run`，之后每当被监视的文件（或目录中的任一文件）变化时清屏再运行一次，
+/// 直到进程被中断
+///
+/// 通过定期检查修改时间和大小发现变化，不依赖各平台的文件系统通知。编辑器保存文件时常常分几步写入，
+/// 发现变化后要等状态连续两次检查都不再变化才重新比较。
+pub fn watch(paths: &[PathBuf], mut run: impl FnMut()) -> ! {
+    let mut last = snapshot(paths);
+    loop {
+        if io::stdout().is_terminal() {
+            print!("{CLEAR_SCREEN}");
+        }
+        run();
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let current = snapshot(paths);
+            if current != last {
+                last = current;
+                break;
+            }
+        }
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let current = snapshot(paths);
+            if current == last {
+                break;
+            }
+            last = current;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::snapshot;
+    use std::fs;
+
+    #[test]
+    fn test_snapshot_detects_changes() {
+        let dir = std::env::temp_dir().join(format!("yml-diff-watch-{}", std::process::id()));
+        fs::create_dir_all(dir.join("conf")).unwrap();
+        let file = dir.join("conf/app.yml");
+        fs::write(&file, "a: 1\n").unwrap();
+        let missing = dir.join("missing.yml");
+        let paths = [dir.clone(), missing.clone()];
+
+        let before = snapshot(&paths);
+        assert_eq!(before.len(), 2);
+        assert_eq!(before[1], (missing.clone(), None));
+        assert_eq!(snapshot(&paths), before);
+
+        fs::write(&file, "a: 10\n").unwrap();
+        assert_ne!(snapshot(&paths), before);
+        fs::write(&missing, "b: 1\n").unwrap();
+        assert!(snapshot(&paths)[1].1.is_some());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}